@@ -0,0 +1,104 @@
+use chrono::NaiveDate;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_decimal::Decimal;
+
+// The app is a binary crate, so the pricing module is compiled in directly
+// alongside stand-ins for the models it uses.
+mod models {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use crate::money::{Money, PerCredit};
+
+    pub struct TuitionCosts {
+        pub credits_cost: PerCredit,
+        pub nonresidency_fee: Money,
+    }
+
+    pub struct ProrationRule {
+        pub starts_on: NaiveDate,
+        pub after_week: u32,
+        pub tuition_percent: Decimal,
+    }
+
+    pub struct RefundRule {
+        pub starts_on: NaiveDate,
+        pub through_week: u32,
+        pub refund_percent: Decimal,
+    }
+
+    pub struct ReciprocityAgreement {
+        pub state: String,
+        pub residency: String,
+        pub nonresidency_fee_percent: Decimal,
+    }
+}
+
+#[path = "../src/money.rs"]
+#[allow(dead_code)]
+mod money;
+// Not every pricing function has a benchmark.
+#[path = "../src/pricing.rs"]
+#[allow(dead_code)]
+mod pricing;
+
+use models::{ProrationRule, RefundRule, TuitionCosts};
+use money::{Money, PerCredit};
+
+fn tuition_total(c: &mut Criterion) {
+    let resident = TuitionCosts { credits_cost: PerCredit(Money::new(Decimal::new(32500, 2))), nonresidency_fee: Money::zero() };
+    let nonresident = TuitionCosts { credits_cost: PerCredit(Money::new(Decimal::new(61075, 2))), nonresidency_fee: Money::new(Decimal::new(125000, 2)) };
+    let orientation_fee = Money::new(Decimal::new(15000, 2));
+
+    c.bench_function("tuition_total resident", |b| {
+        b.iter(|| pricing::tuition_total(black_box(12), black_box(false), black_box(&resident), black_box(orientation_fee)))
+    });
+    c.bench_function("tuition_total nonresident with orientation", |b| {
+        b.iter(|| pricing::tuition_total(black_box(18), black_box(true), black_box(&nonresident), black_box(orientation_fee)))
+    });
+    // A spread of credit loads, as a lookup page full of records would price them.
+    c.bench_function("tuition_total 1..=24 credits", |b| {
+        b.iter(|| {
+            (1..=24u8)
+                .map(|num_credits| pricing::tuition_total(black_box(num_credits), true, &nonresident, orientation_fee))
+                .sum::<Money>()
+        })
+    });
+}
+
+fn proration(c: &mut Criterion) {
+    let costs = TuitionCosts { credits_cost: PerCredit(Money::new(Decimal::new(32500, 2))), nonresidency_fee: Money::zero() };
+    let starts_on = NaiveDate::from_ymd_opt(2026, 8, 24).unwrap();
+    let rules = [
+        ProrationRule { starts_on, after_week: 4, tuition_percent: Decimal::new(75, 0) },
+        ProrationRule { starts_on, after_week: 8, tuition_percent: Decimal::new(50, 0) },
+    ];
+    let enrollment_date = NaiveDate::from_ymd_opt(2026, 10, 26).unwrap();
+
+    c.bench_function("proration week 10", |b| {
+        b.iter(|| {
+            pricing::proration(black_box(&rules), black_box(enrollment_date))
+                .map(|val| pricing::proration_adjustment(black_box(12), &costs, val.tuition_percent))
+        })
+    });
+}
+
+fn refund(c: &mut Criterion) {
+    let starts_on = NaiveDate::from_ymd_opt(2026, 8, 24).unwrap();
+    let rules = [
+        RefundRule { starts_on, through_week: 1, refund_percent: Decimal::new(100, 0) },
+        RefundRule { starts_on, through_week: 4, refund_percent: Decimal::new(50, 0) },
+    ];
+    let withdrawal_date = NaiveDate::from_ymd_opt(2026, 9, 8).unwrap();
+
+    c.bench_function("refund week 3", |b| {
+        b.iter(|| {
+            let charged = pricing::charged_tuition(black_box(12), black_box(PerCredit(Money::new(Decimal::new(32500, 2)))), black_box(Some(Decimal::new(75, 0))));
+            let (_week, percent) = pricing::refund_percent(black_box(&rules), black_box(withdrawal_date));
+            pricing::refund_amount(charged, percent)
+        })
+    });
+}
+
+criterion_group!(benches, tuition_total, proration, refund);
+criterion_main!(benches);
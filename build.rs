@@ -0,0 +1,54 @@
+// Bundles the static files in src/htdoc (everything but the templates) into the binary. Each is
+// named after a hash of its contents, so it can be cached for good and a change gets a new
+// URL, and is Brotli-compressed here once instead of on every request.
+use sha2::{Digest, Sha256};
+use std::{env, fs, io::Write, path::Path};
+
+const ASSET_DIR: &str = "src/htdoc";
+
+// The extensions that are served as-is, and their Content-Type.
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("ico", "image/x-icon"),
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", ASSET_DIR);
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo.");
+
+    let mut entries: Vec<_> = fs::read_dir(ASSET_DIR).expect("Can't read src/htdoc.").filter_map(Result::ok).map(|entry| entry.path()).collect();
+    entries.sort();
+
+    let mut bundle = String::from("pub static ASSETS: &[Asset] = &[\n");
+    for path in entries {
+        let extension = path.extension().and_then(|val| val.to_str()).unwrap_or_default();
+        let content_type = match CONTENT_TYPES.iter().find(|(val, _)| *val == extension) {
+            Some((_, val)) => *val,
+            None => continue,
+        };
+        println!("cargo:rerun-if-changed={}", path.display());
+        let name = path.file_name().and_then(|val| val.to_str()).expect("Asset names are UTF-8.");
+        let stem = path.file_stem().and_then(|val| val.to_str()).expect("Asset names are UTF-8.");
+        let body = fs::read(&path).expect("Can't read an asset.");
+
+        let hash: String = Sha256::digest(&body).iter().take(6).map(|byte| format!("{:02x}", byte)).collect();
+        let hashed = format!("{}.{}.{}", stem, hash, extension);
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+            writer.write_all(&body).expect("Can't compress an asset.");
+        }
+        fs::write(Path::new(&out_dir).join(&hashed), &body).expect("Can't write an asset.");
+        fs::write(Path::new(&out_dir).join(format!("{}.br", hashed)), &compressed).expect("Can't write a compressed asset.");
+
+        bundle += &format!(
+            "    Asset {{ name: {:?}, hashed: {:?}, content_type: {:?}, body: include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{}\")), brotli: include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{}.br\")) }},\n",
+            name, hashed, content_type, hashed, hashed,
+        );
+    }
+    bundle += "];\n";
+    fs::write(Path::new(&out_dir).join("assets.rs"), bundle).expect("Can't write the asset bundle.");
+}
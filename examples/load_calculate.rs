@@ -0,0 +1,96 @@
+// Load test for /calculate. Start the server against a database with rates loaded
+// (or FEE_SCHEDULE_FILE=fees.example.toml) and with captcha turned off, then run
+//
+//     cargo run --release --example load_calculate -- http://127.0.0.1:8080 20 2000
+//
+// for the base URL, number of concurrent clients and total requests. Each request
+// uses a different student name, so every one of them writes new rows the way first
+// visits do.
+use std::{
+    env,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+#[actix_web::main]
+async fn main() {
+    let mut args = env::args().skip(1);
+    let base_url = args.next().unwrap_or_else(|| "http://127.0.0.1:8080".to_string());
+    let concurrency: usize = args.next().and_then(|val| val.parse().ok()).unwrap_or(10);
+    let total: usize = args.next().and_then(|val| val.parse().ok()).unwrap_or(1000);
+
+    let url = format!("{}/calculate", base_url.trim_end_matches('/'));
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build the HTTP client.");
+    let next = Arc::new(AtomicUsize::new(0));
+    // Tagged per run so repeated runs don't just update the students from the last one.
+    let run = chrono::Local::now().format("%H%M%S").to_string();
+
+    println!("Sending {} requests to {} from {} clients.", total, url, concurrency);
+    let started = Instant::now();
+    let mut workers = Vec::new();
+    for _ in 0..concurrency {
+        let client = client.clone();
+        let url = url.clone();
+        let next = next.clone();
+        let run = run.clone();
+        workers.push(actix_web::rt::spawn(async move {
+            let mut latencies = Vec::new();
+            let mut failures = 0;
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= total {
+                    break;
+                }
+                let last_name = format!("Load{}x{}", run, i);
+                let num_credits = (1 + i % 18).to_string();
+                let form = [
+                    ("first_name", "Bench"),
+                    ("last_name", last_name.as_str()),
+                    ("num_credits", num_credits.as_str()),
+                    ("student_type", if i.is_multiple_of(3) { "nonresident" } else { "resident" }),
+                    ("student_studies", if i.is_multiple_of(4) { "graduate" } else { "undergraduate" }),
+                    ("orientation", "on"),
+                ];
+                let sent = Instant::now();
+                match client.post(&url).form(&form).send().await {
+                    Ok(response) if response.status().is_success() => latencies.push(sent.elapsed()),
+                    Ok(response) => {
+                        if failures == 0 {
+                            println!("Request {} failed with {}.", i, response.status());
+                        }
+                        failures += 1;
+                    }
+                    Err(why) => {
+                        if failures == 0 {
+                            println!("Request {} failed: {}", i, why);
+                        }
+                        failures += 1;
+                    }
+                }
+            }
+            (latencies, failures)
+        }));
+    }
+
+    let mut latencies: Vec<Duration> = Vec::new();
+    let mut failures = 0;
+    for worker in workers {
+        let (worker_latencies, worker_failures) = worker.await.expect("A load worker panicked.");
+        latencies.extend(worker_latencies);
+        failures += worker_failures;
+    }
+    let elapsed = started.elapsed();
+    latencies.sort();
+
+    println!("{} succeeded, {} failed in {:.2?} ({:.1} requests/s).", latencies.len(), failures, elapsed, total as f64 / elapsed.as_secs_f64());
+    if !latencies.is_empty() {
+        let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+        println!("Latency p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}.", percentile(50), percentile(90), percentile(99), latencies[latencies.len() - 1]);
+    }
+}
@@ -0,0 +1,18 @@
+-- Tables the calculator has always used. Existing databases already have them.
+CREATE TABLE IF NOT EXISTS CreditCosts (
+    Studies VARCHAR(32) NOT NULL,
+    Residency VARCHAR(32) NOT NULL,
+    CreditsCost DECIMAL(10, 2) NOT NULL,
+    NonresidencyFee DECIMAL(10, 2) NOT NULL,
+    PRIMARY KEY (Studies, Residency)
+);
+
+CREATE TABLE IF NOT EXISTS orientation_fee (
+    Fee DECIMAL(10, 2) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS UserTuition (
+    FirstName VARCHAR(255) NOT NULL,
+    LastName VARCHAR(255) NOT NULL,
+    TuitionCost DECIMAL(10, 2) NOT NULL
+);
@@ -0,0 +1,8 @@
+-- Estimated costs of attendance that are not billed by the school (books, supplies, transportation).
+CREATE TABLE IF NOT EXISTS IndirectCosts (
+    Id INT NOT NULL AUTO_INCREMENT,
+    Studies VARCHAR(32) NOT NULL,
+    Label VARCHAR(255) NOT NULL,
+    Amount DECIMAL(10, 2) NOT NULL,
+    PRIMARY KEY (Id)
+);
@@ -0,0 +1,15 @@
+-- Named calculator inputs a student can reload into the form later.
+CREATE TABLE IF NOT EXISTS Scenarios (
+    Id INT NOT NULL AUTO_INCREMENT,
+    ScenarioName VARCHAR(255) NOT NULL,
+    FirstName VARCHAR(255) NOT NULL,
+    LastName VARCHAR(255) NOT NULL,
+    NumCredits TINYINT UNSIGNED NOT NULL,
+    NewStudent BOOL NOT NULL,
+    Orientation BOOL NOT NULL,
+    StudentType VARCHAR(32) NOT NULL,
+    StudentStudies VARCHAR(32) NOT NULL,
+    IncludeAdditionalCosts BOOL NOT NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (FirstName, LastName, ScenarioName)
+);
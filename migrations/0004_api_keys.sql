@@ -0,0 +1,11 @@
+-- Keys for other campus systems calling the /api routes. Only a SHA-256 hash of each key is kept.
+CREATE TABLE IF NOT EXISTS ApiKeys (
+    Id INT NOT NULL AUTO_INCREMENT,
+    Name VARCHAR(255) NOT NULL,
+    KeyHash CHAR(64) NOT NULL,
+    RequestsPerMinute INT UNSIGNED NOT NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    RevokedAt DATETIME NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (KeyHash)
+);
@@ -0,0 +1,6 @@
+-- Keep the inputs behind each saved total so it can be re-priced. Older rows leave these empty.
+ALTER TABLE UserTuition
+    ADD COLUMN NumCredits TINYINT UNSIGNED NULL,
+    ADD COLUMN Orientation BOOL NULL,
+    ADD COLUMN StudentType VARCHAR(32) NULL,
+    ADD COLUMN StudentStudies VARCHAR(32) NULL;
@@ -0,0 +1,49 @@
+-- Several campuses can share one deployment. Everything that existed before belongs to campus 1.
+CREATE TABLE IF NOT EXISTS Campuses (
+    Id INT NOT NULL AUTO_INCREMENT,
+    Slug VARCHAR(64) NOT NULL,
+    Name VARCHAR(255) NOT NULL,
+    Hostname VARCHAR(255) NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (Slug),
+    UNIQUE KEY (Hostname)
+);
+
+INSERT IGNORE INTO Campuses (Id, Slug, Name) VALUES (1, 'main', 'Main Campus');
+
+-- Rebuild the rate tables keyed by campus; older installs may not have had a primary key on them.
+CREATE TABLE CampusCreditCosts (
+    CampusId INT NOT NULL,
+    Studies VARCHAR(32) NOT NULL,
+    Residency VARCHAR(32) NOT NULL,
+    CreditsCost DECIMAL(10, 2) NOT NULL,
+    NonresidencyFee DECIMAL(10, 2) NOT NULL,
+    PRIMARY KEY (CampusId, Studies, Residency)
+);
+INSERT IGNORE INTO CampusCreditCosts (CampusId, Studies, Residency, CreditsCost, NonresidencyFee)
+    SELECT 1, Studies, Residency, CreditsCost, NonresidencyFee FROM CreditCosts;
+DROP TABLE CreditCosts;
+RENAME TABLE CampusCreditCosts TO CreditCosts;
+
+CREATE TABLE CampusOrientationFee (
+    CampusId INT NOT NULL,
+    Fee DECIMAL(10, 2) NOT NULL,
+    PRIMARY KEY (CampusId)
+);
+INSERT IGNORE INTO CampusOrientationFee (CampusId, Fee)
+    SELECT 1, Fee FROM orientation_fee;
+DROP TABLE orientation_fee;
+RENAME TABLE CampusOrientationFee TO orientation_fee;
+
+ALTER TABLE IndirectCosts
+    ADD COLUMN CampusId INT NOT NULL DEFAULT 1 AFTER Id,
+    ADD INDEX (CampusId, Studies);
+
+ALTER TABLE UserTuition
+    ADD COLUMN CampusId INT NOT NULL DEFAULT 1 FIRST,
+    ADD INDEX (CampusId, LastName, FirstName);
+
+ALTER TABLE Scenarios
+    ADD COLUMN CampusId INT NOT NULL DEFAULT 1 AFTER Id,
+    DROP INDEX FirstName,
+    ADD UNIQUE KEY (CampusId, FirstName, LastName, ScenarioName);
@@ -0,0 +1,4 @@
+-- Give each saved calculation an id so it can be referred to directly.
+ALTER TABLE UserTuition
+    ADD COLUMN Id INT NOT NULL AUTO_INCREMENT FIRST,
+    ADD PRIMARY KEY (Id);
@@ -0,0 +1,22 @@
+-- Each calculation keeps the exact rates it was priced with, so a saved total can be explained after rates change.
+CREATE TABLE IF NOT EXISTS Receipts (
+    Id INT NOT NULL AUTO_INCREMENT,
+    Code CHAR(10) NOT NULL,
+    CampusId INT NOT NULL,
+    StudentId INT NOT NULL,
+    FirstName VARCHAR(255) NOT NULL,
+    LastName VARCHAR(255) NOT NULL,
+    Term VARCHAR(32) NOT NULL,
+    NumCredits TINYINT UNSIGNED NOT NULL,
+    Orientation BOOL NOT NULL,
+    StudentType VARCHAR(32) NOT NULL,
+    StudentStudies VARCHAR(32) NOT NULL,
+    CreditsCost DECIMAL(10, 2) NOT NULL,
+    NonresidencyFee DECIMAL(10, 2) NOT NULL,
+    OrientationFee DECIMAL(10, 2) NOT NULL,
+    TuitionCost DECIMAL(10, 2) NOT NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (Code),
+    INDEX (CampusId, StudentId)
+);
@@ -0,0 +1,10 @@
+-- A single row holding the maintenance switch, so it survives restarts.
+CREATE TABLE IF NOT EXISTS Maintenance (
+    Id TINYINT NOT NULL,
+    Enabled BOOL NOT NULL DEFAULT FALSE,
+    Message VARCHAR(1024) NULL,
+    UpdatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id)
+);
+
+INSERT IGNORE INTO Maintenance (Id, Enabled) VALUES (1, FALSE);
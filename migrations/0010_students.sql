@@ -0,0 +1,51 @@
+-- Split UserTuition into the student and their per-term totals, so a student's identity lives in one row.
+CREATE TABLE IF NOT EXISTS Students (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    FirstName VARCHAR(255) NOT NULL,
+    LastName VARCHAR(255) NOT NULL,
+    Email VARCHAR(255) NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (CampusId, LastName, FirstName),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
+
+-- Keep the old ids so existing receipts still point at the right student.
+INSERT IGNORE INTO Students (Id, CampusId, FirstName, LastName)
+    SELECT Id, CampusId, FirstName, LastName FROM UserTuition ORDER BY Id;
+
+-- Rows saved before terms were recorded have no term.
+CREATE TABLE IF NOT EXISTS TuitionRecords (
+    Id INT NOT NULL AUTO_INCREMENT,
+    StudentId INT NOT NULL,
+    Term VARCHAR(32) NULL,
+    TuitionCost DECIMAL(10, 2) NOT NULL,
+    NumCredits TINYINT UNSIGNED NULL,
+    Orientation BOOL NULL,
+    StudentType VARCHAR(32) NULL,
+    StudentStudies VARCHAR(32) NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (StudentId, Term),
+    FOREIGN KEY (StudentId) REFERENCES Students (Id)
+);
+
+INSERT INTO TuitionRecords (StudentId, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies)
+    SELECT Students.Id, UserTuition.TuitionCost, UserTuition.NumCredits, UserTuition.Orientation, UserTuition.StudentType, UserTuition.StudentStudies
+    FROM UserTuition
+    JOIN Students ON Students.CampusId = UserTuition.CampusId
+        AND Students.FirstName = UserTuition.FirstName
+        AND Students.LastName = UserTuition.LastName;
+
+-- Duplicate names were folded into one student; move their receipts along.
+UPDATE Receipts
+    JOIN UserTuition ON UserTuition.Id = Receipts.StudentId
+    JOIN Students ON Students.CampusId = UserTuition.CampusId
+        AND Students.FirstName = UserTuition.FirstName
+        AND Students.LastName = UserTuition.LastName
+    SET Receipts.StudentId = Students.Id;
+
+ALTER TABLE Receipts
+    ADD FOREIGN KEY (StudentId) REFERENCES Students (Id);
+
+DROP TABLE UserTuition;
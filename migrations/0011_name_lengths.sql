@@ -0,0 +1,16 @@
+-- Names are capped at 100 characters; the calculator rejects anything longer.
+UPDATE Students SET FirstName = LEFT(FirstName, 100), LastName = LEFT(LastName, 100);
+ALTER TABLE Students
+    MODIFY FirstName VARCHAR(100) NOT NULL,
+    MODIFY LastName VARCHAR(100) NOT NULL;
+
+UPDATE Receipts SET FirstName = LEFT(FirstName, 100), LastName = LEFT(LastName, 100);
+ALTER TABLE Receipts
+    MODIFY FirstName VARCHAR(100) NOT NULL,
+    MODIFY LastName VARCHAR(100) NOT NULL;
+
+UPDATE Scenarios SET FirstName = LEFT(FirstName, 100), LastName = LEFT(LastName, 100), ScenarioName = LEFT(ScenarioName, 100);
+ALTER TABLE Scenarios
+    MODIFY ScenarioName VARCHAR(100) NOT NULL,
+    MODIFY FirstName VARCHAR(100) NOT NULL,
+    MODIFY LastName VARCHAR(100) NOT NULL;
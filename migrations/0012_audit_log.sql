@@ -0,0 +1,12 @@
+-- Changes staff make to stored data through the admin pages.
+CREATE TABLE IF NOT EXISTS AuditLog (
+    Id INT NOT NULL AUTO_INCREMENT,
+    Actor VARCHAR(255) NOT NULL,
+    Action VARCHAR(64) NOT NULL,
+    Entity VARCHAR(64) NOT NULL,
+    EntityId INT NOT NULL,
+    Details TEXT NOT NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id),
+    INDEX (Entity, EntityId)
+);
@@ -0,0 +1,26 @@
+-- Terms with a known start date, so enrolling partway through one can be prorated.
+CREATE TABLE IF NOT EXISTS Terms (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Name VARCHAR(32) NOT NULL,
+    StartsOn DATE NOT NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (CampusId, Name),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
+
+-- Enrolling after week AfterWeek of the term is charged TuitionPercent of tuition, e.g. 50 after week 8.
+CREATE TABLE IF NOT EXISTS ProrationRules (
+    Id INT NOT NULL AUTO_INCREMENT,
+    TermId INT NOT NULL,
+    AfterWeek INT UNSIGNED NOT NULL,
+    TuitionPercent DECIMAL(5, 2) NOT NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (TermId, AfterWeek),
+    FOREIGN KEY (TermId) REFERENCES Terms (Id) ON DELETE CASCADE
+);
+
+-- Receipts made without an enrollment date, or before any rule applied, leave these empty.
+ALTER TABLE Receipts
+    ADD COLUMN EnrollmentDate DATE NULL,
+    ADD COLUMN TuitionPercent DECIMAL(5, 2) NULL;
@@ -0,0 +1,24 @@
+-- Withdrawing by the end of week ThroughWeek of the term refunds RefundPercent of the tuition charged.
+CREATE TABLE IF NOT EXISTS RefundRules (
+    Id INT NOT NULL AUTO_INCREMENT,
+    TermId INT NOT NULL,
+    ThroughWeek INT UNSIGNED NOT NULL,
+    RefundPercent DECIMAL(5, 2) NOT NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (TermId, ThroughWeek),
+    FOREIGN KEY (TermId) REFERENCES Terms (Id) ON DELETE CASCADE
+);
+
+-- Refund estimates made against a receipt, with the schedule's answer at the time.
+CREATE TABLE IF NOT EXISTS RefundEstimates (
+    Id INT NOT NULL AUTO_INCREMENT,
+    ReceiptId INT NOT NULL,
+    WithdrawalDate DATE NOT NULL,
+    Week INT NOT NULL,
+    RefundPercent DECIMAL(5, 2) NOT NULL,
+    RefundAmount DECIMAL(10, 2) NOT NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id),
+    INDEX (ReceiptId),
+    FOREIGN KEY (ReceiptId) REFERENCES Receipts (Id) ON DELETE CASCADE
+);
@@ -0,0 +1,6 @@
+-- Dual-enrollment (high school) students start out at the undergraduate rates; adjust them per campus.
+-- A campus without dual enrollment can delete its rows and the option is refused there.
+INSERT IGNORE INTO CreditCosts (CampusId, Studies, Residency, CreditsCost, NonresidencyFee)
+    SELECT CampusId, 'dual_enrollment', Residency, CreditsCost, NonresidencyFee
+    FROM CreditCosts
+    WHERE Studies = 'undergraduate';
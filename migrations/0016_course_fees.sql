@@ -0,0 +1,20 @@
+-- Flat lab and course fees, listed by department. Codes are matched ignoring case and spaces.
+CREATE TABLE IF NOT EXISTS CourseFees (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Department VARCHAR(64) NOT NULL,
+    CourseCode VARCHAR(16) NOT NULL,
+    Label VARCHAR(255) NOT NULL,
+    Fee DECIMAL(10, 2) NOT NULL,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (CampusId, CourseCode),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
+
+-- The courses a calculation included, as entered, and what their fees came to.
+ALTER TABLE Receipts
+    ADD COLUMN CourseCodes VARCHAR(255) NULL,
+    ADD COLUMN CourseFees DECIMAL(10, 2) NOT NULL DEFAULT 0;
+
+ALTER TABLE Scenarios
+    ADD COLUMN CourseCodes VARCHAR(255) NULL;
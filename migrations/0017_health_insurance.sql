@@ -0,0 +1,18 @@
+-- Health insurance is charged to every student unless they waive it with their own coverage.
+CREATE TABLE IF NOT EXISTS HealthInsuranceFee (
+    CampusId INT NOT NULL,
+    Fee DECIMAL(10, 2) NOT NULL,
+    PRIMARY KEY (CampusId),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
+
+-- Rows saved before the waiver was offered leave it empty.
+ALTER TABLE TuitionRecords
+    ADD COLUMN InsuranceWaived BOOL NULL;
+
+ALTER TABLE Receipts
+    ADD COLUMN InsuranceWaived BOOL NOT NULL DEFAULT FALSE,
+    ADD COLUMN HealthInsuranceFee DECIMAL(10, 2) NOT NULL DEFAULT 0;
+
+ALTER TABLE Scenarios
+    ADD COLUMN InsuranceWaived BOOL NOT NULL DEFAULT FALSE;
@@ -0,0 +1,19 @@
+-- International students start out at the nonresident rates; adjust them per campus.
+INSERT IGNORE INTO CreditCosts (CampusId, Studies, Residency, CreditsCost, NonresidencyFee)
+    SELECT CampusId, Studies, 'international', CreditsCost, NonresidencyFee
+    FROM CreditCosts
+    WHERE Residency = 'nonresident';
+
+-- Flat fees only international students pay, e.g. the SEVIS I-901 fee.
+CREATE TABLE IF NOT EXISTS InternationalFees (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Label VARCHAR(255) NOT NULL,
+    Amount DECIMAL(10, 2) NOT NULL,
+    PRIMARY KEY (Id),
+    INDEX (CampusId),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
+
+ALTER TABLE Receipts
+    ADD COLUMN InternationalFees DECIMAL(10, 2) NOT NULL DEFAULT 0;
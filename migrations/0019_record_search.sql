@@ -0,0 +1,15 @@
+-- When a record was last calculated, so the admin search can filter by date.
+ALTER TABLE TuitionRecords
+    ADD COLUMN UpdatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    ADD INDEX (Term),
+    ADD INDEX (UpdatedAt);
+
+-- Older records take the time of their latest receipt; ones without a receipt keep the migration time.
+UPDATE TuitionRecords
+    JOIN (
+        SELECT StudentId, Term, MAX(CreatedAt) AS CreatedAt
+        FROM Receipts
+        GROUP BY StudentId, Term
+    ) AS Latest ON Latest.StudentId = TuitionRecords.StudentId
+        AND Latest.Term = TuitionRecords.Term
+    SET TuitionRecords.UpdatedAt = Latest.CreatedAt;
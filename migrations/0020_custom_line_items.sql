@@ -0,0 +1,17 @@
+-- One-off charges for a term, e.g. a graduation fee, for the students AppliesWhen picks out.
+-- AppliesWhen is a rule like "studies = undergraduate and credits >= 12"; blank applies to everyone.
+CREATE TABLE IF NOT EXISTS CustomLineItems (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Term VARCHAR(32) NOT NULL,
+    Label VARCHAR(255) NOT NULL,
+    Amount DECIMAL(10, 2) NOT NULL,
+    AppliesWhen VARCHAR(255) NOT NULL DEFAULT '',
+    PRIMARY KEY (Id),
+    INDEX (CampusId, Term),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
+
+-- Empty when no line item applied.
+ALTER TABLE Receipts
+    ADD COLUMN CustomFees DECIMAL(10, 2) NULL;
@@ -0,0 +1,12 @@
+-- An unguessable id for each student, for anywhere an id is shown outside the admin pages.
+-- New students get a UUIDv7 from the server; existing ones get MySQL's own UUID.
+ALTER TABLE Students
+    ADD COLUMN PublicId CHAR(36) NULL;
+
+UPDATE Students
+    SET PublicId = UUID()
+    WHERE PublicId IS NULL;
+
+ALTER TABLE Students
+    MODIFY PublicId CHAR(36) NOT NULL,
+    ADD UNIQUE KEY (PublicId);
@@ -0,0 +1,6 @@
+-- Where each calculation came from. The IP is salted and hashed before it is stored, and all
+-- three stay NULL when REQUEST_METADATA is off.
+ALTER TABLE Receipts
+    ADD COLUMN ClientIpHash CHAR(64) NULL,
+    ADD COLUMN UserAgent VARCHAR(255) NULL,
+    ADD COLUMN Referrer VARCHAR(255) NULL;
@@ -0,0 +1,4 @@
+-- The counselor who calculated each estimate on a student's behalf. NULL for estimates
+-- students made themselves.
+ALTER TABLE Receipts
+    ADD COLUMN EnteredBy VARCHAR(255) NULL;
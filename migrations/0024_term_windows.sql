@@ -0,0 +1,4 @@
+-- When students can make estimates for each term. NULL leaves that end open.
+ALTER TABLE Terms
+    ADD COLUMN OpensOn DATE NULL,
+    ADD COLUMN ClosesOn DATE NULL;
@@ -0,0 +1,13 @@
+-- Combinations of answers a campus doesn't accept, e.g. graduate students asking for orientation.
+-- A submission matching RejectWhen (a rule like "studies = graduate and orientation") is turned
+-- away with Message shown on Field, before anything is priced.
+CREATE TABLE IF NOT EXISTS ValidationRules (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Field VARCHAR(32) NOT NULL,
+    RejectWhen VARCHAR(255) NOT NULL,
+    Message VARCHAR(255) NOT NULL,
+    PRIMARY KEY (Id),
+    INDEX (CampusId),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
@@ -0,0 +1,9 @@
+-- Which section of the breakdown a custom line item is shown in: tuition, mandatory_fees,
+-- optional_fees or aid. Aid amounts are entered as positive numbers and taken off the total.
+ALTER TABLE CustomLineItems
+    ADD COLUMN Category VARCHAR(32) NOT NULL DEFAULT 'mandatory_fees';
+
+-- The custom line items that applied, with their categories, as a JSON array; CustomFees stays
+-- their net total. Empty on receipts from before categories, which show CustomFees as one line.
+ALTER TABLE Receipts
+    ADD COLUMN CustomItems TEXT NULL;
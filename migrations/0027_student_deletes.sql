@@ -0,0 +1,14 @@
+-- Deleting a student takes their saved records with them. Receipts can outlive the student when
+-- the delete anonymizes them for the stats, so their StudentId is cleared instead.
+ALTER TABLE TuitionRecords
+    DROP FOREIGN KEY TuitionRecords_ibfk_1;
+
+ALTER TABLE TuitionRecords
+    ADD FOREIGN KEY (StudentId) REFERENCES Students (Id) ON DELETE CASCADE;
+
+ALTER TABLE Receipts
+    DROP FOREIGN KEY Receipts_ibfk_1;
+
+ALTER TABLE Receipts
+    MODIFY StudentId INT NULL,
+    ADD FOREIGN KEY (StudentId) REFERENCES Students (Id) ON DELETE SET NULL;
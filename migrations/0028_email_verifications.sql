@@ -0,0 +1,17 @@
+-- Email addresses given with a calculation, waiting for the student to enter the code sent to
+-- them. Students.Email is only set once the code is confirmed. Only a hash of the code is kept.
+CREATE TABLE IF NOT EXISTS EmailVerifications (
+    Id INT NOT NULL AUTO_INCREMENT,
+    PublicId CHAR(36) NOT NULL,
+    StudentId INT NOT NULL,
+    Email VARCHAR(255) NOT NULL,
+    CodeHash CHAR(64) NOT NULL,
+    Attempts INT UNSIGNED NOT NULL DEFAULT 0,
+    ExpiresAt DATETIME NOT NULL,
+    ConfirmedAt DATETIME NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (PublicId),
+    INDEX (StudentId),
+    FOREIGN KEY (StudentId) REFERENCES Students (Id) ON DELETE CASCADE
+);
@@ -0,0 +1,12 @@
+-- Students who don't pay for orientation whether or not they check the box, e.g. transfer or
+-- graduate students. ExemptWhen is a rule like "studies = graduate" or "not new_student"; the
+-- first one that matches names the exemption on the result page.
+CREATE TABLE IF NOT EXISTS OrientationExemptions (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Label VARCHAR(255) NOT NULL,
+    ExemptWhen VARCHAR(255) NOT NULL,
+    PRIMARY KEY (Id),
+    INDEX (CampusId),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
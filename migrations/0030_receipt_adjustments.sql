@@ -0,0 +1,16 @@
+-- Changes counselors make to single charges on a receipt, e.g. a fee the student was granted
+-- relief from. The receipt itself is never changed; its revised total is the original with each
+-- charge's latest adjustment in place of its amount.
+CREATE TABLE IF NOT EXISTS ReceiptAdjustments (
+    Id INT NOT NULL AUTO_INCREMENT,
+    ReceiptId INT NOT NULL,
+    ItemLabel VARCHAR(255) NOT NULL,
+    OriginalAmount DECIMAL(10, 2) NOT NULL,
+    AdjustedAmount DECIMAL(10, 2) NOT NULL,
+    Note VARCHAR(255) NOT NULL,
+    AdjustedBy VARCHAR(255) NOT NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id),
+    INDEX (ReceiptId),
+    FOREIGN KEY (ReceiptId) REFERENCES Receipts (Id) ON DELETE CASCADE
+);
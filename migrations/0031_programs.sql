@@ -0,0 +1,12 @@
+-- Typical credits to finish a program, e.g. 120 for a bachelor's degree. The result page uses
+-- them to project how many terms the student's load would take and what it would cost.
+CREATE TABLE IF NOT EXISTS Programs (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Studies VARCHAR(32) NOT NULL,
+    Name VARCHAR(255) NOT NULL,
+    TotalCredits INT UNSIGNED NOT NULL,
+    PRIMARY KEY (Id),
+    INDEX (CampusId, Studies),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
@@ -0,0 +1,8 @@
+-- The yearly rate increase multi-year projections assume, as a percent, e.g. 3.50. Campuses
+-- without a row project flat rates.
+CREATE TABLE IF NOT EXISTS RateIncrease (
+    CampusId INT NOT NULL,
+    AnnualPercent DECIMAL(5, 2) NOT NULL,
+    PRIMARY KEY (CampusId),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
@@ -0,0 +1,10 @@
+-- Queries that took longer than SLOW_QUERY_MS, by their name in `queries`, for finding the
+-- lookups that need an index. Rows older than two weeks are pruned as new ones are written.
+CREATE TABLE IF NOT EXISTS SlowQueries (
+    Id INT NOT NULL AUTO_INCREMENT,
+    QueryName VARCHAR(64) NOT NULL,
+    DurationMs INT UNSIGNED NOT NULL,
+    RecordedAt DATETIME NOT NULL,
+    PRIMARY KEY (Id),
+    INDEX (RecordedAt)
+);
@@ -0,0 +1,8 @@
+-- Indexes for the lookups that scanned Receipts: a student's receipts (their history, export
+-- and deletion), the stats for a date range, and the retention cutoff. Name lookups on Students
+-- and a student's record for a term already have their unique keys, and every fee lookup is by
+-- a primary or unique key on CampusId; `schema::check` warns at startup if any are missing.
+ALTER TABLE Receipts
+    ADD INDEX (StudentId, Term),
+    ADD INDEX (CampusId, CreatedAt),
+    ADD INDEX (CreatedAt);
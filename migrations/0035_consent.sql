@@ -0,0 +1,4 @@
+-- When the student last agreed to their estimate being kept, where REQUIRE_CONSENT asks for
+-- it. Records saved without it being asked for have none.
+ALTER TABLE TuitionRecords
+    ADD COLUMN ConsentedAt DATETIME NULL;
@@ -0,0 +1,9 @@
+-- Optional inputs an admin has taken off a campus's calculator form. Fields without a row are
+-- shown.
+CREATE TABLE IF NOT EXISTS FormFields (
+    CampusId INT NOT NULL,
+    Field VARCHAR(32) NOT NULL,
+    Visible BOOLEAN NOT NULL,
+    PRIMARY KEY (CampusId, Field),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
@@ -0,0 +1,5 @@
+-- What the student answered to "Orientation (optional)", before any exemption: 1 for checked, 0
+-- when the box was offered and left unchecked, and none when it wasn't offered or for receipts
+-- from before this was kept.
+ALTER TABLE Receipts
+    ADD COLUMN OrientationAnswer BOOLEAN NULL;
@@ -0,0 +1,4 @@
+-- The entry point each calculation came through: web for the calculator page, api for JSON
+-- requests and counselor for the admin pages. None for receipts from before this was kept.
+ALTER TABLE Receipts
+    ADD COLUMN Channel VARCHAR(16) NULL;
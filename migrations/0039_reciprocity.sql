@@ -0,0 +1,23 @@
+-- The state each campus is in, by its USPS code. Set by hand like the rest of the campus row;
+-- with it, the calculator asks students for their home state instead of whether they're residents.
+ALTER TABLE Campuses
+    ADD COLUMN State CHAR(2) NULL;
+
+-- Reciprocity agreements: students from State pay the Residency rates (resident or nonresident)
+-- and NonresidencyFeePercent of the non-residency fee, e.g. 50 for a neighboring state's discount
+-- tier. Students from states without one pay nonresident rates in full.
+CREATE TABLE IF NOT EXISTS ReciprocityAgreements (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    State CHAR(2) NOT NULL,
+    Label VARCHAR(255) NOT NULL,
+    Residency VARCHAR(32) NOT NULL,
+    NonresidencyFeePercent DECIMAL(5, 2) NOT NULL DEFAULT 100,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (CampusId, State),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
+
+-- The home state a scenario was saved with, so it loads back into the state picker.
+ALTER TABLE Scenarios
+    ADD COLUMN HomeState CHAR(2) NULL;
@@ -0,0 +1,32 @@
+-- Scenarios belong to the student who saved them, signed in with a code sent to the address they
+-- confirmed, instead of to anyone who types the same name. Existing ones go to the student with
+-- their name; one saved under a name no student has is left without an owner and isn't listed.
+ALTER TABLE Scenarios
+    ADD COLUMN StudentId INT NULL AFTER CampusId;
+
+UPDATE Scenarios
+    JOIN Students ON Students.CampusId = Scenarios.CampusId
+        AND Students.FirstName = Scenarios.FirstName
+        AND Students.LastName = Scenarios.LastName
+    SET Scenarios.StudentId = Students.Id;
+
+-- Replaces the unique key on (CampusId, FirstName, LastName, ScenarioName) from 0006.
+ALTER TABLE Scenarios
+    DROP INDEX CampusId,
+    ADD UNIQUE KEY (StudentId, ScenarioName);
+
+-- Codes sent to sign a student in, as with EmailVerifications. Only a hash of the code is kept.
+CREATE TABLE IF NOT EXISTS StudentSignIns (
+    Id INT NOT NULL AUTO_INCREMENT,
+    PublicId CHAR(36) NOT NULL,
+    StudentId INT NOT NULL,
+    CodeHash CHAR(64) NOT NULL,
+    Attempts INT UNSIGNED NOT NULL DEFAULT 0,
+    ExpiresAt DATETIME NOT NULL,
+    UsedAt DATETIME NULL,
+    CreatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (PublicId),
+    INDEX (StudentId),
+    FOREIGN KEY (StudentId) REFERENCES Students (Id) ON DELETE CASCADE
+);
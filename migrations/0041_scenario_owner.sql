@@ -0,0 +1,9 @@
+-- Scenarios are kept by their student's id, and go when the student does, like their records.
+-- Ones 0040 couldn't give an owner aren't listed for anyone, so they're removed here.
+DELETE FROM Scenarios
+    WHERE StudentId IS NULL
+    OR StudentId NOT IN (SELECT Id FROM Students);
+
+ALTER TABLE Scenarios
+    MODIFY COLUMN StudentId INT NOT NULL,
+    ADD FOREIGN KEY (StudentId) REFERENCES Students (Id) ON DELETE CASCADE;
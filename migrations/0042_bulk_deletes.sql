@@ -0,0 +1,25 @@
+-- Bulk deletes (see bulk_delete.rs), kept here rather than in one server's memory so their
+-- progress outlasts a restart and every replica shows the same one. RunningCampusId is the
+-- campus until the job finishes, and unique, so only one runs per campus at a time.
+CREATE TABLE IF NOT EXISTS BulkDeletes (
+    Id INT NOT NULL AUTO_INCREMENT,
+    CampusId INT NOT NULL,
+    Selection VARCHAR(64) NOT NULL,
+    Action VARCHAR(32) NOT NULL,
+    Actor VARCHAR(255) NOT NULL,
+    ReceiptsTotal BIGINT NOT NULL,
+    RecordsTotal BIGINT NOT NULL,
+    ReceiptsDone BIGINT NOT NULL DEFAULT 0,
+    RecordsDone BIGINT NOT NULL DEFAULT 0,
+    StudentsDone BIGINT NOT NULL DEFAULT 0,
+    Batches BIGINT NOT NULL DEFAULT 0,
+    StartedAt DATETIME NOT NULL,
+    UpdatedAt DATETIME NOT NULL,
+    FinishedAt DATETIME NULL,
+    Error TEXT NULL,
+    RunningCampusId INT AS (IF(FinishedAt IS NULL, CampusId, NULL)) STORED,
+    PRIMARY KEY (Id),
+    UNIQUE KEY (RunningCampusId),
+    INDEX (CampusId, Id),
+    FOREIGN KEY (CampusId) REFERENCES Campuses (Id)
+);
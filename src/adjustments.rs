@@ -0,0 +1,158 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, QueryBuilder};
+
+use crate::{audit, error::AppError, form, models::{Campus, Receipt, ReceiptId}, money::Money, pricing::{self, BreakdownItem, CategorySubtotal}, queries, receipts, recent, render, AppState};
+
+// A counselor's change to one charge on a receipt. The receipt keeps what it was priced at.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct Adjustment {
+    #[serde(skip_serializing)]
+    pub receipt_id: ReceiptId,
+    pub item_label: String,
+    pub original_amount: Money,
+    pub adjusted_amount: Money,
+    pub note: String,
+    pub adjusted_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+// The receipt's breakdown with the adjustments made to it, shown beside the original.
+#[derive(Serialize, Debug, Clone)]
+pub struct Revision {
+    pub categories: Vec<CategorySubtotal>,
+    pub total: Money,
+    // Oldest first, including ones a later adjustment to the same charge replaced.
+    pub adjustments: Vec<Adjustment>,
+}
+
+// Each charge's latest adjustment in place of its amount, or None when there are none.
+pub fn revise(receipt: &Receipt, categories: &[CategorySubtotal], adjustments: Vec<Adjustment>) -> Option<Revision> {
+    if adjustments.is_empty() {
+        return None;
+    }
+    let mut total = receipt.tuition_cost;
+    let mut items = Vec::new();
+    for item in categories.iter().flat_map(|category| category.items.iter()) {
+        match adjustments.iter().rev().find(|adjustment| adjustment.item_label == item.label) {
+            Some(adjustment) => {
+                total += adjustment.adjusted_amount - item.amount;
+                items.push(BreakdownItem { amount: adjustment.adjusted_amount, ..item.clone() });
+            }
+            None => items.push(item.clone()),
+        }
+    }
+    Some(Revision { categories: pricing::categorize(items), total, adjustments })
+}
+
+impl AppState {
+    // The adjustments to these receipts, oldest first, in one query.
+    pub async fn adjustments(&self, receipt_ids: &[ReceiptId]) -> Result<Vec<Adjustment>, AppError> {
+        if receipt_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(queries::RECEIPT_ADJUSTMENTS.sql);
+        let mut separated = query.separated(", ");
+        for id in receipt_ids {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(") order by Id");
+
+        match queries::RECEIPT_ADJUSTMENTS.time(query.build_query_as::<Adjustment>().fetch_all(&self.conn)).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    pub async fn revision(&self, receipt: &Receipt, categories: &[CategorySubtotal]) -> Result<Option<Revision>, AppError> {
+        Ok(revise(receipt, categories, self.adjustments(&[receipt.id]).await?))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FindReceiptParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    code: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AdjustReceiptPage {
+    code: Option<String>,
+    receipt: Option<Receipt>,
+    categories: Vec<CategorySubtotal>,
+    revision: Option<Revision>,
+}
+
+// GET /admin/receipts?code=.. finds the receipt a student reads out and lists its charges to adjust.
+pub async fn receipt(state: web::Data<AppState>, campus: Campus, params: web::Query<FindReceiptParams>) -> Result<HttpResponse, AppError> {
+    let code = match &params.code {
+        Some(val) => val.clone(),
+        None => {
+            return render(&state, "admin_receipt", &AdjustReceiptPage { code: None, receipt: None, categories: Vec::new(), revision: None }).await;
+        }
+    };
+    let receipt = receipts::fetch_receipt(&state, &campus, &code).await?;
+    let categories = recent::breakdown(&receipt);
+    let revision = state.revision(&receipt, &categories).await?;
+    render(&state, "admin_receipt", &AdjustReceiptPage { code: Some(receipt.code.clone()), receipt: Some(receipt), categories, revision }).await
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AdjustParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    item_label: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    adjusted_amount: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    note: Option<String>,
+}
+
+// Change one charge. Aid keeps its sign, so an adjusted scholarship is still negative; a charge
+// can't be made negative.
+pub async fn adjust(state: web::Data<AppState>, campus: Campus, req: HttpRequest, code: web::Path<String>, params: web::Form<AdjustParams>) -> Result<HttpResponse, AppError> {
+    let receipt = receipts::fetch_receipt(&state, &campus, &code).await?;
+    let categories = recent::breakdown(&receipt);
+    let item = match categories.iter().flat_map(|category| category.items.iter()).find(|item| Some(&item.label) == params.item_label.as_ref()) {
+        Some(val) => val,
+        None => {
+            return Err(AppError::validation("item_label", "Pick a charge on this receipt to adjust."));
+        }
+    };
+    let adjusted_amount = match params.adjusted_amount.as_deref().map(str::parse::<Decimal>) {
+        Some(Ok(val)) if val.scale() <= 2 && (val.is_sign_negative() == item.amount.is_sign_negative() || val.is_zero()) => Money::new(val),
+        Some(_) => {
+            return Err(AppError::validation("adjusted_amount", &format!("\"{}\" is not a valid amount for {}.", params.adjusted_amount.as_deref().unwrap_or_default(), item.label)));
+        }
+        None => {
+            return Err(AppError::validation("adjusted_amount", "No adjusted amount was provided!"));
+        }
+    };
+    let note = match &params.note {
+        Some(val) if val.chars().count() <= 255 => val.clone(),
+        _ => {
+            return Err(AppError::validation("note", "A note saying why, of at most 255 characters, is required."));
+        }
+    };
+
+    let actor = audit::actor(&req);
+    let mut tx = state.conn.begin().await?;
+    let id = queries::INSERT_RECEIPT_ADJUSTMENT.run(|sql| sqlx::query(sql)
+    .bind(receipt.id)
+    .bind(&item.label)
+    .bind(item.amount)
+    .bind(adjusted_amount)
+    .bind(&note)
+    .bind(&actor)
+    .execute(&mut tx)).await?.last_insert_id() as i32;
+    let details = format!("Adjusted {} on receipt {} from {} to {}: {}", item.label, receipt.code, item.amount.amount(), adjusted_amount.amount(), note);
+    audit::record(&mut tx, &actor, "create", "ReceiptAdjustment", id, &details).await?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/admin/receipts?code={}", receipt.code)))
+        .finish())
+}
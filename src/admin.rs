@@ -0,0 +1,1446 @@
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{NaiveDate, NaiveDateTime};
+use rand::RngCore;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{api, audit, error::AppError, filters, form, maintenance::{self, Maintenance}, money::{Money, PerCredit}, models::{ApiKey, ApiKeyId, Campus, LineItemId, OrientationExemptionId, ReciprocityAgreement, ReciprocityAgreementId, RefundRuleId, TermWindow, TuitionRecord, TuitionRecordId, ValidationRuleId}, estimate, Channel, fees, form_with_errors, normalize_name, pricing, queries, render, residency, rules, staff_auth, AppState, CalculateTuitionFormParams, IndexPage};
+
+#[derive(Serialize)]
+struct AdminIndexPage {
+    // Only set when staff sign in through the app, so they have somewhere to sign out.
+    signed_in_as: Option<String>,
+}
+
+pub async fn index(state: web::Data<AppState>, session: Session) -> Result<HttpResponse, AppError> {
+    let signed_in_as = state.staff_auth.as_ref().and_then(|provider| staff_auth::signed_in(provider.as_ref(), &session));
+    render(&state, "admin_index", &AdminIndexPage { signed_in_as }).await
+}
+
+#[derive(Serialize)]
+struct ApiKeysPage {
+    api_keys: Vec<ApiKey>,
+    // Only set right after a key is issued; the plain key is never stored.
+    issued_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IssueApiKeyFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    name: Option<String>,
+    #[serde(default, deserialize_with = "form::optional")]
+    requests_per_minute: Option<form::Bounded<1, { u32::MAX }>>,
+}
+
+async fn api_keys_page(state: &AppState, issued_key: Option<String>) -> Result<HttpResponse, AppError> {
+    let api_keys = match queries::API_KEYS.run(|sql| sqlx::query_as::<_, ApiKey>(sql)
+    .fetch_all(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    render(state, "admin_api_keys", &ApiKeysPage { api_keys, issued_key }).await
+}
+
+pub async fn api_keys(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    api_keys_page(&state, None).await
+}
+
+pub async fn issue_api_key(state: web::Data<AppState>, params: web::Form<IssueApiKeyFormParams>) -> Result<HttpResponse, AppError> {
+    let name = match &params.name {
+        Some(val) => val.clone(),
+        None => {
+            return Err(AppError::validation("name", "No API key name was provided!"));
+        }
+    };
+    let requests_per_minute = match &params.requests_per_minute {
+        Some(val) => match val.value() {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(AppError::validation("requests_per_minute", &format!("Rate limit: {}", why)));
+            }
+        },
+        None => {
+            return Err(AppError::validation("requests_per_minute", "No rate limit was provided!"));
+        }
+    };
+
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let key = format!("tc_{}", hex::encode(bytes));
+
+    match queries::INSERT_API_KEY.run(|sql| sqlx::query(sql)
+    .bind(&name)
+    .bind(api::hash_key(&key))
+    .bind(requests_per_minute)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    api_keys_page(&state, Some(key)).await
+}
+
+pub async fn revoke_api_key(state: web::Data<AppState>, id: web::Path<ApiKeyId>) -> Result<HttpResponse, AppError> {
+    match queries::REVOKE_API_KEY.run(|sql| sqlx::query(sql)
+    .bind(id.into_inner())
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/api-keys"))
+        .finish())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SimulateFormParams {
+    studies: Option<String>,
+    residency: Option<String>,
+    credits_cost: Option<String>,
+    nonresidency_fee: Option<String>,
+    orientation_fee: Option<String>,
+    sample_size: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SimulationResult {
+    repriced: usize,
+    skipped: usize,
+    min_delta: Money,
+    avg_delta: Money,
+    max_delta: Money,
+}
+
+#[derive(Serialize)]
+struct SimulatePage {
+    form: SimulateFormParams,
+    result: Option<SimulationResult>,
+}
+
+// Blank means "keep the current value".
+fn optional_amount(field: &'static str, value: &Option<String>) -> Result<Option<Decimal>, AppError> {
+    match value {
+        Some(val) if !val.trim().is_empty() => match val.trim().parse::<Decimal>() {
+            Ok(amount) if !amount.is_sign_negative() => Ok(Some(amount)),
+            _ => Err(AppError::validation(field, &format!("\"{}\" is not a valid amount.", val))),
+        },
+        _ => Ok(None),
+    }
+}
+
+pub async fn simulate_form(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    render(&state, "admin_simulate", &SimulatePage { form: SimulateFormParams::default(), result: None }).await
+}
+
+// Re-price stored calculations against proposed rates without changing anything.
+pub async fn simulate(state: web::Data<AppState>, campus: Campus, params: web::Form<SimulateFormParams>) -> Result<HttpResponse, AppError> {
+    let studies = match &params.studies {
+        Some(val) if fees::STUDIES.contains(&val.as_str()) => val.clone(),
+        _ => {
+            return Err(AppError::validation("studies", "Studies must be undergraduate, graduate, or dual enrollment."));
+        }
+    };
+    let residency = match &params.residency {
+        Some(val) if fees::RESIDENCIES.contains(&val.as_str()) => val.clone(),
+        _ => {
+            return Err(AppError::validation("residency", "Residency must be resident, nonresident, or international."));
+        }
+    };
+    let proposed_credits_cost = optional_amount("credits_cost", &params.credits_cost)?;
+    let proposed_nonresidency_fee = optional_amount("nonresidency_fee", &params.nonresidency_fee)?;
+    let proposed_orientation_fee = optional_amount("orientation_fee", &params.orientation_fee)?;
+    let sample_size = match &params.sample_size {
+        Some(val) if !val.trim().is_empty() => match val.trim().parse::<u32>() {
+            Ok(size) if size > 0 => Some(size),
+            _ => {
+                return Err(AppError::validation("sample_size", &format!("\"{}\" is not a valid sample size.", val)));
+            }
+        },
+        _ => None,
+    };
+
+    // Current rates for every combination, with the proposal laid over the one being changed.
+    let mut rates = HashMap::new();
+    for rate_studies in fees::STUDIES {
+        for rate_residency in fees::RESIDENCIES {
+            let mut costs = match state.tuition_costs(&campus, rate_studies, rate_residency).await {
+                Ok(val) => val,
+                // Records for a kind of study the campus doesn't price are counted as skipped.
+                Err(AppError::Validation { .. }) if rate_studies != studies || rate_residency != residency => continue,
+                Err(why) => {
+                    return Err(why);
+                }
+            };
+            if rate_studies == studies && rate_residency == residency {
+                if let Some(val) = proposed_credits_cost {
+                    costs.credits_cost = PerCredit(Money::new(val));
+                }
+                if let Some(val) = proposed_nonresidency_fee {
+                    costs.nonresidency_fee = Money::new(val);
+                }
+            }
+            rates.insert((rate_studies.to_string(), rate_residency.to_string()), costs);
+        }
+    }
+    let orientation_fee = match proposed_orientation_fee {
+        Some(val) => Money::new(val),
+        None => state.orientation_fee(&campus).await?,
+    };
+
+    let stored = match sample_size {
+        Some(size) => queries::SIMULATE_SAMPLE.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, TuitionRecord>(sql)
+            .bind(campus_id)
+            .bind(size)
+            .fetch_all(state.read_conn())).await,
+        None => queries::SIMULATE_ALL.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, TuitionRecord>(sql)
+            .bind(campus_id)
+            .fetch_all(state.read_conn())).await,
+    };
+    let stored = match stored {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let mut deltas = Vec::new();
+    let mut skipped = 0;
+    for calculation in &stored {
+        // Rows saved before the inputs were recorded can't be re-priced.
+        let inputs = match calculation.pricing_inputs() {
+            Some(val) => val,
+            None => {
+                skipped += 1;
+                continue;
+            }
+        };
+        match rates.get(&(inputs.student_studies.to_string(), inputs.student_type.to_string())) {
+            Some(costs) => deltas.push(pricing::tuition_total(inputs.num_credits, inputs.orientation, costs, orientation_fee) - Money::new(calculation.tuition_cost)),
+            None => skipped += 1,
+        }
+    }
+
+    let sum: Money = deltas.iter().sum();
+    let result = SimulationResult {
+        repriced: deltas.len(),
+        skipped,
+        min_delta: deltas.iter().min().copied().unwrap_or_default(),
+        avg_delta: if deltas.is_empty() { sum } else { Money::new((sum.amount() / Decimal::from(deltas.len())).round_dp(2)) },
+        max_delta: deltas.iter().max().copied().unwrap_or_default(),
+    };
+
+    render(&state, "admin_simulate", &SimulatePage { form: params.into_inner(), result: Some(result) }).await
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MaintenanceFormParams {
+    #[serde(default, deserialize_with = "form::checkbox")]
+    enabled: bool,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    message: Option<String>,
+}
+
+pub async fn maintenance_form(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    render(&state, "admin_maintenance", &state.maintenance()).await
+}
+
+pub async fn update_maintenance(state: web::Data<AppState>, params: web::Form<MaintenanceFormParams>) -> Result<HttpResponse, AppError> {
+    let updated = Maintenance {
+        enabled: params.enabled,
+        message: params.message.clone(),
+    };
+
+    match maintenance::save(&state.conn, &updated).await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    println!("Maintenance mode is now {}.", if updated.enabled { "on" } else { "off" });
+    state.set_maintenance(updated);
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/maintenance"))
+        .finish())
+}
+
+#[derive(Serialize)]
+struct RecordPage {
+    record: TuitionRecord,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EditRecordFormParams {
+    first_name: Option<String>,
+    last_name: Option<String>,
+    tuition_cost: Option<String>,
+}
+
+async fn fetch_record(state: &AppState, campus: &Campus, id: TuitionRecordId) -> Result<TuitionRecord, AppError> {
+    match queries::RECORD_BY_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, TuitionRecord>(sql)
+    .bind(id)
+    .bind(campus_id)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => Ok(val),
+        Ok(None) => Err(AppError::NotFound(format!("Tuition record {} doesn't exist.", id))),
+        Err(why) => Err(AppError::from(why)),
+    }
+}
+
+fn redirect_to_record(id: TuitionRecordId) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", format!("/admin/records/{}", id)))
+        .finish()
+}
+
+pub async fn record(state: web::Data<AppState>, campus: Campus, id: web::Path<TuitionRecordId>) -> Result<HttpResponse, AppError> {
+    let record = fetch_record(&state, &campus, id.into_inner()).await?;
+    render(&state, "admin_record", &RecordPage { record }).await
+}
+
+// Fix a typo'd name or a wrong total. Every change goes in the audit log.
+pub async fn edit_record(state: web::Data<AppState>, campus: Campus, req: HttpRequest, id: web::Path<TuitionRecordId>, params: web::Form<EditRecordFormParams>) -> Result<HttpResponse, AppError> {
+    let record = fetch_record(&state, &campus, id.into_inner()).await?;
+    let first_name = match &params.first_name {
+        Some(val) => normalize_name("first_name", val)?,
+        None => record.first_name.clone(),
+    };
+    let last_name = match &params.last_name {
+        Some(val) => normalize_name("last_name", val)?,
+        None => record.last_name.clone(),
+    };
+    let tuition_cost = match optional_amount("tuition_cost", &params.tuition_cost)? {
+        Some(val) => val,
+        None => record.tuition_cost,
+    };
+    let actor = audit::actor(&req);
+    // The change and its audit entry go in together or not at all.
+    let mut tx = state.conn.begin().await?;
+
+    if first_name != record.first_name || last_name != record.last_name {
+        // Names are unique per campus; fixing one into a name that's taken is a duplicate, not a typo.
+        match queries::OTHER_STUDENT_WITH_NAME.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, i32>(sql)
+        .bind(campus_id)
+        .bind(&first_name)
+        .bind(&last_name)
+        .bind(record.student_id)
+        .fetch_optional(&mut tx)).await {
+            Ok(None) => {},
+            Ok(Some(other)) => {
+                return Err(AppError::validation("last_name", &format!("Student #{} already has the name {} {}.", other, first_name, last_name)));
+            }
+            Err(why) => {
+                return Err(AppError::from(why));
+            }
+        };
+
+        match queries::RENAME_STUDENT.run(|sql| sqlx::query(sql)
+        .bind(&first_name)
+        .bind(&last_name)
+        .bind(record.student_id)
+        .execute(&mut tx))
+        .await {
+            Ok(_val) => {},
+            Err(why) => {
+                return Err(AppError::from(why));
+            }
+        };
+        let details = format!("Name: {} {} -> {} {}", record.first_name, record.last_name, first_name, last_name);
+        if let Err(why) = audit::record(&mut tx, &actor, "update", "Student", record.student_id.0, &details).await {
+            return Err(AppError::from(why));
+        }
+    }
+
+    if tuition_cost != record.tuition_cost {
+        match queries::UPDATE_TUITION_COST.run(|sql| sqlx::query(sql)
+        .bind(tuition_cost)
+        .bind(record.id)
+        .execute(&mut tx))
+        .await {
+            Ok(_val) => {},
+            Err(why) => {
+                return Err(AppError::from(why));
+            }
+        };
+        let details = format!("TuitionCost: {} -> {}", record.tuition_cost, tuition_cost);
+        if let Err(why) = audit::record(&mut tx, &actor, "update", "TuitionRecord", record.id.0, &details).await {
+            return Err(AppError::from(why));
+        }
+    }
+
+    tx.commit().await?;
+    Ok(redirect_to_record(record.id))
+}
+
+pub async fn delete_record(state: web::Data<AppState>, campus: Campus, req: HttpRequest, id: web::Path<TuitionRecordId>) -> Result<HttpResponse, AppError> {
+    let record = fetch_record(&state, &campus, id.into_inner()).await?;
+    let mut tx = state.conn.begin().await?;
+
+    match queries::DELETE_TUITION_RECORD.run(|sql| sqlx::query(sql)
+    .bind(record.id)
+    .execute(&mut tx))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    // Keep enough in the log to put the row back by hand.
+    let details = format!("Deleted {} {} (student #{}), term {}, tuition {}",
+        record.first_name, record.last_name, record.student_id, record.term.as_deref().unwrap_or("-"), record.tuition_cost);
+    if let Err(why) = audit::record(&mut tx, &audit::actor(&req), "delete", "TuitionRecord", record.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    tx.commit().await?;
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin"))
+        .finish())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RecordSearchParams {
+    name: Option<String>,
+    term: Option<String>,
+    residency: Option<String>,
+    studies: Option<String>,
+    min_amount: Option<String>,
+    max_amount: Option<String>,
+    // YYYY-MM-DD, both inclusive.
+    from: Option<String>,
+    to: Option<String>,
+    sort: Option<String>,
+    order: Option<String>,
+    page: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct RecordSearchRow {
+    id: TuitionRecordId,
+    student_public_id: String,
+    first_name: String,
+    last_name: String,
+    term: Option<String>,
+    tuition_cost: Decimal,
+    num_credits: Option<u8>,
+    student_type: Option<String>,
+    student_studies: Option<String>,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+struct SortLink {
+    label: &'static str,
+    url: String,
+    // "ascending" or "descending" on the column the results are sorted by, for aria-sort.
+    sorted: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct RecordsPage {
+    form: RecordSearchParams,
+    records: Vec<RecordSearchRow>,
+    total: i64,
+    page: i64,
+    pages: i64,
+    sort_links: Vec<SortLink>,
+    prev_url: Option<String>,
+    next_url: Option<String>,
+    residencies: [&'static str; 3],
+    studies: [&'static str; 3],
+}
+
+const RECORDS_PER_PAGE: i64 = 50;
+
+// The columns the results can be sorted by; only these fragments ever reach the order by clause.
+const SORT_COLUMNS: [(&str, &str, &str); 6] = [
+    ("name", "Name", "LastName"),
+    ("term", "Term", "Term"),
+    ("residency", "Residency", "StudentType"),
+    ("studies", "Studies", "StudentStudies"),
+    ("amount", "Tuition", "TuitionCost"),
+    ("updated", "Calculated", "UpdatedAt"),
+];
+
+// Blank filters are the same as leaving them out.
+fn filter_value(value: &Option<String>) -> Option<String> {
+    match value {
+        Some(val) if !val.trim().is_empty() => Some(val.trim().to_string()),
+        _ => None,
+    }
+}
+
+fn filter_date(field: &'static str, value: &Option<String>) -> Result<Option<NaiveDate>, AppError> {
+    match filter_value(value) {
+        Some(val) => match NaiveDate::parse_from_str(&val, "%Y-%m-%d") {
+            Ok(date) => Ok(Some(date)),
+            Err(_) => Err(AppError::validation(field, &format!("\"{}\" is not a valid date.", val))),
+        },
+        None => Ok(None),
+    }
+}
+
+// The search page with one thing changed, e.g. the sort column or the page number.
+fn records_url(form: &RecordSearchParams, change: impl FnOnce(&mut RecordSearchParams)) -> String {
+    let mut form = form.clone();
+    change(&mut form);
+    format!("/admin/records?{}", serde_urlencoded::to_string(&form).unwrap_or_default())
+}
+
+// Every tuition record on the campus, narrowed down by whichever filters are filled in.
+pub async fn records(state: web::Data<AppState>, campus: Campus, params: web::Query<RecordSearchParams>) -> Result<HttpResponse, AppError> {
+    let form = params.into_inner();
+
+    let name = match filter_value(&form.name) {
+        Some(val) => Some(filters::contains(&normalize_name("name", &val)?)),
+        None => None,
+    };
+    let term = filter_value(&form.term);
+    let residency = filter_value(&form.residency);
+    if let Some(val) = &residency {
+        if !fees::RESIDENCIES.contains(&val.as_str()) {
+            return Err(AppError::validation("residency", "Residency must be resident, nonresident, or international."));
+        }
+    }
+    let studies = filter_value(&form.studies);
+    if let Some(val) = &studies {
+        if !fees::STUDIES.contains(&val.as_str()) {
+            return Err(AppError::validation("studies", "Studies must be undergraduate, graduate, or dual enrollment."));
+        }
+    }
+    let min_amount = optional_amount("min_amount", &form.min_amount)?;
+    let max_amount = optional_amount("max_amount", &form.max_amount)?;
+    let from = filter_date("from", &form.from)?;
+    let to = filter_date("to", &form.to)?;
+
+    let sort = match filter_value(&form.sort) {
+        Some(val) => match SORT_COLUMNS.iter().find(|(key, _, _)| *key == val) {
+            Some(column) => *column,
+            None => {
+                return Err(AppError::validation("sort", &format!("Records can't be sorted by \"{}\".", val)));
+            }
+        },
+        None => SORT_COLUMNS[5],
+    };
+    let descending = match filter_value(&form.order).as_deref() {
+        Some("asc") => false,
+        Some("desc") => true,
+        Some(val) => {
+            return Err(AppError::validation("order", &format!("\"{}\" is not asc or desc.", val)));
+        }
+        // Newest first, everything else A to Z.
+        None => sort.0 == "updated",
+    };
+    let page = match filter_value(&form.page) {
+        Some(val) => match val.parse::<i64>() {
+            Ok(page) if page >= 1 => page,
+            _ => {
+                return Err(AppError::validation("page", &format!("\"{}\" is not a valid page.", val)));
+            }
+        },
+        None => 1,
+    };
+
+    // The count and the page of results share the same conditions.
+    let conditions = |select: &str| {
+        let mut conditions = filters::Conditions::new(select);
+        conditions
+            .and("CampusId = ", campus.id)
+            .and_some("concat(FirstName, ' ', LastName) like ", name.clone())
+            .and_some("Term = ", term.clone())
+            .and_some("StudentType = ", residency.clone())
+            .and_some("StudentStudies = ", studies.clone())
+            .and_some("TuitionCost >= ", min_amount)
+            .and_some("TuitionCost <= ", max_amount)
+            .and_some("UpdatedAt >= ", from)
+            .and_some("UpdatedAt < ", to.and_then(|date| date.succ_opt()));
+        conditions
+    };
+
+    let total = match queries::SEARCH_RECORDS_COUNT.time(conditions(queries::SEARCH_RECORDS_COUNT.sql)
+    .finish()
+    .build_query_as::<(i64,)>()
+    .fetch_one(state.read_conn())).await {
+        Ok(val) => val.0,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let mut query = conditions(queries::SEARCH_RECORDS.sql).finish();
+    let direction = if descending { " desc" } else { " asc" };
+    query.push(" order by ").push(sort.2).push(direction)
+        .push(", TuitionRecords.Id").push(direction)
+        .push(" limit ").push_bind(RECORDS_PER_PAGE)
+        .push(" offset ").push_bind((page - 1) * RECORDS_PER_PAGE);
+    let records = match queries::SEARCH_RECORDS.time(query.build_query_as::<RecordSearchRow>().fetch_all(state.read_conn())).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let sort_links = SORT_COLUMNS.iter().map(|(key, label, _)| {
+        let current = *key == sort.0;
+        SortLink {
+            label,
+            // Clicking the current column flips it; any other starts ascending.
+            url: records_url(&form, |form| {
+                form.sort = Some(key.to_string());
+                form.order = Some(if current && !descending { "desc" } else { "asc" }.to_string());
+                form.page = None;
+            }),
+            sorted: match (current, descending) {
+                (true, true) => Some("descending"),
+                (true, false) => Some("ascending"),
+                _ => None,
+            },
+        }
+    }).collect();
+
+    let pages = ((total + RECORDS_PER_PAGE - 1) / RECORDS_PER_PAGE).max(1);
+    let prev_url = if page > 1 { Some(records_url(&form, |form| form.page = Some((page - 1).to_string()))) } else { None };
+    let next_url = if page < pages { Some(records_url(&form, |form| form.page = Some((page + 1).to_string()))) } else { None };
+
+    render(&state, "admin_records", &RecordsPage {
+        form,
+        records,
+        total,
+        page,
+        pages,
+        sort_links,
+        prev_url,
+        next_url,
+        residencies: fees::RESIDENCIES,
+        studies: fees::STUDIES,
+    }).await
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct RefundScheduleRow {
+    id: RefundRuleId,
+    term: String,
+    starts_on: NaiveDate,
+    through_week: u32,
+    refund_percent: Decimal,
+}
+
+#[derive(Serialize)]
+struct RefundsPage {
+    rules: Vec<RefundScheduleRow>,
+    // Rules in the database aren't used while rates come from FEE_SCHEDULE_FILE.
+    from_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddRefundRuleFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    term: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    starts_on: Option<String>,
+    #[serde(default, deserialize_with = "form::optional")]
+    through_week: Option<form::Bounded<1, { u32::MAX }>>,
+    refund_percent: Option<String>,
+}
+
+pub async fn refunds(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let rules = match queries::REFUND_SCHEDULE.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, RefundScheduleRow>(sql)
+    .bind(campus_id)
+    .fetch_all(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    render(&state, "admin_refunds", &RefundsPage { rules, from_file: state.fee_schedule.is_some() }).await
+}
+
+// Add a week to a term's refund schedule, creating the term if needed. Re-adding a week replaces its percentage.
+pub async fn add_refund_rule(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<AddRefundRuleFormParams>) -> Result<HttpResponse, AppError> {
+    let term = match &params.term {
+        Some(val) if val.len() <= 32 => val.clone(),
+        _ => {
+            return Err(AppError::validation("term", "A term name like \"Fall 2026\" is required."));
+        }
+    };
+    let starts_on = match &params.starts_on {
+        Some(val) => match NaiveDate::parse_from_str(val, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                return Err(AppError::validation("starts_on", &format!("\"{}\" is not a valid date.", val)));
+            }
+        },
+        None => {
+            return Err(AppError::validation("starts_on", "No term start date was provided!"));
+        }
+    };
+    let through_week = match &params.through_week {
+        Some(val) => match val.value() {
+            Ok(week) => week,
+            Err(why) => {
+                return Err(AppError::validation("through_week", &format!("Week: {}", why)));
+            }
+        },
+        None => {
+            return Err(AppError::validation("through_week", "No week was provided!"));
+        }
+    };
+    let refund_percent = match optional_amount("refund_percent", &params.refund_percent)? {
+        Some(val) if val <= Decimal::ONE_HUNDRED => val,
+        _ => {
+            return Err(AppError::validation("refund_percent", "The refund must be between 0 and 100 percent."));
+        }
+    };
+
+    match queries::UPSERT_TERM_START.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(&term)
+    .bind(starts_on)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let term_id = match queries::TERM_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, i32>(sql)
+    .bind(campus_id)
+    .bind(&term)
+    .fetch_one(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    match queries::UPSERT_REFUND_RULE.run(|sql| sqlx::query(sql)
+    .bind(term_id)
+    .bind(through_week)
+    .bind(refund_percent)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("{} (starts {}): refund {}% through week {}", term, starts_on, refund_percent, through_week);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "update", "Term", term_id, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/refunds"))
+        .finish())
+}
+
+pub async fn delete_refund_rule(state: web::Data<AppState>, campus: Campus, req: HttpRequest, id: web::Path<RefundRuleId>) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let rule = match queries::REFUND_RULE_BY_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, RefundScheduleRow>(sql)
+    .bind(id)
+    .bind(campus_id)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(AppError::NotFound(format!("Refund rule {} doesn't exist.", id)));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    match queries::DELETE_REFUND_RULE.run(|sql| sqlx::query(sql)
+    .bind(rule.id)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Deleted {}: refund {}% through week {}", rule.term, rule.refund_percent, rule.through_week);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "delete", "RefundRule", rule.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/refunds"))
+        .finish())
+}
+
+#[derive(Serialize)]
+struct TermsPage {
+    terms: Vec<TermWindow>,
+    // Terms in the database aren't used while rates come from FEE_SCHEDULE_FILE.
+    from_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetTermFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    term: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    starts_on: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    opens_on: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    closes_on: Option<String>,
+}
+
+fn optional_date(field: &'static str, value: &Option<String>) -> Result<Option<NaiveDate>, AppError> {
+    match value {
+        Some(val) => match NaiveDate::parse_from_str(val, "%Y-%m-%d") {
+            Ok(date) => Ok(Some(date)),
+            Err(_) => Err(AppError::validation(field, &format!("\"{}\" is not a valid date.", val))),
+        },
+        None => Ok(None),
+    }
+}
+
+// When students can make estimates for each term. Counselors can calculate for any term.
+pub async fn terms(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let terms = state.term_windows(&campus).await?;
+    render(&state, "admin_terms", &TermsPage { terms, from_file: state.fee_schedule.is_some() }).await
+}
+
+// Set a term's start date and estimate window, creating the term if needed. A blank open or
+// close date leaves that end of the window open.
+pub async fn set_term(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<SetTermFormParams>) -> Result<HttpResponse, AppError> {
+    let term = match &params.term {
+        Some(val) if val.len() <= 32 => val.clone(),
+        _ => {
+            return Err(AppError::validation("term", "A term name like \"Fall 2026\" is required."));
+        }
+    };
+    let starts_on = match optional_date("starts_on", &params.starts_on)? {
+        Some(val) => val,
+        None => {
+            return Err(AppError::validation("starts_on", "No term start date was provided!"));
+        }
+    };
+    let opens_on = optional_date("opens_on", &params.opens_on)?;
+    let closes_on = optional_date("closes_on", &params.closes_on)?;
+    if let (Some(opens_on), Some(closes_on)) = (opens_on, closes_on) {
+        if closes_on < opens_on {
+            return Err(AppError::validation("closes_on", "Estimates can't close before they open."));
+        }
+    }
+
+    match queries::UPSERT_TERM.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(&term)
+    .bind(starts_on)
+    .bind(opens_on)
+    .bind(closes_on)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let term_id = match queries::TERM_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, i32>(sql)
+    .bind(campus_id)
+    .bind(&term)
+    .fetch_one(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let describe = |date: Option<NaiveDate>| date.map(|val| val.to_string()).unwrap_or_else(|| "-".to_string());
+    let details = format!("{} (starts {}): estimates open {} through {}", term, starts_on, describe(opens_on), describe(closes_on));
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "update", "Term", term_id, &details).await {
+        return Err(AppError::from(why));
+    }
+    // The calculator page shows whether the term is open.
+    state.index_cache.clear().await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/terms"))
+        .finish())
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct LineItemRow {
+    id: LineItemId,
+    term: String,
+    label: String,
+    amount: Decimal,
+    applies_when: String,
+    category: pricing::FeeCategory,
+}
+
+#[derive(Serialize)]
+struct CategoryChoice {
+    value: &'static str,
+    label: &'static str,
+}
+
+#[derive(Serialize)]
+struct LineItemsPage {
+    line_items: Vec<LineItemRow>,
+    categories: Vec<CategoryChoice>,
+    // Line items in the database aren't used while rates come from FEE_SCHEDULE_FILE.
+    from_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddLineItemFormParams {
+    term: Option<String>,
+    label: Option<String>,
+    amount: Option<String>,
+    applies_when: Option<String>,
+    category: Option<String>,
+}
+
+pub async fn line_items(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let line_items = match queries::ADMIN_LINE_ITEMS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, LineItemRow>(sql)
+    .bind(campus_id)
+    .fetch_all(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let categories = pricing::FeeCategory::ALL.iter().map(|category| CategoryChoice { value: category.as_str(), label: category.label() }).collect();
+    render(&state, "admin_line_items", &LineItemsPage { line_items, categories, from_file: state.fee_schedule.is_some() }).await
+}
+
+// Add a one-off charge to a term. The rule is checked here so a typo can't break calculations.
+pub async fn add_line_item(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<AddLineItemFormParams>) -> Result<HttpResponse, AppError> {
+    let term = match &params.term {
+        Some(val) if !val.trim().is_empty() && val.trim().len() <= 32 => val.trim().to_string(),
+        _ => {
+            return Err(AppError::validation("term", "A term name like \"Fall 2026\" is required."));
+        }
+    };
+    let label = match &params.label {
+        Some(val) if !val.trim().is_empty() && val.trim().chars().count() <= 255 => val.trim().to_string(),
+        _ => {
+            return Err(AppError::validation("label", "A description of the charge is required."));
+        }
+    };
+    let amount = match optional_amount("amount", &params.amount)? {
+        Some(val) => val,
+        None => {
+            return Err(AppError::validation("amount", "No amount was provided!"));
+        }
+    };
+    let applies_when = params.applies_when.as_deref().unwrap_or_default().trim().to_string();
+    if applies_when.len() > 255 {
+        return Err(AppError::validation("applies_when", "Rules can be at most 255 characters."));
+    }
+    if let Err(why) = rules::parse(&applies_when) {
+        return Err(AppError::validation("applies_when", &why));
+    }
+    // Older forms didn't send a category.
+    let category = match params.category.as_deref() {
+        None | Some("") => pricing::FeeCategory::default(),
+        Some(val) => match pricing::FeeCategory::parse(val) {
+            Some(category) => category,
+            None => {
+                return Err(AppError::validation("category", &format!("\"{}\" isn't a category.", val)));
+            }
+        },
+    };
+
+    let id = match queries::INSERT_LINE_ITEM.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(&term)
+    .bind(&label)
+    .bind(amount)
+    .bind(&applies_when)
+    .bind(category)
+    .execute(&state.conn))
+    .await {
+        Ok(val) => val.last_insert_id() as i32,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("{}: {} of {} under {} when \"{}\"", term, label, amount, category.label(), applies_when);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "create", "CustomLineItem", id, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/line-items"))
+        .finish())
+}
+
+pub async fn delete_line_item(state: web::Data<AppState>, campus: Campus, req: HttpRequest, id: web::Path<LineItemId>) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let line_item = match queries::LINE_ITEM_BY_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, LineItemRow>(sql)
+    .bind(id)
+    .bind(campus_id)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(AppError::NotFound(format!("Line item {} doesn't exist.", id)));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    match queries::DELETE_LINE_ITEM.run(|sql| sqlx::query(sql)
+    .bind(line_item.id)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Deleted {}: {} of {}", line_item.term, line_item.label, line_item.amount);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "delete", "CustomLineItem", line_item.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/line-items"))
+        .finish())
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct ValidationRuleRow {
+    id: ValidationRuleId,
+    field: String,
+    reject_when: String,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ValidationRulesPage {
+    validation_rules: Vec<ValidationRuleRow>,
+    fields: &'static [&'static str],
+    // Rules in the database aren't used while rates come from FEE_SCHEDULE_FILE.
+    from_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddValidationRuleFormParams {
+    field: Option<String>,
+    reject_when: Option<String>,
+    message: Option<String>,
+}
+
+pub async fn validation_rules(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let validation_rules = match queries::ADMIN_VALIDATION_RULES.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, ValidationRuleRow>(sql)
+    .bind(campus_id)
+    .fetch_all(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    render(&state, "admin_validation_rules", &ValidationRulesPage { validation_rules, fields: &rules::FIELDS, from_file: state.fee_schedule.is_some() }).await
+}
+
+// Turn away a combination of answers. Like line item rules, the rule is checked here so a typo
+// can't break calculations.
+pub async fn add_validation_rule(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<AddValidationRuleFormParams>) -> Result<HttpResponse, AppError> {
+    let field = params.field.as_deref().unwrap_or_default().trim().to_string();
+    let reject_when = params.reject_when.as_deref().unwrap_or_default().trim().to_string();
+    if reject_when.len() > 255 {
+        return Err(AppError::validation("reject_when", "Rules can be at most 255 characters."));
+    }
+    if let Err(why) = rules::check_validation_rule(&field, &reject_when) {
+        let field_name = if rules::field(&field).is_none() { "field" } else { "reject_when" };
+        return Err(AppError::validation(field_name, &why));
+    }
+    let message = match &params.message {
+        Some(val) if !val.trim().is_empty() && val.trim().chars().count() <= 255 => val.trim().to_string(),
+        _ => {
+            return Err(AppError::validation("message", "A message telling students what to change is required."));
+        }
+    };
+
+    let id = match queries::INSERT_VALIDATION_RULE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(&field)
+    .bind(&reject_when)
+    .bind(&message)
+    .execute(&state.conn))
+    .await {
+        Ok(val) => val.last_insert_id() as i32,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Reject when \"{}\": {} ({})", reject_when, message, field);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "create", "ValidationRule", id, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/validation-rules"))
+        .finish())
+}
+
+pub async fn delete_validation_rule(state: web::Data<AppState>, campus: Campus, req: HttpRequest, id: web::Path<ValidationRuleId>) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let validation_rule = match queries::VALIDATION_RULE_BY_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, ValidationRuleRow>(sql)
+    .bind(id)
+    .bind(campus_id)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(AppError::NotFound(format!("Validation rule {} doesn't exist.", id)));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    match queries::DELETE_VALIDATION_RULE.run(|sql| sqlx::query(sql)
+    .bind(validation_rule.id)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Deleted reject when \"{}\": {}", validation_rule.reject_when, validation_rule.message);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "delete", "ValidationRule", validation_rule.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/validation-rules"))
+        .finish())
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct OrientationExemptionRow {
+    id: OrientationExemptionId,
+    label: String,
+    exempt_when: String,
+}
+
+#[derive(Serialize)]
+struct OrientationExemptionsPage {
+    orientation_exemptions: Vec<OrientationExemptionRow>,
+    // Exemptions in the database aren't used while rates come from FEE_SCHEDULE_FILE.
+    from_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddOrientationExemptionFormParams {
+    label: Option<String>,
+    exempt_when: Option<String>,
+}
+
+pub async fn orientation_exemptions(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let orientation_exemptions = match queries::ADMIN_ORIENTATION_EXEMPTIONS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, OrientationExemptionRow>(sql)
+    .bind(campus_id)
+    .fetch_all(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    render(&state, "admin_orientation_exemptions", &OrientationExemptionsPage { orientation_exemptions, from_file: state.fee_schedule.is_some() }).await
+}
+
+// Exempt a group of students from the orientation fee, checked here like the other rules.
+pub async fn add_orientation_exemption(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<AddOrientationExemptionFormParams>) -> Result<HttpResponse, AppError> {
+    let exempt_when = params.exempt_when.as_deref().unwrap_or_default().trim().to_string();
+    if exempt_when.len() > 255 {
+        return Err(AppError::validation("exempt_when", "Rules can be at most 255 characters."));
+    }
+    if let Err(why) = rules::check_orientation_exemption(&exempt_when) {
+        return Err(AppError::validation("exempt_when", &why));
+    }
+    let label = match &params.label {
+        Some(val) if !val.trim().is_empty() && val.trim().chars().count() <= 255 => val.trim().to_string(),
+        _ => {
+            return Err(AppError::validation("label", "A label saying who is exempt is required."));
+        }
+    };
+
+    let id = match queries::INSERT_ORIENTATION_EXEMPTION.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(&label)
+    .bind(&exempt_when)
+    .execute(&state.conn))
+    .await {
+        Ok(val) => val.last_insert_id() as i32,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Exempt from orientation when \"{}\": {}", exempt_when, label);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "create", "OrientationExemption", id, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/orientation-exemptions"))
+        .finish())
+}
+
+pub async fn delete_orientation_exemption(state: web::Data<AppState>, campus: Campus, req: HttpRequest, id: web::Path<OrientationExemptionId>) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let exemption = match queries::ORIENTATION_EXEMPTION_BY_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, OrientationExemptionRow>(sql)
+    .bind(id)
+    .bind(campus_id)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(AppError::NotFound(format!("Orientation exemption {} doesn't exist.", id)));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    match queries::DELETE_ORIENTATION_EXEMPTION.run(|sql| sqlx::query(sql)
+    .bind(exemption.id)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Deleted exemption from orientation when \"{}\": {}", exemption.exempt_when, exemption.label);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "delete", "OrientationExemption", exemption.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/orientation-exemptions"))
+        .finish())
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct ReciprocityAgreementRow {
+    id: ReciprocityAgreementId,
+    state: String,
+    label: String,
+    residency: String,
+    nonresidency_fee_percent: Decimal,
+}
+
+#[derive(Serialize)]
+struct ReciprocityPage {
+    campus_state: Option<String>,
+    agreements: Vec<ReciprocityAgreementRow>,
+    states: &'static [residency::HomeState],
+    // Agreements in the database aren't used while rates come from FEE_SCHEDULE_FILE.
+    from_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddReciprocityFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    state: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    label: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    residency: Option<String>,
+    #[serde(default, deserialize_with = "form::optional")]
+    nonresidency_fee_percent: Option<Decimal>,
+}
+
+pub async fn reciprocity(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let agreements = match queries::ADMIN_RECIPROCITY_AGREEMENTS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, ReciprocityAgreementRow>(sql)
+    .bind(campus_id)
+    .fetch_all(&state.conn)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    render(&state, "admin_reciprocity", &ReciprocityPage {
+        campus_state: campus.state.clone(),
+        agreements,
+        states: &residency::STATES,
+        from_file: state.fee_schedule.is_some(),
+    }).await
+}
+
+// Add an agreement with a state, or replace the one it already has.
+pub async fn set_reciprocity(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<AddReciprocityFormParams>) -> Result<HttpResponse, AppError> {
+    let label = match &params.label {
+        Some(val) if val.chars().count() <= 255 => val.clone(),
+        _ => {
+            return Err(AppError::validation("label", "A label naming the agreement is required."));
+        }
+    };
+    let agreement = ReciprocityAgreement {
+        state: params.state.as_deref().and_then(residency::find).map(|val| val.code.to_string()).unwrap_or_default(),
+        label,
+        residency: params.residency.clone().unwrap_or_default(),
+        nonresidency_fee_percent: params.nonresidency_fee_percent.unwrap_or(Decimal::ONE_HUNDRED),
+    };
+    if agreement.state.is_empty() {
+        return Err(AppError::validation("state", "Pick the state the agreement is with."));
+    }
+    if campus.state.as_deref() == Some(agreement.state.as_str()) {
+        return Err(AppError::validation("state", "Students from the campus's own state already pay resident rates."));
+    }
+    if let Err(why) = fees::check_reciprocity(&agreement) {
+        return Err(AppError::validation("residency", &why));
+    }
+
+    let id = match queries::UPSERT_RECIPROCITY_AGREEMENT.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(&agreement.state)
+    .bind(&agreement.label)
+    .bind(&agreement.residency)
+    .bind(agreement.nonresidency_fee_percent)
+    .execute(&state.conn))
+    .await {
+        Ok(val) => val.last_insert_id() as i32,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Students from {} pay {} rates and {}% of the non-residency fee: {}",
+        agreement.state, agreement.residency, agreement.nonresidency_fee_percent.normalize(), agreement.label);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "set", "ReciprocityAgreement", id, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/reciprocity"))
+        .finish())
+}
+
+pub async fn delete_reciprocity(state: web::Data<AppState>, campus: Campus, req: HttpRequest, id: web::Path<ReciprocityAgreementId>) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let agreement = match queries::RECIPROCITY_AGREEMENT_BY_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, ReciprocityAgreementRow>(sql)
+    .bind(id)
+    .bind(campus_id)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(AppError::NotFound(format!("Reciprocity agreement {} doesn't exist.", id)));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    match queries::DELETE_RECIPROCITY_AGREEMENT.run(|sql| sqlx::query(sql)
+    .bind(agreement.id)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Deleted the agreement with {}: {}", agreement.state, agreement.label);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "delete", "ReciprocityAgreement", agreement.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/reciprocity"))
+        .finish())
+}
+
+#[derive(Serialize)]
+struct RateIncreasePage {
+    annual_percent: Decimal,
+    // The database value isn't used while rates come from FEE_SCHEDULE_FILE.
+    from_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RateIncreaseFormParams {
+    annual_percent: Option<String>,
+}
+
+pub async fn rate_increase(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let annual_percent = state.annual_rate_increase(&campus).await?;
+    render(&state, "admin_rate_increase", &RateIncreasePage { annual_percent, from_file: state.fee_schedule.is_some() }).await
+}
+
+// What multi-year projections assume rates go up by each year.
+pub async fn set_rate_increase(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<RateIncreaseFormParams>) -> Result<HttpResponse, AppError> {
+    let annual_percent = match optional_amount("annual_percent", &params.annual_percent)? {
+        Some(val) if val <= Decimal::ONE_HUNDRED => val.round_dp(2),
+        _ => {
+            return Err(AppError::validation("annual_percent", "The increase must be a percent from 0 to 100."));
+        }
+    };
+
+    match queries::SET_RATE_INCREASE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(annual_percent)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+    let details = format!("Projections assume rates go up {}% a year", annual_percent);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "update", "RateIncrease", campus.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/rate-increase"))
+        .finish())
+}
+
+// The student calculator, for a counselor entering an estimate on a student's behalf. The
+// receipt records the counselor, so the history shows who entered it.
+pub async fn calculate_form(state: web::Data<AppState>, campus: Campus, req: HttpRequest, session: Session) -> Result<HttpResponse, AppError> {
+    let recent_receipts = state.recent_receipts(&campus, &session).await?;
+    let fields = state.form_fields(&campus).await?;
+    render(&state, "index", &IndexPage {
+        campus,
+        form: None,
+        scenario_name: None,
+        captcha: None,
+        can_email: state.mailer.is_some(),
+        recent_receipts,
+        errors: None,
+        counselor: Some(audit::actor(&req)),
+        term_notice: None,
+        consent: state.consent.clone(),
+        fields,
+        states: &residency::STATES,
+    }).await
+}
+
+pub async fn calculate(state: web::Data<AppState>, campus: Campus, req: HttpRequest, session: Session, params: web::Form<CalculateTuitionFormParams>) -> Result<HttpResponse, AppError> {
+    let form = params.into_inner();
+    let counselor = audit::actor(&req);
+    match estimate(&state, campus.clone(), &req, &session, &form, Some(&counselor), Channel::Counselor).await {
+        Err(why @ (AppError::Validation { .. } | AppError::Rejected(_))) => form_with_errors(&state, campus, &session, form, None, Some(counselor), &why).await,
+        result => result,
+    }
+}
@@ -0,0 +1,671 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method},
+    middleware::Next,
+    web, HttpRequest, HttpResponse, ResponseError, Result,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{error::{AppError, ErrorCode, FieldError, RequestId}, fees, form, logs, metrics, models::{ApiKey, Campus, CampusId, CourseFee, FlatFee, TuitionCosts, TuitionRecord}, money::Money, pricing, queries, receipts, residency, studies::StudentStudies, AppState, LookupFormParams, TypeSafeLookupFormParams, DUAL_ENROLLMENT_MAX_CREDITS, MAX_COURSE_CODES, MAX_NAME_LENGTH};
+
+// `code` is stable for clients to branch on; `error` is for people.
+#[derive(Serialize)]
+struct ApiError {
+    code: ErrorCode,
+    error: String,
+    // For invalid requests, each field that was wrong, so clients can show the message next to
+    // the input.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+fn api_error(mut response: actix_web::HttpResponseBuilder, code: ErrorCode, message: &str) -> HttpResponse {
+    response.json(ApiError { code, error: message.to_string(), errors: Vec::new() })
+}
+
+fn field_error(field: &'static str, message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiError {
+        code: ErrorCode::InvalidRequest,
+        error: message.to_string(),
+        errors: vec![FieldError { field, message: message.to_string() }],
+    })
+}
+
+// A handler error as the API reports it; validation errors name their field.
+fn app_error(why: &AppError) -> HttpResponse {
+    match why {
+        AppError::Validation { field, message } => field_error(field, message),
+        AppError::Rejected(errors) => HttpResponse::BadRequest().json(ApiError {
+            code: ErrorCode::InvalidRequest,
+            error: why.user_message(),
+            errors: errors.clone(),
+        }),
+        why => api_error(HttpResponse::build(why.status_code()), why.code(), &why.user_message()),
+    }
+}
+
+// Failures on our side are logged in full, tagged with the request ID the client gets back in
+// the X-Request-ID header. The response itself only ever carries generic text.
+fn log_failure(request_id: &RequestId, line: &str) {
+    logs::throttled_as(line, &format!("[{}] {}", request_id, line));
+}
+
+// Keys are only stored hashed, so a leaked database dump can't be used to call the API.
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+// The caller's key from `Authorization: Bearer <key>`, counted against its rate limit. None when
+// there's no Authorization header; a bad key or one over its limit is the response to send.
+pub async fn api_key(state: &AppState, req: &HttpRequest) -> Result<Option<ApiKey>, HttpResponse> {
+    let key = match req.headers().get(header::AUTHORIZATION).and_then(|val| val.to_str().ok()) {
+        Some(val) => match val.strip_prefix("Bearer ") {
+            Some(key) => key.trim().to_string(),
+            None => {
+                return Err(api_error(HttpResponse::Unauthorized(), ErrorCode::Unauthorized, "Authorization must use the Bearer scheme."));
+            }
+        },
+        None => {
+            return Ok(None);
+        }
+    };
+
+    let api_key = match queries::API_KEY_BY_HASH.run(|sql| sqlx::query_as::<_, ApiKey>(sql)
+    .bind(hash_key(&key))
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(api_error(HttpResponse::Unauthorized(), ErrorCode::Unauthorized, "Invalid or revoked API key."));
+        }
+        Err(why) => {
+            log_failure(&RequestId::of(req), &format!("Error while checking API key: {:?}", why));
+            return Err(api_error(HttpResponse::InternalServerError(), ErrorCode::DatabaseError, "Error while checking API key."));
+        }
+    };
+
+    if !state.rate_limiter.check(api_key.id, api_key.requests_per_minute) {
+        return Err(api_error(HttpResponse::TooManyRequests(), ErrorCode::RateLimited, "Rate limit exceeded."));
+    }
+    Ok(Some(api_key))
+}
+
+// Middleware for the /api scope: requires `Authorization: Bearer <key>` with an active key.
+pub async fn require_api_key(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let state = match req.app_data::<web::Data<AppState>>() {
+        Some(val) => val.clone(),
+        None => {
+            return Ok(req.into_response(api_error(HttpResponse::InternalServerError(), ErrorCode::InternalError, "Application state missing.")).map_into_right_body());
+        }
+    };
+
+    match api_key(&state, req.request()).await {
+        Ok(Some(_key)) => next.call(req).await.map(ServiceResponse::map_into_left_body),
+        Ok(None) => Ok(req.into_response(api_error(HttpResponse::Unauthorized(), ErrorCode::Unauthorized, "API key required.")).map_into_right_body()),
+        Err(response) => Ok(req.into_response(response).map_into_right_body()),
+    }
+}
+
+pub async fn lookup(state: web::Data<AppState>, campus: Campus, request_id: RequestId, params: web::Query<LookupFormParams>) -> Result<HttpResponse> {
+    let pool = state.read_conn();
+
+    let type_safe_params = match TypeSafeLookupFormParams::from_form(&params) {
+        Ok(val) => val,
+        Err(why) => {
+            return Ok(app_error(&why));
+        }
+    };
+
+    // The most recent term's record.
+    match queries::LATEST_RECORD_BY_NAME.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, TuitionRecord>(sql)
+    .bind(campus_id)
+    .bind(&type_safe_params.first_name)
+    .bind(&type_safe_params.last_name)
+    .fetch_optional(pool)).await {
+        Ok(Some(val)) => Ok(HttpResponse::Ok().json(val)),
+        // Not an error on our side, so there's no point retrying.
+        Ok(None) => Ok(api_error(HttpResponse::NotFound(), ErrorCode::NotFound, &format!("No saved tuition calculation was found for {} {}.", type_safe_params.first_name, type_safe_params.last_name))),
+        Err(sqlx::Error::PoolTimedOut) => {
+            metrics::record_acquire_timeout();
+            let mut response = HttpResponse::ServiceUnavailable();
+            response.insert_header((header::RETRY_AFTER, "5"));
+            Ok(api_error(response, ErrorCode::Busy, "The server is busy right now. Try again shortly."))
+        }
+        // Usually temporary; tell clients when to try again.
+        Err(why) => {
+            log_failure(&request_id, &format!("Error while accessing database: {:?}", why));
+            let mut response = HttpResponse::ServiceUnavailable();
+            response.insert_header((header::RETRY_AFTER, "5"));
+            Ok(api_error(response, ErrorCode::DatabaseError, "Error while accessing database."))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RatesParams {
+    term: Option<String>,
+    studies: Option<String>,
+    residency: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CreditRate {
+    studies: &'static str,
+    residency: &'static str,
+    #[serde(flatten)]
+    costs: TuitionCosts,
+}
+
+#[derive(Serialize)]
+struct Rates {
+    campus: String,
+    term: String,
+    orientation_fee: Money,
+    health_insurance_fee: Money,
+    credit_costs: Vec<CreditRate>,
+    international_fees: Vec<FlatFee>,
+}
+
+// Public, read-only: the rates the calculator is using right now, for the marketing site.
+pub async fn rates(state: web::Data<AppState>, campus: Campus, request_id: RequestId, params: web::Query<RatesParams>) -> Result<HttpResponse> {
+    // Only the current term's rates are kept.
+    let term = receipts::term_for(chrono::Local::now().date_naive());
+    if let Some(val) = &params.term {
+        if !val.eq_ignore_ascii_case(&term) {
+            return Ok(api_error(HttpResponse::NotFound(), ErrorCode::NotFound, &format!("Rates are only published for the current term, {}.", term)));
+        }
+    }
+    let studies = match &params.studies {
+        Some(val) => match fees::STUDIES.iter().find(|studies| *studies == val) {
+            Some(studies) => vec![*studies],
+            None => {
+                return Ok(field_error("studies", "studies must be undergraduate, graduate or dual_enrollment."));
+            }
+        },
+        None => fees::STUDIES.to_vec(),
+    };
+    let residencies = match &params.residency {
+        Some(val) => match fees::RESIDENCIES.iter().find(|residency| *residency == val) {
+            Some(residency) => vec![*residency],
+            None => {
+                return Ok(field_error("residency", "residency must be resident, nonresident or international."));
+            }
+        },
+        None => fees::RESIDENCIES.to_vec(),
+    };
+
+    let mut credit_costs = Vec::new();
+    for studies in &studies {
+        for residency in &residencies {
+            match state.tuition_costs(&campus, studies, residency).await {
+                Ok(costs) => credit_costs.push(CreditRate { studies, residency, costs }),
+                // Not every campus offers every kind of study; only list what it does.
+                Err(AppError::Validation { .. }) if params.studies.is_none() || params.residency.is_none() => {},
+                Err(why) => {
+                    log_failure(&request_id, &format!("Error while loading rates: {}", why.detail()));
+                    return Ok(api_error(HttpResponse::InternalServerError(), ErrorCode::InternalError, &why.user_message()));
+                }
+            }
+        }
+    }
+    let orientation_fee = match state.orientation_fee(&campus).await {
+        Ok(val) => val,
+        Err(why) => {
+            log_failure(&request_id, &format!("Error while loading rates: {}", why.detail()));
+            return Ok(api_error(HttpResponse::InternalServerError(), ErrorCode::InternalError, &why.user_message()));
+        }
+    };
+
+    let health_insurance_fee = match state.health_insurance_fee(&campus).await {
+        Ok(val) => val,
+        Err(why) => {
+            log_failure(&request_id, &format!("Error while loading rates: {}", why.detail()));
+            return Ok(api_error(HttpResponse::InternalServerError(), ErrorCode::InternalError, &why.user_message()));
+        }
+    };
+
+    let international_fees = match state.international_fees(&campus).await {
+        Ok(val) => val,
+        Err(why) => {
+            log_failure(&request_id, &format!("Error while loading rates: {}", why.detail()));
+            return Ok(api_error(HttpResponse::InternalServerError(), ErrorCode::InternalError, &why.user_message()));
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(Rates { campus: campus.slug, term, orientation_fee, health_insurance_fee, credit_costs, international_fees }))
+}
+
+// The Access-Control-Allow-Origin to send back to `origin`, if RATES_CORS_ORIGINS lets it in.
+fn allowed_origin(allowed: &[String], origin: &str) -> Option<String> {
+    if allowed.iter().any(|val| val == "*") {
+        return Some("*".to_string());
+    }
+    allowed.iter().find(|val| val.eq_ignore_ascii_case(origin)).map(|_| origin.to_string())
+}
+
+// Middleware for /api/v1/rates: lets the pages of RATES_CORS_ORIGINS, like the marketing site's
+// rates widget, fetch it from the browser. Other origins get no CORS headers, so browsers keep
+// the response from their scripts.
+pub async fn rates_cors(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let origin = req.headers().get(header::ORIGIN).and_then(|val| val.to_str().ok());
+    let allowed = match (req.app_data::<web::Data<AppState>>(), origin) {
+        (Some(state), Some(origin)) => allowed_origin(&state.rates_cors_origins, origin),
+        _ => None,
+    };
+
+    // The preflight for a widget sending its own headers; answered here, since the resource only
+    // has GET.
+    if req.method() == Method::OPTIONS {
+        let mut response = HttpResponse::NoContent();
+        response.insert_header((header::VARY, "Origin"));
+        if let Some(origin) = allowed {
+            response.insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, origin))
+                .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, "GET"))
+                .insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, "Accept, Content-Type"))
+                .insert_header((header::ACCESS_CONTROL_MAX_AGE, "86400"));
+        }
+        return Ok(req.into_response(response.finish()).map_into_right_body());
+    }
+
+    let mut response = next.call(req).await?;
+    let headers = response.headers_mut();
+    headers.append(header::VARY, header::HeaderValue::from_static("Origin"));
+    if let Some(origin) = allowed.and_then(|origin| header::HeaderValue::from_str(&origin).ok()) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        // So a widget can quote the request ID when reporting a failure.
+        headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, header::HeaderValue::from_static("X-Request-ID"));
+    }
+    Ok(response.map_into_left_body())
+}
+
+#[derive(Serialize)]
+struct Choice {
+    value: &'static str,
+    label: &'static str,
+}
+
+// One input of the calculate form. Checkboxes are sent as "on" when checked and left out
+// otherwise, as a browser would.
+#[derive(Serialize)]
+struct FormField {
+    name: &'static str,
+    // text, integer, checkbox, choice, date or course_codes.
+    kind: &'static str,
+    label: &'static str,
+    required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    choices: Vec<Choice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<String>,
+}
+
+impl FormField {
+    fn new(name: &'static str, kind: &'static str, label: &'static str, required: bool) -> FormField {
+        FormField { name, kind, label, required, max_length: None, min: None, max: None, format: None, choices: Vec::new(), help: None }
+    }
+}
+
+// A check that involves more than one field.
+#[derive(Serialize)]
+struct FormRule {
+    fields: Vec<&'static str>,
+    rule: String,
+}
+
+#[derive(Serialize)]
+struct FormSchema {
+    campus: String,
+    // The term a calculation made today is priced for.
+    current_term: String,
+    // Terms with their own proration, refund or line item rules.
+    terms: Vec<String>,
+    action: String,
+    fields: Vec<FormField>,
+    rules: Vec<FormRule>,
+    // The courses entered in course_codes that carry a fee; any other code is free.
+    course_fees: Vec<CourseFee>,
+}
+
+async fn form_schema_for(state: &AppState, campus: &Campus) -> Result<FormSchema, AppError> {
+    // Only what this campus has rates for.
+    let mut programs = Vec::new();
+    let mut residencies = Vec::new();
+    for studies in fees::STUDIES {
+        for residency in fees::RESIDENCIES {
+            match state.tuition_costs(campus, studies, residency).await {
+                Ok(_costs) => {
+                    if !programs.contains(&studies) {
+                        programs.push(studies);
+                    }
+                    if !residencies.contains(&residency) {
+                        residencies.push(residency);
+                    }
+                }
+                Err(AppError::Validation { .. }) => {},
+                Err(why) => {
+                    return Err(why);
+                }
+            }
+        }
+    }
+    let health_insurance_fee = state.health_insurance_fee(campus).await?;
+    let orientation_fee = state.orientation_fee(campus).await?;
+
+    let mut first_name = FormField::new("first_name", "text", "First name", true);
+    first_name.max_length = Some(MAX_NAME_LENGTH);
+    let mut last_name = FormField::new("last_name", "text", "Last name", true);
+    last_name.max_length = Some(MAX_NAME_LENGTH);
+    let mut num_credits = FormField::new("num_credits", "integer", "Credit hours", true);
+    num_credits.min = Some(0);
+    num_credits.max = Some(u32::from(u8::MAX));
+    let mut student_type = FormField::new("student_type", "choice", "Residency", true);
+    student_type.choices = residencies.iter().map(|residency| Choice { value: residency, label: residency_label(residency) }).collect();
+    // Campuses in a state ask where the student lives instead, as the calculator page does, and
+    // price it through their reciprocity agreements.
+    let residency_field = match &campus.state {
+        Some(_) => {
+            let mut home_state = FormField::new("home_state", "choice", "Home state", true);
+            home_state.choices = residency::STATES.iter().map(|state| Choice { value: state.code, label: state.name }).collect();
+            if residencies.contains(&"international") {
+                home_state.choices.push(Choice { value: residency::INTERNATIONAL, label: "Outside the U.S., on a student visa" });
+            }
+            home_state
+        }
+        None => student_type,
+    };
+    let mut student_studies = FormField::new("student_studies", "choice", "Studies", true);
+    student_studies.choices = programs.iter().map(|studies| Choice { value: studies, label: studies_label(studies) }).collect();
+    let mut orientation = FormField::new("orientation", "checkbox", "Attending orientation", false);
+    orientation.help = Some(format!("Adds the {} orientation fee.", orientation_fee.format()));
+    let mut enrollment_date = FormField::new("enrollment_date", "date", "Enrollment date", false);
+    enrollment_date.format = Some("YYYY-MM-DD");
+    enrollment_date.help = Some("Leave empty for the full term; a later date may prorate tuition.".to_string());
+    let mut course_codes = FormField::new("course_codes", "course_codes", "Courses with lab or course fees", false);
+    course_codes.max = Some(MAX_COURSE_CODES as u32);
+    course_codes.help = Some("Comma-separated, e.g. \"CHEM 101, BIOL 110\".".to_string());
+    let mut insurance_waiver = FormField::new("insurance_waiver", "checkbox", "I have my own health insurance", false);
+    insurance_waiver.help = Some(format!("Waives the {} health insurance fee.", health_insurance_fee.format()));
+
+    let mut fields = vec![
+        first_name,
+        last_name,
+        num_credits,
+        FormField::new("new_student", "checkbox", "New student", false),
+        orientation,
+        residency_field,
+        student_studies,
+        FormField::new("include_additional_costs", "checkbox", "Include estimated additional costs (books, supplies, transportation)", false),
+        enrollment_date,
+        course_codes,
+        insurance_waiver,
+    ];
+    if let Some(consent) = &state.consent {
+        let mut field = FormField::new("consent", "checkbox", "Save this estimate and my answers to my student record", false);
+        field.help = Some(match &consent.privacy_notice_url {
+            Some(url) => format!("Without it the estimate is shown but not kept; see {}.", url),
+            None => "Without it the estimate is shown but not kept.".to_string(),
+        });
+        fields.push(field);
+    }
+    // Only the inputs the campus's own form shows.
+    let shown = state.form_fields(campus).await?;
+    fields.retain(|field| shown.shown(field.name));
+
+    let mut rules = vec![FormRule {
+        fields: vec!["new_student", "orientation"],
+        rule: "Orientation is only offered to new students.".to_string(),
+    }];
+    if programs.contains(&"dual_enrollment") {
+        rules.push(FormRule {
+            fields: vec!["student_studies", "num_credits"],
+            rule: format!("Dual-enrollment students can take at most {} credits.", DUAL_ENROLLMENT_MAX_CREDITS),
+        });
+        rules.push(FormRule {
+            fields: vec!["student_studies", "orientation"],
+            rule: "Dual-enrollment students never pay for orientation; the box is ignored.".to_string(),
+        });
+    }
+    for exemption in state.orientation_exemptions(campus).await? {
+        rules.push(FormRule {
+            fields: vec!["orientation"],
+            rule: format!("No orientation fee when {} ({}); the box is ignored.", exemption.exempt_when, exemption.label),
+        });
+    }
+    rules.retain(|rule| rule.fields.iter().all(|field| shown.shown(field)));
+
+    Ok(FormSchema {
+        campus: campus.slug.clone(),
+        current_term: receipts::term_for(chrono::Local::now().date_naive()),
+        terms: state.term_names(campus).await?,
+        action: "/calculate".to_string(),
+        fields,
+        rules,
+        course_fees: state.course_fees(campus).await?,
+    })
+}
+
+fn residency_label(residency: &str) -> &'static str {
+    match residency {
+        "resident" => "Resident",
+        "nonresident" => "Non-Resident",
+        _ => "International",
+    }
+}
+
+fn studies_label(studies: &str) -> &'static str {
+    StudentStudies::parse(studies).label()
+}
+
+// Public, read-only: the calculate form as data, so the campus portal can build its own UI that
+// matches what the server accepts.
+pub async fn form_schema(state: web::Data<AppState>, campus: Campus, request_id: RequestId) -> Result<HttpResponse> {
+    match form_schema_for(&state, &campus).await {
+        Ok(schema) => Ok(HttpResponse::Ok().json(schema)),
+        Err(why) => {
+            log_failure(&request_id, &format!("Error while building the form schema: {}", why.detail()));
+            Ok(api_error(HttpResponse::InternalServerError(), ErrorCode::InternalError, &why.user_message()))
+        }
+    }
+}
+
+// What a preview needs to price one kind of student at one campus, looked up together so a
+// preview is a single cache hit.
+#[derive(Debug, Clone)]
+struct PreviewRates {
+    costs: TuitionCosts,
+    orientation_fee: Money,
+    health_insurance_fee: Money,
+    international_fee_total: Money,
+    default_rates: bool,
+}
+
+// Who a preview prices: a student from a home state (a state code or "international"), priced
+// through the campus's agreements, or one of a residency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PreviewStudent {
+    HomeState(&'static str),
+    Residency(&'static str),
+}
+
+// A campus, level of study and student.
+type PreviewKey = (CampusId, &'static str, PreviewStudent);
+
+// Preview rates by campus, level of study and student, each kept for PREVIEW_CACHE_SECS, with any
+// reciprocity agreement already applied. Rates from FEE_SCHEDULE_FILE are already in memory and
+// aren't kept here.
+#[derive(Debug)]
+pub struct PreviewCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<PreviewKey, (Instant, PreviewRates)>>,
+}
+
+impl PreviewCache {
+    pub fn new(ttl: Duration) -> PreviewCache {
+        PreviewCache { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn get(&self, key: &PreviewKey) -> Option<PreviewRates> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((cached_at, rates)) if cached_at.elapsed() < self.ttl => Some(rates.clone()),
+            _ => None,
+        }
+    }
+
+    fn put(&self, key: PreviewKey, rates: &PreviewRates) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (cached_at, _)| cached_at.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), rates.clone()));
+    }
+}
+
+async fn load_preview_rates(state: &AppState, campus: &Campus, studies: &'static str, student: PreviewStudent) -> Result<PreviewRates, AppError> {
+    let key = (campus.id, studies, student);
+    if let Some(rates) = state.preview_cache.get(&key) {
+        return Ok(rates);
+    }
+
+    let (residency, agreement) = match student {
+        PreviewStudent::HomeState(residency::INTERNATIONAL) => ("international", None),
+        PreviewStudent::HomeState(code) => fees::agreement_residency(campus, code, &state.reciprocity_agreements(campus).await?),
+        PreviewStudent::Residency(val) => (val, None),
+    };
+    let (mut costs, default_rates) = match state.tuition_costs(campus, studies, residency).await {
+        Ok(val) => (val, false),
+        Err(why @ AppError::Validation { .. }) => match state.default_tuition_costs(residency) {
+            Some(val) => (val, true),
+            None => {
+                return Err(why);
+            }
+        },
+        Err(why) => {
+            return Err(why);
+        }
+    };
+    let international_fee_total = if residency == "international" {
+        state.international_fees(campus).await?.iter().map(|fee| fee.amount).sum()
+    } else {
+        Money::zero()
+    };
+    costs.nonresidency_fee = pricing::reciprocity_fee(costs.nonresidency_fee, agreement.as_ref());
+    let rates = PreviewRates {
+        costs,
+        orientation_fee: state.orientation_fee(campus).await?,
+        health_insurance_fee: state.health_insurance_fee(campus).await?,
+        international_fee_total,
+        default_rates,
+    };
+    if state.schedule_section(campus).is_none() {
+        state.preview_cache.put(key, &rates);
+    }
+    Ok(rates)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PreviewParams {
+    #[serde(default, deserialize_with = "form::optional")]
+    num_credits: Option<form::Bounded<0, 255>>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    student_type: Option<String>,
+    // A state code or "international", in place of student_type.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    home_state: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    student_studies: Option<String>,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    orientation: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    insurance_waiver: bool,
+}
+
+#[derive(Serialize)]
+struct Preview {
+    total: Money,
+    // Credits, the non-residency fee and orientation.
+    tuition: Money,
+    orientation_fee: Money,
+    health_insurance_fee: Money,
+    international_fees: Money,
+    // Priced with DEFAULT_CREDITS_COST, since the campus has no rate for the student.
+    default_rates: bool,
+}
+
+// Public: a running total for the calculator page to show while the student fills it in, e.g. as
+// they move the credits slider. Nothing is saved or added to their history, and it leaves out
+// what needs the full form (course fees, late-enrollment proration, exemptions and custom line
+// items), so the total on the result page can differ.
+pub async fn preview(state: web::Data<AppState>, campus: Campus, request_id: RequestId, params: form::Submitted<PreviewParams>) -> Result<HttpResponse> {
+    let num_credits = match params.num_credits.as_ref().map(form::Bounded::value) {
+        Some(Ok(val)) => val as u8,
+        Some(Err(why)) => {
+            return Ok(field_error("num_credits", &format!("Credit hours: {}", why)));
+        }
+        None => {
+            return Ok(field_error("num_credits", "No credits were provided!"));
+        }
+    };
+    let studies = match params.student_studies.as_deref().and_then(|val| fees::STUDIES.iter().find(|studies| **studies == val)) {
+        Some(val) => *val,
+        None => {
+            return Ok(field_error("student_studies", "student_studies must be undergraduate, graduate or dual_enrollment."));
+        }
+    };
+    // A home state is priced as the full form prices it, through the campus's agreements.
+    let student = match (&params.home_state, params.student_type.as_deref().and_then(|val| fees::RESIDENCIES.iter().find(|residency| **residency == val))) {
+        (Some(home_state), _) => match fees::home_state_code(home_state) {
+            Ok(val) => PreviewStudent::HomeState(val),
+            Err(why) => {
+                return Ok(app_error(&why));
+            }
+        },
+        (None, Some(val)) => PreviewStudent::Residency(val),
+        (None, None) => {
+            return Ok(field_error("student_type", "student_type must be resident, nonresident or international."));
+        }
+    };
+    // The same as the full form: dual-enrollment students have a credit cap and skip orientation.
+    let dual_enrollment = studies == "dual_enrollment";
+    if dual_enrollment && num_credits > DUAL_ENROLLMENT_MAX_CREDITS {
+        return Ok(field_error("num_credits", &format!("Dual-enrollment students can take at most {} credits.", DUAL_ENROLLMENT_MAX_CREDITS)));
+    }
+    let orientation = params.orientation && !dual_enrollment;
+
+    let rates = match load_preview_rates(&state, &campus, studies, student).await {
+        Ok(val) => val,
+        Err(why @ (AppError::Validation { .. } | AppError::Busy)) => {
+            return Ok(app_error(&why));
+        }
+        Err(why) => {
+            log_failure(&request_id, &format!("Error while loading preview rates: {}", why.detail()));
+            return Ok(app_error(&why));
+        }
+    };
+
+    let tuition = pricing::tuition_total(num_credits, orientation, &rates.costs, rates.orientation_fee);
+    let health_insurance_fee = pricing::health_insurance_charge(params.insurance_waiver, rates.health_insurance_fee);
+    Ok(HttpResponse::Ok().json(Preview {
+        total: tuition + health_insurance_fee + rates.international_fee_total,
+        tuition,
+        orientation_fee: if orientation { rates.orientation_fee } else { Money::zero() },
+        health_insurance_fee,
+        international_fees: rates.international_fee_total,
+        default_rates: rates.default_rates,
+    }))
+}
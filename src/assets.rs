@@ -0,0 +1,85 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderError};
+
+// A static file bundled by build.rs, under a name with a hash of its contents.
+pub struct Asset {
+    // As it's called in src/htdoc, e.g. "style.css".
+    pub name: &'static str,
+    // What it's served as, e.g. "style.3f2a9c1b04de.css".
+    pub hashed: &'static str,
+    pub content_type: &'static str,
+    pub body: &'static [u8],
+    pub brotli: &'static [u8],
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+// A year, the most caches honor. The name changes with the contents, so nothing goes stale.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+pub fn find(name: &str) -> Option<&'static Asset> {
+    ASSETS.iter().find(|asset| asset.name == name)
+}
+
+// Where templates link to an asset.
+pub fn url(asset: &Asset) -> String {
+    format!("/assets/{}", asset.hashed)
+}
+
+// `{{asset "style.css"}}` in a template gives the asset's current URL.
+pub fn asset_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    let name = h.param(0).and_then(|param| param.value().as_str()).unwrap_or_default();
+    match find(name) {
+        Some(asset) => {
+            out.write(&url(asset))?;
+            Ok(())
+        }
+        None => Err(RenderError::new(format!("No asset named \"{}\" is bundled.", name))),
+    }
+}
+
+// Whether the client takes Brotli, going by Accept-Encoding. "br;q=0" turns it down.
+fn accepts_brotli(req: &HttpRequest) -> bool {
+    let accept = match req.headers().get(header::ACCEPT_ENCODING).and_then(|val| val.to_str().ok()) {
+        Some(val) => val,
+        None => {
+            return false;
+        }
+    };
+    accept.split(',').any(|coding| {
+        let mut parts = coding.split(';').map(str::trim);
+        parts.next().is_some_and(|name| name.eq_ignore_ascii_case("br"))
+            && !parts.any(|param| param.strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0))
+    })
+}
+
+fn respond(req: &HttpRequest, asset: &Asset, cache_control: &str) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(asset.content_type)
+        .insert_header((header::CACHE_CONTROL, cache_control))
+        .insert_header((header::VARY, "Accept-Encoding"));
+    if accepts_brotli(req) {
+        response.insert_header((header::CONTENT_ENCODING, "br"));
+        return response.body(asset.brotli);
+    }
+    response.body(asset.body)
+}
+
+// GET /assets/{hashed name}. A name from an older build is a 404, not the new file: the page
+// that asked for it is stale too.
+pub async fn serve(req: HttpRequest, hashed: web::Path<String>) -> HttpResponse {
+    match ASSETS.iter().find(|asset| asset.hashed == hashed.as_str()) {
+        Some(asset) => respond(&req, asset, IMMUTABLE),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+// The unhashed /style.css, for pages cached before assets were hashed. It has to be checked
+// again each time, since its contents change under the same name.
+pub async fn stylesheet(req: HttpRequest) -> HttpResponse {
+    match find("style.css") {
+        Some(asset) => respond(&req, asset, "no-cache"),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
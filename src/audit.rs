@@ -0,0 +1,48 @@
+use actix_session::SessionExt;
+use actix_web::{web, HttpRequest};
+
+use crate::{client_ip, queries, staff_auth, AppState};
+
+// Who made an admin change. With OIDC_ISSUER set it's the staff member signed in with their
+// campus account, and with ADMIN_BASIC_AUTH the name they signed in with. Otherwise there are no
+// admin accounts: when ADMIN_USER_HEADER is set, it's the staff member the proxy in front of
+// /admin signed in, otherwise the client address.
+pub fn actor(req: &HttpRequest) -> String {
+    let state = req.app_data::<web::Data<AppState>>();
+    if let Some(provider) = state.and_then(|state| state.staff_auth.as_ref()) {
+        if let Some(name) = staff_auth::signed_in(provider.as_ref(), &req.get_session()) {
+            return name;
+        }
+    }
+    if let Some(name) = staff_auth::basic_auth_user(req) {
+        return name.chars().take(255).collect();
+    }
+    let header = state.and_then(|state| state.admin_user_header.clone());
+    if let Some(val) = header.and_then(|header| req.headers().get(header.as_str()).cloned()) {
+        match val.to_str() {
+            // Actor columns are VARCHAR(255).
+            Ok(name) if !name.trim().is_empty() => return name.trim().chars().take(255).collect(),
+            _ => {},
+        }
+    }
+    match client_ip::for_request(req) {
+        Some(ip) => ip.to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+// Add an entry to the audit log. `details` says what changed, e.g. "FirstName: Jhon -> John".
+// Pass a transaction to log the change along with it.
+pub async fn record<'c>(conn: impl sqlx::Executor<'c, Database = sqlx::MySql>, actor: &str, action: &str, entity: &str, entity_id: i32, details: &str) -> Result<(), sqlx::Error> {
+    match queries::INSERT_AUDIT_ENTRY.run(|sql| sqlx::query(sql)
+    .bind(actor)
+    .bind(action)
+    .bind(entity)
+    .bind(entity_id)
+    .bind(details)
+    .execute(conn))
+    .await {
+        Ok(_val) => Ok(()),
+        Err(why) => Err(why),
+    }
+}
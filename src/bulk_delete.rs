@@ -0,0 +1,404 @@
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, MySqlPool, QueryBuilder};
+
+use crate::{audit, error::AppError, form, models::{BulkDeleteId, Campus, CampusId, StudentId}, queries, render, retention::{RetentionAction, ANONYMIZED}, AppState};
+
+// Rows each batch takes from each table, in its own transaction, so a large clear-out doesn't
+// hold locks on Receipts for long while students are calculating.
+const BATCH_SIZE: i64 = 500;
+
+// How long a clear-out can go without finishing a batch before it's taken to have gone down with
+// the server running it, so the campus can start another.
+const STALE_AFTER_MINUTES: i64 = 10;
+
+// Which calculations a clear-out takes: one term's, or everything saved from one day through
+// another.
+#[derive(Debug, Clone)]
+enum Selection {
+    Term(String),
+    Dates(NaiveDate, NaiveDate),
+}
+
+impl Selection {
+    fn describe(&self) -> String {
+        match self {
+            Selection::Term(term) => term.clone(),
+            Selection::Dates(from, to) => format!("{} to {}", from, to),
+        }
+    }
+
+    // The term, and the start and end of the range, as the bulk queries bind them.
+    fn bounds(&self) -> (Option<&str>, Option<NaiveDateTime>, Option<NaiveDateTime>) {
+        match self {
+            Selection::Term(term) => (Some(term), None, None),
+            Selection::Dates(from, to) => (None, from.and_hms_opt(0, 0, 0), (*to + Duration::days(1)).and_hms_opt(0, 0, 0)),
+        }
+    }
+}
+
+// A clear-out as kept in BulkDeletes, for the progress shown on the admin page.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct Progress {
+    selection: String,
+    action: String,
+    actor: String,
+    started_at: NaiveDateTime,
+    // Counted when it started; calculations saved since can add to what's done.
+    receipts_total: i64,
+    records_total: i64,
+    receipts_done: i64,
+    records_done: i64,
+    students_done: i64,
+    batches: i64,
+    finished_at: Option<NaiveDateTime>,
+    error: Option<String>,
+}
+
+impl Progress {
+    fn running(&self) -> bool {
+        self.finished_at.is_none()
+    }
+}
+
+// Finish a clear-out at the campus that's stopped moving, with an error saying so.
+async fn stop_stale(pool: &MySqlPool, campus_id: CampusId) -> Result<(), sqlx::Error> {
+    let now = Local::now().naive_local();
+    queries::STOP_STALE_BULK_DELETES.scoped(campus_id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(now)
+    .bind(format!("Nothing was done for {} minutes, most likely because the server running it restarted", STALE_AFTER_MINUTES))
+    .bind(campus_id)
+    .bind(now - Duration::minutes(STALE_AFTER_MINUTES))
+    .execute(pool)).await?;
+    Ok(())
+}
+
+// The latest clear-out at the campus, on any server.
+async fn latest(pool: &MySqlPool, campus_id: CampusId) -> Result<Option<Progress>, sqlx::Error> {
+    stop_stale(pool, campus_id).await?;
+    queries::LATEST_BULK_DELETE.scoped(campus_id).run(|sql, campus_id| sqlx::query_as::<_, Progress>(sql)
+    .bind(campus_id)
+    .fetch_optional(pool)).await
+}
+
+// The receipts and records in the selection, and the students who'd be left with nothing.
+async fn count(pool: &MySqlPool, campus_id: CampusId, selection: &Selection) -> Result<(i64, i64, i64), sqlx::Error> {
+    let (term, from, until) = selection.bounds();
+    let receipts = queries::COUNT_BULK_RECEIPTS.scoped(campus_id).run(|sql, campus_id| sqlx::query_scalar::<_, i64>(sql)
+    .bind(campus_id)
+    .bind(term)
+    .bind(term)
+    .bind(from)
+    .bind(from)
+    .bind(until)
+    .bind(until)
+    .fetch_one(pool)).await?;
+    let records = queries::COUNT_BULK_RECORDS.scoped(campus_id).run(|sql, campus_id| sqlx::query_scalar::<_, i64>(sql)
+    .bind(campus_id)
+    .bind(term)
+    .bind(term)
+    .bind(from)
+    .bind(from)
+    .bind(until)
+    .bind(until)
+    .fetch_one(pool)).await?;
+    let students = queries::COUNT_BULK_STUDENTS.scoped(campus_id).run(|sql, campus_id| {
+        let mut query = sqlx::query_scalar::<_, i64>(sql).bind(campus_id);
+        for _ in 0..4 {
+            query = query.bind(term).bind(term).bind(from).bind(from).bind(until).bind(until);
+        }
+        query.fetch_one(pool)
+    }).await?;
+    Ok((receipts, records, students))
+}
+
+// One batch: up to BATCH_SIZE receipts, then saved records, then the students they belonged to
+// who are left with neither. Returns how many of each went.
+async fn batch(pool: &MySqlPool, campus_id: CampusId, selection: &Selection, action: RetentionAction) -> Result<(u64, u64, u64), sqlx::Error> {
+    let (term, from, until) = selection.bounds();
+    let mut tx = pool.begin().await?;
+
+    // Only these students can be left with nothing by this batch; anyone else at the campus with
+    // nothing saved is left alone.
+    let anonymized = match action {
+        RetentionAction::Anonymize => Some(ANONYMIZED),
+        RetentionAction::Purge => None,
+    };
+    let mut student_ids: Vec<StudentId> = queries::BULK_RECEIPT_STUDENTS.scoped(campus_id).run(|sql, campus_id| sqlx::query_scalar::<_, Option<StudentId>>(sql)
+    .bind(campus_id)
+    .bind(anonymized)
+    .bind(anonymized)
+    .bind(term)
+    .bind(term)
+    .bind(from)
+    .bind(from)
+    .bind(until)
+    .bind(until)
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut tx)).await?.into_iter().flatten().collect();
+
+    // Anonymized receipts are let go of their student as well, as when a student is deleted.
+    let receipts = match action {
+        RetentionAction::Anonymize => queries::ANONYMIZE_BULK_RECEIPTS.scoped(campus_id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(ANONYMIZED)
+            .bind(ANONYMIZED)
+            .bind(campus_id)
+            .bind(ANONYMIZED)
+            .bind(term)
+            .bind(term)
+            .bind(from)
+            .bind(from)
+            .bind(until)
+            .bind(until)
+            .bind(BATCH_SIZE)
+            .execute(&mut tx)).await?,
+        RetentionAction::Purge => queries::DELETE_BULK_RECEIPTS.scoped(campus_id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(campus_id)
+            .bind(term)
+            .bind(term)
+            .bind(from)
+            .bind(from)
+            .bind(until)
+            .bind(until)
+            .bind(BATCH_SIZE)
+            .execute(&mut tx)).await?,
+    };
+    // A saved record is the student's own total for the term, so there's nothing to keep of it
+    // without them; it's deleted either way.
+    let record_student_ids = queries::BULK_RECORD_STUDENTS.scoped(campus_id).run(|sql, campus_id| sqlx::query_scalar::<_, StudentId>(sql)
+    .bind(campus_id)
+    .bind(term)
+    .bind(term)
+    .bind(from)
+    .bind(from)
+    .bind(until)
+    .bind(until)
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut tx)).await?;
+    student_ids.extend(record_student_ids);
+    student_ids.sort_by_key(|id| id.0);
+    student_ids.dedup();
+    let records = queries::DELETE_BULK_RECORDS.scoped(campus_id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(term)
+    .bind(term)
+    .bind(from)
+    .bind(from)
+    .bind(until)
+    .bind(until)
+    .bind(BATCH_SIZE)
+    .execute(&mut tx)).await?;
+    let students = if student_ids.is_empty() {
+        0
+    } else {
+        let conn = &mut tx;
+        queries::DELETE_LEFTOVER_STUDENTS.scoped(campus_id).run(|sql, campus_id| {
+            let mut query = QueryBuilder::<MySql>::new(sql);
+            query.push_bind(campus_id);
+            query.push(" and Id in (");
+            let mut separated = query.separated(", ");
+            for id in &student_ids {
+                separated.push_bind(*id);
+            }
+            separated.push_unseparated(")");
+            async move { query.build().execute(conn).await }
+        }).await?.rows_affected()
+    };
+
+    tx.commit().await?;
+    Ok((receipts.rows_affected(), records.rows_affected(), students))
+}
+
+// Batch after batch until one comes back short on receipts and records, then record the whole clear-out in
+// the audit log, with how far it got if it failed partway. Returns the error it stopped on, if any.
+async fn run(pool: MySqlPool, campus: Campus, job_id: BulkDeleteId, selection: Selection, action: RetentionAction, actor: String) -> Option<String> {
+    let (mut receipts, mut records, mut students, mut batches) = (0u64, 0u64, 0u64, 0u64);
+    let error = loop {
+        match batch(&pool, campus.id, &selection, action).await {
+            Ok((batch_receipts, batch_records, batch_students)) => {
+                receipts += batch_receipts;
+                records += batch_records;
+                students += batch_students;
+                batches += 1;
+                // Also shows the job is still alive; see STALE_AFTER_MINUTES.
+                let saved = queries::BULK_DELETE_PROGRESS.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+                .bind(receipts)
+                .bind(records)
+                .bind(students)
+                .bind(batches)
+                .bind(Local::now().naive_local())
+                .bind(campus_id)
+                .bind(job_id)
+                .execute(&pool)).await;
+                if let Err(why) = saved {
+                    break Some(why.to_string());
+                }
+                println!("Bulk {} of {} at {}: batch {}, {} receipt(s), {} record(s) and {} student(s) so far.", action, selection.describe(), campus.name, batches, receipts, records, students);
+                let limit = BATCH_SIZE as u64;
+                if batch_receipts < limit && batch_records < limit {
+                    break None;
+                }
+            }
+            Err(why) => {
+                break Some(why.to_string());
+            }
+        }
+    };
+
+    let mut details = format!("{} receipt(s) {}d, {} record(s) and {} student(s) deleted for {} in {} batch(es)",
+        receipts, action, records, students, selection.describe(), batches);
+    if let Some(why) = &error {
+        details += &format!("; stopped by an error: {}", why);
+    }
+    println!("Bulk {} at {}: {}.", action, campus.name, details);
+    if let Err(why) = audit::record(&pool, &actor, &format!("bulk {}", action), "Receipt", 0, &details).await {
+        println!("Error while recording the bulk {} in the audit log: {}", action, why);
+    }
+    error
+}
+
+// Mark the clear-out finished, however it ended. Should this fail too, it's finished as stale
+// once STALE_AFTER_MINUTES have passed.
+async fn finish(pool: &MySqlPool, campus_id: CampusId, job_id: BulkDeleteId, error: Option<String>) {
+    let now = Local::now().naive_local();
+    let finished = queries::FINISH_BULK_DELETE.scoped(campus_id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(now)
+    .bind(now)
+    .bind(error)
+    .bind(campus_id)
+    .bind(job_id)
+    .execute(pool)).await;
+    if let Err(why) = finished {
+        println!("Error while marking bulk delete #{} finished: {}", job_id, why);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BulkDeleteParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    term: Option<String>,
+    // YYYY-MM-DD, both days included.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    from: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    to: Option<String>,
+    // anonymize or purge.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    action: Option<String>,
+    // The phrase typed to confirm, e.g. "purge Fall 2025".
+    #[serde(default, deserialize_with = "form::trimmed", skip_serializing)]
+    confirm: Option<String>,
+}
+
+fn parse_date(field: &'static str, val: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(val, "%Y-%m-%d").map_err(|_| AppError::validation(field, &format!("\"{}\" isn't a date like 2025-08-25.", val)))
+}
+
+impl BulkDeleteParams {
+    fn selection(&self) -> Result<Selection, AppError> {
+        match (&self.term, &self.from, &self.to) {
+            (Some(term), None, None) => Ok(Selection::Term(term.clone())),
+            (None, Some(from), Some(to)) => {
+                let (from, to) = (parse_date("from", from)?, parse_date("to", to)?);
+                if from > to {
+                    return Err(AppError::validation("to", "The range has to end on or after the day it starts."));
+                }
+                Ok(Selection::Dates(from, to))
+            }
+            (Some(_), _, _) => Err(AppError::validation("term", "Give a term or a range of dates, not both.")),
+            (None, _, _) => Err(AppError::validation("term", "Give a term, or the first and last day of a range.")),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BulkDeletePage {
+    form: BulkDeleteParams,
+    progress: Option<Progress>,
+    running: bool,
+    percent: Option<i64>,
+}
+
+// GET /admin/bulk-delete: the form, and how the latest clear-out at this campus went. The page
+// refreshes itself while one is running.
+pub async fn bulk_delete_form(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let progress = latest(&state.conn, campus.id).await?;
+    let running = progress.as_ref().is_some_and(Progress::running);
+    let percent = progress.as_ref().and_then(|progress| {
+        let total = progress.receipts_total + progress.records_total;
+        let done = progress.receipts_done + progress.records_done;
+        if total > 0 { Some((done * 100 / total).min(100)) } else { None }
+    });
+    render(&state, "admin_bulk_delete", &BulkDeletePage { form: BulkDeleteParams::default(), progress, running, percent }).await
+}
+
+// POST /admin/bulk-delete, e.g. to clear out test calculations after a dry run. Nothing happens
+// until the confirmation phrase is typed exactly; a wrong one is told what it would take. It then
+// runs in the background, one batch at a time, and only one clear-out runs per campus at once.
+pub async fn bulk_delete(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<BulkDeleteParams>) -> Result<HttpResponse, AppError> {
+    let selection = params.selection()?;
+    let action = match params.action.as_deref().map(str::parse::<RetentionAction>) {
+        Some(Ok(val)) => val,
+        Some(Err(why)) => {
+            return Err(AppError::validation("action", &why));
+        }
+        None => {
+            return Err(AppError::validation("action", "Pick whether to anonymize or purge the receipts."));
+        }
+    };
+
+    let (receipts_total, records_total, students_total) = count(&state.conn, campus.id, &selection).await?;
+    if receipts_total == 0 && records_total == 0 {
+        return Err(AppError::validation("term", &format!("Nothing was saved at {} for {}.", campus.name, selection.describe())));
+    }
+    let phrase = format!("{} {}", action, selection.describe());
+    if !params.confirm.as_deref().is_some_and(|confirm| confirm.eq_ignore_ascii_case(&phrase)) {
+        return Err(AppError::validation("confirm", &format!(
+            "Type \"{}\" to confirm. It will {} {} receipt(s) and delete {} saved record(s) at {}, and delete {} student(s) left with nothing saved, with their scenarios.",
+            phrase, action, receipts_total, records_total, campus.name, students_total)));
+    }
+
+    let actor = audit::actor(&req);
+    stop_stale(&state.conn, campus.id).await?;
+    let now = Local::now().naive_local();
+    let started = queries::START_BULK_DELETE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(selection.describe())
+    .bind(action.to_string())
+    .bind(&actor)
+    .bind(receipts_total)
+    .bind(records_total)
+    .bind(now)
+    .bind(now)
+    .execute(&state.conn)).await;
+    let job_id = match started {
+        Ok(val) => BulkDeleteId(val.last_insert_id() as i32),
+        // RunningCampusId is taken, on this server or another.
+        Err(sqlx::Error::Database(why)) if why.code().as_deref() == Some("23000") => {
+            return Err(AppError::validation("term", "A bulk delete is already running at this campus; wait for it to finish."));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    // Run on a task of its own, so a panic partway through still finishes the job.
+    let pool = state.conn.clone();
+    rt::spawn(async move {
+        let campus_id = campus.id;
+        let error = match rt::spawn(run(pool.clone(), campus, job_id, selection, action, actor)).await {
+            Ok(val) => val,
+            Err(why) => {
+                println!("Bulk delete #{} stopped unexpectedly: {}", job_id, why);
+                Some(format!("It stopped unexpectedly ({})", why))
+            }
+        };
+        finish(&pool, campus_id, job_id, error).await;
+    });
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/bulk-delete"))
+        .finish())
+}
@@ -0,0 +1,39 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use std::future::{ready, Ready};
+
+use crate::{error::AppError, models::Campus, queries, AppState};
+
+pub async fn load_campuses(pool: &sqlx::MySqlPool) -> Result<Vec<Campus>, sqlx::Error> {
+    queries::CAMPUSES.run(|sql| sqlx::query_as::<_, Campus>(sql)
+    .fetch_all(pool)).await
+}
+
+impl AppState {
+    // A campus is picked by its configured hostname, then by subdomain (`west.example.edu` -> "west").
+    // Anything else goes to the first campus, so single-campus installs need no setup.
+    pub fn campus_for_host(&self, host: &str) -> Option<Campus> {
+        let host = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+        let subdomain = host.split('.').next().unwrap_or("");
+        self.campuses.iter()
+            .find(|campus| campus.hostname.as_deref().map(|val| val.eq_ignore_ascii_case(&host)).unwrap_or(false))
+            .or_else(|| self.campuses.iter().find(|campus| campus.slug.eq_ignore_ascii_case(subdomain)))
+            .or_else(|| self.campuses.first())
+            .cloned()
+    }
+}
+
+impl FromRequest for Campus {
+    type Error = AppError;
+    type Future = Ready<Result<Campus, AppError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let campus = match req.app_data::<web::Data<AppState>>() {
+            Some(state) => state.campus_for_host(req.connection_info().host()),
+            None => None,
+        };
+        ready(match campus {
+            Some(val) => Ok(val),
+            None => Err(AppError::Internal("No campuses are configured.".to_string())),
+        })
+    }
+}
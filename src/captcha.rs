@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{config::CaptchaConfig, error::AppError, http_client::HttpClient, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    Recaptcha,
+    Hcaptcha,
+}
+
+impl FromStr for CaptchaProvider {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<CaptchaProvider, String> {
+        match val.to_ascii_lowercase().as_str() {
+            "recaptcha" => Ok(CaptchaProvider::Recaptcha),
+            "hcaptcha" => Ok(CaptchaProvider::Hcaptcha),
+            _ => Err(format!("Unknown captcha provider \"{}\".", val)),
+        }
+    }
+}
+
+impl CaptchaProvider {
+    fn verify_url(&self) -> &'static str {
+        match self {
+            CaptchaProvider::Recaptcha => "https://www.google.com/recaptcha/api/siteverify",
+            CaptchaProvider::Hcaptcha => "https://api.hcaptcha.com/siteverify",
+        }
+    }
+}
+
+// What the index page needs to show the captcha on its forms.
+#[derive(Serialize, Debug, Clone)]
+pub struct CaptchaWidget {
+    script_url: &'static str,
+    class: &'static str,
+    site_key: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyResponse {
+    success: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Captcha {
+    provider: CaptchaProvider,
+    site_key: String,
+    secret_key: String,
+}
+
+impl Captcha {
+    pub fn new(config: &CaptchaConfig) -> Captcha {
+        Captcha {
+            provider: config.provider,
+            site_key: config.site_key.clone(),
+            secret_key: config.secret_key.clone(),
+        }
+    }
+
+    pub fn widget(&self) -> CaptchaWidget {
+        match self.provider {
+            CaptchaProvider::Recaptcha => CaptchaWidget {
+                script_url: "https://www.google.com/recaptcha/api.js",
+                class: "g-recaptcha",
+                site_key: self.site_key.clone(),
+            },
+            CaptchaProvider::Hcaptcha => CaptchaWidget {
+                script_url: "https://js.hcaptcha.com/1/api.js",
+                class: "h-captcha",
+                site_key: self.site_key.clone(),
+            },
+        }
+    }
+
+    // Ask the provider whether the token the widget put in the form is genuine.
+    async fn verify(&self, http: &dyn HttpClient, token: &str, remote_ip: Option<&str>) -> Result<bool, String> {
+        let mut form = vec![("secret", self.secret_key.as_str()), ("response", token)];
+        if let Some(ip) = remote_ip {
+            form.push(("remoteip", ip));
+        }
+        let response = match http.post_form(self.provider.verify_url(), &form).await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Error contacting the captcha provider: {}", why));
+            }
+        };
+        if response.status != 200 {
+            return Err(format!("The captcha provider answered with status {}.", response.status));
+        }
+        match serde_json::from_str::<VerifyResponse>(&response.body) {
+            Ok(val) => Ok(val.success),
+            Err(why) => Err(format!("Invalid response from the captcha provider: {}", why)),
+        }
+    }
+}
+
+impl AppState {
+    pub fn captcha_widget(&self) -> Option<CaptchaWidget> {
+        self.captcha.as_ref().map(|captcha| captcha.widget())
+    }
+
+    // Passes when no captcha is configured.
+    pub async fn check_captcha(&self, token: &Option<String>, remote_ip: Option<&str>) -> Result<(), AppError> {
+        let captcha = match &self.captcha {
+            Some(val) => val,
+            None => {
+                return Ok(());
+            }
+        };
+        let token = match token {
+            Some(val) if !val.is_empty() => val,
+            _ => {
+                return Err(AppError::validation("captcha", "Please complete the captcha."));
+            }
+        };
+        match captcha.verify(self.http.as_ref(), token, remote_ip).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(AppError::validation("captcha", "The captcha could not be verified. Please try again.")),
+            Err(why) => Err(AppError::Internal(why)),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::money::format_money;
+
+const WIDTH: f64 = 600.0;
+const BAR_HEIGHT: f64 = 28.0;
+const LEGEND_ROW: f64 = 22.0;
+const COLORS: [&str; 5] = ["#4e79a7", "#f28e2b", "#59a14f", "#e15759", "#76b7b2"];
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// An inline SVG of one stacked bar with a legend, showing how much of the estimate each part makes up.
+// Zero amounts are left out of the bar but still listed.
+pub fn breakdown_svg(segments: &[(&str, Decimal)]) -> String {
+    let total = segments.iter().fold(Decimal::new(000, 2), |sum, (_, amount)| sum + amount);
+    let height = BAR_HEIGHT + 10.0 + LEGEND_ROW * segments.len() as f64;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\" aria-label=\"Cost breakdown\">",
+        WIDTH, height
+    );
+
+    let mut x = 0.0;
+    for (i, (label, amount)) in segments.iter().enumerate() {
+        let share = if total.is_zero() { 0.0 } else { (amount / total).to_f64().unwrap_or(0.0) };
+        let color = COLORS[i % COLORS.len()];
+        if share > 0.0 {
+            svg += &format!(
+                "<rect x=\"{:.1}\" y=\"0\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"><title>{}</title></rect>",
+                x, share * WIDTH, BAR_HEIGHT, color, escape(label)
+            );
+            x += share * WIDTH;
+        }
+        let y = BAR_HEIGHT + 10.0 + LEGEND_ROW * i as f64;
+        svg += &format!(
+            "<rect x=\"0\" y=\"{:.1}\" width=\"14\" height=\"14\" fill=\"{}\" /><text x=\"20\" y=\"{:.1}\" font-size=\"14\" fill=\"currentColor\">{}: {} ({:.0}%)</text>",
+            y, color, y + 12.0, escape(label), format_money(*amount), share * 100.0
+        );
+    }
+
+    svg + "</svg>"
+}
+
+const SPARK_WIDTH: f64 = 160.0;
+const SPARK_HEIGHT: f64 = 32.0;
+const SPARK_PAD: f64 = 3.0;
+
+// A small inline SVG line of the estimate for each term, oldest on the left, with the amount on
+// hover. Needs two terms to draw a line; a flat line sits in the middle.
+pub fn sparkline_svg(points: &[(&str, Decimal)]) -> Option<String> {
+    if points.len() < 2 {
+        return None;
+    }
+    let values: Vec<f64> = points.iter().map(|(_, amount)| amount.to_f64().unwrap_or(0.0)).collect();
+    let low = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let step = (SPARK_WIDTH - 2.0 * SPARK_PAD) / (points.len() - 1) as f64;
+    let position = |i: usize, value: f64| {
+        let y = if high > low {
+            SPARK_HEIGHT - SPARK_PAD - (value - low) / (high - low) * (SPARK_HEIGHT - 2.0 * SPARK_PAD)
+        } else {
+            SPARK_HEIGHT / 2.0
+        };
+        (SPARK_PAD + step * i as f64, y)
+    };
+
+    let first = points.first().map(|(term, _)| *term).unwrap_or_default();
+    let last = points.last().map(|(term, _)| *term).unwrap_or_default();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\" aria-label=\"Estimate by term, {} to {}\">",
+        SPARK_WIDTH, SPARK_HEIGHT, escape(first), escape(last)
+    );
+    let line: Vec<String> = values.iter().enumerate().map(|(i, value)| {
+        let (x, y) = position(i, *value);
+        format!("{:.1},{:.1}", x, y)
+    }).collect();
+    svg += &format!("<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" />", line.join(" "), COLORS[0]);
+    for (i, ((term, amount), value)) in points.iter().zip(&values).enumerate() {
+        let (x, y) = position(i, *value);
+        svg += &format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2.5\" fill=\"{}\"><title>{}: {}</title></circle>",
+            x, y, COLORS[0], escape(term), format_money(*amount)
+        );
+    }
+
+    Some(svg + "</svg>")
+}
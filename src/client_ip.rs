@@ -0,0 +1,157 @@
+use actix_web::{http::header, web, HttpRequest};
+use std::{net::IpAddr, str::FromStr};
+
+use crate::AppState;
+
+// An address or CIDR block, e.g. 10.0.0.0/8 or ::1.
+#[derive(Debug, Clone, Copy)]
+struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Network, String> {
+        let (addr, prefix) = match val.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (val, None),
+        };
+        let addr = match addr.trim().parse::<IpAddr>() {
+            Ok(val) => val,
+            Err(_) => {
+                return Err(format!("\"{}\" is not an IP address.", addr));
+            }
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(val) => match val.trim().parse::<u8>() {
+                Ok(val) if val <= max => val,
+                _ => {
+                    return Err(format!("\"{}\" is not a valid prefix length for {}.", val, addr));
+                }
+            },
+            None => max,
+        };
+        Ok(Network { addr, prefix })
+    }
+}
+
+impl Network {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => mask_matches(&net.octets(), &ip.octets(), self.prefix),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => mask_matches(&net.octets(), &ip.octets(), self.prefix),
+            _ => false,
+        }
+    }
+}
+
+fn mask_matches(net: &[u8], ip: &[u8], prefix: u8) -> bool {
+    let mut bits = prefix as usize;
+    for (net, ip) in net.iter().zip(ip) {
+        if bits == 0 {
+            return true;
+        }
+        let mask = if bits >= 8 { 0xff } else { 0xffu8 << (8 - bits) };
+        if net & mask != ip & mask {
+            return false;
+        }
+        bits = bits.saturating_sub(8);
+    }
+    true
+}
+
+// The reverse proxies allowed to say who the client is. Forwarding headers from anyone else are
+// ignored, since a client can send whatever it likes.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<Network>,
+}
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<TrustedProxies, String> {
+        let mut networks = Vec::new();
+        for part in val.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            networks.push(part.parse::<Network>()?);
+        }
+        Ok(TrustedProxies { networks })
+    }
+}
+
+impl TrustedProxies {
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    // Each proxy appends the address it got the request from, so walk back from the nearest hop
+    // and stop at the first one that isn't ours.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: &[IpAddr]) -> IpAddr {
+        if !self.trusts(peer) {
+            return peer;
+        }
+        self.nearest_untrusted(forwarded_for).unwrap_or(peer)
+    }
+
+    // For a request from a peer that's trusted already. None when the chain is empty.
+    fn nearest_untrusted(&self, forwarded_for: &[IpAddr]) -> Option<IpAddr> {
+        let mut client = None;
+        for hop in forwarded_for.iter().rev() {
+            client = Some(*hop);
+            if !self.trusts(*hop) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+// An address as it appears in Forwarded or X-Forwarded-For: maybe quoted, maybe with a port,
+// IPv6 maybe in brackets.
+fn parse_hop(val: &str) -> Option<IpAddr> {
+    let val = val.trim().trim_matches('"');
+    if let Ok(ip) = val.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Some(rest) = val.strip_prefix('[') {
+        return rest.split(']').next()?.parse().ok();
+    }
+    val.rsplit_once(':')?.0.parse().ok()
+}
+
+// The chain of client addresses the proxies reported, oldest first. The standard Forwarded
+// header wins over X-Forwarded-For when a request has both.
+fn forwarded_for(req: &HttpRequest) -> Vec<IpAddr> {
+    let headers = req.headers();
+    let forwarded: Vec<&str> = headers.get_all(header::FORWARDED).filter_map(|val| val.to_str().ok()).collect();
+    if !forwarded.is_empty() {
+        return forwarded.iter()
+            .flat_map(|val| val.split(','))
+            .filter_map(|element| element.split(';').find_map(|pair| {
+                let (key, val) = pair.split_once('=')?;
+                if key.trim().eq_ignore_ascii_case("for") { parse_hop(val) } else { None }
+            }))
+            .collect();
+    }
+    headers.get_all(header::X_FORWARDED_FOR)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .filter_map(parse_hop)
+        .collect()
+}
+
+// Who actually sent the request, for captcha checks, the logs and the request metadata. Over
+// UNIX_SOCKET there's no peer address, and only a proxy on this machine can connect, so it's
+// trusted like one in TRUSTED_PROXIES. None when it didn't say who the client was.
+pub fn for_request(req: &HttpRequest) -> Option<IpAddr> {
+    let state = req.app_data::<web::Data<AppState>>();
+    match (req.peer_addr(), state) {
+        (Some(peer), Some(state)) => Some(state.trusted_proxies.client_ip(peer.ip(), &forwarded_for(req))),
+        (Some(peer), None) => Some(peer.ip()),
+        (None, Some(state)) => state.trusted_proxies.nearest_untrusted(&forwarded_for(req)),
+        (None, None) => TrustedProxies::default().nearest_untrusted(&forwarded_for(req)),
+    }
+}
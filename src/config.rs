@@ -0,0 +1,688 @@
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{env, fmt, path::Path, str::FromStr, time::Duration};
+
+use crate::{captcha::CaptchaProvider, client_ip::TrustedProxies, fees::FeeSchedule, http_client::ReqwestClient, mailer::Mailer, retention::RetentionAction, passwords::PasswordHash, screening::ScreeningMode};
+
+// Certificate and key for serving HTTPS. HTTP/2 is negotiated automatically over TLS.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_file: String,
+    pub key_file: String,
+}
+
+impl TlsConfig {
+    // The acceptor for one bound address, with the key and certificate chain read in.
+    pub fn acceptor(&self) -> Result<SslAcceptorBuilder, String> {
+        let mut builder = match SslAcceptor::mozilla_intermediate(SslMethod::tls()) {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Error setting up TLS: {}", why));
+            }
+        };
+        if let Err(why) = builder.set_private_key_file(&self.key_file, SslFiletype::PEM) {
+            return Err(format!("TLS_KEY_FILE \"{}\" can't be used: {}", self.key_file, why));
+        }
+        if let Err(why) = builder.set_certificate_chain_file(&self.cert_file) {
+            return Err(format!("TLS_CERT_FILE \"{}\" can't be used: {}", self.cert_file, why));
+        }
+        Ok(builder)
+    }
+}
+
+// HTTP server tuning. Anything left unset keeps the actix-web default.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub workers: Option<usize>,
+    // None disables keep-alive.
+    pub keep_alive: Option<Duration>,
+    pub client_request_timeout: Option<Duration>,
+    pub client_disconnect_timeout: Option<Duration>,
+    pub max_connections: Option<usize>,
+    pub backlog: Option<u32>,
+    pub tls: Option<TlsConfig>,
+}
+
+// Outgoing mail. Port defaults to 587 with STARTTLS.
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+}
+
+// Who gets the nightly summary, and the local hour it goes out for the previous day.
+#[derive(Debug, Clone)]
+pub struct SummaryConfig {
+    pub recipients: Vec<String>,
+    pub hour: u32,
+}
+
+// How long calculations are kept before the nightly job anonymizes or purges them. A dry run
+// only records in the audit log how many it would have changed.
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    pub days: u32,
+    pub action: RetentionAction,
+    pub dry_run: bool,
+    pub hour: u32,
+}
+
+// Captcha on the public forms; verification happens on the server with the secret key.
+#[derive(Debug, Clone)]
+pub struct CaptchaConfig {
+    pub provider: CaptchaProvider,
+    pub site_key: String,
+    pub secret_key: String,
+}
+
+// Staff sign in to /admin with their campus account through an OpenID Connect provider
+// (Entra ID, Okta, Google Workspace, Shibboleth's OIDC module...). `allowed_groups`, when set,
+// is checked against the ID token's groups claim.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+    pub allowed_groups: Vec<String>,
+    pub session_hours: u32,
+}
+
+// Database connection pool. A request that can't get a connection within the acquire
+// timeout fails as busy instead of queueing indefinitely. Each connection keeps up to
+// `statement_cache_capacity` prepared statements; 0 prepares every query afresh.
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    // Opened at startup and kept open while idle.
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    pub statement_cache_capacity: usize,
+}
+
+// Estimates whose save failed while the database was out, kept for up to `max_pending` and
+// retried every `interval`. With `file` set they survive a restart.
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    pub file: Option<String>,
+    pub max_pending: usize,
+    pub interval: Duration,
+}
+
+// The shared client for calls to outside services.
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub proxy: Option<String>,
+}
+
+// Where request traces are sent, as OTLP over HTTP.
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    // The collector's base URL; traces go to its /v1/traces.
+    pub endpoint: String,
+    pub service_name: String,
+}
+
+// The school's name, logo, color and contact lines on every page, printed receipt and email,
+// so another school can deploy the calculator without editing the templates. All optional.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BrandingConfig {
+    pub name: Option<String>,
+    // A URL, or a path on this host, e.g. one the proxy serves.
+    pub logo: Option<String>,
+    // A CSS hex color. It goes into a style element, so nothing else is accepted.
+    pub primary_color: Option<String>,
+    // Shown in the footer.
+    pub contact_lines: Vec<String>,
+}
+
+fn hex_color(val: &str) -> bool {
+    match val.strip_prefix('#') {
+        Some(digits) => matches!(digits.len(), 3 | 6) && digits.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+// The school's letterhead on printed receipts. The name falls back to the branding name, then
+// the campus name.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LetterheadConfig {
+    pub name: Option<String>,
+    pub address_lines: Vec<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub website: Option<String>,
+}
+
+// Set when REQUIRE_CONSENT is on: estimates are only saved when the student ticks the consent
+// box, and are shown without being kept otherwise.
+#[derive(Serialize, Debug, Clone)]
+pub struct ConsentConfig {
+    // Linked from the consent box.
+    pub privacy_notice_url: Option<String>,
+}
+
+// Rates to estimate with when a campus has no CreditCosts row for the student's level of study
+// and residency. The non-residency fee is only charged to non-resident and international students.
+#[derive(Debug, Clone)]
+pub struct DefaultRatesConfig {
+    pub credits_cost: Decimal,
+    pub nonresidency_fee: Decimal,
+}
+
+// What is recorded about the request behind each calculation. Referrers starting with one of
+// `counselor_referrers` count as counselor-entered in the stats.
+#[derive(Clone)]
+pub struct RequestMetadataConfig {
+    pub enabled: bool,
+    pub ip_salt: Option<String>,
+    pub counselor_referrers: Vec<String>,
+}
+
+impl fmt::Debug for RequestMetadataConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestMetadataConfig")
+            .field("enabled", &self.enabled)
+            .field("counselor_referrers", &self.counselor_referrers)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub database_url: String,
+    // A read-only replica for lookups, search and stats. Writes always go to DATABASE_URL.
+    pub read_database_url: Option<String>,
+    // Reads fail over to this database while DATABASE_URL isn't answering.
+    pub secondary_database_url: Option<String>,
+    // How often the primary is pinged to notice it going down and coming back.
+    pub failover_probe_interval: Duration,
+    pub pool: PoolConfig,
+    // host:port pairs to listen on; HOST and PORT when BIND_ADDRESSES isn't set.
+    pub bind_addresses: Vec<String>,
+    // Also listen on this Unix domain socket, e.g. for an nginx upstream.
+    pub unix_socket: Option<String>,
+    pub fee_schedule_file: Option<String>,
+    pub server: ServerConfig,
+    pub smtp: Option<SmtpConfig>,
+    pub summary: Option<SummaryConfig>,
+    pub retention: Option<RetentionConfig>,
+    pub replay: ReplayConfig,
+    pub captcha: Option<CaptchaConfig>,
+    pub http_client: HttpClientConfig,
+    // Signs the session cookie; at least 64 bytes.
+    pub session_key: Option<Vec<u8>>,
+    // Signs the links students share with parents; at least 32 bytes.
+    pub share_key: Option<Vec<u8>>,
+    pub share_link_days: u32,
+    pub branding: BrandingConfig,
+    pub letterhead: LetterheadConfig,
+    pub request_metadata: RequestMetadataConfig,
+    pub consent: Option<ConsentConfig>,
+    // Price a sample estimate at every campus before serving, and refuse to start if one can't.
+    pub self_test: bool,
+    // Set when DEFAULT_CREDITS_COST is; otherwise a missing rate is an error.
+    pub default_rates: Option<DefaultRatesConfig>,
+    // Header the proxy guarding /admin puts the signed-in staff member's name in.
+    pub admin_user_header: Option<String>,
+    // Set when the app signs staff in itself instead of leaving /admin to the proxy.
+    pub oidc: Option<OidcConfig>,
+    // Staff names and their argon2id or bcrypt password hashes, for HTTP Basic auth on /admin when
+    // there's no sign-in provider or proxy to guard it. Empty leaves it off.
+    pub admin_basic_auth: Vec<(String, PasswordHash)>,
+    // Proxies whose Forwarded and X-Forwarded-For headers are believed.
+    pub trusted_proxies: TrustedProxies,
+    // What's done with public form submissions that look like attacks.
+    pub screening: ScreeningMode,
+    // How long the calculator page is served from memory before it's rendered again.
+    pub index_cache_ttl: Duration,
+    // How long the rates behind /api/v1/preview are kept before they're looked up again.
+    pub preview_cache_ttl: Duration,
+    // Queries taking at least this long are written to SlowQueries. Unset leaves it off.
+    pub slow_query_threshold: Option<Duration>,
+    // Set when OTEL_EXPORTER_OTLP_ENDPOINT is; otherwise nothing is traced.
+    pub telemetry: Option<TelemetryConfig>,
+    // Origins whose pages may fetch /api/v1/rates from the browser, or "*" for any. Empty sends
+    // no CORS headers.
+    pub rates_cors_origins: Vec<String>,
+    // Requests each client address may make to /api/v1/rates a minute.
+    pub rates_requests_per_minute: u32,
+    // Shared store for sessions and the page cache when running more than one replica.
+    #[cfg(feature = "redis")]
+    pub redis_url: Option<String>,
+}
+
+// Everything wrong with the environment, gathered up so it can be fixed in one go
+// instead of one restart per missing variable.
+#[derive(Debug, Default)]
+pub struct ConfigReport {
+    pub problems: Vec<String>,
+    pub defaults: Vec<String>,
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.problems.is_empty() {
+            writeln!(f, "The configuration has {} problem(s):", self.problems.len())?;
+            for problem in &self.problems {
+                writeln!(f, "  - {}", problem)?;
+            }
+        }
+        if !self.defaults.is_empty() {
+            writeln!(f, "Using defaults for:")?;
+            for default in &self.defaults {
+                writeln!(f, "  - {}", default)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConfigReport {
+    // An optional variable; present but unparsable is a problem rather than a silent default.
+    fn optional<T: FromStr>(&mut self, name: &str) -> Option<T> {
+        match env::var(name) {
+            Ok(val) if !val.trim().is_empty() => match val.trim().parse::<T>() {
+                Ok(parsed) => Some(parsed),
+                Err(_) => {
+                    self.problems.push(format!("{} has an invalid value \"{}\".", name, val));
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    fn required(&mut self, name: &str, description: &str) -> String {
+        match self.optional::<String>(name) {
+            Some(val) => val,
+            None => {
+                self.problems.push(format!("{} ({}) is not set.", name, description));
+                String::new()
+            }
+        }
+    }
+
+    fn or_default<T: FromStr + fmt::Display>(&mut self, name: &str, default: T) -> T {
+        let problems = self.problems.len();
+        match self.optional::<T>(name) {
+            Some(val) => val,
+            // Only report the default when the variable was left out, not when it was invalid.
+            None if self.problems.len() > problems => default,
+            None => {
+                self.defaults.push(format!("{}={}", name, default));
+                default
+            }
+        }
+    }
+
+    fn requires(&mut self, name: &str, needed_with: &str) -> Option<String> {
+        let val = self.optional::<String>(name);
+        if val.is_none() {
+            self.problems.push(format!("{} must be set when {} is.", name, needed_with));
+        }
+        val
+    }
+}
+
+impl AppConfig {
+    // Reads every variable before giving up, so the report lists all the problems at once.
+    pub fn from_env() -> Result<(AppConfig, ConfigReport), ConfigReport> {
+        let mut report = ConfigReport::default();
+
+        let database_url = report.required("DATABASE_URL", "the database connection URL");
+        let bind_addresses = match report.optional::<String>("BIND_ADDRESSES") {
+            Some(val) => val.split(',').map(|address| address.trim().to_string()).filter(|address| !address.is_empty()).collect(),
+            None => {
+                let host = report.or_default("HOST", "127.0.0.1".to_string());
+                let port = report.or_default("PORT", 8080u16);
+                vec![format!("{}:{}", host, port)]
+            }
+        };
+        let unix_socket = report.optional::<String>("UNIX_SOCKET");
+        if unix_socket.is_some() && !cfg!(unix) {
+            report.problems.push("UNIX_SOCKET is only supported on Unix systems.".to_string());
+        }
+
+        let tls = match (report.optional::<String>("TLS_CERT_FILE"), report.optional::<String>("TLS_KEY_FILE")) {
+            (Some(cert_file), Some(key_file)) => Some(TlsConfig { cert_file, key_file }),
+            (None, None) => None,
+            _ => {
+                report.problems.push("TLS_CERT_FILE and TLS_KEY_FILE must be set together.".to_string());
+                None
+            }
+        };
+
+        let smtp = report.optional::<String>("SMTP_HOST").map(|host| SmtpConfig {
+            host,
+            port: report.optional("SMTP_PORT"),
+            username: report.optional("SMTP_USERNAME"),
+            password: report.optional("SMTP_PASSWORD"),
+            from: report.requires("SMTP_FROM", "SMTP_HOST").unwrap_or_default(),
+        });
+
+        let summary = match report.optional::<String>("SUMMARY_RECIPIENTS") {
+            Some(val) => {
+                let hour = report.optional::<u32>("SUMMARY_HOUR").unwrap_or(1);
+                if hour > 23 {
+                    report.problems.push("SUMMARY_HOUR must be between 0 and 23.".to_string());
+                }
+                if smtp.is_none() {
+                    report.problems.push("SUMMARY_RECIPIENTS needs SMTP_HOST to be set.".to_string());
+                }
+                Some(SummaryConfig {
+                    recipients: val.split(',').map(|recipient| recipient.trim().to_string()).filter(|recipient| !recipient.is_empty()).collect(),
+                    hour,
+                })
+            }
+            None => None,
+        };
+
+        let retention = match report.optional::<u32>("RETENTION_DAYS") {
+            Some(0) => {
+                report.problems.push("RETENTION_DAYS must be at least 1.".to_string());
+                None
+            }
+            Some(days) => {
+                let hour = report.or_default("RETENTION_HOUR", 3u32);
+                if hour > 23 {
+                    report.problems.push("RETENTION_HOUR must be between 0 and 23.".to_string());
+                }
+                Some(RetentionConfig {
+                    days,
+                    action: report.or_default("RETENTION_ACTION", RetentionAction::Anonymize),
+                    dry_run: match report.or_default("RETENTION_DRY_RUN", "off".to_string()).as_str() {
+                        "on" => true,
+                        "off" => false,
+                        val => {
+                            report.problems.push(format!("RETENTION_DRY_RUN must be on or off, not \"{}\".", val));
+                            true
+                        }
+                    },
+                    hour,
+                })
+            }
+            None => None,
+        };
+
+        let captcha = report.optional::<CaptchaProvider>("CAPTCHA_PROVIDER").map(|provider| CaptchaConfig {
+            provider,
+            site_key: report.requires("CAPTCHA_SITE_KEY", "CAPTCHA_PROVIDER").unwrap_or_default(),
+            secret_key: report.requires("CAPTCHA_SECRET_KEY", "CAPTCHA_PROVIDER").unwrap_or_default(),
+        });
+
+        let oidc = match report.optional::<String>("OIDC_ISSUER") {
+            Some(issuer) => {
+                // The ID token is trusted because it comes straight from the provider over TLS.
+                if !issuer.starts_with("https://") {
+                    report.problems.push("OIDC_ISSUER must be an https:// URL.".to_string());
+                }
+                let session_hours = report.or_default("STAFF_SESSION_HOURS", 8u32);
+                if session_hours == 0 {
+                    report.problems.push("STAFF_SESSION_HOURS must be at least 1.".to_string());
+                }
+                Some(OidcConfig {
+                    issuer: issuer.trim_end_matches('/').to_string(),
+                    client_id: report.requires("OIDC_CLIENT_ID", "OIDC_ISSUER").unwrap_or_default(),
+                    client_secret: report.requires("OIDC_CLIENT_SECRET", "OIDC_ISSUER").unwrap_or_default(),
+                    redirect_url: report.requires("OIDC_REDIRECT_URL", "OIDC_ISSUER").unwrap_or_default(),
+                    allowed_groups: match report.optional::<String>("OIDC_ALLOWED_GROUPS") {
+                        Some(val) => val.split(',').map(|group| group.trim().to_string()).filter(|group| !group.is_empty()).collect(),
+                        None => Vec::new(),
+                    },
+                    session_hours,
+                })
+            }
+            None => None,
+        };
+
+        let mut admin_basic_auth = Vec::new();
+        if let Some(val) = report.optional::<String>("ADMIN_BASIC_AUTH") {
+            // Separated by spaces, since argon2 hashes have commas in them.
+            for entry in val.split_whitespace() {
+                match entry.split_once(':') {
+                    Some((name, hash)) if !name.is_empty() => match PasswordHash::parse(hash) {
+                        Ok(hash) => admin_basic_auth.push((name.to_string(), hash)),
+                        Err(why) => report.problems.push(format!("ADMIN_BASIC_AUTH's hash for \"{}\" {}.", name, why)),
+                    },
+                    // Not shown, in case it's a password.
+                    _ => report.problems.push("ADMIN_BASIC_AUTH entries must be name:hash with an argon2id or bcrypt hash.".to_string()),
+                }
+            }
+            if oidc.is_some() {
+                report.problems.push("ADMIN_BASIC_AUTH can't be used together with OIDC_ISSUER.".to_string());
+            }
+        }
+
+        let branding = BrandingConfig {
+            name: report.optional("BRAND_NAME"),
+            logo: report.optional("BRAND_LOGO"),
+            primary_color: match report.optional::<String>("BRAND_COLOR") {
+                Some(val) if hex_color(&val) => Some(val),
+                Some(val) => {
+                    report.problems.push(format!("BRAND_COLOR must be a hex color like #00447c, not \"{}\".", val));
+                    None
+                }
+                None => None,
+            },
+            contact_lines: match report.optional::<String>("BRAND_CONTACT") {
+                Some(val) => val.split('|').map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect(),
+                None => Vec::new(),
+            },
+        };
+
+        let session_key = match report.optional::<String>("SESSION_KEY") {
+            Some(val) => match hex::decode(&val) {
+                Ok(bytes) if bytes.len() >= 64 => Some(bytes),
+                _ => {
+                    report.problems.push("SESSION_KEY must be at least 64 bytes of hex.".to_string());
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let redis_url = report.optional::<String>("REDIS_URL");
+        if redis_url.is_some() {
+            if !cfg!(feature = "redis") {
+                report.problems.push("REDIS_URL is set, but this build doesn't include Redis support; build with --features redis.".to_string());
+            }
+            // Every replica has to sign the session cookie with the same key.
+            if session_key.is_none() && std::env::var("SESSION_KEY").is_err() {
+                report.problems.push("SESSION_KEY must be set when REDIS_URL is.".to_string());
+            }
+        }
+
+        let share_key = match report.optional::<String>("SHARE_LINK_KEY") {
+            Some(val) => match hex::decode(&val) {
+                Ok(bytes) if bytes.len() >= 32 => Some(bytes),
+                _ => {
+                    report.problems.push("SHARE_LINK_KEY must be at least 32 bytes of hex.".to_string());
+                    None
+                }
+            },
+            None => None,
+        };
+        let request_metadata = RequestMetadataConfig {
+            enabled: match report.or_default("REQUEST_METADATA", "on".to_string()).as_str() {
+                "on" => true,
+                "off" => false,
+                val => {
+                    report.problems.push(format!("REQUEST_METADATA must be on or off, not \"{}\".", val));
+                    false
+                }
+            },
+            ip_salt: report.optional("REQUEST_METADATA_SALT"),
+            counselor_referrers: match report.optional::<String>("COUNSELOR_REFERRERS") {
+                Some(val) => val.split(',').map(|prefix| prefix.trim().to_string()).filter(|prefix| !prefix.is_empty()).collect(),
+                None => Vec::new(),
+            },
+        };
+
+        let consent = match report.or_default("REQUIRE_CONSENT", "off".to_string()).as_str() {
+            "on" => Some(ConsentConfig { privacy_notice_url: report.optional("PRIVACY_NOTICE_URL") }),
+            "off" => None,
+            val => {
+                report.problems.push(format!("REQUIRE_CONSENT must be on or off, not \"{}\".", val));
+                None
+            }
+        };
+
+        let default_rates = match report.optional::<Decimal>("DEFAULT_CREDITS_COST") {
+            Some(credits_cost) => {
+                let nonresidency_fee = report.optional::<Decimal>("DEFAULT_NONRESIDENCY_FEE").unwrap_or(Decimal::ZERO);
+                if credits_cost.is_sign_negative() || nonresidency_fee.is_sign_negative() {
+                    report.problems.push("DEFAULT_CREDITS_COST and DEFAULT_NONRESIDENCY_FEE can't be negative.".to_string());
+                }
+                Some(DefaultRatesConfig { credits_cost, nonresidency_fee })
+            }
+            None => None,
+        };
+
+        let self_test = match report.or_default("STARTUP_SELF_TEST", "on".to_string()).as_str() {
+            "on" => true,
+            "off" => false,
+            val => {
+                report.problems.push(format!("STARTUP_SELF_TEST must be on or off, not \"{}\".", val));
+                true
+            }
+        };
+
+        let share_link_days = report.or_default("SHARE_LINK_DAYS", 14u32);
+        if share_link_days == 0 {
+            report.problems.push("SHARE_LINK_DAYS must be at least 1.".to_string());
+        }
+
+        // Origins are compared as the browser sends them: scheme, host and port, no path.
+        let rates_cors_origins: Vec<String> = match report.optional::<String>("RATES_CORS_ORIGINS") {
+            Some(val) => val.split(',').map(|origin| origin.trim().trim_end_matches('/').to_string()).filter(|origin| !origin.is_empty()).collect(),
+            None => Vec::new(),
+        };
+        for origin in &rates_cors_origins {
+            if origin != "*" && !origin.starts_with("https://") && !origin.starts_with("http://") {
+                report.problems.push(format!("RATES_CORS_ORIGINS must be \"*\" or origins like https://www.example.edu, not \"{}\".", origin));
+            }
+        }
+
+        let rates_requests_per_minute = report.or_default("RATES_REQUESTS_PER_MINUTE", 60u32);
+        if rates_requests_per_minute == 0 {
+            report.problems.push("RATES_REQUESTS_PER_MINUTE must be at least 1.".to_string());
+        }
+
+        let config = AppConfig {
+            database_url,
+            read_database_url: report.optional("READ_DATABASE_URL"),
+            secondary_database_url: report.optional("SECONDARY_DATABASE_URL"),
+            failover_probe_interval: Duration::from_secs(report.optional::<u64>("FAILOVER_PROBE_SECS").unwrap_or(10).max(1)),
+            pool: PoolConfig {
+                max_connections: report.optional("DB_MAX_CONNECTIONS").unwrap_or(10),
+                min_connections: report.optional("DB_MIN_CONNECTIONS").unwrap_or(2),
+                acquire_timeout: Duration::from_millis(report.optional("DB_ACQUIRE_TIMEOUT_MS").unwrap_or(3000)),
+                statement_cache_capacity: report.optional("DB_STATEMENT_CACHE_CAPACITY").unwrap_or(100),
+            },
+            bind_addresses,
+            unix_socket,
+            fee_schedule_file: report.optional("FEE_SCHEDULE_FILE"),
+            server: ServerConfig {
+                workers: report.optional("WORKERS"),
+                keep_alive: match report.optional::<u64>("KEEP_ALIVE_SECS") {
+                    Some(0) => None,
+                    Some(secs) => Some(Duration::from_secs(secs)),
+                    None => Some(Duration::from_secs(5)),
+                },
+                client_request_timeout: report.optional::<u64>("CLIENT_REQUEST_TIMEOUT_MS").map(Duration::from_millis),
+                client_disconnect_timeout: report.optional::<u64>("CLIENT_DISCONNECT_TIMEOUT_MS").map(Duration::from_millis),
+                max_connections: report.optional("MAX_CONNECTIONS"),
+                backlog: report.optional("BACKLOG"),
+                tls,
+            },
+            smtp,
+            summary,
+            retention,
+            replay: ReplayConfig {
+                file: report.optional("REPLAY_QUEUE_FILE"),
+                max_pending: report.optional("REPLAY_QUEUE_MAX").unwrap_or(1000),
+                interval: Duration::from_secs(report.optional::<u64>("REPLAY_INTERVAL_SECS").unwrap_or(15).max(1)),
+            },
+            captcha,
+            http_client: HttpClientConfig {
+                timeout: Duration::from_millis(report.optional("HTTP_TIMEOUT_MS").unwrap_or(10000)),
+                connect_timeout: Duration::from_millis(report.optional("HTTP_CONNECT_TIMEOUT_MS").unwrap_or(3000)),
+                proxy: report.optional("OUTBOUND_PROXY"),
+            },
+            session_key,
+            share_key,
+            share_link_days,
+            branding,
+            letterhead: LetterheadConfig {
+                name: report.optional("LETTERHEAD_NAME"),
+                address_lines: match report.optional::<String>("LETTERHEAD_ADDRESS") {
+                    Some(val) => val.split('|').map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect(),
+                    None => Vec::new(),
+                },
+                phone: report.optional("LETTERHEAD_PHONE"),
+                email: report.optional("LETTERHEAD_EMAIL"),
+                website: report.optional("LETTERHEAD_WEBSITE"),
+            },
+            request_metadata,
+            consent,
+            self_test,
+            default_rates,
+            admin_user_header: report.optional("ADMIN_USER_HEADER"),
+            oidc,
+            admin_basic_auth,
+            trusted_proxies: report.optional("TRUSTED_PROXIES").unwrap_or_default(),
+            screening: report.or_default("SCREENING", ScreeningMode::Log),
+            index_cache_ttl: Duration::from_secs(report.or_default("INDEX_CACHE_SECS", 10u64)),
+            preview_cache_ttl: Duration::from_secs(report.or_default("PREVIEW_CACHE_SECS", 60u64)),
+            slow_query_threshold: report.optional::<u64>("SLOW_QUERY_MS").map(Duration::from_millis),
+            telemetry: report.optional::<String>("OTEL_EXPORTER_OTLP_ENDPOINT").map(|endpoint| TelemetryConfig {
+                endpoint,
+                service_name: report.or_default("OTEL_SERVICE_NAME", "tuition-calculator".to_string()),
+            }),
+            rates_cors_origins,
+            rates_requests_per_minute,
+            #[cfg(feature = "redis")]
+            redis_url,
+        };
+
+        // What's only known to be wrong once something is built from it, built here and dropped so
+        // it's in the report too, instead of stopping the server halfway through starting.
+        if let Some(path) = &config.fee_schedule_file {
+            if let Err(why) = FeeSchedule::load(Path::new(path)) {
+                report.problems.push(format!("FEE_SCHEDULE_FILE can't be used: {}.", why));
+            }
+        }
+        if let Err(why) = ReqwestClient::new(&config.http_client) {
+            report.problems.push(format!("{}.", why));
+        }
+        if let Some(smtp) = &config.smtp {
+            if let Err(why) = Mailer::new(smtp) {
+                report.problems.push(format!("{}.", why));
+            }
+        }
+        #[cfg(feature = "redis")]
+        if let Some(url) = &config.redis_url {
+            if let Err(why) = crate::redis_store::Redis::new(url, Duration::from_secs(2)) {
+                report.problems.push(format!("{}.", why));
+            }
+        }
+        if let Some(tls) = &config.server.tls {
+            if let Err(why) = tls.acceptor() {
+                report.problems.push(format!("{}.", why));
+            }
+        }
+
+        if report.problems.is_empty() {
+            Ok((config, report))
+        } else {
+            Err(report)
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::{header::{self, HeaderName, HeaderValue}, Method, StatusCode},
+    middleware::Next,
+    web, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError,
+};
+use rand::RngCore;
+use serde::Serialize;
+use std::{collections::HashMap, fmt, future::{ready, Ready}};
+
+use crate::{logs, metrics, negotiate, renderer, AppState};
+
+// Everything a handler can fail with. The request context middleware turns these into the error page.
+#[derive(Debug)]
+pub enum AppError {
+    // A submitted value was missing or invalid; `field` is the form field name.
+    Validation { field: &'static str, message: String },
+    // The submission broke one or more of the campus's validation rules; every one is listed.
+    Rejected(Vec<FieldError>),
+    NotFound(String),
+    // Staff sign-in was refused or didn't complete.
+    Unauthorized(String),
+    // Estimates for the term aren't open to students right now; the message says when they are.
+    Closed(String),
+    // Input screening turned the submission away before it reached the handler.
+    Blocked(String),
+    Database(sqlx::Error),
+    // No database connection freed up within the acquire timeout.
+    Busy,
+    Internal(String),
+}
+
+// The machine-readable part of a JSON error, for clients to branch on instead of the message.
+// These names are part of the API: add new ones, but never rename or reuse them.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidRequest,
+    Unauthorized,
+    NotFound,
+    TermClosed,
+    RateLimited,
+    Busy,
+    DatabaseError,
+    InternalError,
+}
+
+impl ErrorCode {
+    // The only text a user is shown for a failure on our side, whatever the underlying error
+    // said. None for errors about the request, whose own message is safe to show.
+    pub fn generic_message(&self) -> Option<&'static str> {
+        match self {
+            ErrorCode::DatabaseError => Some("We couldn't reach the tuition records right now. Please try again in a few minutes."),
+            ErrorCode::Busy => Some("The server is busy right now. Please try again in a moment."),
+            ErrorCode::InternalError => Some("Something went wrong on our end. Please try again."),
+            _ => None,
+        }
+    }
+
+    // For a failure that didn't come from one of our handlers, e.g. a panic or an actix error.
+    fn for_status(status: StatusCode) -> ErrorCode {
+        match status {
+            StatusCode::SERVICE_UNAVAILABLE => ErrorCode::Busy,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorCode::Unauthorized,
+            status if status.is_client_error() => ErrorCode::InvalidRequest,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
+impl AppError {
+    pub fn validation(field: &'static str, message: &str) -> AppError {
+        AppError::Validation { field, message: message.to_string() }
+    }
+
+    // What the student sees. Database and internal details stay in the server log.
+    pub fn user_message(&self) -> String {
+        match self {
+            AppError::Validation { message, .. } => message.clone(),
+            AppError::Rejected(errors) => errors.iter().map(|error| error.message.as_str()).collect::<Vec<_>>().join(" "),
+            AppError::NotFound(message) => message.clone(),
+            AppError::Unauthorized(message) => message.clone(),
+            AppError::Closed(message) => message.clone(),
+            AppError::Blocked(message) => message.clone(),
+            why => why.code().generic_message().unwrap_or_default().to_string(),
+        }
+    }
+
+    // Everything we know, for the server log only. The Debug form of a database error has the
+    // server's error number and SQL state, which the Display form leaves out.
+    pub fn detail(&self) -> String {
+        match self {
+            AppError::Database(why) => format!("Error while accessing database: {:?}", why),
+            why => why.to_string(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Validation { .. } | AppError::Rejected(_) | AppError::Blocked(_) => ErrorCode::InvalidRequest,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Closed(_) => ErrorCode::TermClosed,
+            AppError::Database(_) => ErrorCode::DatabaseError,
+            AppError::Busy => ErrorCode::Busy,
+            AppError::Internal(_) => ErrorCode::InternalError,
+        }
+    }
+
+    // Every field the error is about, with its message.
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            AppError::Validation { field, message } => vec![FieldError { field, message: message.clone() }],
+            AppError::Rejected(errors) => errors.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Validation { field, message } => write!(f, "Invalid {}: {}", field, message),
+            AppError::Rejected(errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| format!("{}: {}", error.field, error.message)).collect();
+                write!(f, "Rejected by validation rules: {}", errors.join("; "))
+            }
+            AppError::NotFound(message) => write!(f, "Not found: {}", message),
+            AppError::Unauthorized(message) => write!(f, "Unauthorized: {}", message),
+            AppError::Closed(message) => write!(f, "Closed: {}", message),
+            AppError::Blocked(message) => write!(f, "Blocked: {}", message),
+            AppError::Database(why) => write!(f, "Error while accessing database: {}", why),
+            AppError::Busy => write!(f, "Timed out waiting for a database connection"),
+            AppError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(why: sqlx::Error) -> AppError {
+        match why {
+            sqlx::Error::PoolTimedOut => {
+                metrics::record_acquire_timeout();
+                AppError::Busy
+            }
+            why => AppError::Database(why),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Validation { .. } | AppError::Rejected(_) | AppError::Blocked(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Closed(_) => StatusCode::CONFLICT,
+            AppError::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // Plain fallback; the middleware replaces it with the templated page.
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::Busy = self {
+            response.insert_header((header::RETRY_AFTER, "5"));
+        }
+        response
+            .content_type("text/plain; charset=utf-8")
+            .body(self.user_message())
+    }
+}
+
+// A validation error laid out for the form it came from: the summary at the top links to each
+// field, and each field's message is what its aria-describedby points at.
+#[derive(Serialize, Debug, Default)]
+pub struct FormErrors {
+    pub summary: Vec<FieldError>,
+    pub fields: HashMap<&'static str, String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FormErrors {
+    // None for anything that isn't about a submitted field.
+    pub fn from_error(why: &AppError) -> Option<FormErrors> {
+        let summary = why.field_errors();
+        if summary.is_empty() {
+            return None;
+        }
+        let mut errors = FormErrors::default();
+        for error in &summary {
+            // Two rules on the same field both show in the summary; the field shows the first.
+            errors.fields.entry(error.field).or_insert_with(|| error.message.clone());
+        }
+        errors.summary = summary;
+        Some(errors)
+    }
+}
+
+// Identifies one request in the logs and on the error page.
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestId(pub String);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+impl RequestId {
+    // Keep an ID handed to us by a reverse proxy, otherwise make a new one.
+    fn for_request(req: &ServiceRequest) -> RequestId {
+        if let Some(val) = req.headers().get(REQUEST_ID_HEADER).and_then(|val| val.to_str().ok()) {
+            if !val.is_empty() && val.len() <= 64 && val.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return RequestId(val.to_string());
+            }
+        }
+        let mut bytes = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        RequestId(hex::encode(bytes))
+    }
+
+    pub fn of(req: &HttpRequest) -> RequestId {
+        req.extensions().get::<RequestId>().cloned().unwrap_or_else(|| RequestId("-".to_string()))
+    }
+}
+
+// The ID the request context middleware gave this request, for handlers that log errors
+// themselves rather than returning them.
+impl FromRequest for RequestId {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<RequestId, actix_web::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(RequestId::of(req)))
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorPage {
+    code: ErrorCode,
+    message: String,
+    field: Option<&'static str>,
+    // Each field the error is about, in the shape the API uses; `field` is the first of them.
+    errors: Vec<FieldError>,
+    request_id: RequestId,
+}
+
+impl ErrorPage {
+    fn new(code: ErrorCode, message: String, errors: Vec<FieldError>, request_id: &RequestId) -> ErrorPage {
+        let field = errors.first().map(|error| error.field);
+        ErrorPage { code, message, field, errors, request_id: request_id.clone() }
+    }
+}
+
+// The policy for rendering a failed request. The full detail always goes to the server log,
+// tagged with the request ID. The user sees the reason when the problem is with what they sent,
+// but for anything that went wrong on our side they only get fixed text and the ID, which they
+// can quote when they get in touch. Nothing from a database or internal error reaches the page.
+fn error_page(why: &actix_web::Error, status: StatusCode, request_id: &RequestId, method: &Method, path: &str) -> ErrorPage {
+    let app_error = why.as_error::<AppError>();
+    let detail = match app_error {
+        Some(app_error) => app_error.detail(),
+        None => why.to_string(),
+    };
+    let line = format!("[{}] {} {}: {}", request_id, method, path, detail);
+    match app_error {
+        // The same for every request during an outage.
+        Some(AppError::Database(_)) | Some(AppError::Busy) => logs::throttled_as(&why.to_string(), &line),
+        _ => println!("{}", line),
+    }
+
+    let code = match app_error {
+        Some(app_error) => app_error.code(),
+        None => ErrorCode::for_status(status),
+    };
+    if status.is_server_error() {
+        let message = code.generic_message()
+            .or(ErrorCode::InternalError.generic_message())
+            .unwrap_or_default();
+        return ErrorPage::new(code, message.to_string(), Vec::new(), request_id);
+    }
+    match app_error {
+        Some(app_error) => ErrorPage::new(code, app_error.user_message(), app_error.field_errors(), request_id),
+        // Errors from actix itself, e.g. a form that couldn't be parsed.
+        None => ErrorPage::new(code, why.to_string(), Vec::new(), request_id),
+    }
+}
+
+// Gives every request an ID and renders any handler error as the error page.
+pub async fn request_context(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let request_id = RequestId::for_request(&req);
+    req.extensions_mut().insert(request_id.clone());
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let json = negotiate::wants_json(&req);
+    let method = req.method().clone();
+    let path = req.path().to_string();
+
+    let mut res = next.call(req).await?.map_into_boxed_body();
+    if let Ok(val) = HeaderValue::from_str(&request_id.0) {
+        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), val);
+    }
+
+    let page = match res.response().error() {
+        Some(why) => error_page(why, res.status(), &request_id, &method, &path),
+        None => {
+            return Ok(res);
+        }
+    };
+
+    let status = res.status();
+    let retry_after = res.headers().get(header::RETRY_AFTER).cloned();
+    if json {
+        let (req, _) = res.into_parts();
+        let mut response = HttpResponse::build(status);
+        if let Some(val) = retry_after {
+            response.insert_header((header::RETRY_AFTER, val));
+        }
+        let response = response
+            .insert_header((REQUEST_ID_HEADER, request_id.0.clone()))
+            .json(&page);
+        return Ok(ServiceResponse::new(req, response));
+    }
+
+    let body = match state.as_ref().map(|state| renderer::render_string(state, "error", &page)) {
+        Some(Ok(val)) => val,
+        _ => page.message.clone(),
+    };
+    let (req, _) = res.into_parts();
+    let mut response = HttpResponse::build(status);
+    if let Some(val) = retry_after {
+        response.insert_header((header::RETRY_AFTER, val));
+    }
+    let response = response
+        .content_type("text/html; charset=utf-8")
+        .insert_header((REQUEST_ID_HEADER, request_id.0))
+        .body(body);
+    Ok(ServiceResponse::new(req, response))
+}
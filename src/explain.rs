@@ -0,0 +1,302 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AppError, FieldError},
+    fees, form,
+    models::{Campus, TermWindow},
+    money::Money,
+    negotiate, pricing::{self, FeeCategory, Proration}, receipts, residency, rules, terms, AppState, DUAL_ENROLLMENT_MAX_CREDITS,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ExplainParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    studies: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    residency: Option<String>,
+    // A state code or "international", priced as `/calculate` prices it; overrides `residency`.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    home_state: Option<String>,
+    #[serde(default, deserialize_with = "form::optional")]
+    credits: Option<form::Bounded<1, 255>>,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    new_student: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    orientation: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    insurance_waived: bool,
+    // Which term to price, and how far into it; today when left out.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    date: Option<String>,
+}
+
+// One charge and the rate it came from.
+#[derive(Serialize, Debug)]
+struct Charge {
+    label: String,
+    amount: Money,
+    category: &'static str,
+    source: String,
+}
+
+// A custom line item for the term, whether or not its rule picked the student out.
+#[derive(Serialize, Debug)]
+struct LineItemCheck {
+    label: String,
+    amount: Money,
+    category: &'static str,
+    applies_when: String,
+    applies: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct Explanation {
+    rates_from: String,
+    date: NaiveDate,
+    term: String,
+    term_window: Option<TermWindow>,
+    terms_source: String,
+    proration: Option<Proration>,
+    charges: Vec<Charge>,
+    line_items: Vec<LineItemCheck>,
+    line_items_source: String,
+    violations: Vec<FieldError>,
+    validation_source: String,
+    // Anything else that changed the answer, e.g. the estimate window being closed.
+    notes: Vec<String>,
+    total: Money,
+}
+
+#[derive(Serialize, Debug)]
+struct ExplainPage {
+    form: ExplainParams,
+    explanation: Option<Explanation>,
+    states: &'static [residency::HomeState],
+}
+
+// Where one rate was read from: the table and row in the database, or the key in the fee
+// schedule file. `key` narrows it down the same way for both.
+struct Sources {
+    campus_id: String,
+    file_section: Option<String>,
+}
+
+impl Sources {
+    fn of(&self, table: &str, file_key: &str, key: &str) -> String {
+        let key = if key.is_empty() { String::new() } else { format!("; {}", key) };
+        match &self.file_section {
+            Some(section) => format!("{} in {} of the fee schedule file{}", file_key, section, key),
+            None => format!("{} (CampusId {}{})", table, self.campus_id, key),
+        }
+    }
+}
+
+// Every rate, term and rule a price is made from, without saving anything. For the "why was I
+// charged the nonresident fee" tickets; custom line items and validation rules are listed
+// whether or not they applied.
+async fn explanation(state: &AppState, campus: &Campus, params: &ExplainParams) -> Result<Option<Explanation>, AppError> {
+    let studies = match &params.studies {
+        Some(val) if fees::STUDIES.contains(&val.as_str()) => val.as_str(),
+        Some(_) => {
+            return Err(AppError::validation("studies", "Studies must be undergraduate, graduate, or dual_enrollment."));
+        }
+        // Nothing asked yet; just the form.
+        None => {
+            return Ok(None);
+        }
+    };
+    let (residency, agreement) = match (&params.home_state, &params.residency) {
+        (Some(home_state), _) => state.home_state_residency(campus, home_state).await?,
+        (None, Some(val)) if fees::RESIDENCIES.contains(&val.as_str()) => (val.as_str(), None),
+        _ => {
+            return Err(AppError::validation("residency", "Residency must be resident, nonresident, or international."));
+        }
+    };
+    let credits = match &params.credits {
+        Some(val) => match val.value() {
+            Ok(val) => val as u8,
+            Err(why) => {
+                return Err(AppError::validation("credits", &format!("Credits: {}", why)));
+            }
+        },
+        None => {
+            return Err(AppError::validation("credits", "No credits were provided!"));
+        }
+    };
+    let date = match &params.date {
+        Some(val) => match NaiveDate::parse_from_str(val, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                return Err(AppError::validation("date", &format!("\"{}\" is not a valid date.", val)));
+            }
+        },
+        None => chrono::Local::now().date_naive(),
+    };
+
+    let sources = Sources { campus_id: campus.id.to_string(), file_section: state.schedule_section(campus) };
+    let rates_from = match &sources.file_section {
+        Some(section) => format!("the fee schedule file ({})", section),
+        None => "the database".to_string(),
+    };
+    let mut notes = Vec::new();
+    if let Some(home_state) = &params.home_state {
+        match &agreement {
+            Some(val) => notes.push(format!("Students from {} pay {} rates and {}% of the non-residency fee under \"{}\" ({}).",
+                val.state, residency, val.nonresidency_fee_percent, val.label,
+                sources.of("ReciprocityAgreements", "reciprocity", &format!("state {}", val.state)))),
+            None if residency != "international" => notes.push(format!("Students from {} pay {} rates, with no reciprocity agreement.",
+                home_state.to_uppercase(), residency)),
+            None => {},
+        }
+    }
+    let mut orientation = params.orientation;
+    if studies == "dual_enrollment" {
+        if orientation {
+            notes.push("Dual-enrollment students never pay for orientation, so it was left off.".to_string());
+            orientation = false;
+        }
+        if credits > DUAL_ENROLLMENT_MAX_CREDITS {
+            notes.push(format!("The calculator turns away dual-enrollment students taking more than {} credits.", DUAL_ENROLLMENT_MAX_CREDITS));
+        }
+    }
+    let mut facts = rules::Facts { studies, residency, credits, new_student: params.new_student, orientation };
+    if orientation {
+        match rules::orientation_exemption(&state.orientation_exemptions(campus).await?, &facts) {
+            Ok(Some(exemption)) => {
+                notes.push(format!("Orientation was left off under the exemption \"{}\" ({}).", exemption.label,
+                    sources.of("OrientationExemptions", "orientation_exemptions", "")));
+                orientation = false;
+                facts.orientation = false;
+            }
+            Ok(None) => {},
+            Err(why) => {
+                return Err(AppError::Internal(why));
+            }
+        }
+    }
+
+    let validation_rules = state.validation_rules(campus).await?;
+    let violations = match rules::violations(&validation_rules, &facts) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::Internal(why));
+        }
+    };
+
+    let term = receipts::term_for(date);
+    let windows = state.term_windows(campus).await?;
+    if let Some(notice) = terms::notice(&windows, &term, date) {
+        notes.push(format!("{} Only counselors can calculate for it.", notice));
+    }
+    let term_window = windows.into_iter().find(|window| window.name == term);
+    let proration = pricing::proration(&state.proration_rules(campus, &term).await?, date);
+
+    let mut charges = Vec::new();
+    let mut costs = state.tuition_costs(campus, studies, residency).await?;
+    costs.nonresidency_fee = pricing::reciprocity_fee(costs.nonresidency_fee, agreement.as_ref());
+    let rate_key = format!("studies {}, residency {}", studies, residency);
+    charges.push(Charge {
+        label: format!("Tuition, {} credit(s) at {}", credits, costs.credits_cost.amount()),
+        amount: costs.credits_cost * credits,
+        category: FeeCategory::Tuition.label(),
+        source: sources.of("CreditCosts.CreditsCost", "credit_costs.credits_cost", &rate_key),
+    });
+    if let Some(val) = &proration {
+        charges.push(Charge {
+            label: format!("Proration, week {} of {}: {}% charged", val.week, term, val.tuition_percent),
+            amount: pricing::proration_adjustment(credits, &costs, val.tuition_percent),
+            category: FeeCategory::Tuition.label(),
+            source: sources.of("ProrationRules", "terms.proration", &format!("term {}", term)),
+        });
+    }
+    if !costs.nonresidency_fee.is_zero() {
+        charges.push(Charge {
+            label: match &agreement {
+                Some(val) => format!("Non-residency fee, {}% under {}", val.nonresidency_fee_percent, val.label),
+                None => "Non-residency fee".to_string(),
+            },
+            amount: costs.nonresidency_fee,
+            category: FeeCategory::MandatoryFees.label(),
+            source: sources.of("CreditCosts.NonresidencyFee", "credit_costs.nonresidency_fee", &rate_key),
+        });
+    }
+    let health_insurance_fee = state.health_insurance_fee(campus).await?;
+    if !health_insurance_fee.is_zero() {
+        charges.push(Charge {
+            label: if params.insurance_waived { "Health insurance (waived)".to_string() } else { "Health insurance".to_string() },
+            amount: pricing::health_insurance_charge(params.insurance_waived, health_insurance_fee),
+            category: FeeCategory::MandatoryFees.label(),
+            source: sources.of("HealthInsuranceFee", "health_insurance_fee", ""),
+        });
+    }
+    if residency == "international" {
+        for fee in state.international_fees(campus).await? {
+            charges.push(Charge {
+                label: fee.label,
+                amount: fee.amount,
+                category: FeeCategory::MandatoryFees.label(),
+                source: sources.of("InternationalFees", "international_fees", ""),
+            });
+        }
+    }
+    if orientation {
+        charges.push(Charge {
+            label: "Orientation fee".to_string(),
+            amount: state.orientation_fee(campus).await?,
+            category: FeeCategory::OptionalFees.label(),
+            source: sources.of("orientation_fee", "orientation_fee", ""),
+        });
+    }
+
+    let mut line_items = Vec::new();
+    for item in state.line_items(campus, &term).await? {
+        let applies = match rules::parse(&item.applies_when) {
+            Ok(rule) => rule.applies(&facts),
+            Err(why) => {
+                return Err(AppError::Internal(format!("The rule for \"{}\" is invalid: {}", item.label, why)));
+            }
+        };
+        if applies {
+            charges.push(Charge {
+                label: item.label.clone(),
+                amount: item.category.signed(item.amount),
+                category: item.category.label(),
+                source: sources.of("CustomLineItems", "terms.line_items", &format!("term {}, applies when {}", term, if item.applies_when.trim().is_empty() { "always" } else { &item.applies_when })),
+            });
+        }
+        line_items.push(LineItemCheck {
+            label: item.label,
+            amount: item.amount,
+            category: item.category.label(),
+            applies_when: item.applies_when,
+            applies,
+        });
+    }
+
+    let total: Money = charges.iter().map(|charge| charge.amount).sum();
+    Ok(Some(Explanation {
+        rates_from,
+        date,
+        term: term.clone(),
+        term_window,
+        terms_source: sources.of("Terms", "terms", &format!("term {}", term)),
+        proration,
+        charges,
+        line_items,
+        line_items_source: sources.of("CustomLineItems", "terms.line_items", &format!("term {}", term)),
+        violations,
+        validation_source: sources.of("ValidationRules", "validation_rules", ""),
+        notes,
+        total,
+    }))
+}
+
+// GET /admin/explain?studies=&residency=&credits=, or home_state= in place of residency, as a page
+// or JSON.
+pub async fn explain(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Query<ExplainParams>) -> Result<HttpResponse, AppError> {
+    let form = params.into_inner();
+    let explanation = explanation(&state, &campus, &form).await?;
+    negotiate::respond(&state, &req, "admin_explain", &ExplainPage { form, explanation, states: &residency::STATES }).await
+}
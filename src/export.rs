@@ -0,0 +1,461 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use futures_util::stream;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+
+use crate::{adjustments, audit, error::AppError, fees, form, models::{Campus, CampusId, CourseFee, FlatFee, LineItem, ProrationRule, RefundRule, Receipt, ReceiptId, Scenario, StudentId, TuitionCosts, TuitionRecord, TuitionRecordId}, money::Money, pricing, queries, recent, AppState};
+
+// Rows fetched per query while streaming the records export.
+const RECORDS_BATCH: i64 = 1000;
+
+// Says the first row is column names, as RFC 4180 has it.
+const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8; header=present";
+
+// Excel only reads a CSV file as UTF-8 when it starts with a byte order mark; without one,
+// accented names come out garbled.
+const UTF8_BOM: &str = "\u{feff}";
+
+// One CSV field, quoted when it has to be.
+pub fn csv_field(val: &str, delimiter: char) -> String {
+    if val.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", val.replace('"', "\"\""))
+    } else {
+        val.to_string()
+    }
+}
+
+pub fn csv_row(fields: &[&str], delimiter: char) -> String {
+    let mut row = fields.iter().map(|field| csv_field(field, delimiter)).collect::<Vec<_>>().join(&delimiter.to_string());
+    row.push_str("\r\n");
+    row
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExportParams {
+    // json (the default) or csv.
+    format: Option<String>,
+    // For CSV: start the file with a byte order mark, for Excel.
+    #[serde(default, deserialize_with = "form::checkbox")]
+    bom: bool,
+    // For CSV: comma (the default) or semicolon, which Excel expects where the decimal
+    // separator is a comma.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    delimiter: Option<String>,
+}
+
+impl ExportParams {
+    fn delimiter(&self) -> Result<char, AppError> {
+        match self.delimiter.as_deref() {
+            None | Some("comma") | Some(",") => Ok(','),
+            Some("semicolon") | Some(";") => Ok(';'),
+            Some(val) => Err(AppError::validation("delimiter", &format!("\"{}\" is not comma or semicolon.", val))),
+        }
+    }
+
+    // What the file starts with: the byte order mark if it was asked for.
+    fn preamble(&self) -> &'static str {
+        if self.bom { UTF8_BOM } else { "" }
+    }
+}
+
+#[derive(Serialize)]
+struct CreditRate {
+    studies: &'static str,
+    residency: &'static str,
+    #[serde(flatten)]
+    costs: TuitionCosts,
+}
+
+#[derive(Serialize)]
+struct IndirectCostRate {
+    studies: &'static str,
+    label: String,
+    amount: Money,
+}
+
+#[derive(Serialize)]
+struct TermRates {
+    name: String,
+    proration: Vec<ProrationRule>,
+    refunds: Vec<RefundRule>,
+    line_items: Vec<LineItem>,
+}
+
+// Everything the calculator prices with for one campus, whichever source it comes from.
+#[derive(Serialize)]
+struct RateExport {
+    campus: String,
+    generated_at: NaiveDateTime,
+    // "database" or "file".
+    source: &'static str,
+    orientation_fee: Money,
+    // Waived for students with their own coverage.
+    health_insurance_fee: Money,
+    credit_costs: Vec<CreditRate>,
+    international_fees: Vec<FlatFee>,
+    indirect_costs: Vec<IndirectCostRate>,
+    course_fees: Vec<CourseFee>,
+    terms: Vec<TermRates>,
+}
+
+async fn rate_export(state: &AppState, campus: &Campus) -> Result<RateExport, AppError> {
+    let mut credit_costs = Vec::new();
+    for studies in fees::STUDIES {
+        for residency in fees::RESIDENCIES {
+            match state.tuition_costs(campus, studies, residency).await {
+                Ok(costs) => credit_costs.push(CreditRate { studies, residency, costs }),
+                // Not offered at this campus.
+                Err(AppError::Validation { .. }) => {},
+                Err(why) => {
+                    return Err(why);
+                }
+            }
+        }
+    }
+
+    let mut indirect_costs = Vec::new();
+    for studies in fees::STUDIES {
+        for cost in state.indirect_costs(campus, studies).await? {
+            indirect_costs.push(IndirectCostRate { studies, label: cost.label, amount: cost.amount });
+        }
+    }
+
+    let mut terms = Vec::new();
+    for name in state.term_names(campus).await? {
+        terms.push(TermRates {
+            proration: state.proration_rules(campus, &name).await?,
+            refunds: state.refund_rules(campus, &name).await?,
+            line_items: state.line_items(campus, &name).await?,
+            name,
+        });
+    }
+
+    Ok(RateExport {
+        campus: campus.slug.clone(),
+        generated_at: Local::now().naive_local(),
+        source: if state.fee_schedule.is_some() { "file" } else { "database" },
+        orientation_fee: state.orientation_fee(campus).await?,
+        health_insurance_fee: state.health_insurance_fee(campus).await?,
+        credit_costs,
+        international_fees: state.international_fees(campus).await?,
+        indirect_costs,
+        course_fees: state.course_fees(campus).await?,
+        terms,
+    })
+}
+
+// One row per rate: what it is, which term and students it's for, the amount, and anything
+// else needed to read it.
+fn rates_csv(export: &RateExport, delimiter: char) -> String {
+    let row = |fields: &[&str]| csv_row(fields, delimiter);
+    let mut body = row(&["category", "term", "studies", "residency", "item", "amount", "detail"]);
+    for rate in &export.credit_costs {
+        body += &row(&["credit_cost", "", rate.studies, rate.residency, "credits_cost", &rate.costs.credits_cost.amount().to_string(), "per credit"]);
+        body += &row(&["credit_cost", "", rate.studies, rate.residency, "nonresidency_fee", &rate.costs.nonresidency_fee.amount().to_string(), ""]);
+    }
+    body += &row(&["fee", "", "", "", "orientation_fee", &export.orientation_fee.amount().to_string(), "new students who opt in"]);
+    body += &row(&["fee", "", "", "", "health_insurance_fee", &export.health_insurance_fee.amount().to_string(), "waived with own coverage"]);
+    for fee in &export.international_fees {
+        body += &row(&["international_fee", "", "", "international", &fee.label, &fee.amount.amount().to_string(), ""]);
+    }
+    for cost in &export.indirect_costs {
+        body += &row(&["indirect_cost", "", cost.studies, "", &cost.label, &cost.amount.amount().to_string(), "estimate"]);
+    }
+    for fee in &export.course_fees {
+        body += &row(&["course_fee", "", "", "", &fee.course_code, &fee.fee.amount().to_string(), &format!("{}: {}", fee.department, fee.label)]);
+    }
+    for term in &export.terms {
+        for rule in &term.proration {
+            body += &row(&["proration", &term.name, "", "", &format!("after week {}", rule.after_week), &rule.tuition_percent.to_string(), &format!("percent of tuition; term starts {}", rule.starts_on)]);
+        }
+        for rule in &term.refunds {
+            body += &row(&["refund", &term.name, "", "", &format!("through week {}", rule.through_week), &rule.refund_percent.to_string(), &format!("percent of tuition; term starts {}", rule.starts_on)]);
+        }
+        for item in &term.line_items {
+            body += &row(&["line_item", &term.name, "", "", &item.label, &item.amount.amount().to_string(), &item.applies_when]);
+        }
+    }
+    body
+}
+
+// The complete effective fee schedule, for auditors to check against the published rates.
+pub async fn rates(state: web::Data<AppState>, campus: Campus, params: web::Query<ExportParams>) -> Result<HttpResponse, AppError> {
+    let export = rate_export(&state, &campus).await?;
+    let file_name = format!("rates-{}-{}", campus.slug, export.generated_at.format("%Y-%m-%d"));
+    match params.format.as_deref() {
+        None | Some("json") => Ok(HttpResponse::Ok()
+            .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.json\"", file_name)))
+            .json(&export)),
+        Some("csv") => {
+            let delimiter = params.delimiter()?;
+            Ok(HttpResponse::Ok()
+                .content_type(CSV_CONTENT_TYPE)
+                .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.csv\"", file_name)))
+                .body(format!("{}{}", params.preamble(), rates_csv(&export, delimiter))))
+        }
+        Some(val) => Err(AppError::validation("format", &format!("\"{}\" is not json or csv.", val))),
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct RecordRow {
+    id: TuitionRecordId,
+    student_public_id: String,
+    first_name: String,
+    last_name: String,
+    term: Option<String>,
+    tuition_cost: Decimal,
+    num_credits: Option<u8>,
+    orientation: Option<bool>,
+    student_type: Option<String>,
+    student_studies: Option<String>,
+    insurance_waived: Option<bool>,
+    updated_at: NaiveDateTime,
+}
+
+fn record_csv(record: &RecordRow, delimiter: char) -> String {
+    let flag = |val: Option<bool>| match val {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "",
+    };
+    csv_row(&[
+        &record.id.to_string(),
+        &record.student_public_id,
+        &record.first_name,
+        &record.last_name,
+        record.term.as_deref().unwrap_or(""),
+        &record.tuition_cost.to_string(),
+        &record.num_credits.map(|val| val.to_string()).unwrap_or_default(),
+        flag(record.orientation),
+        record.student_type.as_deref().unwrap_or(""),
+        record.student_studies.as_deref().unwrap_or(""),
+        flag(record.insurance_waived),
+        &record.updated_at.to_string(),
+    ], delimiter)
+}
+
+// Where the records export is up to: a batch read but not sent yet (with the CSV header before
+// the first), or the last id sent.
+enum NextRecords {
+    Batch(Vec<RecordRow>, String),
+    After(TuitionRecordId),
+    Done,
+}
+
+// The next batch of records after `after`, by id, so each query picks up where the last left
+// off instead of skipping past an ever larger offset.
+async fn records_after(pool: &Pool<MySql>, campus_id: CampusId, after: TuitionRecordId) -> Result<Vec<RecordRow>, sqlx::Error> {
+    queries::EXPORT_RECORDS_PAGE.scoped(campus_id).run(|sql, campus_id| sqlx::query_as::<_, RecordRow>(sql)
+    .bind(campus_id)
+    .bind(after)
+    .bind(RECORDS_BATCH)
+    .fetch_all(pool)).await
+}
+
+// Every tuition record on the campus as CSV. There can be a million of them, so they're sent a
+// batch at a time as they're read rather than built up in memory first.
+pub async fn records(state: web::Data<AppState>, campus: Campus, params: web::Query<ExportParams>) -> Result<HttpResponse, AppError> {
+    match params.format.as_deref() {
+        None | Some("csv") => {},
+        Some(val) => {
+            return Err(AppError::validation("format", &format!("Records can only be exported as csv, not \"{}\".", val)));
+        }
+    }
+    let delimiter = params.delimiter()?;
+
+    // Fail before the headers go out if the database is down; after that, an error can only cut
+    // the download short.
+    let first = match records_after(state.read_conn(), campus.id, TuitionRecordId(0)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let header_row = params.preamble().to_string() + &csv_row(&[
+        "id", "student_id", "first_name", "last_name", "term", "tuition_cost", "num_credits",
+        "orientation", "residency", "studies", "insurance_waived", "updated_at",
+    ], delimiter);
+    let pool = state.read_conn().clone();
+    let campus_id = campus.id;
+    let body = stream::unfold(NextRecords::Batch(first, header_row), move |next| {
+        let pool = pool.clone();
+        async move {
+            let (batch, chunk) = match next {
+                NextRecords::Batch(batch, prefix) => (batch, prefix),
+                NextRecords::After(after) => match records_after(&pool, campus_id, after).await {
+                    Ok(val) => (val, String::new()),
+                    Err(why) => {
+                        println!("Error while exporting records after {}: {}", after, why);
+                        return Some((Err(why), NextRecords::Done));
+                    }
+                },
+                NextRecords::Done => {
+                    return None;
+                }
+            };
+            let next = match batch.last() {
+                Some(last) if batch.len() as i64 == RECORDS_BATCH => NextRecords::After(last.id),
+                _ => NextRecords::Done,
+            };
+            let chunk = batch.iter().fold(chunk, |mut chunk, record| {
+                chunk.push_str(&record_csv(record, delimiter));
+                chunk
+            });
+            Some((Ok(web::Bytes::from(chunk)), next))
+        }
+    });
+
+    let file_name = format!("records-{}-{}", campus.slug, Local::now().format("%Y-%m-%d"));
+    Ok(HttpResponse::Ok()
+        .content_type(CSV_CONTENT_TYPE)
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.csv\"", file_name)))
+        .streaming(body))
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct StudentProfile {
+    #[serde(skip_serializing)]
+    id: StudentId,
+    #[serde(rename = "id")]
+    public_id: String,
+    first_name: String,
+    last_name: String,
+    email: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct RefundEstimateRow {
+    #[serde(skip_serializing)]
+    receipt_id: ReceiptId,
+    withdrawal_date: NaiveDate,
+    week: i32,
+    refund_percent: Decimal,
+    refund_amount: Decimal,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+struct ExportedReceipt {
+    #[serde(flatten)]
+    receipt: Receipt,
+    categories: Vec<pricing::CategorySubtotal>,
+    revision: Option<adjustments::Revision>,
+    refund_estimates: Vec<RefundEstimateRow>,
+}
+
+// Everything kept about one student, for a data-portability request or to move the record to
+// another campus system.
+#[derive(Serialize)]
+struct StudentExport {
+    generated_at: NaiveDateTime,
+    campus: String,
+    student: StudentProfile,
+    tuition_records: Vec<TuitionRecord>,
+    receipts: Vec<ExportedReceipt>,
+    scenarios: Vec<Scenario>,
+}
+
+async fn student_export(state: &AppState, campus: &Campus, public_id: &str) -> Result<StudentExport, AppError> {
+    let pool = &state.conn;
+    let student = match queries::STUDENT_BY_PUBLIC_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, StudentProfile>(sql)
+    .bind(campus_id)
+    .bind(public_id)
+    .fetch_optional(pool)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(AppError::NotFound(format!("No student {} was found at {}.", public_id, campus.name)));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let tuition_records = match queries::STUDENT_RECORDS.run(|sql| sqlx::query_as::<_, TuitionRecord>(sql)
+    .bind(student.id)
+    .fetch_all(pool)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let receipts = match queries::STUDENT_RECEIPTS.run(|sql| sqlx::query_as::<_, Receipt>(sql)
+    .bind(student.id)
+    .fetch_all(pool)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let refund_estimates = match queries::STUDENT_REFUND_ESTIMATES.run(|sql| sqlx::query_as::<_, RefundEstimateRow>(sql)
+    .bind(student.id)
+    .fetch_all(pool)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let receipt_adjustments = match queries::STUDENT_RECEIPT_ADJUSTMENTS.run(|sql| sqlx::query_as::<_, adjustments::Adjustment>(sql)
+    .bind(student.id)
+    .fetch_all(pool)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let scenarios = match queries::SCENARIOS_FOR_STUDENT.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, Scenario>(sql)
+    .bind(campus_id)
+    .bind(student.id)
+    .fetch_all(pool)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let receipts = receipts.into_iter()
+        .map(|receipt| {
+            let categories = recent::breakdown(&receipt);
+            let own = receipt_adjustments.iter().filter(|adjustment| adjustment.receipt_id == receipt.id).cloned().collect();
+            ExportedReceipt {
+                revision: adjustments::revise(&receipt, &categories, own),
+                categories,
+                refund_estimates: refund_estimates.iter().filter(|estimate| estimate.receipt_id == receipt.id).cloned().collect(),
+                receipt,
+            }
+        })
+        .collect();
+
+    Ok(StudentExport {
+        generated_at: Local::now().naive_local(),
+        campus: campus.slug.clone(),
+        student,
+        tuition_records,
+        receipts,
+        scenarios,
+    })
+}
+
+// One student's data as a JSON download. Exports are audited, since they hand over everything.
+pub async fn student(state: web::Data<AppState>, campus: Campus, req: HttpRequest, public_id: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let export = student_export(&state, &campus, &public_id).await?;
+    let details = format!("Exported {} {} ({})", export.student.first_name, export.student.last_name, export.student.public_id);
+    if let Err(why) = audit::record(&state.conn, &audit::actor(&req), "export", "Student", export.student.id.0, &details).await {
+        return Err(AppError::from(why));
+    }
+    let file_name = format!("student-{}-{}", export.student.public_id, export.generated_at.format("%Y-%m-%d"));
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.json\"", file_name)))
+        .json(&export))
+}
@@ -0,0 +1,136 @@
+use actix_web::{rt, web, HttpResponse};
+use chrono::{Local, NaiveDateTime};
+use sqlx::{MySql, Pool};
+use std::{
+    fmt::Write,
+    sync::{atomic::{AtomicBool, Ordering}, Mutex},
+    time::Duration,
+};
+
+use crate::{logs, queries, AppState};
+
+// A second database to read from while the primary is down. Writes still go to the primary,
+// and estimates it can't take wait in the replay queue.
+#[derive(Debug)]
+pub struct Failover {
+    secondary: Pool<MySql>,
+    probe_interval: Duration,
+    failed_over: AtomicBool,
+    // When reads moved to the secondary, for /readyz.
+    since: Mutex<Option<NaiveDateTime>>,
+}
+
+impl Failover {
+    pub fn new(secondary: Pool<MySql>, probe_interval: Duration) -> Failover {
+        Failover { secondary, probe_interval, failed_over: AtomicBool::new(false), since: Mutex::new(None) }
+    }
+
+    pub fn failed_over(&self) -> bool {
+        self.failed_over.load(Ordering::Relaxed)
+    }
+
+    fn since(&self) -> Option<NaiveDateTime> {
+        self.since.lock().map(|since| *since).unwrap_or(None)
+    }
+
+    fn set(&self, failed_over: bool) {
+        self.failed_over.store(failed_over, Ordering::Relaxed);
+        if let Ok(mut since) = self.since.lock() {
+            *since = if failed_over { Some(Local::now().naive_local()) } else { None };
+        }
+    }
+}
+
+async fn ping(pool: &Pool<MySql>) -> Result<(), sqlx::Error> {
+    match queries::PING.run(|sql| sqlx::query(sql).execute(pool)).await {
+        Ok(_val) => Ok(()),
+        Err(why) => Err(why),
+    }
+}
+
+impl AppState {
+    // For reads that don't mind being a moment behind: the replica when there is one, and the
+    // secondary while the primary is down.
+    pub fn read_conn(&self) -> &Pool<MySql> {
+        match &self.failover {
+            Some(failover) if failover.failed_over() => &failover.secondary,
+            _ => &self.replica_conn,
+        }
+    }
+
+    // For rate and fee lookups: the primary, so an admin's change shows up straight away, or the
+    // secondary while the primary is down.
+    pub fn lookup_conn(&self) -> &Pool<MySql> {
+        match &self.failover {
+            Some(failover) if failover.failed_over() => &failover.secondary,
+            _ => &self.conn,
+        }
+    }
+}
+
+// Pings the primary every probe interval; reads move to the secondary when it stops answering
+// and back once it answers again.
+pub fn spawn(state: AppState) {
+    let failover = match &state.failover {
+        Some(val) => val.clone(),
+        None => {
+            return;
+        }
+    };
+    rt::spawn(async move {
+        loop {
+            rt::time::sleep(failover.probe_interval).await;
+            match ping(&state.conn).await {
+                Ok(()) if failover.failed_over() => {
+                    println!("The primary database is answering again; reads are back on it.");
+                    failover.set(false);
+                }
+                Ok(()) => {},
+                Err(why) if !failover.failed_over() => {
+                    println!("The primary database isn't answering ({}); reads are failing over to the secondary.", why);
+                    failover.set(true);
+                }
+                Err(why) => {
+                    logs::throttled(&format!("The primary database is still down: {}", why));
+                }
+            }
+        }
+    });
+}
+
+// For load balancers deciding whether to send traffic: ready while whichever database reads are
+// on answers, even if that's the secondary.
+pub async fn readyz(state: web::Data<AppState>) -> HttpResponse {
+    let mut body = String::new();
+    let primary = ping(&state.conn).await;
+    let _ = writeln!(body, "primary: {}", if primary.is_ok() { "up" } else { "down" });
+
+    let ready = match &state.failover {
+        Some(failover) => {
+            let secondary = ping(&failover.secondary).await;
+            let _ = writeln!(body, "secondary: {}", if secondary.is_ok() { "up" } else { "down" });
+            if failover.failed_over() {
+                match failover.since() {
+                    Some(since) => {
+                        let _ = writeln!(body, "reads: secondary (failed over at {})", since.format("%Y-%m-%d %H:%M:%S"));
+                    }
+                    None => {
+                        let _ = writeln!(body, "reads: secondary");
+                    }
+                }
+                secondary.is_ok()
+            } else {
+                let _ = writeln!(body, "reads: primary");
+                primary.is_ok()
+            }
+        }
+        None => {
+            let _ = writeln!(body, "secondary: not configured");
+            let _ = writeln!(body, "reads: primary");
+            primary.is_ok()
+        }
+    };
+
+    let mut response = if ready { HttpResponse::Ok() } else { HttpResponse::ServiceUnavailable() };
+    response.content_type("text/plain").body(body)
+}
@@ -0,0 +1,658 @@
+use chrono::NaiveDate;
+use notify::{RecursiveMode, Watcher};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, RwLock},
+    thread,
+};
+
+use crate::{error::AppError, models::{Campus, CourseFee, FlatFee, IndirectCost, LineItem, OrientationExemption, Program, ProrationRule, ReciprocityAgreement, RefundRule, TermWindow, TuitionCosts, ValidationRule}, money::{Money, PerCredit}, pricing, queries, residency, rules, AppState};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreditCostEntry {
+    pub studies: String,
+    pub residency: String,
+    #[serde(flatten)]
+    pub costs: TuitionCosts,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IndirectCostEntry {
+    pub studies: String,
+    #[serde(flatten)]
+    pub cost: IndirectCost,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProgramEntry {
+    pub studies: String,
+    #[serde(flatten)]
+    pub program: Program,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProrationEntry {
+    pub after_week: u32,
+    pub tuition_percent: Decimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RefundEntry {
+    pub through_week: u32,
+    pub refund_percent: Decimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TermEntry {
+    pub name: String,
+    pub starts_on: NaiveDate,
+    // When students can start making estimates for the term, and the last day they can.
+    #[serde(default)]
+    pub opens_on: Option<NaiveDate>,
+    #[serde(default)]
+    pub closes_on: Option<NaiveDate>,
+    #[serde(default)]
+    pub proration: Vec<ProrationEntry>,
+    #[serde(default)]
+    pub refunds: Vec<RefundEntry>,
+    #[serde(default)]
+    pub line_items: Vec<LineItem>,
+}
+
+// The fee schedule as written in a FEE_SCHEDULE_FILE, for schools that can't edit the database tables.
+#[derive(Deserialize, Debug, Clone)]
+pub struct FeeSchedule {
+    pub orientation_fee: Money,
+    // Left out when the campus doesn't charge for health insurance.
+    #[serde(default)]
+    pub health_insurance_fee: Money,
+    // The yearly increase multi-year projections assume, as a percent; left out for flat rates.
+    #[serde(default)]
+    pub annual_rate_increase: Decimal,
+    pub credit_costs: Vec<CreditCostEntry>,
+    #[serde(default)]
+    pub indirect_costs: Vec<IndirectCostEntry>,
+    #[serde(default)]
+    pub programs: Vec<ProgramEntry>,
+    #[serde(default)]
+    pub terms: Vec<TermEntry>,
+    #[serde(default)]
+    pub course_fees: Vec<CourseFee>,
+    #[serde(default)]
+    pub international_fees: Vec<FlatFee>,
+    #[serde(default)]
+    pub validation_rules: Vec<ValidationRule>,
+    #[serde(default)]
+    pub orientation_exemptions: Vec<OrientationExemption>,
+    #[serde(default)]
+    pub reciprocity: Vec<ReciprocityAgreement>,
+    // Campuses whose rates differ from the top-level schedule, keyed by campus slug.
+    #[serde(default)]
+    pub campuses: HashMap<String, FeeSchedule>,
+}
+
+pub const STUDIES: [&str; 3] = ["undergraduate", "graduate", "dual_enrollment"];
+// Every schedule has to price these; a campus without dual enrollment just leaves it out.
+const REQUIRED_STUDIES: [&str; 2] = ["undergraduate", "graduate"];
+pub const RESIDENCIES: [&str; 3] = ["resident", "nonresident", "international"];
+// International students are optional the same way.
+const REQUIRED_RESIDENCIES: [&str; 2] = ["resident", "nonresident"];
+
+impl FeeSchedule {
+    pub fn load(path: &Path) -> Result<FeeSchedule, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Could not read {}: {}", path.display(), why));
+            }
+        };
+        FeeSchedule::parse(&text, &path.display().to_string())
+    }
+
+    // `source` names where the text came from, for the error messages.
+    pub fn parse(text: &str, source: &str) -> Result<FeeSchedule, String> {
+        let schedule = match toml::from_str::<FeeSchedule>(text) {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Could not parse {}: {}", source, why));
+            }
+        };
+        schedule.validate()?;
+        for (slug, campus_schedule) in &schedule.campuses {
+            if !campus_schedule.campuses.is_empty() {
+                return Err(format!("Campus \"{}\" can't have campuses of its own", slug));
+            }
+            if let Err(why) = campus_schedule.validate() {
+                return Err(format!("Campus \"{}\": {}", slug, why));
+            }
+        }
+        Ok(schedule)
+    }
+
+    pub fn for_campus(&self, campus: &Campus) -> &FeeSchedule {
+        self.campuses.get(&campus.slug).unwrap_or(self)
+    }
+
+    // Every required studies/residency pair needs exactly one rate, the others at most one,
+    // and no amount may be negative.
+    fn validate(&self) -> Result<(), String> {
+        if self.orientation_fee.is_sign_negative() {
+            return Err("orientation_fee can't be negative".to_string());
+        }
+        if self.health_insurance_fee.is_sign_negative() {
+            return Err("health_insurance_fee can't be negative".to_string());
+        }
+        if self.annual_rate_increase.is_sign_negative() || self.annual_rate_increase > Decimal::ONE_HUNDRED {
+            return Err("annual_rate_increase must be between 0 and 100 percent".to_string());
+        }
+        for entry in &self.credit_costs {
+            if !STUDIES.contains(&entry.studies.as_str()) || !RESIDENCIES.contains(&entry.residency.as_str()) {
+                return Err(format!("Unknown credit cost \"{}\"/\"{}\"", entry.studies, entry.residency));
+            }
+            if entry.costs.credits_cost.amount().is_sign_negative() || entry.costs.nonresidency_fee.is_sign_negative() {
+                return Err(format!("Credit cost for {}/{} can't be negative", entry.studies, entry.residency));
+            }
+        }
+        for studies in STUDIES {
+            for residency in RESIDENCIES {
+                let count = self.credit_costs.iter()
+                    .filter(|entry| entry.studies == studies && entry.residency == residency)
+                    .count();
+                if count > 1 || (count == 0 && REQUIRED_STUDIES.contains(&studies) && REQUIRED_RESIDENCIES.contains(&residency)) {
+                    return Err(format!("Expected one credit cost for {}/{}, found {}", studies, residency, count));
+                }
+            }
+        }
+        for entry in &self.indirect_costs {
+            if !STUDIES.contains(&entry.studies.as_str()) {
+                return Err(format!("Unknown studies \"{}\" for indirect cost \"{}\"", entry.studies, entry.cost.label));
+            }
+            if entry.cost.amount.is_sign_negative() {
+                return Err(format!("Indirect cost \"{}\" can't be negative", entry.cost.label));
+            }
+        }
+        for entry in &self.programs {
+            if !STUDIES.contains(&entry.studies.as_str()) {
+                return Err(format!("Unknown studies \"{}\" for program \"{}\"", entry.studies, entry.program.name));
+            }
+            if entry.program.total_credits == 0 {
+                return Err(format!("Program \"{}\" needs more than 0 credits", entry.program.name));
+            }
+        }
+        for entry in &self.international_fees {
+            if entry.amount.is_sign_negative() {
+                return Err(format!("International fee \"{}\" can't be negative", entry.label));
+            }
+        }
+        for entry in &self.course_fees {
+            if entry.fee.is_sign_negative() {
+                return Err(format!("Course fee for {} can't be negative", entry.course_code));
+            }
+            let code = course_code_key(&entry.course_code);
+            if self.course_fees.iter().filter(|other| course_code_key(&other.course_code) == code).count() > 1 {
+                return Err(format!("Course {} is listed more than once", entry.course_code));
+            }
+        }
+        for entry in &self.validation_rules {
+            if let Err(why) = rules::check_validation_rule(&entry.field, &entry.reject_when) {
+                return Err(format!("Validation rule \"{}\": {}", entry.message, why));
+            }
+        }
+        for entry in &self.orientation_exemptions {
+            if let Err(why) = rules::check_orientation_exemption(&entry.exempt_when) {
+                return Err(format!("Orientation exemption \"{}\": {}", entry.label, why));
+            }
+        }
+        for entry in &self.reciprocity {
+            if let Err(why) = check_reciprocity(entry) {
+                return Err(format!("Reciprocity with {}: {}", entry.state, why));
+            }
+            if self.reciprocity.iter().filter(|other| other.state.eq_ignore_ascii_case(&entry.state)).count() > 1 {
+                return Err(format!("Reciprocity with {} is listed more than once", entry.state));
+            }
+        }
+        for term in &self.terms {
+            if let (Some(opens_on), Some(closes_on)) = (term.opens_on, term.closes_on) {
+                if closes_on < opens_on {
+                    return Err(format!("{} closes on {}, before it opens on {}", term.name, closes_on, opens_on));
+                }
+            }
+            for entry in &term.proration {
+                if entry.tuition_percent.is_sign_negative() || entry.tuition_percent > Decimal::ONE_HUNDRED {
+                    return Err(format!("Proration for {} after week {} must be between 0 and 100 percent", term.name, entry.after_week));
+                }
+            }
+            for entry in &term.refunds {
+                if entry.refund_percent.is_sign_negative() || entry.refund_percent > Decimal::ONE_HUNDRED {
+                    return Err(format!("Refund for {} through week {} must be between 0 and 100 percent", term.name, entry.through_week));
+                }
+            }
+            for entry in &term.line_items {
+                if entry.amount.is_sign_negative() {
+                    return Err(format!("Line item \"{}\" for {} can't be negative", entry.label, term.name));
+                }
+                if let Err(why) = rules::parse(&entry.applies_when) {
+                    return Err(format!("Line item \"{}\" for {}: {}", entry.label, term.name, why));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Readers clone the inner Arc, so a reload swaps the whole schedule at once.
+pub type SharedFeeSchedule = Arc<RwLock<Arc<FeeSchedule>>>;
+
+// Load the schedule and keep reloading it whenever the file changes. An invalid edit is
+// reported and the previous schedule stays in use.
+pub fn watch(path: PathBuf) -> Result<SharedFeeSchedule, String> {
+    let shared: SharedFeeSchedule = Arc::new(RwLock::new(Arc::new(FeeSchedule::load(&path)?)));
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(format!("Could not watch {}: {}", path.display(), why));
+        }
+    };
+    // Watch the directory, since editors often replace the file instead of writing to it.
+    let directory = match path.parent() {
+        Some(val) if !val.as_os_str().is_empty() => val.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if let Err(why) = watcher.watch(&directory, RecursiveMode::NonRecursive) {
+        return Err(format!("Could not watch {}: {}", directory.display(), why));
+    }
+
+    let reloaded = shared.clone();
+    thread::spawn(move || {
+        // The watcher stops when dropped, so it lives on this thread.
+        let _watcher = watcher;
+        let file_name = path.file_name().map(|val| val.to_os_string());
+        for event in receiver {
+            let event = match event {
+                Ok(val) => val,
+                Err(why) => {
+                    println!("Error while watching the fee schedule: {}", why);
+                    continue;
+                }
+            };
+            if !event.kind.is_create() && !event.kind.is_modify() {
+                continue;
+            }
+            if !event.paths.iter().any(|changed| changed.file_name().map(|val| val.to_os_string()) == file_name) {
+                continue;
+            }
+            match FeeSchedule::load(&path) {
+                Ok(schedule) => {
+                    *reloaded.write().unwrap() = Arc::new(schedule);
+                    println!("Reloaded the fee schedule from {}.", path.display());
+                }
+                Err(why) => {
+                    println!("Keeping the previous fee schedule: {}", why);
+                }
+            }
+        }
+    });
+
+    Ok(shared)
+}
+
+// The same checks for an agreement from the admin page or the fee schedule file.
+pub fn check_reciprocity(agreement: &ReciprocityAgreement) -> Result<(), String> {
+    if residency::find(&agreement.state).is_none() {
+        return Err(format!("\"{}\" isn't the code of a U.S. state, e.g. WI.", agreement.state));
+    }
+    if !residency::AGREEMENT_RESIDENCIES.contains(&agreement.residency.as_str()) {
+        return Err("The rates have to be resident or nonresident.".to_string());
+    }
+    if agreement.nonresidency_fee_percent.is_sign_negative() || agreement.nonresidency_fee_percent > Decimal::ONE_HUNDRED {
+        return Err("The share of the non-residency fee must be between 0 and 100 percent.".to_string());
+    }
+    Ok(())
+}
+
+// Course codes compare without case or spaces, so "chem101" finds "CHEM 101".
+pub fn course_code_key(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+}
+
+// A campus that has no rate for this kind of student, e.g. one without dual enrollment.
+// A home state as its USPS code, or "international".
+pub fn home_state_code(home_state: &str) -> Result<&'static str, AppError> {
+    if home_state.eq_ignore_ascii_case(residency::INTERNATIONAL) {
+        return Ok(residency::INTERNATIONAL);
+    }
+    match residency::find(home_state) {
+        Some(val) => Ok(val.code),
+        None => Err(AppError::validation("home_state", &format!("\"{}\" isn't a U.S. state.", home_state))),
+    }
+}
+
+// The rates a student from the state `code` pays at this campus under its agreements, and the
+// agreement that set them, if any.
+pub fn agreement_residency(campus: &Campus, code: &str, agreements: &[ReciprocityAgreement]) -> (&'static str, Option<ReciprocityAgreement>) {
+    let (residency, agreement) = pricing::residency_for_state(campus.state.as_deref(), code, agreements);
+    // Agreements are checked to name resident or nonresident when they're saved or loaded.
+    let residency = if residency == "resident" { "resident" } else { "nonresident" };
+    (residency, agreement.cloned())
+}
+
+fn not_offered(studies: &str, residency: &str) -> AppError {
+    AppError::validation("student_studies", &format!("No {}/{} rate is set up at this campus.", studies, residency))
+}
+
+impl AppState {
+    fn fee_schedule(&self) -> Option<Arc<FeeSchedule>> {
+        self.fee_schedule.as_ref().map(|shared| shared.read().unwrap().clone())
+    }
+
+    // Which part of the fee schedule file prices this campus, or None when rates come from the database.
+    pub fn schedule_section(&self, campus: &Campus) -> Option<String> {
+        let schedule = self.fee_schedule()?;
+        if schedule.campuses.contains_key(&campus.slug) {
+            Some(format!("[campuses.{}]", campus.slug))
+        } else {
+            Some("the top level".to_string())
+        }
+    }
+
+    pub async fn tuition_costs(&self, campus: &Campus, studies: &str, residency: &str) -> Result<TuitionCosts, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return match schedule.for_campus(campus).credit_costs.iter().find(|entry| entry.studies == studies && entry.residency == residency) {
+                Some(entry) => Ok(entry.costs.clone()),
+                None => Err(not_offered(studies, residency)),
+            };
+        }
+
+        // Get the cost per credit from the database by using prepared statements.
+        match queries::CREDIT_COSTS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, TuitionCosts>(sql)
+            .bind(campus_id)
+            .bind(studies)
+            .bind(residency)
+            .fetch_optional(self.lookup_conn())).await {
+            Ok(Some(val)) => Ok(val),
+            Ok(None) => Err(not_offered(studies, residency)),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The configured default rates, for when `tuition_costs` has none for the student. None when
+    // DEFAULT_CREDITS_COST isn't set.
+    pub fn default_tuition_costs(&self, residency: &str) -> Option<TuitionCosts> {
+        let rates = self.default_rates.as_ref()?;
+        Some(TuitionCosts {
+            credits_cost: PerCredit(Money::new(rates.credits_cost)),
+            nonresidency_fee: if residency == "resident" { Money::zero() } else { Money::new(rates.nonresidency_fee) },
+        })
+    }
+
+    pub async fn orientation_fee(&self, campus: &Campus) -> Result<Money, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).orientation_fee);
+        }
+
+        match queries::ORIENTATION_FEE.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, Money>(sql)
+            .bind(campus_id)
+            .fetch_one(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // Zero at campuses that haven't set a health insurance fee.
+    pub async fn health_insurance_fee(&self, campus: &Campus) -> Result<Money, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).health_insurance_fee);
+        }
+
+        match queries::HEALTH_INSURANCE_FEE.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, Money>(sql)
+            .bind(campus_id)
+            .fetch_optional(self.lookup_conn())).await {
+            Ok(val) => Ok(val.unwrap_or_default()),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The percent rates are assumed to go up each year; zero when none is set.
+    pub async fn annual_rate_increase(&self, campus: &Campus) -> Result<Decimal, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).annual_rate_increase);
+        }
+
+        match queries::RATE_INCREASE.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, Decimal>(sql)
+            .bind(campus_id)
+            .fetch_optional(self.lookup_conn())).await {
+            Ok(val) => Ok(val.unwrap_or_default()),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // Flat fees only international students pay.
+    pub async fn international_fees(&self, campus: &Campus) -> Result<Vec<FlatFee>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).international_fees.clone());
+        }
+
+        match queries::INTERNATIONAL_FEES.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, FlatFee>(sql)
+            .bind(campus_id)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    pub async fn indirect_costs(&self, campus: &Campus, studies: &str) -> Result<Vec<IndirectCost>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).indirect_costs.iter()
+                .filter(|entry| entry.studies == studies)
+                .map(|entry| entry.cost.clone())
+                .collect());
+        }
+
+        match queries::INDIRECT_COSTS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, IndirectCost>(sql)
+            .bind(campus_id)
+            .bind(studies)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The programs at this level of study, shortest first.
+    pub async fn programs(&self, campus: &Campus, studies: &str) -> Result<Vec<Program>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            let mut programs: Vec<Program> = schedule.for_campus(campus).programs.iter()
+                .filter(|entry| entry.studies == studies)
+                .map(|entry| entry.program.clone())
+                .collect();
+            programs.sort_by(|a, b| (a.total_credits, &a.name).cmp(&(b.total_credits, &b.name)));
+            return Ok(programs);
+        }
+
+        match queries::PROGRAMS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, Program>(sql)
+            .bind(campus_id)
+            .bind(studies)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The proration rules for one term, with the term's start date on each.
+    pub async fn proration_rules(&self, campus: &Campus, term: &str) -> Result<Vec<ProrationRule>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).terms.iter()
+                .filter(|entry| entry.name == term)
+                .flat_map(|entry| entry.proration.iter().map(|rule| ProrationRule {
+                    starts_on: entry.starts_on,
+                    after_week: rule.after_week,
+                    tuition_percent: rule.tuition_percent,
+                }))
+                .collect());
+        }
+
+        match queries::PRORATION_RULES.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, ProrationRule>(sql)
+            .bind(campus_id)
+            .bind(term)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The refund schedule for one term, with the term's start date on each rule.
+    pub async fn refund_rules(&self, campus: &Campus, term: &str) -> Result<Vec<RefundRule>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).terms.iter()
+                .filter(|entry| entry.name == term)
+                .flat_map(|entry| entry.refunds.iter().map(|rule| RefundRule {
+                    starts_on: entry.starts_on,
+                    through_week: rule.through_week,
+                    refund_percent: rule.refund_percent,
+                }))
+                .collect());
+        }
+
+        match queries::REFUND_RULES.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, RefundRule>(sql)
+            .bind(campus_id)
+            .bind(term)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // Every term with a proration or refund schedule or line items, oldest first.
+    pub async fn term_names(&self, campus: &Campus) -> Result<Vec<String>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            let mut terms = schedule.for_campus(campus).terms.clone();
+            terms.sort_by_key(|entry| entry.starts_on);
+            return Ok(terms.into_iter().map(|entry| entry.name).collect());
+        }
+
+        // Line items can be for a term that has no start date on file; those go last.
+        match queries::TERM_NAMES.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, String>(sql)
+            .bind(campus_id)
+            .bind(campus_id)
+            .bind(campus_id)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // Every term's estimate window, by start date.
+    pub async fn term_windows(&self, campus: &Campus) -> Result<Vec<TermWindow>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            let mut windows: Vec<TermWindow> = schedule.for_campus(campus).terms.iter()
+                .map(|entry| TermWindow {
+                    name: entry.name.clone(),
+                    starts_on: entry.starts_on,
+                    opens_on: entry.opens_on,
+                    closes_on: entry.closes_on,
+                })
+                .collect();
+            windows.sort_by_key(|window| window.starts_on);
+            return Ok(windows);
+        }
+
+        match queries::TERM_WINDOWS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, TermWindow>(sql)
+            .bind(campus_id)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The admin-defined charges for one term, before their rules are checked.
+    pub async fn line_items(&self, campus: &Campus, term: &str) -> Result<Vec<LineItem>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).terms.iter()
+                .filter(|entry| entry.name == term)
+                .flat_map(|entry| entry.line_items.iter().cloned())
+                .collect());
+        }
+
+        match queries::LINE_ITEMS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, LineItem>(sql)
+            .bind(campus_id)
+            .bind(term)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The combinations of answers the campus turns away.
+    pub async fn validation_rules(&self, campus: &Campus) -> Result<Vec<ValidationRule>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).validation_rules.clone());
+        }
+
+        match queries::VALIDATION_RULES.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, ValidationRule>(sql)
+            .bind(campus_id)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // Who doesn't pay for orientation, in the order they're checked.
+    pub async fn orientation_exemptions(&self, campus: &Campus) -> Result<Vec<OrientationExemption>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).orientation_exemptions.clone());
+        }
+
+        match queries::ORIENTATION_EXEMPTIONS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, OrientationExemption>(sql)
+            .bind(campus_id)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The campus's agreements with other states, by state.
+    pub async fn reciprocity_agreements(&self, campus: &Campus) -> Result<Vec<ReciprocityAgreement>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            return Ok(schedule.for_campus(campus).reciprocity.clone());
+        }
+
+        match queries::RECIPROCITY_AGREEMENTS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, ReciprocityAgreement>(sql)
+            .bind(campus_id)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+
+    // The rates a student from `home_state`, a state code or "international", pays at this campus,
+    // and the agreement that set them, if any. Everything that prices by home state goes through
+    // here, so the explain page, the API and GraphQL agree with `/calculate`.
+    pub async fn home_state_residency(&self, campus: &Campus, home_state: &str) -> Result<(&'static str, Option<ReciprocityAgreement>), AppError> {
+        let home_state = home_state_code(home_state)?;
+        if home_state == residency::INTERNATIONAL {
+            return Ok(("international", None));
+        }
+        let agreements = self.reciprocity_agreements(campus).await?;
+        Ok(agreement_residency(campus, home_state, &agreements))
+    }
+
+    // The campus's whole course fee catalog, by department.
+    pub async fn course_fees(&self, campus: &Campus) -> Result<Vec<CourseFee>, AppError> {
+        if let Some(schedule) = self.fee_schedule() {
+            let mut fees = schedule.for_campus(campus).course_fees.clone();
+            fees.sort_by(|a, b| (&a.department, &a.course_code).cmp(&(&b.department, &b.course_code)));
+            return Ok(fees);
+        }
+
+        match queries::COURSE_FEES.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, CourseFee>(sql)
+            .bind(campus_id)
+            .fetch_all(self.lookup_conn())).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(AppError::from(why)),
+        }
+    }
+}
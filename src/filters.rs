@@ -0,0 +1,42 @@
+use sqlx::{Encode, MySql, QueryBuilder, Type};
+
+// A query whose `where` clause depends on which filters were filled in. Every value goes in as a
+// bind parameter; only the fixed SQL fragments are pasted into the query text.
+pub struct Conditions<'a> {
+    builder: QueryBuilder<'a, MySql>,
+    any: bool,
+}
+
+impl<'a> Conditions<'a> {
+    pub fn new(select: &str) -> Conditions<'a> {
+        Conditions { builder: QueryBuilder::new(select), any: false }
+    }
+
+    // Adds `<sql> ?` with `val` bound, e.g. `and("TuitionCost >= ", min)`.
+    pub fn and<T: 'a + Encode<'a, MySql> + Type<MySql> + Send>(&mut self, sql: &str, val: T) -> &mut Self {
+        self.builder.push(if self.any { " and " } else { " where " });
+        self.builder.push(sql);
+        self.builder.push_bind(val);
+        self.any = true;
+        self
+    }
+
+    // Same as `and`, when the filter is set.
+    pub fn and_some<T: 'a + Encode<'a, MySql> + Type<MySql> + Send>(&mut self, sql: &str, val: Option<T>) -> &mut Self {
+        if let Some(val) = val {
+            self.and(sql, val);
+        }
+        self
+    }
+
+    // For what comes after the conditions: ordering and paging.
+    pub fn finish(self) -> QueryBuilder<'a, MySql> {
+        self.builder
+    }
+}
+
+// For a `like` filter matching `val` anywhere, with the wildcards in `val` itself escaped.
+pub fn contains(val: &str) -> String {
+    let escaped = val.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
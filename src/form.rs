@@ -0,0 +1,137 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::{
+    de::{DeserializeOwned, Error, IntoDeserializer},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+// Deserializers for the fields of submitted forms, so handlers get values that are already
+// trimmed and parsed. Fields using them also need #[serde(default)], since browsers leave
+// unchecked boxes and some empty inputs out of the submission entirely.
+
+// A field as text. A form sends everything as text; a JSON body can send numbers and booleans
+// instead, which are read the same as their text.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Text(String),
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    fn into_text(self) -> String {
+        match self {
+            Value::Text(val) => val,
+            Value::Bool(val) => val.to_string(),
+            Value::Int(val) => val.to_string(),
+            Value::Float(val) => val.to_string(),
+        }
+    }
+}
+
+// Text with the surrounding whitespace removed; blank is the same as not filled in.
+pub fn trimmed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    match Option::<Value>::deserialize(deserializer)?.map(Value::into_text) {
+        Some(val) if !val.trim().is_empty() => Ok(Some(val.trim().to_string())),
+        _ => Ok(None),
+    }
+}
+
+// Any other field type, left as None when the input is blank.
+pub fn optional<'de, D: Deserializer<'de>, T: DeserializeOwned>(deserializer: D) -> Result<Option<T>, D::Error> {
+    match trimmed(deserializer)? {
+        Some(val) => T::deserialize(val.into_deserializer()).map(Some),
+        None => Ok(None),
+    }
+}
+
+// Browsers send "on" for a checked box and nothing for an unchecked one.
+pub fn checkbox<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(tristate(deserializer)?.unwrap_or(false))
+}
+
+// A checkbox where saying no is worth telling apart from not answering: None when it was left
+// out, Some(false) only when "off", "false" or "0" was sent for it.
+pub fn tristate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    match trimmed(deserializer)?.map(|val| val.to_ascii_lowercase()).as_deref() {
+        None => Ok(None),
+        Some("off") | Some("false") | Some("0") => Ok(Some(false)),
+        Some("on") | Some("true") | Some("1") => Ok(Some(true)),
+        Some(val) => Err(D::Error::custom(format!("\"{}\" is not a checkbox value; send on or off, or leave it out.", val))),
+    }
+}
+
+// A whole number from MIN to MAX. A bad value doesn't fail the whole form: it keeps what was
+// typed, so the form can be shown again as it was filled in, and `value` says what was wrong
+// for the handler to put on the field.
+#[derive(Debug, Clone)]
+pub struct Bounded<const MIN: u32, const MAX: u32> {
+    raw: String,
+    value: Result<u32, String>,
+}
+
+impl<const MIN: u32, const MAX: u32> Bounded<MIN, MAX> {
+    pub fn new(val: u32) -> Bounded<MIN, MAX> {
+        Bounded::parse(&val.to_string())
+    }
+
+    fn parse(raw: &str) -> Bounded<MIN, MAX> {
+        let value = match raw.parse::<u64>() {
+            Ok(val) if (u64::from(MIN)..=u64::from(MAX)).contains(&val) => Ok(val as u32),
+            Ok(val) if MAX == u32::MAX && val < u64::from(MIN) => Err(format!("{} is out of range; it must be at least {}.", raw, MIN)),
+            Ok(_) => Err(format!("{} is out of range; it must be from {} to {}.", raw, MIN, MAX)),
+            Err(_) => Err(format!("\"{}\" is not a whole number.", raw)),
+        };
+        Bounded { raw: raw.to_string(), value }
+    }
+
+    pub fn value(&self) -> Result<u32, String> {
+        self.value.clone()
+    }
+}
+
+impl<const MIN: u32, const MAX: u32> Serialize for Bounded<MIN, MAX> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de, const MIN: u32, const MAX: u32> Deserialize<'de> for Bounded<MIN, MAX> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Bounded<MIN, MAX>, D::Error> {
+        Ok(Bounded::parse(Value::deserialize(deserializer)?.into_text().trim()))
+    }
+}
+
+// The submitted fields, from either a form post or a JSON body, going by the Content-Type.
+// Both go through the same deserializers above.
+pub struct Submitted<T>(pub T);
+
+impl<T> Submitted<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for Submitted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Submitted<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Submitted<T>, actix_web::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        if req.content_type() == "application/json" {
+            let json = web::Json::<T>::from_request(req, payload);
+            return Box::pin(async move { Ok(Submitted(json.await?.into_inner())) });
+        }
+        let form = web::Form::<T>::from_request(req, payload);
+        Box::pin(async move { Ok(Submitted(form.await?.into_inner())) })
+    }
+}
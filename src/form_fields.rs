@@ -0,0 +1,165 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{audit, error::AppError, form, models::Campus, queries, render, AppState, TypeSafeParameters};
+
+// The optional inputs an admin can take off the calculator form, as the admin page lists them.
+pub const FIELDS: [(&str, &str); 6] = [
+    ("new_student", "New student"),
+    ("orientation", "Orientation"),
+    ("enrollment_date", "Enrollment date"),
+    ("course_codes", "Courses with lab or course fees"),
+    ("insurance_waiver", "Own health insurance"),
+    ("include_additional_costs", "Include estimated additional costs"),
+];
+
+// Which of them the form shows. Submitted from the admin page as checkboxes, so a field left out
+// is hidden there; a campus without settings shows them all.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct FormFields {
+    #[serde(default, deserialize_with = "form::checkbox")]
+    pub new_student: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    pub orientation: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    pub enrollment_date: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    pub course_codes: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    pub insurance_waiver: bool,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    pub include_additional_costs: bool,
+}
+
+impl Default for FormFields {
+    fn default() -> FormFields {
+        FormFields {
+            new_student: true,
+            orientation: true,
+            enrollment_date: true,
+            course_codes: true,
+            insurance_waiver: true,
+            include_additional_costs: true,
+        }
+    }
+}
+
+impl FormFields {
+    // Inputs that can't be hidden are always shown.
+    pub fn shown(&self, field: &str) -> bool {
+        match field {
+            "new_student" => self.new_student,
+            "orientation" => self.orientation,
+            "enrollment_date" => self.enrollment_date,
+            "course_codes" => self.course_codes,
+            "insurance_waiver" => self.insurance_waiver,
+            "include_additional_costs" => self.include_additional_costs,
+            _ => true,
+        }
+    }
+
+    fn set(&mut self, field: &str, visible: bool) {
+        match field {
+            "new_student" => self.new_student = visible,
+            "orientation" => self.orientation = visible,
+            "enrollment_date" => self.enrollment_date = visible,
+            "course_codes" => self.course_codes = visible,
+            "insurance_waiver" => self.insurance_waiver = visible,
+            "include_additional_costs" => self.include_additional_costs = visible,
+            _ => {},
+        }
+    }
+
+    // A hidden input counts as left empty, whatever was sent for it, so a cached page or a hand-made
+    // request can't claim what the form no longer asks.
+    pub fn apply(&self, params: &mut TypeSafeParameters) {
+        if !self.new_student {
+            params.new_student = false;
+        }
+        if !self.orientation {
+            params.orientation = false;
+            params.orientation_answer = None;
+        }
+        if !self.enrollment_date {
+            params.enrollment_date = None;
+        }
+        if !self.course_codes {
+            params.course_codes = Vec::new();
+        }
+        if !self.insurance_waiver {
+            params.insurance_waived = false;
+        }
+        if !self.include_additional_costs {
+            params.include_additional_costs = false;
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+#[sqlx(rename_all = "PascalCase")]
+struct FormFieldRow {
+    field: String,
+    visible: bool,
+}
+
+impl AppState {
+    pub async fn form_fields(&self, campus: &Campus) -> Result<FormFields, AppError> {
+        let rows = queries::FORM_FIELDS.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, FormFieldRow>(sql)
+        .bind(campus_id)
+        .fetch_all(&self.conn)).await?;
+
+        let mut fields = FormFields::default();
+        for row in rows {
+            fields.set(&row.field, row.visible);
+        }
+        // Orientation is only offered to new students, so it goes with the new student box.
+        fields.orientation = fields.orientation && fields.new_student;
+        Ok(fields)
+    }
+}
+
+#[derive(Serialize)]
+struct FormFieldSetting {
+    field: &'static str,
+    label: &'static str,
+    visible: bool,
+}
+
+#[derive(Serialize)]
+struct FormFieldsPage {
+    fields: Vec<FormFieldSetting>,
+}
+
+pub async fn form_fields(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let shown = state.form_fields(&campus).await?;
+    let fields = FIELDS.iter()
+        .map(|(field, label)| FormFieldSetting { field, label, visible: shown.shown(field) })
+        .collect();
+    render(&state, "admin_form_fields", &FormFieldsPage { fields }).await
+}
+
+pub async fn set_form_fields(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<FormFields>) -> Result<HttpResponse, AppError> {
+    let actor = audit::actor(&req);
+    let mut tx = state.conn.begin().await?;
+    for (field, _label) in FIELDS {
+        queries::SET_FORM_FIELD.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(campus_id)
+        .bind(field)
+        .bind(params.shown(field))
+        .execute(&mut tx)).await?;
+    }
+    let hidden: Vec<&str> = FIELDS.iter().filter(|(field, _label)| !params.shown(field)).map(|(_field, label)| *label).collect();
+    let details = if hidden.is_empty() {
+        "The form shows every optional input".to_string()
+    } else {
+        format!("The form hides: {}", hidden.join(", "))
+    };
+    audit::record(&mut tx, &actor, "update", "FormFields", campus.id.0, &details).await?;
+    tx.commit().await?;
+    // The calculator page is cached with the inputs it showed.
+    state.index_cache.clear().await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/form-fields"))
+        .finish())
+}
@@ -0,0 +1,273 @@
+use actix_web::{web, Either, HttpRequest, HttpResponse};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, ErrorExtensions, Guard, Object, Schema, SimpleObject};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use rust_decimal::Decimal;
+
+use crate::{api, error::{AppError, RequestId}, fees, logs, models::{ApiKey, Campus, StudentId, TuitionRecord}, normalize_name, pricing, queries, AppState};
+
+// One query for what the student portal would otherwise stitch together from several REST calls.
+// Rates and fee definitions are public like /api/v1/rates; students and their calculations need
+// an API key, checked field by field so a keyless query still gets the public parts.
+pub type CalculatorSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+// Deep enough for student -> calculations, and no deeper.
+const MAX_DEPTH: usize = 6;
+
+// Fields cost 1 each and the ones that look something up 10. Asking for everything once comes to
+// under 100; repeating the lookups under aliases runs out well before it could tie up the pool.
+const MAX_COMPLEXITY: usize = 150;
+
+pub fn schema() -> CalculatorSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+// The key the request came with, if any.
+struct Caller(Option<ApiKey>);
+
+struct ApiKeyRequired;
+
+impl Guard for ApiKeyRequired {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        match ctx.data::<Caller>() {
+            Ok(Caller(Some(_key))) => Ok(()),
+            _ => Err(async_graphql::Error::new("An API key is required for this field.").extend_with(|_, extensions| extensions.set("code", "unauthorized"))),
+        }
+    }
+}
+
+// The same message and code the REST API would give; what went wrong on our side is only logged.
+fn graphql_error(ctx: &Context<'_>, why: AppError) -> async_graphql::Error {
+    if let AppError::Database(_) | AppError::Internal(_) = why {
+        let request_id = ctx.data::<RequestId>().map(|id| id.to_string()).unwrap_or_default();
+        logs::throttled_as(&why.detail(), &format!("[{}] {}", request_id, why.detail()));
+    }
+    let code = serde_json::to_value(why.code()).unwrap_or_default();
+    async_graphql::Error::new(why.user_message()).extend_with(|_, extensions| extensions.set("code", code.as_str().unwrap_or_default()))
+}
+
+fn state<'a>(ctx: &Context<'a>) -> &'a AppState {
+    ctx.data_unchecked::<web::Data<AppState>>()
+}
+
+fn campus<'a>(ctx: &Context<'a>) -> &'a Campus {
+    ctx.data_unchecked::<Campus>()
+}
+
+#[derive(SimpleObject)]
+struct CampusInfo {
+    slug: String,
+    name: String,
+}
+
+#[derive(SimpleObject)]
+struct CreditRate {
+    studies: String,
+    residency: String,
+    credits_cost: Decimal,
+    nonresidency_fee: Decimal,
+    // The reciprocity agreement the fee was discounted under, when asked for a home state.
+    reciprocity: Option<String>,
+}
+
+#[derive(SimpleObject)]
+struct Fee {
+    label: String,
+    amount: Decimal,
+}
+
+#[derive(SimpleObject)]
+struct CourseFee {
+    department: String,
+    course_code: String,
+    label: String,
+    fee: Decimal,
+}
+
+#[derive(SimpleObject)]
+struct FeeDefinitions {
+    orientation_fee: Decimal,
+    // Zero when waived for students with their own coverage.
+    health_insurance_fee: Decimal,
+    international_fees: Vec<Fee>,
+    // Courses carrying a fee; any other course is free.
+    course_fees: Vec<CourseFee>,
+}
+
+// A saved total for one term. Empty inputs are from before they were recorded.
+#[derive(SimpleObject)]
+struct Calculation {
+    term: Option<String>,
+    tuition_cost: Decimal,
+    num_credits: Option<u8>,
+    orientation: Option<bool>,
+    student_type: Option<String>,
+    student_studies: Option<String>,
+    insurance_waived: Option<bool>,
+}
+
+impl From<TuitionRecord> for Calculation {
+    fn from(record: TuitionRecord) -> Calculation {
+        Calculation {
+            term: record.term,
+            tuition_cost: record.tuition_cost,
+            num_credits: record.num_credits,
+            orientation: record.orientation,
+            student_type: record.student_type,
+            student_studies: record.student_studies,
+            insurance_waived: record.insurance_waived,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+#[sqlx(rename_all = "PascalCase")]
+struct Student {
+    id: StudentId,
+    public_id: String,
+    first_name: String,
+    last_name: String,
+    email: Option<String>,
+}
+
+#[Object]
+impl Student {
+    // The public id; database ids never leave the server.
+    async fn id(&self) -> &str {
+        &self.public_id
+    }
+
+    async fn first_name(&self) -> &str {
+        &self.first_name
+    }
+
+    async fn last_name(&self) -> &str {
+        &self.last_name
+    }
+
+    // Only once confirmed by the student.
+    async fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    // Oldest term first.
+    #[graphql(complexity = 10)]
+    async fn calculations(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Calculation>> {
+        let records = queries::STUDENT_RECORDS.run(|sql| sqlx::query_as::<_, TuitionRecord>(sql)
+        .bind(self.id)
+        .fetch_all(state(ctx).read_conn())).await;
+        match records {
+            Ok(val) => Ok(val.into_iter().map(Calculation::from).collect()),
+            Err(why) => Err(graphql_error(ctx, AppError::from(why))),
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    // The campus the request was made to.
+    async fn campus(&self, ctx: &Context<'_>) -> CampusInfo {
+        let campus = campus(ctx);
+        CampusInfo { slug: campus.slug.clone(), name: campus.name.clone() }
+    }
+
+    // The per-credit rates the calculator is using now, for what the campus offers. With a home
+    // state (a state code or "international"), only the rates a student from there pays, after
+    // any reciprocity agreement, as the calculator prices them.
+    #[graphql(complexity = 10)]
+    async fn rates(&self, ctx: &Context<'_>, studies: Option<String>, residency: Option<String>, home_state: Option<String>) -> async_graphql::Result<Vec<CreditRate>> {
+        let (state, campus) = (state(ctx), campus(ctx));
+        let (residency, agreement) = match &home_state {
+            Some(val) => match state.home_state_residency(campus, val).await {
+                Ok((residency, agreement)) => (Some(residency.to_string()), agreement),
+                Err(why) => {
+                    return Err(graphql_error(ctx, why));
+                }
+            },
+            None => (residency, None),
+        };
+        let mut rates = Vec::new();
+        for each_studies in fees::STUDIES.iter().filter(|val| studies.as_deref().is_none_or(|studies| studies == **val)) {
+            for each_residency in fees::RESIDENCIES.iter().filter(|val| residency.as_deref().is_none_or(|residency| residency == **val)) {
+                match state.tuition_costs(campus, each_studies, each_residency).await {
+                    Ok(costs) => rates.push(CreditRate {
+                        studies: each_studies.to_string(),
+                        residency: each_residency.to_string(),
+                        credits_cost: costs.credits_cost.amount(),
+                        nonresidency_fee: pricing::reciprocity_fee(costs.nonresidency_fee, agreement.as_ref()).amount(),
+                        reciprocity: agreement.as_ref().map(|agreement| agreement.label.clone()),
+                    }),
+                    // Not every campus offers every kind of study.
+                    Err(AppError::Validation { .. }) => {},
+                    Err(why) => {
+                        return Err(graphql_error(ctx, why));
+                    }
+                }
+            }
+        }
+        Ok(rates)
+    }
+
+    // The fees charged on top of the per-credit rate.
+    #[graphql(complexity = 10)]
+    async fn fees(&self, ctx: &Context<'_>) -> async_graphql::Result<FeeDefinitions> {
+        let (state, campus) = (state(ctx), campus(ctx));
+        let orientation_fee = state.orientation_fee(campus).await.map_err(|why| graphql_error(ctx, why))?;
+        let health_insurance_fee = state.health_insurance_fee(campus).await.map_err(|why| graphql_error(ctx, why))?;
+        let international_fees = state.international_fees(campus).await.map_err(|why| graphql_error(ctx, why))?;
+        let course_fees = state.course_fees(campus).await.map_err(|why| graphql_error(ctx, why))?;
+        Ok(FeeDefinitions {
+            orientation_fee: orientation_fee.amount(),
+            health_insurance_fee: health_insurance_fee.amount(),
+            international_fees: international_fees.into_iter().map(|fee| Fee { label: fee.label, amount: fee.amount.amount() }).collect(),
+            course_fees: course_fees.into_iter().map(|fee| CourseFee { department: fee.department, course_code: fee.course_code, label: fee.label, fee: fee.fee.amount() }).collect(),
+        })
+    }
+
+    // A student by their public id, as in the export links.
+    #[graphql(guard = "ApiKeyRequired", complexity = 10)]
+    async fn student(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<Student>> {
+        let student = queries::STUDENT_BY_PUBLIC_ID.scoped(campus(ctx).id).run(|sql, campus_id| sqlx::query_as::<_, Student>(sql)
+        .bind(campus_id)
+        .bind(&id)
+        .fetch_optional(state(ctx).read_conn())).await;
+        student.map_err(|why| graphql_error(ctx, AppError::from(why)))
+    }
+
+    // Every saved calculation for a name, newest first, as the lookup page finds them.
+    #[graphql(guard = "ApiKeyRequired", complexity = 10)]
+    async fn calculations(&self, ctx: &Context<'_>, first_name: String, last_name: String) -> async_graphql::Result<Vec<Calculation>> {
+        let first_name = normalize_name("first_name", &first_name).map_err(|why| graphql_error(ctx, why))?;
+        let last_name = normalize_name("last_name", &last_name).map_err(|why| graphql_error(ctx, why))?;
+        let records = queries::RECORDS_BY_NAME.scoped(campus(ctx).id).run(|sql, campus_id| sqlx::query_as::<_, TuitionRecord>(sql)
+        .bind(campus_id)
+        .bind(&first_name)
+        .bind(&last_name)
+        .fetch_all(state(ctx).read_conn())).await;
+        match records {
+            Ok(val) => Ok(val.into_iter().map(Calculation::from).collect()),
+            Err(why) => Err(graphql_error(ctx, AppError::from(why))),
+        }
+    }
+}
+
+// POST /api/graphql. A key that's sent has to be good, even for public fields, so a client with a
+// revoked key finds out straight away.
+pub async fn graphql(state: web::Data<AppState>, schema: web::Data<CalculatorSchema>, campus: Campus, req: HttpRequest, request: GraphQLRequest) -> Either<GraphQLResponse, HttpResponse> {
+    let caller = match api::api_key(&state, &req).await {
+        Ok(val) => val,
+        Err(response) => {
+            return Either::Right(response);
+        }
+    };
+    let request = request.into_inner()
+        .data(state)
+        .data(campus)
+        .data(Caller(caller))
+        .data(RequestId::of(&req));
+    Either::Left(schema.execute(request).await.into())
+}
@@ -0,0 +1,68 @@
+use std::{fmt, future::Future, pin::Pin};
+
+use crate::config::HttpClientConfig;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Debug, Clone)]
+pub struct OutboundResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+// Outbound HTTP for integrations (captcha, webhooks, payments). It's a trait so a canned
+// client can stand in for the real one.
+pub trait HttpClient: fmt::Debug + Send + Sync {
+    fn post_form<'a>(&'a self, url: &'a str, form: &'a [(&'a str, &'a str)]) -> BoxFuture<'a, Result<OutboundResponse, String>>;
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<OutboundResponse, String>>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ReqwestClient {
+    client: reqwest::Client,
+}
+
+impl ReqwestClient {
+    pub fn new(config: &HttpClientConfig) -> Result<ReqwestClient, String> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .user_agent(concat!("tuition-calculator/", env!("CARGO_PKG_VERSION")));
+        if let Some(proxy) = &config.proxy {
+            builder = match reqwest::Proxy::all(proxy) {
+                Ok(val) => builder.proxy(val),
+                Err(why) => {
+                    return Err(format!("OUTBOUND_PROXY \"{}\" is not a valid proxy: {}", proxy, why));
+                }
+            };
+        }
+        match builder.build() {
+            Ok(client) => Ok(ReqwestClient { client }),
+            Err(why) => Err(format!("Error setting up the HTTP client: {}", why)),
+        }
+    }
+
+    async fn send(request: reqwest::RequestBuilder) -> Result<OutboundResponse, String> {
+        let response = match request.send().await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Error sending request: {}", why));
+            }
+        };
+        let status = response.status().as_u16();
+        match response.text().await {
+            Ok(body) => Ok(OutboundResponse { status, body }),
+            Err(why) => Err(format!("Error reading response: {}", why)),
+        }
+    }
+}
+
+impl HttpClient for ReqwestClient {
+    fn post_form<'a>(&'a self, url: &'a str, form: &'a [(&'a str, &'a str)]) -> BoxFuture<'a, Result<OutboundResponse, String>> {
+        Box::pin(ReqwestClient::send(self.client.post(url).form(form)))
+    }
+
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<OutboundResponse, String>> {
+        Box::pin(ReqwestClient::send(self.client.get(url)))
+    }
+}
@@ -0,0 +1,20 @@
+use rand::RngCore;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Identifiers that are safe to show outside the admin pages. Database ids count up, so one
+// student's id gives away everyone else's; these are UUIDv7s instead, made here rather than by
+// the database so every server can create them at once without coordinating. The leading
+// timestamp keeps them roughly in insert order, which keeps the unique index cheap to update,
+// and the 74 random bits make them unguessable.
+pub fn new_public_id() -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|val| val.as_millis() as u64).unwrap_or_default();
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes[6..]);
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    // Version 7, and the RFC 9562 variant.
+    bytes[6] = (bytes[6] & 0x0f) | 0x70;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
@@ -0,0 +1,73 @@
+use actix_web::rt;
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+// While the database is down every request fails with the same error. The first of each
+// distinct line is printed as usual; the same line again within WINDOW is only counted, and
+// the count is printed once the window is over.
+const WINDOW: Duration = Duration::from_secs(60);
+
+struct Seen {
+    since: Instant,
+    suppressed: u64,
+}
+
+static SEEN: LazyLock<Mutex<HashMap<String, Seen>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn summary(key: &str, suppressed: u64) {
+    println!("(repeated {} more time(s) in the last {}s: {})", suppressed, WINDOW.as_secs(), key);
+}
+
+// Print `line` unless a line with the same `key` was printed within the window. The key leaves
+// out whatever differs between otherwise identical lines, like the request ID.
+pub fn throttled_as(key: &str, line: &str) {
+    let mut seen = SEEN.lock().unwrap();
+    let now = Instant::now();
+    match seen.get_mut(key) {
+        Some(entry) if now.duration_since(entry.since) < WINDOW => {
+            entry.suppressed += 1;
+            return;
+        }
+        Some(entry) => {
+            if entry.suppressed > 0 {
+                summary(key, entry.suppressed);
+            }
+            *entry = Seen { since: now, suppressed: 0 };
+        }
+        None => {
+            seen.insert(key.to_string(), Seen { since: now, suppressed: 0 });
+        }
+    }
+    println!("{}", line);
+}
+
+pub fn throttled(line: &str) {
+    throttled_as(line, line);
+}
+
+// Report the counts for windows that have ended, and forget them.
+fn flush() {
+    let mut seen = SEEN.lock().unwrap();
+    let now = Instant::now();
+    seen.retain(|key, entry| {
+        if now.duration_since(entry.since) < WINDOW {
+            return true;
+        }
+        if entry.suppressed > 0 {
+            summary(key, entry.suppressed);
+        }
+        false
+    });
+}
+
+pub fn spawn_flush() {
+    rt::spawn(async {
+        loop {
+            rt::time::sleep(WINDOW).await;
+            flush();
+        }
+    });
+}
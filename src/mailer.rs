@@ -0,0 +1,82 @@
+use lettre::{
+    message::{Mailbox, MessageBuilder, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::config::SmtpConfig;
+
+#[derive(Debug, Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &SmtpConfig) -> Result<Mailer, String> {
+        let from = match config.from.parse::<Mailbox>() {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("SMTP_FROM \"{}\" is not a valid address: {}", config.from, why));
+            }
+        };
+        let mut builder = match AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host) {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Error setting up SMTP for {}: {}", config.host, why));
+            }
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Mailer { transport: builder.build(), from })
+    }
+
+    // Whether mail can be sent to `address`, checked before anything is sent to it.
+    pub fn valid_address(address: &str) -> bool {
+        address.parse::<Mailbox>().is_ok()
+    }
+
+    fn addressed(&self, recipients: &[String], subject: &str) -> Result<MessageBuilder, String> {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for recipient in recipients {
+            match recipient.parse::<Mailbox>() {
+                Ok(val) => message = message.to(val),
+                Err(why) => {
+                    return Err(format!("\"{}\" is not a valid address: {}", recipient, why));
+                }
+            }
+        }
+        Ok(message)
+    }
+
+    async fn deliver(&self, message: Result<Message, lettre::error::Error>) -> Result<(), String> {
+        let message = match message {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Error building message: {}", why));
+            }
+        };
+
+        match self.transport.send(message).await {
+            Ok(_val) => Ok(()),
+            Err(why) => Err(format!("Error sending mail: {}", why)),
+        }
+    }
+
+    // Send a plain text message to each recipient.
+    pub async fn send(&self, recipients: &[String], subject: &str, body: &str) -> Result<(), String> {
+        let message = self.addressed(recipients, subject)?;
+        self.deliver(message.body(body.to_string())).await
+    }
+
+    // An HTML message, with the plain text for mail clients that don't show HTML.
+    pub async fn send_html(&self, recipients: &[String], subject: &str, text: &str, html: &str) -> Result<(), String> {
+        let message = self.addressed(recipients, subject)?;
+        self.deliver(message.multipart(MultiPart::alternative_plain_html(text.to_string(), html.to_string()))).await
+    }
+}
@@ -1,42 +1,171 @@
-use actix_web::{web, App, HttpResponse, HttpServer, Result};
+use actix_session::Session;
+use actix_web::{cookie::Key, http::KeepAlive, middleware, web, App, HttpRequest, HttpResponse, HttpServer, ResponseError, Result};
 use serde::{Deserialize, Serialize};
-use sqlx::{MySqlPool, Pool, MySql};
+use sqlx::{mysql::{MySqlConnectOptions, MySqlPoolOptions}, Pool, MySql};
 use rust_decimal::Decimal;
+use chrono::{NaiveDate, Utc};
 use dotenvy::dotenv;
-use std::{env};
-use webbrowser;
+use models::Campus;
+use config::AppConfig;
+use error::{AppError, FormErrors};
+use money::{Money, PerCredit};
+use renderer::{html, render, render_string, templates};
+use studies::StudentStudies;
+use std::sync::Arc;
+use unicode_normalization::UnicodeNormalization;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+mod adjustments;
+mod admin;
+mod api;
+mod assets;
+mod audit;
+mod bulk_delete;
+mod campus;
+mod captcha;
+mod chart;
+mod client_ip;
+mod config;
+mod error;
+mod explain;
+mod export;
+mod failover;
+mod fees;
+mod filters;
+mod form;
+mod form_fields;
+mod graphql;
+mod http_client;
+mod ids;
+mod logs;
+mod mailer;
+mod maintenance;
+mod metrics;
+mod migrations;
+mod models;
+mod money;
+mod negotiate;
+mod page_cache;
+mod passwords;
+mod pricing;
+mod projection;
+mod queries;
+mod rate_limit;
+mod receipts;
+mod recent;
+mod renderer;
+#[cfg(feature = "redis")]
+mod redis_store;
+mod refunds;
+mod replay;
+mod request_meta;
+mod retention;
+mod rules;
+mod scenarios;
+mod residency;
+mod schema;
+mod screening;
+mod seed;
+mod share;
+mod slow_queries;
+mod startup;
+mod staff_auth;
+mod stats;
+mod student_auth;
+mod students;
+mod studies;
+mod summary;
+mod telemetry;
+mod terms;
+mod verification;
+mod wizard;
+
+// Each field is trimmed and parsed as it is deserialized; see the form module. What's checked
+// afterwards is only what needs more than one field, or something to look up.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct CalculateTuitionFormParams {
-    // We need to use Option<...> because sometimes the fields can be empty from form submission.
+    #[serde(default, deserialize_with = "form::trimmed")]
     first_name: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
     last_name: Option<String>,
-    num_credits: Option<String>,
-    new_student: Option<String>,
-    orientation: Option<String>,
+    #[serde(default, deserialize_with = "form::optional")]
+    num_credits: Option<form::Bounded<0, 255>>,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    new_student: bool,
+    // Left out when the box wasn't offered; the page sends "off" when it was and is left unchecked.
+    #[serde(default, deserialize_with = "form::tristate")]
+    orientation: Option<bool>,
+    #[serde(default, deserialize_with = "form::trimmed")]
     student_type: Option<String>,
+    // A state's code, or "international", where the campus asks for it instead of student_type.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    home_state: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
     student_studies: Option<String>,
+    #[serde(default, deserialize_with = "form::checkbox")]
+    include_additional_costs: bool,
+    // YYYY-MM-DD, from the date input. Left empty for a full term.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    enrollment_date: Option<String>,
+    // Comma-separated codes of courses with lab or course fees, e.g. "CHEM 101, BIOL 110".
+    #[serde(default, deserialize_with = "form::trimmed")]
+    course_codes: Option<String>,
+    // "I have my own insurance".
+    #[serde(default, deserialize_with = "form::checkbox")]
+    insurance_waiver: bool,
+    // Optional. Kept on the student only once they enter the code mailed to it.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    email: Option<String>,
+    // Agreeing to the estimate being saved, where REQUIRE_CONSENT asks for it.
+    #[serde(default, deserialize_with = "form::checkbox")]
+    consent: bool,
+    // Filled in by the captcha widget, when one is configured.
+    #[serde(alias = "g-recaptcha-response", alias = "h-captcha-response", skip_serializing)]
+    captcha_response: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LookupFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
     first_name: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
     last_name: Option<String>,
+    #[serde(alias = "g-recaptcha-response", alias = "h-captcha-response", skip_serializing)]
+    captcha_response: Option<String>,
 }
 
 pub struct TypeSafeLookupFormParams {
-    firstName: String,
-    lastName: String,
+    first_name: String,
+    last_name: String,
 }
 
 enum StudentResidency {
     In,
     Out,
+    // On a student visa; pays the international rate and fees.
+    International,
 }
 
-enum StudentStudies {
-    Undergraduate,
-    Graduate,
+// The entry point a calculation came through, kept on its receipt so the reports can break usage
+// down by source.
+#[derive(Debug, Clone, Copy)]
+enum Channel {
+    // The calculator page.
+    Web,
+    // POST /calculate asking for JSON.
+    Api,
+    // A counselor calculating from the admin pages.
+    Counselor,
+}
+
+impl Channel {
+    // The value kept in the Receipts table.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Web => "web",
+            Channel::Api => "api",
+            Channel::Counselor => "counselor",
+        }
+    }
 }
 
 pub struct TypeSafeParameters {
@@ -45,336 +174,1018 @@ pub struct TypeSafeParameters {
     num_credits: u8,
     new_student: bool,
     orientation: bool,
+    // What the student answered, before exemptions: None when they weren't asked.
+    orientation_answer: Option<bool>,
     student_type: StudentResidency,
+    // The state the student lives in, when they gave one; their residency comes from it.
+    home_state: Option<String>,
     student_studies: StudentStudies,
+    include_additional_costs: bool,
+    enrollment_date: Option<NaiveDate>,
+    course_codes: Vec<String>,
+    insurance_waived: bool,
+    email: Option<String>,
+}
+
+// Students who gave their home state pay the rates it maps to at this campus. Returns the
+// reciprocity agreement that applied, if any.
+async fn resolve_residency(state: &AppState, campus: &Campus, params: &mut TypeSafeParameters) -> Result<Option<models::ReciprocityAgreement>, AppError> {
+    let home_state = match &params.home_state {
+        Some(val) => val,
+        None => {
+            return Ok(None);
+        }
+    };
+    let (residency, agreement) = state.home_state_residency(campus, home_state).await?;
+    params.student_type = if residency == "resident" { StudentResidency::In } else { StudentResidency::Out };
+    Ok(agreement)
+}
+
+// Most courses with fees one calculation can list.
+pub const MAX_COURSE_CODES: usize = 12;
+
+// How course codes are kept on receipts and scenarios.
+pub fn course_codes_column(codes: &[String]) -> Option<String> {
+    if codes.is_empty() { None } else { Some(codes.join(", ")) }
+}
+
+// Most credits a dual-enrollment student can take in one term.
+pub const DUAL_ENROLLMENT_MAX_CREDITS: u8 = 11;
+
+// Longest name the Students table holds.
+pub const MAX_NAME_LENGTH: usize = 100;
+
+// And the longest email address.
+pub const MAX_EMAIL_LENGTH: usize = 255;
+
+// Names are stored NFC-normalized, so "José" typed with a combining accent finds the same record.
+pub fn normalize_name(field: &'static str, val: &str) -> Result<String, AppError> {
+    let name: String = val.trim().nfc().collect();
+    if name.is_empty() {
+        return Err(AppError::validation(field, "A name can't be blank."));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::validation(field, &format!("Names can be at most {} characters.", MAX_NAME_LENGTH)));
+    }
+    Ok(name)
+}
+
+impl TypeSafeLookupFormParams {
+    // Build our typesafe lookup parameters from the submitted form.
+    fn from_form(params: &LookupFormParams) -> Result<TypeSafeLookupFormParams, AppError> {
+        Ok(TypeSafeLookupFormParams {
+            first_name: match &params.first_name {
+                Some(val) => normalize_name("first_name", val)?,
+                None => {
+                    return Err(AppError::validation("first_name", "First name not provided"));
+                }
+            },
+            last_name: match &params.last_name {
+                Some(val) => normalize_name("last_name", val)?,
+                None => {
+                    return Err(AppError::validation("last_name", "Last name not provided"));
+                }
+            }
+        })
+    }
+}
+
+impl TypeSafeParameters {
+    // Build our typesafe parameters from the submitted form.
+    fn from_form(params: &CalculateTuitionFormParams) -> Result<TypeSafeParameters, AppError> {
+        let mut parsed = TypeSafeParameters {
+            first_name: match &params.first_name {
+                Some(val) => normalize_name("first_name", val)?,
+                None => {
+                    return Err(AppError::validation("first_name", "No first name was provided!"));
+                }
+            },
+            last_name: match &params.last_name {
+                Some(val) => normalize_name("last_name", val)?,
+                None => {
+                    return Err(AppError::validation("last_name", "No last name was provided!"));
+                }
+            },
+            num_credits: match &params.num_credits {
+                // Bounded by u8::MAX.
+                Some(val) => match val.value() {
+                    Ok(val) => val as u8,
+                    Err(why) => {
+                        return Err(AppError::validation("num_credits", &format!("Credit hours: {}", why)));
+                    }
+                },
+                None => {
+                    return Err(AppError::validation("num_credits", "No credits were provided!"));
+                }
+            },
+            new_student: params.new_student,
+            orientation: params.orientation.unwrap_or(false),
+            orientation_answer: params.orientation,
+            student_type: match (&params.home_state, &params.student_type) {
+                (Some(val), _) if val.eq_ignore_ascii_case(residency::INTERNATIONAL) => StudentResidency::International,
+                // Until `resolve_residency` looks the state up in the campus's agreements.
+                (Some(_), _) => StudentResidency::Out,
+                (None, Some(val)) => {
+                    if val.eq("resident") 
+                        {StudentResidency::In} 
+                    else if val.eq("international") 
+                        {StudentResidency::International} 
+                    else 
+                        {StudentResidency::Out}
+                }
+                (None, None) => {
+                    return Err(AppError::validation("student_type", "User must be a resident, nonresident, or international student."));
+                }
+            },
+            home_state: match &params.home_state {
+                Some(val) if val.eq_ignore_ascii_case(residency::INTERNATIONAL) => None,
+                Some(val) => match residency::find(val) {
+                    Some(state) => Some(state.code.to_string()),
+                    None => {
+                        return Err(AppError::validation("home_state", &format!("\"{}\" isn't a U.S. state.", val)));
+                    }
+                },
+                None => None,
+            },
+            student_studies: match &params.student_studies {
+                Some(val) => StudentStudies::parse(val),
+                None => {
+                    return Err(AppError::validation("student_studies", "User must be an undergraduate, graduate, or dual-enrollment student."));
+                }
+            },
+            include_additional_costs: params.include_additional_costs,
+            enrollment_date: match &params.enrollment_date {
+                Some(val) => match NaiveDate::parse_from_str(val, "%Y-%m-%d") {
+                    Ok(date) => Some(date),
+                    Err(_) => {
+                        return Err(AppError::validation("enrollment_date", &format!("\"{}\" is not a valid enrollment date.", val)));
+                    }
+                },
+                None => None,
+            },
+            course_codes: Vec::new(),
+            insurance_waived: params.insurance_waiver,
+            email: match &params.email {
+                Some(val) if val.len() > MAX_EMAIL_LENGTH || !mailer::Mailer::valid_address(val) => {
+                    return Err(AppError::validation("email", &format!("\"{}\" is not an email address we can send to.", val)));
+                }
+                Some(val) => Some(val.clone()),
+                None => None,
+            },
+        };
+
+        // The same course listed twice is only charged once.
+        if let Some(val) = &params.course_codes {
+            for code in val.split(',').map(|code| code.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase()) {
+                if !code.is_empty() && !parsed.course_codes.iter().any(|other| fees::course_code_key(other) == fees::course_code_key(&code)) {
+                    parsed.course_codes.push(code);
+                }
+            }
+            if parsed.course_codes.len() > MAX_COURSE_CODES {
+                return Err(AppError::validation("course_codes", &format!("At most {} courses can be listed.", MAX_COURSE_CODES)));
+            }
+        }
+
+        // Dual-enrollment students have a credit cap and never pay for orientation.
+        if let StudentStudies::DualEnrollment = parsed.student_studies {
+            if parsed.num_credits > DUAL_ENROLLMENT_MAX_CREDITS {
+                return Err(AppError::validation("num_credits", &format!("Dual-enrollment students can take at most {} credits.", DUAL_ENROLLMENT_MAX_CREDITS)));
+            }
+            parsed.orientation = false;
+        }
+        Ok(parsed)
+    }
+}
+
+impl StudentResidency {
+    // The value used by the form and the CreditCosts table.
+    fn as_str(&self) -> &'static str {
+        match self {
+            StudentResidency::In => "resident",
+            StudentResidency::Out => "nonresident",
+            StudentResidency::International => "international",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            StudentResidency::In => "Resident",
+            StudentResidency::Out => "Non-Resident",
+            StudentResidency::International => "International",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 struct AppState {
     app_name: String,
     conn: Pool<MySql>,
+    // The replica when READ_DATABASE_URL is set, otherwise the same pool as `conn`. Read through
+    // `read_conn`, which swaps in the secondary while the primary is down.
+    replica_conn: Pool<MySql>,
+    // Set when SECONDARY_DATABASE_URL is configured.
+    failover: Option<Arc<failover::Failover>>,
+    templates: Arc<handlebars::Handlebars<'static>>,
+    rate_limiter: Arc<rate_limit::RateLimiter<models::ApiKeyId>>,
+    // Set when rates come from FEE_SCHEDULE_FILE instead of the database.
+    fee_schedule: Option<fees::SharedFeeSchedule>,
+    // Loaded once at startup; adding a campus needs a restart.
+    campuses: Arc<Vec<Campus>>,
+    // Set when SMTP_HOST is configured.
+    mailer: Option<mailer::Mailer>,
+    maintenance: maintenance::SharedMaintenance,
+    // Set when CAPTCHA_PROVIDER is configured.
+    captcha: Option<captcha::Captcha>,
+    http: Arc<dyn http_client::HttpClient>,
+    branding: config::BrandingConfig,
+    letterhead: config::LetterheadConfig,
+    share_signer: share::ShareSigner,
+    request_metadata: request_meta::MetadataPolicy,
+    // Set when REQUIRE_CONSENT is on.
+    consent: Option<config::ConsentConfig>,
+    // Set when DEFAULT_CREDITS_COST is configured.
+    default_rates: Option<config::DefaultRatesConfig>,
+    admin_user_header: Option<String>,
+    // Set when OIDC_ISSUER is configured; otherwise the proxy guards /admin.
+    staff_auth: Option<Arc<dyn staff_auth::AuthProvider>>,
+    // Set when ADMIN_BASIC_AUTH is configured.
+    basic_auth: Option<Arc<staff_auth::BasicAuth>>,
+    trusted_proxies: client_ip::TrustedProxies,
+    rates_cors_origins: Vec<String>,
+    // Requests to /api/v1/rates per client address, with unknown addresses counted together.
+    rates_limiter: Arc<rate_limit::RateLimiter<Option<std::net::IpAddr>>>,
+    rates_requests_per_minute: u32,
+    // Student sign-in requests per client address, and codes asked for per email address.
+    sign_in_limiter: Arc<rate_limit::RateLimiter<Option<std::net::IpAddr>>>,
+    sign_in_email_limiter: Arc<rate_limit::RateLimiter<String>>,
+    screening: screening::ScreeningMode,
+    index_cache: Arc<page_cache::PageCache>,
+    preview_cache: Arc<api::PreviewCache>,
+    // Estimates waiting to be saved after the database failed to take them.
+    replay: Arc<replay::ReplayQueue>,
 }
 
-async fn lookup(state: web::Data<AppState>, params: web::Form<LookupFormParams>) -> Result<HttpResponse> {
-    let pool = &state.conn;
+// Data for the index page. The form is pre-populated when a saved scenario is loaded.
+#[derive(Serialize)]
+struct IndexPage {
+    campus: Campus,
+    form: Option<CalculateTuitionFormParams>,
+    scenario_name: Option<String>,
+    captcha: Option<captcha::CaptchaWidget>,
+    // Whether to ask for an email address to verify; there's no sending the code without SMTP.
+    can_email: bool,
+    recent_receipts: Vec<models::Receipt>,
+    errors: Option<FormErrors>,
+    // Set when a counselor is calculating on a student's behalf from the admin pages.
+    counselor: Option<String>,
+    // When estimates for the term aren't open to students, and when they will be.
+    term_notice: Option<String>,
+    // Set when the form asks for consent before saving.
+    consent: Option<config::ConsentConfig>,
+    // The optional inputs the campus shows.
+    fields: form_fields::FormFields,
+    // Offered in place of the residency choice when the campus has a state.
+    states: &'static [residency::HomeState],
+}
 
-    let type_safe_params = TypeSafeLookupFormParams {
-        firstName: match &params.first_name {
-            Some(val) => val.to_string(),
-            None => {
-                return error("First name not provided").await;
-            }
-        },
-        lastName: match &params.last_name {
-            Some(val) => val.to_string(),
-            None => {
-                return error("Last name not provided").await;
-            }
+#[derive(Serialize)]
+struct ResultPage {
+    campus: Campus,
+    first_name: String,
+    last_name: String,
+    residency: &'static str,
+    studies: &'static str,
+    new_student: bool,
+    orientation_fee: Money,
+    // Why orientation wasn't charged although the box was checked.
+    orientation_exemption: Option<String>,
+    // The reciprocity agreement with the student's state that set their rates.
+    reciprocity: Option<String>,
+    nonresidency_fee: Money,
+    num_credits: u8,
+    credits_cost: PerCredit,
+    total: Money,
+    enrollment_date: Option<NaiveDate>,
+    course_fees: Vec<models::CourseFee>,
+    course_fee_total: Money,
+    insurance_waived: bool,
+    // Zero when waived.
+    health_insurance_fee: Money,
+    international_fees: Vec<models::FlatFee>,
+    custom_fees: Vec<pricing::BreakdownItem>,
+    // Everything above, grouped into tuition, fees and aid with a subtotal for each.
+    categories: Vec<pricing::CategorySubtotal>,
+    // Set when a proration rule applied; the adjustment is negative.
+    proration: Option<pricing::Proration>,
+    proration_adjustment: Money,
+    // Inline SVG markup from the chart module.
+    breakdown_chart: String,
+    indirect_costs: Vec<models::IndirectCost>,
+    additional_total: Money,
+    grand_total: Option<Money>,
+    // One for each of the campus's programs at this level of study.
+    projections: Vec<projection::Projection>,
+    // None when nothing was saved, without the student's consent.
+    receipt_code: Option<String>,
+    estimate_only: bool,
+    // The database was out, so the receipt is queued and won't open until it's saved.
+    saved_later: bool,
+    // The campus had no rate for the student, so DEFAULT_CREDITS_COST was used.
+    default_rates: bool,
+    // The address a code was sent to, and the id the code is entered under.
+    verify_email: Option<String>,
+    email_verification: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LookupPage {
+    records: Vec<models::TuitionRecord>,
+    // Inline SVG of the estimate across terms, when there are at least two.
+    trend_chart: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Department {
+    name: String,
+    courses: Vec<models::CourseFee>,
+}
+
+#[derive(Serialize)]
+struct CourseFeesPage {
+    campus: Campus,
+    departments: Vec<Department>,
+}
+
+#[derive(Serialize)]
+struct NoRecordPage {
+    first_name: String,
+    last_name: String,
+    calculate_url: String,
+}
+
+fn peer_ip(req: &HttpRequest) -> Option<String> {
+    client_ip::for_request(req).map(|ip| ip.to_string())
+}
+
+async fn lookup(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: form::Submitted<LookupFormParams>) -> Result<HttpResponse, AppError> {
+    let pool = state.read_conn();
+
+    // Names are easy to guess, so keep bots from enumerating them.
+    state.check_captcha(&params.captcha_response, peer_ip(&req).as_deref()).await?;
+
+    let type_safe_params = match TypeSafeLookupFormParams::from_form(&params) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why);
         }
     };
 
-    #[derive(sqlx::FromRow)]
-    struct UserTuition {
-        #[allow(non_snake_case)]
-        FirstName: String,
-        #[allow(non_snake_case)]
-        LastName: String,
-        #[allow(non_snake_case)]
-        TuitionCost: rust_decimal::Decimal,
-    }
-
-    // Get the row from the database.
-    let sql_result = sqlx::query_as::<_, UserTuition>
-    (
-        "select FirstName, LastName, TuitionCost
-        from UserTuition
-        where FirstName = ?
-        and LastName = ?"
-    )
-    .bind(&type_safe_params.firstName)
-    .bind(&type_safe_params.lastName)
-    .fetch_one(pool).await;
-
-    let user_tuition = match sql_result {
-        Ok(val) => val,
+    // Get the student's rows from the database, newest term first.
+    let sql_result = queries::RECORDS_BY_NAME.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, models::TuitionRecord>(sql)
+    .bind(campus_id)
+    .bind(&type_safe_params.first_name)
+    .bind(&type_safe_params.last_name)
+    .fetch_all(pool)).await;
+
+    let records = match sql_result {
+        Ok(val) if !val.is_empty() => val,
+        Ok(_) if negotiate::wants_json(&req) => {
+            return Err(AppError::NotFound(format!("No saved tuition calculation was found for {} {}.", type_safe_params.first_name, type_safe_params.last_name)));
+        }
+        Ok(_) => {
+            // Not an error for the student; point them at the calculator with their name filled in.
+            let query = serde_urlencoded::to_string([("first_name", &type_safe_params.first_name), ("last_name", &type_safe_params.last_name)]).unwrap_or_default();
+            let page = NoRecordPage {
+                first_name: type_safe_params.first_name,
+                last_name: type_safe_params.last_name,
+                calculate_url: format!("/?{}", query),
+            };
+            let mut response = render(&state, "no_record", &page).await?;
+            *response.status_mut() = actix_web::http::StatusCode::NOT_FOUND;
+            return Ok(response);
+        }
         Err(why) => {
             // 'why' is a sqlx::Error type.
-            return error(&format!("Error while accessing database: {}", why.to_string())).await;
+            return Err(AppError::from(why));
         }
     };
 
-    // Print the row!
-    let lookup = "
-        <html>
-            <head>
-                <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />
-                <meta charset=utf-8>
-            </head>
-            <body>
-                <section>
-                    <table>
-                        <tr>
-                            <th>Name</th>
-                            <th>Tuition</th>
-                        </tr>
-                        <tr>
-                            <td>".to_owned() + &format!("{} {}", user_tuition.FirstName, user_tuition.LastName) + "</td>
-                            <td>$" + &user_tuition.TuitionCost.to_string() + "</td>
-                        </tr>
-                    </table>
-                </section>
-            </body>
-        </html>
-    ";
-
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(lookup)
-    )
+    // Records are newest first; the chart reads left to right. Rows from before terms were
+    // recorded have nowhere to go on it.
+    let trend: Vec<(&str, Decimal)> = records.iter().rev()
+        .filter_map(|record| record.term.as_deref().map(|term| (term, record.tuition_cost)))
+        .collect();
+    let trend_chart = chart::sparkline_svg(&trend);
+
+    negotiate::respond(&state, &req, "lookup", &LookupPage { records, trend_chart }).await
 }
 
-async fn error(console_msg: &str) -> Result<HttpResponse> {
-    println!("{}", console_msg);
-    
-    return 
-        Ok(HttpResponse::Ok()
-            .content_type("text/html; charset=utf-8")
-            .body(include_str!("htdoc/error.html")));
+async fn calculate(state: web::Data<AppState>, campus: Campus, req: HttpRequest, session: Session, params: form::Submitted<CalculateTuitionFormParams>) -> Result<HttpResponse, AppError> {
+    let form = params.into_inner();
+    let channel = if negotiate::wants_json(&req) { Channel::Api } else { Channel::Web };
+    match estimate(&state, campus.clone(), &req, &session, &form, None, channel).await {
+        // Browsers get the form back as they filled it in, with the problem marked on its field.
+        Err(why @ (AppError::Validation { .. } | AppError::Rejected(_) | AppError::Closed(_))) if !negotiate::wants_json(&req) => form_with_errors(&state, campus, &session, form, None, None, &why).await,
+        result => result,
+    }
 }
 
-async fn calculate(state: web::Data<AppState>, params: web::Form<CalculateTuitionFormParams>) -> Result<HttpResponse> {  
+// The calculator again, filled in as submitted, with the validation error in the summary and on
+// its field, or the notice saying the term isn't open yet.
+async fn form_with_errors(state: &AppState, campus: Campus, session: &Session, form: CalculateTuitionFormParams, scenario_name: Option<String>, counselor: Option<String>, why: &AppError) -> Result<HttpResponse, AppError> {
+    println!("{}", why);
+    let recent_receipts = state.recent_receipts(&campus, session).await?;
+    let fields = state.form_fields(&campus).await?;
+    let term_notice = match why {
+        AppError::Closed(message) => Some(message.clone()),
+        _ => None,
+    };
+    let mut response = render(state, "index", &IndexPage {
+        campus,
+        form: Some(form),
+        scenario_name,
+        captcha: if counselor.is_some() { None } else { state.captcha_widget() },
+        can_email: state.mailer.is_some(),
+        recent_receipts,
+        errors: FormErrors::from_error(why),
+        counselor,
+        term_notice,
+        consent: state.consent.clone(),
+        fields,
+        states: &residency::STATES,
+    }).await?;
+    *response.status_mut() = why.status_code();
+    Ok(response)
+}
 
+// `entered_by` is the counselor calculating on the student's behalf, if any, and `channel` the
+// entry point it came through.
+async fn estimate(state: &web::Data<AppState>, campus: Campus, req: &HttpRequest, session: &Session, params: &CalculateTuitionFormParams, entered_by: Option<&str>, channel: Channel) -> Result<HttpResponse, AppError> {
     let pool = &state.conn;
 
-    #[derive(sqlx::FromRow)]
-    struct TuitionCosts {
-        #[allow(non_snake_case)]
-        CreditsCost: rust_decimal::Decimal,
-        #[allow(non_snake_case)]
-        NonresidencyFee: rust_decimal::Decimal,
-    }
-
-    #[derive(sqlx::FromRow)]
-    struct OrientationFee {
-        #[allow(non_snake_case)]
-        Fee: rust_decimal::Decimal,
+    // Counselors come through the admin pages, which the proxy already guards.
+    if entered_by.is_none() {
+        state.check_captcha(&params.captcha_response, peer_ip(req).as_deref()).await?;
     }
 
     // Check our values.
-    // Build our typesafe parameters.
-    let type_safe_parameters = TypeSafeParameters {
-        first_name: match &params.first_name {
-            Some(val) => val.to_string(),
-            None => {
-                return error("No first name was provided!").await;
+    let validation = telemetry::stage("validation");
+    let mut type_safe_parameters = match TypeSafeParameters::from_form(params) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why);
+        }
+    };
+    state.form_fields(&campus).await?.apply(&mut type_safe_parameters);
+    let reciprocity = resolve_residency(state, &campus, &mut type_safe_parameters).await?;
+
+    let studies = type_safe_parameters.student_studies.as_str();
+    let mut facts = rules::Facts {
+        studies,
+        residency: type_safe_parameters.student_type.as_str(),
+        credits: type_safe_parameters.num_credits,
+        new_student: type_safe_parameters.new_student,
+        orientation: type_safe_parameters.orientation,
+    };
+
+    // Students the campus exempts don't pay for orientation; like dual enrollment, the box is
+    // ignored, and the rules below see it unchecked.
+    let mut orientation_exemption = None;
+    if type_safe_parameters.orientation {
+        let exemptions = match state.orientation_exemptions(&campus).await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(why);
             }
-        },
-        last_name: match &params.last_name {
-            Some(val) => val.to_string(),
-            None => {
-                return error("No last name was provided!").await;
+        };
+        match rules::orientation_exemption(&exemptions, &facts) {
+            Ok(Some(exemption)) => {
+                orientation_exemption = Some(exemption.label.clone());
+                type_safe_parameters.orientation = false;
+                facts.orientation = false;
             }
-        },
-        num_credits: match &params.num_credits {
-            Some(val) => val.parse::<u8>().unwrap(),
-            None => {
-                return error("No credits were provided!").await;
+            Ok(None) => {},
+            Err(why) => {
+                return Err(AppError::Internal(why));
             }
-        },
-        new_student: match &params.new_student {
-            Some(val) => {
-                if val.eq("on") {true} else {false}
-            },
-            None => false
-        },
-        orientation: match &params.orientation {
-            Some(val) => {
-                if val.eq("on") {true} else {false}
-            },
-            None => false
-        },
-        student_type: match &params.student_type {
+        }
+    }
+
+    // Combinations of answers the campus doesn't accept, all reported together.
+    let validation_rules = match state.validation_rules(&campus).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why);
+        }
+    };
+    match rules::violations(&validation_rules, &facts) {
+        Ok(errors) if errors.is_empty() => {},
+        Ok(errors) => {
+            return Err(AppError::Rejected(errors));
+        }
+        Err(why) => {
+            return Err(AppError::Internal(why));
+        }
+    }
+
+    drop(validation);
+
+    // Get the cost per credit, from the database or the fee schedule file.
+    let pricing = telemetry::stage("pricing");
+    let mut default_rates = false;
+    let mut tuition_cost = match state.tuition_costs(&campus, studies, type_safe_parameters.student_type.as_str()).await {
+        Ok(val) => val,
+        // No rate for this student; estimate with the configured defaults, if there are any.
+        Err(why @ AppError::Validation { .. }) => match state.default_tuition_costs(type_safe_parameters.student_type.as_str()) {
             Some(val) => {
-                if val.eq("resident") 
-                    {StudentResidency::In} 
-                else if val.eq("nonresident") 
-                    {StudentResidency::Out} 
-                else 
-                    {StudentResidency::Out}
+                println!("{}; using the default rates.", why);
+                default_rates = true;
+                val
             }
             None => {
-                return error("User must be either a nonresident or resident.").await;
+                return Err(why);
             }
         },
-        student_studies: match &params.student_studies {
-            Some(val) => {
-                if val.eq("undergraduate") 
-                    {StudentStudies::Undergraduate} 
-                else if val.eq("nonresident") 
-                    {StudentStudies::Graduate} 
-                else 
-                    {StudentStudies::Undergraduate}
-            }
-            None => {
-                return error("User must be either a undergraduate or graduate.").await;
-            }
-        }
-    };
-
-    // Get the cost per credit from the database by using prepared statements.
-    let sql_result = sqlx::query_as::<_, TuitionCosts>(
-    "SELECT CreditCosts.CreditsCost, CreditCosts.NonresidencyFee
-    FROM CreditCosts
-    WHERE CreditCosts.Studies = ?
-    AND CreditCosts.Residency = ?")
-        .bind(&params.student_studies)
-        .bind(&params.student_type)
-        .fetch_one(pool).await;
-
-    let tuition_cost = match sql_result {
-        Ok(val) => val,
         Err(why) => {
             // If there is an error, then throw the html webpage error and exit.
-            return error(&format!("Error while accessing database: {}", why.to_string())).await;
+            return Err(why);
         }
     };
+    tuition_cost.nonresidency_fee = pricing::reciprocity_fee(tuition_cost.nonresidency_fee, reciprocity.as_ref());
     // Also get the orientation fee, if the user checked it.
-    let mut orientation_fee = OrientationFee { Fee: Decimal::new(000, 2) };
+    let mut orientation_fee = Money::zero();
     if type_safe_parameters.orientation {
-        // Get the cost per credit from the database by using prepared statements.
-        let sql_result_orientation_fee = sqlx::query_as::<_, OrientationFee>(
-        "SELECT Fee
-        FROM orientation_fee")
-            .fetch_one(pool).await;
-        orientation_fee = match sql_result_orientation_fee {
+        orientation_fee = match state.orientation_fee(&campus).await {
             Ok(val) => val,
             Err(why) => {
                 // If there is an error, then throw the html webpage error and exit.
-                return error(&format!("Error while accessing database: {}", why.to_string())).await;
+                return Err(why);
             }
         };
     }
 
-    // Multiplty the cost per credit by the credits
-    let total = tuition_cost.CreditsCost * Decimal::from(type_safe_parameters.num_credits) + tuition_cost.NonresidencyFee + orientation_fee.Fee;
-    println!("The total tuition cost is ${}", total);
-
-    // Create the HTML table of the calculation that took place
-    let table = " 
-    <!DOCTYPE html>
-    <html>
-        <head>
-            <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />
-        </head>
-        <body>
-            <section>
-                <h1>Tuition Results</h1>
-                <p>Name: ".to_owned() + &format!("{} {}", type_safe_parameters.first_name, type_safe_parameters.last_name) + "</p>
-                <table>
-                    <tr>
-                        <th>Residency</th>
-                        <th>Studies</th>
-                        <th>New Student Status</th>
-                        <th>Orientation Fee</th>
-                        <th>Non-Residency Fee</th>
-                        <th>Number of Credits</th>
-                        <th>Costs per Credit</th>
-                    </tr>
-                    <tr>
-                        <td>" + match type_safe_parameters.student_type { StudentResidency::In => "Resident", StudentResidency::Out => "Non-Resident" } + "</td>
-                        <td>" + match type_safe_parameters.student_studies { StudentStudies::Undergraduate => "Undergraduate", StudentStudies::Graduate => "Graduate" } + "</td>
-                        <td>" + match type_safe_parameters.new_student { true => "Yes", false => "No" } + "</td>
-                        <td>$" + &orientation_fee.Fee.to_string() + "</td>
-                        <td>$" + &tuition_cost.NonresidencyFee.to_string() + "</td>
-                        <td>" + &type_safe_parameters.num_credits.to_string() + "</td>
-                        <td>$" + &tuition_cost.CreditsCost.to_string() + "</td>
-                    </tr>
-                </table>
-                <p><b>Total: </b> $" + &total.to_string() + "</p>
-            </section>
-        </body>
-    </html>";
-
-    #[derive(sqlx::FromRow)]
-    struct User { FirstName: String, LastName: String, }
-
-    // See if it already exists. If it is, update it.
-    let user_exists = match sqlx::query_as::<_, User>(
-        "select FirstName, LastName
-        from UserTuition
-        where FirstName = ?
-        and LastName = ?"
-    )
-    .bind(&type_safe_parameters.first_name)
-    .bind(&type_safe_parameters.last_name)
-    .fetch_one(pool)
-    .await {
-        Ok(_) => {true},
-        Err(_) => {false}
+    // The estimate is for the term the student enrolls in; without a date, the current one.
+    let term = receipts::term_for(type_safe_parameters.enrollment_date.unwrap_or_else(|| chrono::Local::now().date_naive()));
+
+    // Outside the term's window only counselors can calculate.
+    if entered_by.is_none() {
+        if let Some(notice) = state.term_notice(&campus, &term).await? {
+            return Err(AppError::Closed(notice));
+        }
+    }
+
+    // Students enrolling partway through the term may only pay part of the tuition.
+    let proration = match type_safe_parameters.enrollment_date {
+        Some(date) => match state.proration_rules(&campus, &term).await {
+            Ok(rules) => pricing::proration(&rules, date),
+            Err(why) => {
+                return Err(why);
+            }
+        },
+        None => None,
+    };
+    let proration_adjustment = match &proration {
+        Some(val) => pricing::proration_adjustment(type_safe_parameters.num_credits, &tuition_cost, val.tuition_percent),
+        None => Money::zero(),
+    };
+
+    let total = pricing::tuition_total(type_safe_parameters.num_credits, type_safe_parameters.orientation, &tuition_cost, orientation_fee) + proration_adjustment;
+    println!("The total tuition cost is {}", total.format());
+
+    // Get the estimated indirect costs (books, supplies, transportation) for the study level.
+    let indirect_costs = match state.indirect_costs(&campus, studies).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why);
+        }
     };
+    let additional_total: Money = indirect_costs.iter().map(|cost| cost.amount).sum();
 
-    // Add the result to our user table.
-    if !user_exists {
-        match sqlx::query(
-            "insert into UserTuition 
-            (FirstName, LastName, TuitionCost) 
-            VALUES 
-            (?, ?, ?)")
-        .bind(type_safe_parameters.first_name)
-        .bind(type_safe_parameters.last_name)
-        .bind(total)
-        .execute(pool)
-        .await {
-            Ok(_val) => {},
+    // Lab and course fees for the courses the student listed, looked up in the campus catalog.
+    let mut course_fees = Vec::new();
+    if !type_safe_parameters.course_codes.is_empty() {
+        let catalog = match state.course_fees(&campus).await {
+            Ok(val) => val,
             Err(why) => {
-                return error(&format!("Error while inserting to the database: {}", why.to_string())).await;
+                return Err(why);
             }
         };
+        for code in &type_safe_parameters.course_codes {
+            match catalog.iter().find(|fee| fees::course_code_key(&fee.course_code) == fees::course_code_key(code)) {
+                Some(fee) => course_fees.push(fee.clone()),
+                None => {
+                    return Err(AppError::validation("course_codes", &format!("No course fee is listed for \"{}\". The course fee list shows which courses have one.", code)));
+                }
+            }
+        }
+    }
+    let course_fee_total: Money = course_fees.iter().map(|fee| fee.fee).sum();
+
+    let health_insurance_fee = match state.health_insurance_fee(&campus).await {
+        Ok(val) => pricing::health_insurance_charge(type_safe_parameters.insurance_waived, val),
+        Err(why) => {
+            return Err(why);
+        }
+    };
+
+    // International students also pay their own flat fees, like SEVIS.
+    let international_fees = match type_safe_parameters.student_type {
+        StudentResidency::International => match state.international_fees(&campus).await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(why);
+            }
+        },
+        _ => Vec::new(),
+    };
+    let international_fee_total: Money = international_fees.iter().map(|fee| fee.amount).sum();
+
+    // One-off charges the admins set up for this term, for the students their rules pick out.
+    let line_items = match state.line_items(&campus, &term).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why);
+        }
+    };
+    let custom_fees = match rules::applicable(&line_items, &facts) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::Internal(why));
+        }
+    };
+    let custom_fee_total: Money = custom_fees.iter().map(|fee| fee.amount).sum();
+    // Kept on the receipt so it can show each item under its category later.
+    let custom_items = if custom_fees.is_empty() {
+        None
     } else {
-        // Or, update the result.
-        match sqlx::query(
-            "update UserTuition 
-            set TuitionCost = ?
-            where FirstName = ?
-            and LastName = ?")
-        .bind(total)
-        .bind(type_safe_parameters.first_name)
-        .bind(type_safe_parameters.last_name)
-        .execute(pool)
-        .await {
-            Ok(_val) => {},
+        match serde_json::to_string(&custom_fees) {
+            Ok(val) => Some(val),
             Err(why) => {
-                return error(&format!("Error while updating the database: {}", why.to_string())).await;
+                return Err(AppError::Internal(format!("Couldn't save the custom line items: {}", why)));
+            }
+        }
+    };
+    let total = total + course_fee_total + health_insurance_fee + international_fee_total + custom_fee_total;
+
+    // How long the programs at this level would take at this load, and what they'd cost.
+    let programs = match state.programs(&campus, studies).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why);
+        }
+    };
+    let projections = projection::project(&programs, &projection::TermCharges {
+        credits: type_safe_parameters.num_credits,
+        credits_cost: tuition_cost.credits_cost,
+        term_fees: tuition_cost.nonresidency_fee + course_fee_total + health_insurance_fee + international_fee_total + custom_fee_total,
+        one_time_fees: orientation_fee,
+    });
+
+    drop(pricing);
+
+    // Where consent is asked for and wasn't given, the estimate is shown but nothing is kept: no
+    // student, record or receipt, and no code sent to confirm an email address.
+    let estimate_only = state.consent.is_some() && !params.consent;
+    let (receipt_code, saved_later, email_verification) = if estimate_only {
+        (None, false, None)
+    } else {
+        // Keep the rates this total was priced with, under a code the student can come back to.
+        let receipt_code = receipts::new_code();
+        let metadata = state.request_metadata.capture(req, peer_ip(req).as_deref());
+        let pending = replay::PendingEstimate {
+            campus_id: campus.id,
+            first_name: type_safe_parameters.first_name.clone(),
+            last_name: type_safe_parameters.last_name.clone(),
+            term: term.clone(),
+            total,
+            num_credits: type_safe_parameters.num_credits,
+            orientation: type_safe_parameters.orientation,
+            orientation_answer: type_safe_parameters.orientation_answer,
+            student_type: type_safe_parameters.student_type.as_str().to_string(),
+            student_studies: type_safe_parameters.student_studies.as_str().to_string(),
+            insurance_waived: type_safe_parameters.insurance_waived,
+            receipt_code: receipt_code.clone(),
+            credits_cost: tuition_cost.credits_cost,
+            nonresidency_fee: tuition_cost.nonresidency_fee,
+            orientation_fee,
+            enrollment_date: type_safe_parameters.enrollment_date,
+            tuition_percent: proration.as_ref().map(|val| val.tuition_percent),
+            course_codes: course_codes_column(&type_safe_parameters.course_codes),
+            course_fee_total,
+            health_insurance_fee,
+            international_fee_total,
+            custom_fees: if custom_fees.is_empty() { None } else { Some(custom_fee_total) },
+            custom_items,
+            client_ip_hash: metadata.client_ip_hash,
+            user_agent: metadata.user_agent,
+            referrer: metadata.referrer,
+            entered_by: entered_by.map(str::to_string),
+            channel: Some(channel.as_str().to_string()),
+            consented: params.consent,
+            calculated_at: Utc::now(),
+        };
+
+        // Add the student if they're new, and the result for this term, or update it if they already
+        // calculated it this term. If the database is only briefly out, the student still gets their
+        // result and the save is retried until it goes through.
+        let (student_id, saved_later) = match replay::save(pool, &pending).await {
+            Ok(val) => (Some(val), false),
+            Err(why) if replay::transient(&why) && state.replay.push(pending) => {
+                println!("Queued estimate {} to save once the database is back: {}", receipt_code, why);
+                (None, true)
+            }
+            Err(why) => {
+                return Err(AppError::from(why));
             }
         };
+        recent::remember(session, &receipt_code);
+
+        // An address given with the estimate gets a code to confirm it before it's kept. A queued
+        // save has no student yet, and a code that can't be sent isn't worth failing the estimate.
+        let email_verification = match (&type_safe_parameters.email, &state.mailer, student_id) {
+            (Some(email), Some(mailer), Some(student_id)) => match verification::start(state, mailer, &campus, student_id, email).await {
+                Ok(val) => Some(val),
+                Err(why) => {
+                    println!("Couldn't send a verification code for estimate {}: {}", receipt_code, why);
+                    None
+                }
+            },
+            _ => None,
+        };
+        (Some(receipt_code), saved_later, email_verification)
+    };
+
+    // The same charges as above, each under its category.
+    let mut items = vec![pricing::BreakdownItem {
+        label: format!("Tuition, {} credit(s)", type_safe_parameters.num_credits),
+        amount: tuition_cost.credits_cost * type_safe_parameters.num_credits,
+        category: pricing::FeeCategory::Tuition,
+    }];
+    if let Some(val) = &proration {
+        items.push(pricing::BreakdownItem { label: format!("Proration, {}% charged", val.tuition_percent), amount: proration_adjustment, category: pricing::FeeCategory::Tuition });
     }
+    if !tuition_cost.nonresidency_fee.is_zero() {
+        items.push(pricing::BreakdownItem { label: "Non-residency fee".to_string(), amount: tuition_cost.nonresidency_fee, category: pricing::FeeCategory::MandatoryFees });
+    }
+    for fee in &course_fees {
+        items.push(pricing::BreakdownItem { label: format!("{}: {}", fee.course_code, fee.label), amount: fee.fee, category: pricing::FeeCategory::MandatoryFees });
+    }
+    if !health_insurance_fee.is_zero() {
+        items.push(pricing::BreakdownItem { label: "Health insurance".to_string(), amount: health_insurance_fee, category: pricing::FeeCategory::MandatoryFees });
+    }
+    for fee in &international_fees {
+        items.push(pricing::BreakdownItem { label: fee.label.clone(), amount: fee.amount, category: pricing::FeeCategory::MandatoryFees });
+    }
+    if !orientation_fee.is_zero() {
+        items.push(pricing::BreakdownItem { label: "Orientation fee".to_string(), amount: orientation_fee, category: pricing::FeeCategory::OptionalFees });
+    }
+    items.extend(custom_fees.iter().cloned());
+    let categories = pricing::categorize(items);
+
+    // Where the money goes: each category that adds to the total, then the estimates when
+    // they're included. Aid isn't a slice of what's paid.
+    let mut segments: Vec<(&str, Decimal)> = categories.iter()
+        .filter(|category| category.subtotal.amount() > Decimal::ZERO)
+        .map(|category| (category.label, category.subtotal.amount()))
+        .collect();
+    if type_safe_parameters.include_additional_costs {
+        segments.push(("Estimated additional costs", additional_total.amount()));
+    }
+    let breakdown_chart = chart::breakdown_svg(&segments);
+
+    // The grand total only includes the estimates when the student asked for them.
+    let page = ResultPage {
+        campus,
+        first_name: type_safe_parameters.first_name,
+        last_name: type_safe_parameters.last_name,
+        residency: type_safe_parameters.student_type.label(),
+        studies: type_safe_parameters.student_studies.label(),
+        new_student: type_safe_parameters.new_student,
+        orientation_fee,
+        orientation_exemption,
+        reciprocity: reciprocity.map(|agreement| agreement.label),
+        nonresidency_fee: tuition_cost.nonresidency_fee,
+        num_credits: type_safe_parameters.num_credits,
+        credits_cost: tuition_cost.credits_cost,
+        total,
+        enrollment_date: type_safe_parameters.enrollment_date,
+        course_fees,
+        course_fee_total,
+        insurance_waived: type_safe_parameters.insurance_waived,
+        health_insurance_fee,
+        international_fees,
+        custom_fees,
+        categories,
+        proration,
+        proration_adjustment,
+        breakdown_chart,
+        indirect_costs,
+        additional_total,
+        grand_total: if type_safe_parameters.include_additional_costs { Some(total + additional_total) } else { None },
+        projections,
+        receipt_code,
+        estimate_only,
+        saved_later,
+        default_rates,
+        verify_email: email_verification.as_ref().and(type_safe_parameters.email),
+        email_verification,
+    };
 
 
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(table))
+    negotiate::respond(state, req, "result", &page).await
 }
 
-async fn index() -> Result<HttpResponse> {
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(include_str!("htdoc/index.html")))
+// `/?first_name=..&last_name=..` fills in the name, e.g. from the lookup page.
+async fn index(state: web::Data<AppState>, campus: Campus, session: Session, params: web::Query<LookupFormParams>) -> Result<HttpResponse, AppError> {
+    let form = match (&params.first_name, &params.last_name) {
+        (None, None) => None,
+        _ => Some(CalculateTuitionFormParams {
+            first_name: params.first_name.clone(),
+            last_name: params.last_name.clone(),
+            num_credits: None,
+            new_student: false,
+            orientation: None,
+            student_type: None,
+            home_state: None,
+            student_studies: None,
+            include_additional_costs: false,
+            enrollment_date: None,
+            course_codes: None,
+            insurance_waiver: false,
+            email: None,
+            consent: false,
+            captcha_response: None,
+        }),
+    };
+    // Most visitors get the same page; serve it without touching the database.
+    let shared = form.is_none() && !recent::has_recent(&session);
+    if shared {
+        if let Some(body) = state.index_cache.get(campus.id).await {
+            return Ok(html(body));
+        }
+    }
+
+    let recent_receipts = state.recent_receipts(&campus, &session).await?;
+    let term_notice = state.current_term_notice(&campus).await?;
+    let fields = state.form_fields(&campus).await?;
+    let campus_id = campus.id;
+    let body = render_string(&state, "index", &IndexPage { campus, form, scenario_name: None, captcha: state.captcha_widget(), can_email: state.mailer.is_some(), recent_receipts, errors: None, counselor: None, term_notice, consent: state.consent.clone(), fields, states: &residency::STATES })?;
+    if shared {
+        state.index_cache.put(campus_id, &body).await;
+    }
+    Ok(html(body))
+}
+
+async fn history(state: web::Data<AppState>, campus: Campus, session: Session) -> Result<HttpResponse, AppError> {
+    let history = state.history(&campus, &session).await?;
+    render(&state, "history", &history).await
 }
 
-async fn style() -> Result<HttpResponse> {
-    Ok(HttpResponse::Ok()
-        .content_type("text/css")
-        .body(include_str!("htdoc/style.css")))
+// The lab and course fee catalog, so students know which course codes to enter.
+async fn course_fees(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let mut departments: Vec<Department> = Vec::new();
+    for fee in state.course_fees(&campus).await? {
+        match departments.last_mut() {
+            Some(department) if department.name == fee.department => department.courses.push(fee),
+            _ => departments.push(Department { name: fee.department.clone(), courses: vec![fee] }),
+        }
+    }
+    render(&state, "course_fees", &CourseFeesPage { campus, departments }).await
 }
 
 fn app_config(config: &mut web::ServiceConfig) {
     
+    // Public and read-only, so it sits ahead of the keyed /api scope.
+    config.service(web::resource("/api/v1/rates")
+        .wrap(middleware::from_fn(rate_limit::limit_rates))
+        .wrap(middleware::from_fn(api::rates_cors))
+        .route(web::get().to(api::rates)));
+    config.service(web::resource("/api/v1/form-schema").route(web::get().to(api::form_schema)));
+    // Public too, and sent as the student types, so it shares the rates allowance.
+    config.service(web::resource("/api/v1/preview")
+        .wrap(middleware::from_fn(rate_limit::limit_rates))
+        .route(web::post().to(api::preview)));
+    // Public fields and keyed ones side by side; the key is checked per field.
+    config.service(web::resource("/api/graphql")
+        .wrap(middleware::from_fn(rate_limit::limit_rates))
+        .route(web::post().to(graphql::graphql)));
+    // Machine clients; every route in here needs an API key.
+    config.service(
+        web::scope("/api")
+            .wrap(middleware::from_fn(api::require_api_key))
+            .service(web::resource("/v1/lookup").route(web::get().to(api::lookup))),
+    );
+    config.service(
+        web::scope("/admin")
+            .wrap(middleware::from_fn(staff_auth::require_staff))
+            .service(web::resource("").route(web::get().to(admin::index)))
+            .service(web::resource("/sign-in/callback").route(web::get().to(staff_auth::callback)))
+            .service(web::resource("/sign-out").route(web::post().to(staff_auth::sign_out)))
+            .service(web::resource("/calculate")
+                .route(web::get().to(admin::calculate_form))
+                .route(web::post().to(admin::calculate)))
+            .service(web::resource("/api-keys")
+                .route(web::get().to(admin::api_keys))
+                .route(web::post().to(admin::issue_api_key)))
+            .service(web::resource("/api-keys/{id}/revoke").route(web::post().to(admin::revoke_api_key)))
+            .service(web::resource("/simulate")
+                .route(web::get().to(admin::simulate_form))
+                .route(web::post().to(admin::simulate)))
+            .service(web::resource("/maintenance")
+                .route(web::get().to(admin::maintenance_form))
+                .route(web::post().to(admin::update_maintenance)))
+            .service(web::resource("/records").route(web::get().to(admin::records)))
+            .service(web::resource("/records/{id}")
+                .route(web::get().to(admin::record))
+                .route(web::post().to(admin::edit_record)))
+            .service(web::resource("/records/{id}/delete").route(web::post().to(admin::delete_record)))
+            .service(web::resource("/receipts").route(web::get().to(adjustments::receipt)))
+            .service(web::resource("/receipts/{code}/adjustments").route(web::post().to(adjustments::adjust)))
+            .service(web::resource("/rate-increase")
+                .route(web::get().to(admin::rate_increase))
+                .route(web::post().to(admin::set_rate_increase)))
+            .service(web::resource("/refunds")
+                .route(web::get().to(admin::refunds))
+                .route(web::post().to(admin::add_refund_rule)))
+            .service(web::resource("/refunds/{id}/delete").route(web::post().to(admin::delete_refund_rule)))
+            .service(web::resource("/terms")
+                .route(web::get().to(admin::terms))
+                .route(web::post().to(admin::set_term)))
+            .service(web::resource("/line-items")
+                .route(web::get().to(admin::line_items))
+                .route(web::post().to(admin::add_line_item)))
+            .service(web::resource("/line-items/{id}/delete").route(web::post().to(admin::delete_line_item)))
+            .service(web::resource("/validation-rules")
+                .route(web::get().to(admin::validation_rules))
+                .route(web::post().to(admin::add_validation_rule)))
+            .service(web::resource("/validation-rules/{id}/delete").route(web::post().to(admin::delete_validation_rule)))
+            .service(web::resource("/orientation-exemptions")
+                .route(web::get().to(admin::orientation_exemptions))
+                .route(web::post().to(admin::add_orientation_exemption)))
+            .service(web::resource("/orientation-exemptions/{id}/delete").route(web::post().to(admin::delete_orientation_exemption)))
+            .service(web::resource("/reciprocity")
+                .route(web::get().to(admin::reciprocity))
+                .route(web::post().to(admin::set_reciprocity)))
+            .service(web::resource("/reciprocity/{id}/delete").route(web::post().to(admin::delete_reciprocity)))
+            .service(web::resource("/form-fields")
+                .route(web::get().to(form_fields::form_fields))
+                .route(web::post().to(form_fields::set_form_fields)))
+            .service(web::resource("/slow-queries").route(web::get().to(slow_queries::slow_queries)))
+            .service(web::resource("/slow-queries/clear").route(web::post().to(slow_queries::clear)))
+            .service(web::resource("/bulk-delete")
+                .route(web::get().to(bulk_delete::bulk_delete_form))
+                .route(web::post().to(bulk_delete::bulk_delete)))
+            .service(web::resource("/explain").route(web::get().to(explain::explain)))
+            .service(web::resource("/export/rates").route(web::get().to(export::rates)))
+            .service(web::resource("/export/records").route(web::get().to(export::records)))
+            .service(web::resource("/students/duplicates").route(web::get().to(students::duplicates)))
+            .service(web::resource("/students/merge").route(web::post().to(students::merge_students)))
+            .service(web::resource("/students/{public_id}/export").route(web::get().to(export::student)))
+            .service(web::resource("/students/{public_id}/delete").route(web::post().to(students::delete_student))),
+    );
+    config.service(web::resource("/healthz").route(web::get().to(maintenance::healthz)));
+    config.service(web::resource("/readyz").route(web::get().to(failover::readyz)));
+    config.service(web::resource("/metrics").route(web::get().to(metrics::metrics)));
+    // Student-facing pages; these go dark while maintenance mode is on.
     config.service(
         web::scope("")
-            .route("/style.css", web::get().to(style))
+            .wrap(middleware::from_fn(screening::screen))
+            .wrap(middleware::from_fn(maintenance::check))
+            .route("/style.css", web::get().to(assets::stylesheet))
+            .route("/assets/{hashed}", web::get().to(assets::serve))
             .service(web::resource("/").route(web::get().to(index)))
             .service(web::resource("/lookup").route(web::post().to(lookup)))
-            .service(web::resource("/calculate").route(web::post().to(calculate))),
+            .service(web::resource("/calculate").route(web::post().to(calculate)))
+            .service(web::resource("/wizard").route(web::get().to(wizard::start)))
+            .service(web::resource("/wizard/{step}")
+                .route(web::get().to(wizard::step))
+                .route(web::post().to(wizard::submit)))
+            .service(web::resource("/history").route(web::get().to(history)))
+            .service(web::resource("/course-fees").route(web::get().to(course_fees)))
+            .service(web::resource("/receipt/{code}").route(web::get().to(receipts::receipt)))
+            .service(web::resource("/receipt/{code}/print").route(web::get().to(receipts::print_receipt)))
+            .service(web::resource("/receipt/{code}/share").route(web::post().to(share::share)))
+            .service(web::resource("/receipt/{code}/email").route(web::post().to(receipts::email_receipt)))
+            .service(web::resource("/shared/{token}").route(web::get().to(share::shared)))
+            .service(web::resource("/verify-email/{id}")
+                .route(web::get().to(verification::verify_form))
+                .route(web::post().to(verification::confirm)))
+            .service(web::resource("/refund")
+                .route(web::get().to(refunds::refund_form))
+                .route(web::post().to(refunds::estimate_refund)))
+            .service(web::resource("/projection")
+                .route(web::get().to(projection::projection_form))
+                .route(web::post().to(projection::project_years)))
+            .service(web::resource("/sign-in")
+                .route(web::get().to(student_auth::sign_in_form))
+                .route(web::post().to(student_auth::send_code)))
+            .service(web::resource("/sign-in/code").route(web::post().to(student_auth::confirm_code)))
+            .service(web::resource("/sign-out").route(web::post().to(student_auth::sign_out)))
+            .service(web::resource("/scenarios")
+                .route(web::get().to(scenarios::list))
+                .route(web::post().to(scenarios::save)))
+            .service(web::resource("/scenarios/{id}").route(web::get().to(scenarios::load)))
+            .service(web::resource("/scenarios/{id}/delete").route(web::post().to(scenarios::delete))),
     );
 }
 
@@ -383,32 +1194,304 @@ async fn main() -> Result<(), sqlx::Error> {
 
     // Get our environment variables.
     dotenv().ok();
-    let db_string = env::var("DATABASE_URL").expect("Database connection URL not found in dotenv file.");
-    let host = env::var("HOST").expect("Host URL not found in dotenv file.");
-    let port = env::var("PORT").expect("Port number not found in dotenv file.");
-    let server_url = format!("{}:{}", host, port);
+    let config = match AppConfig::from_env() {
+        Ok((config, report)) => {
+            print!("{}", report);
+            config
+        }
+        Err(report) => {
+            print!("{}", report);
+            println!("Fix the dotenv file and start the server again.");
+            std::process::exit(1);
+        }
+    };
     
+    if let Some(telemetry) = &config.telemetry {
+        match telemetry::init(telemetry) {
+            Ok(()) => println!("Sending traces to {} as {}.", telemetry.endpoint, telemetry.service_name),
+            Err(why) => println!("{}; requests won't be traced.", why),
+        }
+    }
+
     // Start the DB connection with sqlx.
-    let pool = MySqlPool::connect(&db_string).await?;
-    println!("Connected to the database at {}.", db_string);
+    let min_connections = config.pool.min_connections.min(config.pool.max_connections);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(config.pool.max_connections)
+        .min_connections(min_connections)
+        .acquire_timeout(config.pool.acquire_timeout)
+        .connect_with(config.database_url.parse::<MySqlConnectOptions>()?.statement_cache_capacity(config.pool.statement_cache_capacity)).await?;
+    println!("Connected to the database at {}.", config.database_url);
+    let read_pool = match &config.read_database_url {
+        Some(url) => {
+            let read_pool = MySqlPoolOptions::new()
+                .max_connections(config.pool.max_connections)
+                .acquire_timeout(config.pool.acquire_timeout)
+                .connect_with(url.parse::<MySqlConnectOptions>()?.statement_cache_capacity(config.pool.statement_cache_capacity)).await?;
+            println!("Connected to the read replica at {}.", url);
+            read_pool
+        }
+        None => pool.clone(),
+    };
+    // Not connected until it's needed, so a standby that's down doesn't keep the app from starting.
+    let failover = match &config.secondary_database_url {
+        Some(url) => {
+            let secondary = MySqlPoolOptions::new()
+                .max_connections(config.pool.max_connections)
+                .acquire_timeout(config.pool.acquire_timeout)
+                .connect_lazy_with(url.parse::<MySqlConnectOptions>()?.statement_cache_capacity(config.pool.statement_cache_capacity));
+            println!("Reads will fail over to {} while the primary database is down.", url);
+            Some(Arc::new(failover::Failover::new(secondary, config.failover_probe_interval)))
+        }
+        None => None,
+    };
+
+    // Show what migrating would do and stop, instead of doing it.
+    if std::env::args().any(|arg| arg == "--plan-migrations") {
+        migrations::plan(&pool).await?;
+        return Ok(());
+    }
+
+    // Bring the schema up to date.
+    migrations::MIGRATOR.run(&pool).await?;
+    schema::check(&pool, "database").await?;
+    if config.read_database_url.is_some() {
+        schema::check(&read_pool, "read replica").await?;
+    }
+
+    startup::prewarm(&pool, min_connections).await?;
+
+    let campuses = campus::load_campuses(&pool).await?;
+
+    // Fill the database with sample data and stop, instead of serving.
+    if std::env::args().any(|arg| arg == "--seed-demo") {
+        seed::demo(&pool, &campuses).await?;
+        return Ok(());
+    }
+    println!("Serving {} campus(es).", campuses.len());
+
+    // Schools without database admin access can keep their rates in a file instead.
+    let fee_schedule = match &config.fee_schedule_file {
+        Some(path) => {
+            // Checked with the rest of the configuration, but it could have changed since.
+            let schedule = match fees::watch(path.into()) {
+                Ok(val) => val,
+                Err(why) => {
+                    println!("{}", why);
+                    std::process::exit(1);
+                }
+            };
+            println!("Using the fee schedule from {}.", path);
+            Some(schedule)
+        }
+        None => None,
+    };
+    // The outbound HTTP, SMTP, Redis and TLS settings were checked with the rest of the configuration.
+    let http: Arc<dyn http_client::HttpClient> = match http_client::ReqwestClient::new(&config.http_client) {
+        Ok(val) => Arc::new(val),
+        Err(why) => {
+            println!("{}", why);
+            std::process::exit(1);
+        }
+    };
+    let staff_auth = config.oidc.as_ref().map(|oidc| {
+        println!("Staff sign in to /admin through {}.", oidc.issuer);
+        Arc::new(staff_auth::OidcProvider::new(oidc, http.clone())) as Arc<dyn staff_auth::AuthProvider>
+    });
+    let basic_auth = if config.admin_basic_auth.is_empty() {
+        None
+    } else {
+        println!("Staff sign in to /admin with HTTP Basic auth ({} account(s)).", config.admin_basic_auth.len());
+        Some(Arc::new(staff_auth::BasicAuth::new(&config.admin_basic_auth)))
+    };
+    let captcha = config.captcha.as_ref().map(captcha::Captcha::new);
+    let mailer = match config.smtp.as_ref().map(mailer::Mailer::new).transpose() {
+        Ok(val) => val,
+        Err(why) => {
+            println!("{}", why);
+            std::process::exit(1);
+        }
+    };
+
+    // Replicas share sessions and the calculator page through Redis; the config check already
+    // refused REDIS_URL in a build without it.
+    #[cfg(feature = "redis")]
+    let (session_backend, index_cache) = match &config.redis_url {
+        Some(url) => {
+            let redis = match redis_store::Redis::new(url, std::time::Duration::from_secs(2)) {
+                Ok(val) => Arc::new(val),
+                Err(why) => {
+                    println!("{}", why);
+                    std::process::exit(1);
+                }
+            };
+            if let Err(why) = redis.ping().await {
+                println!("{}", why);
+                std::process::exit(1);
+            }
+            println!("Sharing sessions and the calculator page cache through Redis at {}.", redis.address());
+            (recent::SessionBackend { redis: Some(redis.clone()) }, page_cache::PageCache::shared(config.index_cache_ttl, redis))
+        }
+        None => (recent::SessionBackend::default(), page_cache::PageCache::new(config.index_cache_ttl)),
+    };
+    #[cfg(not(feature = "redis"))]
+    let (session_backend, index_cache) = (recent::SessionBackend::default(), page_cache::PageCache::new(config.index_cache_ttl));
+
+    let maintenance = maintenance::load(&pool).await?;
+    if maintenance.enabled {
+        println!("Maintenance mode is on; public pages will show the unavailable page.");
+    }
 
     // Add the connection to our app state so it is shared.
     let state = AppState {
         app_name: String::from("Tuition Calculator"),
         conn: pool,
+        replica_conn: read_pool,
+        failover,
+        templates: Arc::new(templates()),
+        rate_limiter: Arc::new(rate_limit::RateLimiter::default()),
+        fee_schedule,
+        campuses: Arc::new(campuses),
+        mailer,
+        maintenance: Arc::new(std::sync::RwLock::new(maintenance)),
+        captcha,
+        http,
+        branding: config.branding.clone(),
+        letterhead: config.letterhead.clone(),
+        share_signer: share::ShareSigner::new(config.share_key.as_deref(), config.share_link_days),
+        request_metadata: request_meta::MetadataPolicy::new(&config.request_metadata),
+        consent: config.consent.clone(),
+        default_rates: config.default_rates.clone(),
+        admin_user_header: config.admin_user_header.clone(),
+        staff_auth,
+        basic_auth,
+        trusted_proxies: config.trusted_proxies.clone(),
+        rates_cors_origins: config.rates_cors_origins.clone(),
+        rates_limiter: Arc::new(rate_limit::RateLimiter::default()),
+        rates_requests_per_minute: config.rates_requests_per_minute,
+        sign_in_limiter: Arc::new(rate_limit::RateLimiter::default()),
+        sign_in_email_limiter: Arc::new(rate_limit::RateLimiter::default()),
+        screening: config.screening,
+        index_cache: Arc::new(index_cache),
+        preview_cache: Arc::new(api::PreviewCache::new(config.preview_cache_ttl)),
+        replay: Arc::new(replay::ReplayQueue::new(config.replay.clone())),
     };
 
-    println!("Server started at {}. Application name: \"{}\"", server_url, state.app_name);
-    webbrowser::open(&format!("http://{}", server_url)).unwrap();
+    let session_key = match &config.session_key {
+        Some(val) => Key::from(val),
+        None => {
+            println!("SESSION_KEY is not set; recent estimates will be forgotten when the server restarts.");
+            Key::generate()
+        }
+    };
+
+    if let (Some(mailer), Some(summary)) = (&state.mailer, &config.summary) {
+        println!("Emailing a nightly summary to {} at {}:00.", summary.recipients.join(", "), summary.hour);
+        summary::spawn(state.clone(), mailer.clone(), summary.clone());
+    }
+    if let Some(retention) = &config.retention {
+        println!(
+            "Calculations older than {} day(s) will be {}d nightly at {}:00{}.",
+            retention.days, retention.action, retention.hour, if retention.dry_run { " (dry run: only counted)" } else { "" },
+        );
+        retention::spawn(state.clone(), retention.clone());
+    }
+    replay::spawn(state.clone());
+    failover::spawn(state.clone());
+    logs::spawn_flush();
+    if let Some(threshold) = config.slow_query_threshold {
+        println!("Recording queries that take {} ms or more.", threshold.as_millis());
+        slow_queries::spawn(state.clone(), threshold);
+    }
+
+    // A campus that can't price the most common estimate would fail its students; better to find
+    // out now than from the first one who tries.
+    if config.self_test {
+        let problems = startup::self_test(&state).await;
+        if !problems.is_empty() {
+            println!("The startup self-test failed for {} campus(es):", problems.len());
+            for problem in &problems {
+                println!("  - {}", problem);
+            }
+            println!("Set up the missing rates, or set STARTUP_SELF_TEST=off, and start the server again.");
+            std::process::exit(1);
+        }
+    }
+
+    let scheme = if config.server.tls.is_some() { "https" } else { "http" };
+    println!("Application name: \"{}\"", state.app_name);
+    let schema = graphql::schema();
     // Execute our http server application.
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(state.clone()))
+            .app_data(web::Data::new(schema.clone()))
+            .wrap(recent::session_middleware(session_key.clone(), session_backend.clone()))
+            .wrap(middleware::from_fn(error::request_context))
+            .wrap(middleware::from_fn(telemetry::trace))
             .configure(app_config)
-    })
-    .bind(server_url.clone())?
-    .run()
-    .await.expect("Error creating HTTP server.");
+    });
+
+    // Tuning for the enrollment-day traffic spike.
+    let tuning = &config.server;
+    if let Some(workers) = tuning.workers {
+        server = server.workers(workers);
+    }
+    server = match tuning.keep_alive {
+        Some(duration) => server.keep_alive(duration),
+        None => server.keep_alive(KeepAlive::Disabled),
+    };
+    if let Some(timeout) = tuning.client_request_timeout {
+        server = server.client_request_timeout(timeout);
+    }
+    if let Some(timeout) = tuning.client_disconnect_timeout {
+        server = server.client_disconnect_timeout(timeout);
+    }
+    if let Some(max_connections) = tuning.max_connections {
+        server = server.max_connections(max_connections);
+    }
+    if let Some(backlog) = tuning.backlog {
+        server = server.backlog(backlog);
+    }
+
+    // TLS covers the TCP addresses; a Unix socket sits behind a proxy that terminates TLS itself.
+    for address in &config.bind_addresses {
+        match &tuning.tls {
+            Some(tls) => {
+                let builder = match tls.acceptor() {
+                    Ok(val) => val,
+                    Err(why) => {
+                        println!("{}", why);
+                        std::process::exit(1);
+                    }
+                };
+                server = server.bind_openssl(address, builder)?;
+            }
+            None => {
+                server = server.bind(address)?;
+            }
+        }
+        println!("Server started at {}://{}.", scheme, address);
+    }
+    #[cfg(unix)]
+    if let Some(path) = &config.unix_socket {
+        // A socket left over from an earlier run would make the bind fail.
+        if std::path::Path::new(path).exists() {
+            std::fs::remove_file(path)?;
+        }
+        server = server.bind_uds(path)?;
+        println!("Server started on unix socket {}.", path);
+    }
+
+    println!("Ready to serve.");
+
+    if let Some(address) = config.bind_addresses.first() {
+        // Only a convenience; a server without a browser to open still serves.
+        if let Err(why) = webbrowser::open(&format!("{}://{}", scheme, address)) {
+            println!("Could not open a browser at {}://{}: {}", scheme, address, why);
+        }
+    }
+    server.run().await.expect("Error creating HTTP server.");
+    telemetry::shutdown();
 
     // Satisfy the () in the Result.
     Ok(())
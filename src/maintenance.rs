@@ -0,0 +1,84 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, HttpResponse, Result,
+};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+
+use crate::{logs, queries, renderer, AppState};
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone, Default)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct Maintenance {
+    pub enabled: bool,
+    // Shown to students instead of the default wording.
+    pub message: Option<String>,
+}
+
+// Kept in memory so the public routes don't query the flag on every request.
+pub type SharedMaintenance = Arc<RwLock<Maintenance>>;
+
+pub async fn load(pool: &sqlx::MySqlPool) -> Result<Maintenance, sqlx::Error> {
+    match queries::MAINTENANCE.run(|sql| sqlx::query_as::<_, Maintenance>(sql)
+    .fetch_optional(pool)).await {
+        Ok(val) => Ok(val.unwrap_or_default()),
+        Err(why) => Err(why),
+    }
+}
+
+pub async fn save(pool: &sqlx::MySqlPool, maintenance: &Maintenance) -> Result<(), sqlx::Error> {
+    match queries::SET_MAINTENANCE.run(|sql| sqlx::query(sql)
+    .bind(maintenance.enabled)
+    .bind(&maintenance.message)
+    .execute(pool))
+    .await {
+        Ok(_val) => Ok(()),
+        Err(why) => Err(why),
+    }
+}
+
+impl AppState {
+    pub fn maintenance(&self) -> Maintenance {
+        self.maintenance.read().unwrap().clone()
+    }
+
+    pub fn set_maintenance(&self, maintenance: Maintenance) {
+        *self.maintenance.write().unwrap() = maintenance;
+    }
+}
+
+// Middleware for the public routes: while maintenance is on, every page but the stylesheet and
+// other assets gets the "temporarily unavailable" page.
+pub async fn check(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let maintenance = match req.app_data::<web::Data<AppState>>() {
+        Some(state) => state.maintenance(),
+        None => Maintenance::default(),
+    };
+    if !maintenance.enabled || req.path() == "/style.css" || req.path().starts_with("/assets/") {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    let body = match req.app_data::<web::Data<AppState>>().map(|state| renderer::render_string(state, "maintenance", &maintenance)) {
+        Some(Ok(val)) => val,
+        _ => "The tuition calculator is temporarily unavailable. Please try again shortly.".to_string(),
+    };
+    let response = HttpResponse::ServiceUnavailable()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((header::RETRY_AFTER, "300"))
+        .body(body);
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+// For load balancers. Stays up during maintenance and reports whether the database answers.
+pub async fn healthz(state: web::Data<AppState>) -> HttpResponse {
+    match queries::PING.run(|sql| sqlx::query(sql).execute(&state.conn)).await {
+        Ok(_val) => HttpResponse::Ok().content_type("text/plain").body("ok"),
+        Err(why) => {
+            logs::throttled(&format!("Health check failed: {}", why));
+            HttpResponse::ServiceUnavailable().content_type("text/plain").body("database unavailable")
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use actix_web::{web, HttpResponse};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{atomic::{AtomicU64, Ordering}, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::AppState;
+
+// Requests turned away because no connection freed up in time.
+static ACQUIRE_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+
+pub fn record_acquire_timeout() {
+    ACQUIRE_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+}
+
+// How many times each named query in `queries` ran, and the time they took altogether.
+static QUERY_TIMES: Mutex<BTreeMap<&'static str, (u64, f64)>> = Mutex::new(BTreeMap::new());
+
+pub fn record_query(name: &'static str, elapsed: Duration) {
+    if let Ok(mut times) = QUERY_TIMES.lock() {
+        let entry = times.entry(name).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += elapsed.as_secs_f64();
+    }
+}
+
+// Connection pool gauges in the Prometheus text format, for sizing DB_MAX_CONNECTIONS.
+// The wait is timed on a fresh acquire, so it shows how long a request would queue right now.
+pub async fn metrics(state: web::Data<AppState>) -> HttpResponse {
+    let pool = &state.conn;
+    let started = Instant::now();
+    let acquired = pool.acquire().await;
+    let wait = started.elapsed().as_secs_f64();
+    if let Err(sqlx::Error::PoolTimedOut) = acquired {
+        record_acquire_timeout();
+    }
+    drop(acquired);
+
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+    let mut body = String::new();
+    let mut gauge = |name: &str, help: &str, kind: &str, val: String| {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} {}", name, kind);
+        let _ = writeln!(body, "{} {}", name, val);
+    };
+    gauge("db_pool_size", "Connections currently open.", "gauge", size.to_string());
+    gauge("db_pool_idle", "Open connections not in use.", "gauge", idle.to_string());
+    gauge("db_pool_in_use", "Open connections checked out by requests.", "gauge", size.saturating_sub(idle).to_string());
+    gauge("db_pool_acquire_wait_seconds", "Time taken to acquire a connection for this scrape.", "gauge", format!("{:.6}", wait));
+    gauge("db_pool_acquire_timeouts_total", "Requests that gave up waiting for a connection.", "counter", ACQUIRE_TIMEOUTS.load(Ordering::Relaxed).to_string());
+    gauge("replay_queue_pending", "Estimates waiting to be saved after the database failed to take them.", "gauge", state.replay.len().to_string());
+
+    // Dividing the two gives each query's average time since the server started.
+    if let Ok(times) = QUERY_TIMES.lock() {
+        let _ = writeln!(body, "# HELP db_query_total Times each query has run.");
+        let _ = writeln!(body, "# TYPE db_query_total counter");
+        for (name, (count, _)) in times.iter() {
+            let _ = writeln!(body, "db_query_total{{query=\"{}\"}} {}", name, count);
+        }
+        let _ = writeln!(body, "# HELP db_query_seconds_total Time spent running each query, waiting for a connection included.");
+        let _ = writeln!(body, "# TYPE db_query_seconds_total counter");
+        for (name, (_, seconds)) in times.iter() {
+            let _ = writeln!(body, "db_query_seconds_total{{query=\"{}\"}} {:.6}", name, seconds);
+        }
+    }
+
+    HttpResponse::Ok().content_type("text/plain; version=0.0.4").body(body)
+}
@@ -0,0 +1,50 @@
+use sqlx::{migrate::Migrator, MySqlPool};
+
+use crate::queries;
+
+// The migrations in ./migrations, built into the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Print the migrations that would run at the next start, and the DDL in each, without applying
+// any, so a DBA can review schema changes before a deploy. Also flags what would stop sqlx from
+// migrating at all: a migration that failed partway, or one edited after it was applied.
+pub async fn plan(pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    let (has_table,) = queries::MIGRATIONS_TABLE.run(|sql| sqlx::query_as::<_, (i64,)>(sql)
+    .fetch_one(pool)).await?;
+    let applied = if has_table > 0 {
+        queries::APPLIED_MIGRATIONS.run(|sql| sqlx::query_as::<_, (i64, bool, Vec<u8>)>(sql)
+        .fetch_all(pool)).await?
+    } else {
+        Vec::new()
+    };
+
+    let mut problems = Vec::new();
+    for (version, success, checksum) in &applied {
+        match MIGRATOR.iter().find(|migration| migration.version == *version) {
+            _ if !success => problems.push(format!("{} failed partway through and has to be fixed by hand.", version)),
+            Some(migration) if *migration.checksum != checksum[..] => problems.push(format!("{}_{} was changed after it was applied.", version, migration.description)),
+            Some(_migration) => {},
+            None => problems.push(format!("{} was applied but isn't in this version's migrations.", version)),
+        }
+    }
+
+    let pending: Vec<_> = MIGRATOR.iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.iter().any(|(version, _, _)| *version == migration.version))
+        .collect();
+    println!("{} migration(s) applied, {} pending.", applied.len(), pending.len());
+    for migration in &pending {
+        println!();
+        println!("-- {}_{}", migration.version, migration.description);
+        println!("{}", migration.sql.trim_end());
+    }
+
+    if !problems.is_empty() {
+        println!();
+        println!("The server won't be able to migrate until these are sorted out:");
+        for problem in &problems {
+            println!("  - {}", problem);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,324 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    mysql::{MySqlTypeInfo, MySqlValueRef},
+    Decode, Encode, MySql,
+};
+use std::fmt;
+
+use crate::{form, money::{Money, PerCredit}, pricing::FeeCategory, CalculateTuitionFormParams};
+
+// Typed IDs, so a scenario id can't be passed where a student id is expected.
+// They are stored as INT columns.
+macro_rules! id_type {
+    ($name:ident) => {
+        #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[serde(transparent)]
+        pub struct $name(pub i32);
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl sqlx::Type<MySql> for $name {
+            fn type_info() -> MySqlTypeInfo {
+                <i32 as sqlx::Type<MySql>>::type_info()
+            }
+
+            fn compatible(ty: &MySqlTypeInfo) -> bool {
+                <i32 as sqlx::Type<MySql>>::compatible(ty)
+            }
+        }
+
+        impl<'q> Encode<'q, MySql> for $name {
+            fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+                <i32 as Encode<'q, MySql>>::encode_by_ref(&self.0, buf)
+            }
+        }
+
+        impl<'r> Decode<'r, MySql> for $name {
+            fn decode(value: MySqlValueRef<'r>) -> Result<Self, BoxDynError> {
+                Ok($name(<i32 as Decode<'r, MySql>>::decode(value)?))
+            }
+        }
+    };
+}
+
+id_type!(CampusId);
+id_type!(StudentId);
+id_type!(TuitionRecordId);
+id_type!(ScenarioId);
+id_type!(ApiKeyId);
+id_type!(ReceiptId);
+id_type!(RefundRuleId);
+id_type!(LineItemId);
+id_type!(ValidationRuleId);
+id_type!(OrientationExemptionId);
+id_type!(ReciprocityAgreementId);
+id_type!(BulkDeleteId);
+
+// One school in the deployment. Fee tables and student records are kept per campus.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct Campus {
+    pub id: CampusId,
+    pub slug: String,
+    pub name: String,
+    pub hostname: Option<String>,
+    // The USPS code of the state the campus is in. Students are asked for their home state only
+    // when it's set.
+    pub state: Option<String>,
+}
+
+// A student's saved total for one term, joined with the student it belongs to.
+// The term and inputs are empty on rows saved before they were recorded.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct TuitionRecord {
+    pub id: TuitionRecordId,
+    // Only the public id leaves the server.
+    #[serde(skip_serializing)]
+    pub student_id: StudentId,
+    #[serde(rename = "student_id")]
+    pub student_public_id: String,
+    pub campus_id: CampusId,
+    pub first_name: String,
+    pub last_name: String,
+    pub term: Option<String>,
+    pub tuition_cost: Decimal,
+    pub num_credits: Option<u8>,
+    pub orientation: Option<bool>,
+    pub student_type: Option<String>,
+    pub student_studies: Option<String>,
+    pub insurance_waived: Option<bool>,
+}
+
+// The pricing inputs of a saved calculation, when all of them were recorded.
+pub struct PricingInputs<'a> {
+    pub num_credits: u8,
+    pub orientation: bool,
+    pub student_type: &'a str,
+    pub student_studies: &'a str,
+}
+
+impl TuitionRecord {
+    pub fn pricing_inputs(&self) -> Option<PricingInputs<'_>> {
+        match (&self.num_credits, &self.orientation, &self.student_type, &self.student_studies) {
+            (Some(num_credits), Some(orientation), Some(student_type), Some(student_studies)) => Some(PricingInputs {
+                num_credits: *num_credits,
+                orientation: *orientation,
+                student_type,
+                student_studies,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct TuitionCosts {
+    pub credits_cost: PerCredit,
+    pub nonresidency_fee: Money,
+}
+
+// A flat fee charged for taking one course, e.g. a chemistry lab.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct CourseFee {
+    pub department: String,
+    pub course_code: String,
+    pub label: String,
+    pub fee: Money,
+}
+
+// A flat fee charged on top of tuition, e.g. the SEVIS fee for international students.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct FlatFee {
+    pub label: String,
+    pub amount: Money,
+}
+
+// An admin-defined charge for one term; `applies_when` is a rule for `rules::parse`.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct LineItem {
+    pub label: String,
+    pub amount: Money,
+    #[serde(default)]
+    pub applies_when: String,
+    #[serde(default)]
+    pub category: FeeCategory,
+}
+
+// A combination of answers the campus turns away; `reject_when` is a rule for `rules::parse`,
+// and `message` goes on the form's `field`.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct ValidationRule {
+    pub field: String,
+    pub reject_when: String,
+    pub message: String,
+}
+
+// Students `exempt_when` picks out don't pay for orientation; `label` says why on the result.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct OrientationExemption {
+    pub label: String,
+    pub exempt_when: String,
+}
+
+// Students from `state` pay the `residency` rates, and `nonresidency_fee_percent` of the
+// non-residency fee; `label` names the agreement on the result.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct ReciprocityAgreement {
+    pub state: String,
+    pub label: String,
+    pub residency: String,
+    pub nonresidency_fee_percent: Decimal,
+}
+
+// What it typically takes to finish a program, e.g. a bachelor's degree.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct Program {
+    pub name: String,
+    pub total_credits: u32,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct IndirectCost {
+    pub label: String,
+    pub amount: Money,
+}
+
+// Students who enroll after `after_week` weeks of the term starting on `starts_on` pay
+// `tuition_percent` of tuition.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct ProrationRule {
+    pub starts_on: NaiveDate,
+    pub after_week: u32,
+    pub tuition_percent: Decimal,
+}
+
+// Students who withdraw by the end of week `through_week` of the term starting on `starts_on`
+// get `refund_percent` of their tuition back.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct RefundRule {
+    pub starts_on: NaiveDate,
+    pub through_week: u32,
+    pub refund_percent: Decimal,
+}
+
+// When students can make estimates for a term on the public calculator. Either end can be
+// left open.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct TermWindow {
+    pub name: String,
+    pub starts_on: NaiveDate,
+    pub opens_on: Option<NaiveDate>,
+    pub closes_on: Option<NaiveDate>,
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct Scenario {
+    pub id: ScenarioId,
+    pub campus_id: CampusId,
+    pub scenario_name: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub num_credits: u8,
+    pub new_student: bool,
+    pub orientation: bool,
+    pub student_type: String,
+    pub student_studies: String,
+    pub include_additional_costs: bool,
+    pub course_codes: Option<String>,
+    pub insurance_waived: bool,
+    pub home_state: Option<String>,
+}
+
+impl Scenario {
+    // Turn the stored scenario back into the values the calculator form submits.
+    pub fn to_form(&self) -> CalculateTuitionFormParams {
+        CalculateTuitionFormParams {
+            first_name: Some(self.first_name.clone()),
+            last_name: Some(self.last_name.clone()),
+            num_credits: Some(form::Bounded::new(u32::from(self.num_credits))),
+            new_student: self.new_student,
+            orientation: self.orientation.then_some(true),
+            student_type: Some(self.student_type.clone()),
+            home_state: self.home_state.clone(),
+            student_studies: Some(self.student_studies.clone()),
+            include_additional_costs: self.include_additional_costs,
+            enrollment_date: None,
+            course_codes: self.course_codes.clone(),
+            insurance_waiver: self.insurance_waived,
+            email: None,
+            consent: false,
+            captcha_response: None,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+    pub name: String,
+    pub requests_per_minute: u32,
+    pub created_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+// The rates a calculation was priced with, kept as they were at the time.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+pub struct Receipt {
+    pub id: ReceiptId,
+    pub code: String,
+    pub campus_id: CampusId,
+    // None once the student was deleted and the receipt kept anonymized.
+    pub student_id: Option<StudentId>,
+    pub first_name: String,
+    pub last_name: String,
+    pub term: String,
+    pub num_credits: u8,
+    pub orientation: bool,
+    pub student_type: String,
+    pub student_studies: String,
+    pub credits_cost: PerCredit,
+    pub nonresidency_fee: Money,
+    pub orientation_fee: Money,
+    pub tuition_cost: Money,
+    pub created_at: NaiveDateTime,
+    pub enrollment_date: Option<NaiveDate>,
+    // Set when the tuition was prorated.
+    pub tuition_percent: Option<Decimal>,
+    pub course_codes: Option<String>,
+    pub course_fees: Money,
+    pub insurance_waived: bool,
+    // Zero when waived.
+    pub health_insurance_fee: Money,
+    pub international_fees: Money,
+    // Set when any custom line items applied.
+    pub custom_fees: Option<Money>,
+    // The items making up `custom_fees`, as JSON for `recent::breakdown`.
+    #[serde(skip_serializing)]
+    pub custom_items: Option<String>,
+    // The counselor who calculated this for the student; None when the student did it themselves.
+    pub entered_by: Option<String>,
+}
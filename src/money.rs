@@ -0,0 +1,247 @@
+use handlebars::handlebars_helper;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    mysql::{MySqlTypeInfo, MySqlValueRef},
+    Decode, Encode, MySql,
+};
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Currency {
+    #[default]
+    Usd,
+}
+
+impl Currency {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Usd => "$",
+        }
+    }
+}
+
+// An amount charged, paid or refunded. Amounts only add up with others in the same currency,
+// and a per-credit rate has to be multiplied out first; see `PerCredit`. Stored, serialized and
+// parsed from the fee schedule file as the plain decimal, so tables, JSON and templates don't
+// see the difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Money(Decimal, Currency);
+
+impl Money {
+    pub fn new(amount: Decimal) -> Money {
+        Money(amount, Currency::default())
+    }
+
+    // Written with two places, as "0.00", like the amounts read from the database.
+    pub fn zero() -> Money {
+        Money::new(Decimal::new(000, 2))
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_sign_negative(&self) -> bool {
+        self.0.is_sign_negative()
+    }
+
+    // To the cent, halves away from zero, as prorated and refunded tuition always were.
+    pub fn round(&self) -> Money {
+        Money(self.0.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero), self.1)
+    }
+
+    // `percent` percent of this, rounded to the cent, e.g. a prorated or refunded share.
+    pub fn percent(&self, percent: Decimal) -> Money {
+        Money(self.0 * percent / Decimal::ONE_HUNDRED, self.1).round()
+    }
+
+    // Repeated `times` times, e.g. term fees over the terms of a program.
+    pub fn times(&self, times: u32) -> Money {
+        Money(self.0 * Decimal::from(times), self.1)
+    }
+
+    // For display, e.g. 4800 -> "$4,800.00".
+    pub fn format(&self) -> String {
+        let rounded = self.round().0;
+        let text = format!("{:.2}", rounded.abs());
+        let (whole, cents) = text.split_once('.').unwrap_or((&text, "00"));
+
+        // Group the whole part into thousands.
+        let mut grouped = String::new();
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+
+        let sign = if rounded.is_sign_negative() && !rounded.is_zero() { "-" } else { "" };
+        format!("{}{}{}.{}", sign, self.1.symbol(), grouped, cents)
+    }
+
+    fn same_currency(&self, other: &Money) -> Currency {
+        assert_eq!(self.1, other.1, "Can't add or take {:?} amounts from {:?} ones.", other.1, self.1);
+        self.1
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0, self.same_currency(&other))
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0, self.same_currency(&other))
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0, self.1)
+    }
+}
+
+// Starts from `Money::zero`, so an empty sum is "0.00".
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), |sum, amount| sum + amount)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Serialize::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        Ok(Money::new(<Decimal as Deserialize>::deserialize(deserializer)?))
+    }
+}
+
+// Stored in the DECIMAL columns the plain amounts always were.
+impl sqlx::Type<MySql> for Money {
+    fn type_info() -> MySqlTypeInfo {
+        <Decimal as sqlx::Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <Decimal as sqlx::Type<MySql>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, MySql> for Money {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        <Decimal as Encode<'q, MySql>>::encode_by_ref(&self.0, buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for Money {
+    fn decode(value: MySqlValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(Money::new(<Decimal as Decode<'r, MySql>>::decode(value)?))
+    }
+}
+
+// The charge for each credit. It can't be added to amounts, only multiplied by the credits taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PerCredit(pub Money);
+
+impl PerCredit {
+    pub fn amount(&self) -> Decimal {
+        self.0.amount()
+    }
+}
+
+impl sqlx::Type<MySql> for PerCredit {
+    fn type_info() -> MySqlTypeInfo {
+        <Money as sqlx::Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <Money as sqlx::Type<MySql>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, MySql> for PerCredit {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        <Money as Encode<'q, MySql>>::encode_by_ref(&self.0, buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for PerCredit {
+    fn decode(value: MySqlValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(PerCredit(<Money as Decode<'r, MySql>>::decode(value)?))
+    }
+}
+
+impl Mul<u8> for PerCredit {
+    type Output = Money;
+
+    fn mul(self, credits: u8) -> Money {
+        Money(self.0.0 * Decimal::from(credits), self.0.1)
+    }
+}
+
+impl Mul<u32> for PerCredit {
+    type Output = Money;
+
+    fn mul(self, credits: u32) -> Money {
+        Money(self.0.0 * Decimal::from(credits), self.0.1)
+    }
+}
+
+// Format an amount for display, e.g. 4800 -> "$4,800.00".
+pub fn format_money(amount: Decimal) -> String {
+    Money::new(amount).format()
+}
+
+// `{{money amount}}` in templates. Decimals serialize as strings, so accept those as well as numbers.
+handlebars_helper!(money: |amount: Json| {
+    let parsed = match amount {
+        serde_json::Value::String(val) => val.parse::<Decimal>().ok(),
+        serde_json::Value::Number(val) => val.to_string().parse::<Decimal>().ok(),
+        _ => None,
+    };
+    match parsed {
+        Some(val) => format_money(val),
+        None => String::new(),
+    }
+});
@@ -0,0 +1,21 @@
+use actix_web::{http::header::{self, Header}, HttpMessage, HttpRequest, HttpResponse};
+use serde::Serialize;
+
+use crate::{error::AppError, render, AppState};
+
+// Whether the client prefers JSON over HTML, going by the Accept header's q-values. A client
+// that sent a JSON body and doesn't say what it accepts gets JSON back too.
+pub fn wants_json<T: HttpMessage>(msg: &T) -> bool {
+    match header::Accept::parse(msg) {
+        Ok(accept) if !accept.is_empty() && accept.preference().essence_str() != "*/*" => accept.preference().essence_str() == "application/json",
+        _ => msg.content_type() == "application/json",
+    }
+}
+
+// The page data as JSON for clients that ask for it, or rendered into the template for everyone else.
+pub async fn respond<T: Serialize>(state: &AppState, req: &HttpRequest, template: &str, data: &T) -> Result<HttpResponse, AppError> {
+    if wants_json(req) {
+        return Ok(HttpResponse::Ok().json(data));
+    }
+    render(state, template, data).await
+}
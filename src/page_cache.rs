@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "redis")]
+use std::{sync::Arc, time::SystemTime};
+
+use crate::models::CampusId;
+#[cfg(feature = "redis")]
+use crate::redis_store::Redis;
+
+#[cfg(feature = "redis")]
+const REDIS_KEY: &str = "index_pages";
+
+// The calculator as a first-time visitor sees it, rendered at most once per `ttl` for each
+// campus. Anything particular to the visitor (a prefilled name, their recent estimates) is
+// rendered fresh instead. A ttl of zero turns the cache off.
+//
+// With REDIS_URL the pages are kept in Redis instead, so clearing the cache after an admin change
+// clears it for every replica and not just the one that took the change.
+#[derive(Debug)]
+pub struct PageCache {
+    ttl: Duration,
+    pages: Mutex<HashMap<CampusId, (Instant, String)>>,
+    #[cfg(feature = "redis")]
+    redis: Option<Arc<Redis>>,
+}
+
+#[cfg(feature = "redis")]
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|val| val.as_secs()).unwrap_or_default()
+}
+
+impl PageCache {
+    pub fn new(ttl: Duration) -> PageCache {
+        PageCache {
+            ttl,
+            pages: Mutex::new(HashMap::new()),
+            #[cfg(feature = "redis")]
+            redis: None,
+        }
+    }
+
+    #[cfg(feature = "redis")]
+    pub fn shared(ttl: Duration, redis: Arc<Redis>) -> PageCache {
+        PageCache { redis: Some(redis), ..PageCache::new(ttl) }
+    }
+
+    pub async fn get(&self, campus_id: CampusId) -> Option<String> {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            // Stored as "<expires at, in unix seconds>\n<page>"; fields in a hash don't expire by themselves.
+            return match redis.hget(REDIS_KEY, &campus_id.to_string()).await {
+                Ok(Some(val)) => {
+                    let val = String::from_utf8_lossy(&val);
+                    match val.split_once('\n') {
+                        Some((expires_at, body)) if expires_at.parse::<u64>().is_ok_and(|expires_at| expires_at > unix_now()) => Some(body.to_string()),
+                        _ => None,
+                    }
+                }
+                Ok(None) => None,
+                Err(why) => {
+                    println!("Error while reading the cached calculator page from Redis: {}", why);
+                    None
+                }
+            };
+        }
+        let pages = self.pages.lock().unwrap();
+        match pages.get(&campus_id) {
+            Some((rendered_at, body)) if rendered_at.elapsed() < self.ttl => Some(body.clone()),
+            _ => None,
+        }
+    }
+
+    pub async fn put(&self, campus_id: CampusId, body: &str) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            let val = format!("{}\n{}", unix_now() + self.ttl.as_secs().max(1), body);
+            let stored = match redis.hset(REDIS_KEY, &campus_id.to_string(), val.as_bytes()).await {
+                Ok(()) => redis.expire(REDIS_KEY, self.ttl).await,
+                Err(why) => Err(why),
+            };
+            if let Err(why) = stored {
+                println!("Error while caching the calculator page in Redis: {}", why);
+            }
+            return;
+        }
+        self.pages.lock().unwrap().insert(campus_id, (Instant::now(), body.to_string()));
+    }
+
+    // After an admin change the page shows, so it's right on the next request.
+    pub async fn clear(&self) {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            if let Err(why) = redis.delete(REDIS_KEY).await {
+                println!("Error while clearing the cached calculator pages in Redis: {}", why);
+            }
+            return;
+        }
+        self.pages.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,55 @@
+use argon2::{Argon2, PasswordVerifier};
+use std::fmt;
+
+// A staff password hash from ADMIN_BASIC_AUTH, as a PHC string: argon2id, or bcrypt as written by
+// `htpasswd -B`. Both are salted and slow to compute, so a leaked config is slow to guess from.
+#[derive(Clone)]
+pub enum PasswordHash {
+    Argon2id(String),
+    Bcrypt(String),
+}
+
+// Keep the hashes out of logs.
+impl fmt::Debug for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordHash::Argon2id(_) => write!(f, "argon2id"),
+            PasswordHash::Bcrypt(_) => write!(f, "bcrypt"),
+        }
+    }
+}
+
+impl PasswordHash {
+    // Check the hash can be read now, rather than turning every sign-in away later. The error
+    // finishes a sentence about the hash.
+    pub fn parse(val: &str) -> Result<PasswordHash, String> {
+        if val.starts_with("$argon2id$") {
+            return match argon2::PasswordHash::new(val) {
+                Ok(hash) if hash.salt.is_none() || hash.hash.is_none() => Err("is missing its salt or hash".to_string()),
+                Ok(hash) => match argon2::Params::try_from(&hash) {
+                    Ok(_) => Ok(PasswordHash::Argon2id(val.to_string())),
+                    Err(why) => Err(format!("has parameters argon2 can't use: {}", why)),
+                },
+                Err(why) => Err(format!("isn't a valid argon2id hash: {}", why)),
+            };
+        }
+        if ["$2a$", "$2b$", "$2y$"].iter().any(|prefix| val.starts_with(prefix)) {
+            return match val.parse::<bcrypt::HashParts>() {
+                Ok(_) => Ok(PasswordHash::Bcrypt(val.to_string())),
+                Err(why) => Err(format!("isn't a valid bcrypt hash: {}", why)),
+            };
+        }
+        Err("must be an argon2id or bcrypt hash (SHA-256 hashes aren't accepted any more)".to_string())
+    }
+
+    pub fn verify(&self, password: &str) -> bool {
+        match self {
+            PasswordHash::Argon2id(hash) => match argon2::PasswordHash::new(hash) {
+                // The cost comes from the hash itself.
+                Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+                Err(_) => false,
+            },
+            PasswordHash::Bcrypt(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+        }
+    }
+}
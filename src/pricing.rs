@@ -0,0 +1,205 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    mysql::{MySqlTypeInfo, MySqlValueRef},
+    Decode, Encode, MySql,
+};
+
+use crate::{models::{ProrationRule, ReciprocityAgreement, RefundRule, TuitionCosts}, money::{Money, PerCredit}};
+
+// The tuition owed for one term. The orientation fee only applies when the student signed up for orientation.
+pub fn tuition_total(num_credits: u8, orientation: bool, costs: &TuitionCosts, orientation_fee: Money) -> Money {
+    let orientation_fee = if orientation { orientation_fee } else { Money::zero() };
+    // Multiply the cost per credit by the credits
+    costs.credits_cost * num_credits + costs.nonresidency_fee + orientation_fee
+}
+
+// The rate category for a student from `state` at a campus in `campus_state`: resident at home,
+// what the campus's agreement with their state says, and nonresident otherwise. Also the agreement,
+// when one applied.
+pub fn residency_for_state<'a>(campus_state: Option<&str>, state: &str, agreements: &'a [ReciprocityAgreement]) -> (&'a str, Option<&'a ReciprocityAgreement>) {
+    if campus_state.is_some_and(|campus_state| campus_state.eq_ignore_ascii_case(state)) {
+        return ("resident", None);
+    }
+    match agreements.iter().find(|agreement| agreement.state.eq_ignore_ascii_case(state)) {
+        Some(agreement) => (agreement.residency.as_str(), Some(agreement)),
+        None => ("nonresident", None),
+    }
+}
+
+// The non-residency fee after an agreement's discount tier, if one applied.
+pub fn reciprocity_fee(nonresidency_fee: Money, agreement: Option<&ReciprocityAgreement>) -> Money {
+    match agreement {
+        Some(agreement) => nonresidency_fee.percent(agreement.nonresidency_fee_percent),
+        None => nonresidency_fee,
+    }
+}
+
+// Health insurance is charged unless the student waived it with their own coverage.
+pub fn health_insurance_charge(waived: bool, health_insurance_fee: Money) -> Money {
+    if waived { Money::zero() } else { health_insurance_fee }
+}
+
+// Which week of the term a student enrolled in, and the share of tuition they're charged for it.
+#[derive(Serialize, Debug, Clone)]
+pub struct Proration {
+    pub week: i64,
+    pub tuition_percent: Decimal,
+}
+
+// Week 1 is the week the term starts; dates before the start are week 0 or earlier.
+pub fn term_week(starts_on: NaiveDate, date: NaiveDate) -> i64 {
+    (date - starts_on).num_days().div_euclid(7) + 1
+}
+
+// The rule for the latest week already past on the enrollment date, if any.
+pub fn proration(rules: &[ProrationRule], enrollment_date: NaiveDate) -> Option<Proration> {
+    rules.iter()
+        .filter(|rule| term_week(rule.starts_on, enrollment_date) > i64::from(rule.after_week))
+        .max_by_key(|rule| rule.after_week)
+        .map(|rule| Proration { week: term_week(rule.starts_on, enrollment_date), tuition_percent: rule.tuition_percent.normalize() })
+}
+
+// The line item taking tuition down to its prorated share, as a negative amount. Fees aren't prorated.
+pub fn proration_adjustment(num_credits: u8, costs: &TuitionCosts, tuition_percent: Decimal) -> Money {
+    let tuition = costs.credits_cost * num_credits;
+    tuition.percent(tuition_percent) - tuition
+}
+
+// The tuition actually charged on a receipt: credits at the receipt's rate, prorated when it was.
+pub fn charged_tuition(num_credits: u8, credits_cost: PerCredit, tuition_percent: Option<Decimal>) -> Money {
+    let tuition = credits_cost * num_credits;
+    match tuition_percent {
+        Some(percent) => tuition.percent(percent),
+        None => tuition,
+    }
+}
+
+// The refund for withdrawing on `withdrawal_date`: the earliest rule whose last week hasn't passed yet.
+// Past the last rule nothing is refunded.
+pub fn refund_percent(rules: &[RefundRule], withdrawal_date: NaiveDate) -> (i64, Decimal) {
+    let week = match rules.first() {
+        Some(rule) => term_week(rule.starts_on, withdrawal_date),
+        None => 0,
+    };
+    let percent = rules.iter()
+        .filter(|rule| week <= i64::from(rule.through_week))
+        .min_by_key(|rule| rule.through_week)
+        .map(|rule| rule.refund_percent.normalize())
+        .unwrap_or(Decimal::ZERO);
+    (week, percent)
+}
+
+// Fees aren't refunded, only tuition.
+pub fn refund_amount(charged_tuition: Money, refund_percent: Decimal) -> Money {
+    charged_tuition.percent(refund_percent)
+}
+
+// The sections of a breakdown, in the order they're shown. Aid comes off the total.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeCategory {
+    Tuition,
+    #[default]
+    MandatoryFees,
+    OptionalFees,
+    Aid,
+}
+
+impl FeeCategory {
+    pub const ALL: [FeeCategory; 4] = [FeeCategory::Tuition, FeeCategory::MandatoryFees, FeeCategory::OptionalFees, FeeCategory::Aid];
+
+    // The value used by the admin form, the fee schedule file and the CustomLineItems table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeeCategory::Tuition => "tuition",
+            FeeCategory::MandatoryFees => "mandatory_fees",
+            FeeCategory::OptionalFees => "optional_fees",
+            FeeCategory::Aid => "aid",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FeeCategory::Tuition => "Tuition",
+            FeeCategory::MandatoryFees => "Mandatory Fees",
+            FeeCategory::OptionalFees => "Optional Fees",
+            FeeCategory::Aid => "Aid",
+        }
+    }
+
+    pub fn parse(val: &str) -> Option<FeeCategory> {
+        FeeCategory::ALL.into_iter().find(|category| category.as_str() == val)
+    }
+
+    // Amounts are entered as positive numbers; aid is what the student doesn't pay.
+    pub fn signed(&self, amount: Money) -> Money {
+        match self {
+            FeeCategory::Aid => -amount,
+            _ => amount,
+        }
+    }
+}
+
+// Stored as its `as_str` in a VARCHAR column.
+impl sqlx::Type<MySql> for FeeCategory {
+    fn type_info() -> MySqlTypeInfo {
+        <str as sqlx::Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        <str as sqlx::Type<MySql>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, MySql> for FeeCategory {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        <&str as Encode<'q, MySql>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, MySql> for FeeCategory {
+    fn decode(value: MySqlValueRef<'r>) -> Result<Self, BoxDynError> {
+        let val = <&str as Decode<'r, MySql>>::decode(value)?;
+        match FeeCategory::parse(val) {
+            Some(category) => Ok(category),
+            None => Err(format!("\"{}\" isn't a fee category", val).into()),
+        }
+    }
+}
+
+// One line of a breakdown. Aid amounts are negative.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BreakdownItem {
+    pub label: String,
+    pub amount: Money,
+    #[serde(default)]
+    pub category: FeeCategory,
+}
+
+// One section of a breakdown and what it adds up to.
+#[derive(Serialize, Debug, Clone)]
+pub struct CategorySubtotal {
+    pub category: FeeCategory,
+    pub label: &'static str,
+    pub items: Vec<BreakdownItem>,
+    pub subtotal: Money,
+}
+
+// Group the items by category in the order of `FeeCategory::ALL`, keeping their order within
+// each. Categories with nothing in them are left out.
+pub fn categorize(items: Vec<BreakdownItem>) -> Vec<CategorySubtotal> {
+    FeeCategory::ALL.into_iter()
+        .filter_map(|category| {
+            let items: Vec<BreakdownItem> = items.iter().filter(|item| item.category == category).cloned().collect();
+            if items.is_empty() {
+                return None;
+            }
+            let subtotal = items.iter().map(|item| item.amount).sum();
+            Some(CategorySubtotal { category, label: category.label(), items, subtotal })
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::{AppError, FormErrors}, form, models::{Campus, Program, Receipt}, money::{Money, PerCredit}, negotiate, pricing, receipts, render, AppState};
+
+// What finishing a program would take at the load the student just priced, at today's rates.
+#[derive(Serialize, Debug, Clone)]
+pub struct Projection {
+    pub program: String,
+    pub total_credits: u32,
+    pub credits_per_term: u8,
+    pub terms: u32,
+    pub total: Money,
+}
+
+// The parts of one term's total a projection is built from.
+pub struct TermCharges {
+    pub credits: u8,
+    pub credits_cost: PerCredit,
+    // Everything charged per term rather than per credit: non-residency, course, insurance and
+    // international fees, and custom line items.
+    pub term_fees: Money,
+    // Charged once, like orientation.
+    pub one_time_fees: Money,
+}
+
+// Every credit at the per-credit rate, the term fees once for each term it takes, and the one-time
+// fees once. Proration only applies to the term being priced, so it's left out. Nothing to project
+// for a term without credits.
+pub fn project(programs: &[Program], charges: &TermCharges) -> Vec<Projection> {
+    if charges.credits == 0 {
+        return Vec::new();
+    }
+    programs.iter()
+        .map(|program| {
+            let terms = program.total_credits.div_ceil(u32::from(charges.credits));
+            Projection {
+                program: program.name.clone(),
+                total_credits: program.total_credits,
+                credits_per_term: charges.credits,
+                terms,
+                total: charges.credits_cost * program.total_credits + charges.term_fees.times(terms) + charges.one_time_fees,
+            }
+        })
+        .collect()
+}
+
+// Fall and spring; summer terms are too varied to assume.
+pub const TERMS_PER_YEAR: u32 = 2;
+
+// Most credits a projection can cover, well past any degree.
+const MAX_TOTAL_CREDITS: u32 = 400;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProjectedYear {
+    pub year: u32,
+    pub terms: u32,
+    // The term estimate with the increase compounded for each year before this one.
+    pub per_term: Money,
+    pub cost: Money,
+    pub cumulative: Money,
+}
+
+// The terms a program takes, a year at a time, with rates going up by `annual_increase` percent
+// each year after the first. The one-time fees are in the first year.
+pub fn by_year(per_term: Money, one_time_fees: Money, terms: u32, annual_increase: Decimal) -> Vec<ProjectedYear> {
+    let mut years = Vec::new();
+    let mut rate = per_term;
+    let mut cumulative = Money::zero();
+    let mut remaining = terms;
+    while remaining > 0 {
+        let year = years.len() as u32 + 1;
+        if year > 1 {
+            rate = rate.percent(Decimal::ONE_HUNDRED + annual_increase);
+        }
+        let terms = remaining.min(TERMS_PER_YEAR);
+        let cost = rate.times(terms) + if year == 1 { one_time_fees } else { Money::zero() };
+        cumulative += cost;
+        years.push(ProjectedYear { year, terms, per_term: rate, cost, cumulative });
+        remaining -= terms;
+    }
+    years
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProjectionFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    code: Option<String>,
+    // Left empty for the campus's shortest program at the receipt's level of study.
+    #[serde(default, deserialize_with = "form::optional")]
+    total_credits: Option<form::Bounded<1, MAX_TOTAL_CREDITS>>,
+}
+
+#[derive(Serialize)]
+struct ProjectionFormPage {
+    campus: Campus,
+    form: ProjectionFormParams,
+    errors: Option<FormErrors>,
+}
+
+#[derive(Serialize)]
+struct MultiYearPage {
+    campus: Campus,
+    receipt: Receipt,
+    // Where the credits came from, when it was one of the campus's programs.
+    program: Option<String>,
+    programs: Vec<Program>,
+    total_credits: u32,
+    terms: u32,
+    annual_increase: Decimal,
+    years: Vec<ProjectedYear>,
+    total: Money,
+}
+
+// `/projection?code=..` fills in the receipt code, e.g. from the receipt page.
+pub async fn projection_form(state: web::Data<AppState>, campus: Campus, params: web::Query<ProjectionFormParams>) -> Result<HttpResponse, AppError> {
+    render(&state, "projection", &ProjectionFormPage { campus, form: params.into_inner(), errors: None }).await
+}
+
+pub async fn project_years(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: form::Submitted<ProjectionFormParams>) -> Result<HttpResponse, AppError> {
+    let form = params.into_inner();
+    match multi_year(&state, campus.clone(), &req, &form).await {
+        // The form again, as filled in, with the problem marked on its field.
+        Err(why @ AppError::Validation { .. }) if !negotiate::wants_json(&req) => {
+            println!("{}", why);
+            let mut response = render(&state, "projection", &ProjectionFormPage { campus, form, errors: FormErrors::from_error(&why) }).await?;
+            *response.status_mut() = actix_web::http::StatusCode::BAD_REQUEST;
+            Ok(response)
+        }
+        result => result,
+    }
+}
+
+// The receipt's term estimate carried across a whole program. The estimate is taken at the full
+// rate even if its own term was prorated, and orientation is only paid once.
+async fn multi_year(state: &AppState, campus: Campus, req: &HttpRequest, params: &ProjectionFormParams) -> Result<HttpResponse, AppError> {
+    let code = match &params.code {
+        Some(val) => val.clone(),
+        None => {
+            return Err(AppError::validation("code", "No receipt code was provided!"));
+        }
+    };
+    let receipt = receipts::fetch_receipt(state, &campus, &code).await?;
+    if receipt.num_credits == 0 {
+        return Err(AppError::validation("code", "This estimate has no credits to project from."));
+    }
+
+    let programs = state.programs(&campus, &receipt.student_studies).await?;
+    let (program, total_credits) = match &params.total_credits {
+        Some(val) => match val.value() {
+            Ok(credits) => (programs.iter().find(|program| program.total_credits == credits).map(|program| program.name.clone()), credits),
+            Err(why) => {
+                return Err(AppError::validation("total_credits", &format!("Credits to finish: {}", why)));
+            }
+        },
+        None => match programs.first() {
+            Some(program) => (Some(program.name.clone()), program.total_credits),
+            None => {
+                return Err(AppError::validation("total_credits", "Enter how many credits your program takes."));
+            }
+        },
+    };
+
+    let one_time_fees = if receipt.orientation { receipt.orientation_fee } else { Money::zero() };
+    let full_tuition = receipt.credits_cost * receipt.num_credits;
+    let per_term = receipt.tuition_cost - one_time_fees + full_tuition - pricing::charged_tuition(receipt.num_credits, receipt.credits_cost, receipt.tuition_percent);
+    let terms = total_credits.div_ceil(u32::from(receipt.num_credits));
+    let annual_increase = state.annual_rate_increase(&campus).await?;
+    let years = by_year(per_term, one_time_fees, terms, annual_increase);
+    let total = years.last().map(|year| year.cumulative).unwrap_or_default();
+
+    negotiate::respond(state, req, "projection_result", &MultiYearPage { campus, receipt, program, programs, total_credits, terms, annual_increase, years, total }).await
+}
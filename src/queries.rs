@@ -0,0 +1,935 @@
+use std::{future::Future, time::Instant};
+
+use crate::{metrics, slow_queries, telemetry};
+
+// Every SQL statement the app runs, by name, so they can be reviewed in one place and timed per
+// query on /metrics. Run one with `queries::NAME.run(|sql| sqlx::query(sql).bind(..).execute(pool))`,
+// or `queries::NAME.scoped(campus.id).run(|sql, campus_id| ..)` for a campus's rows.
+#[derive(Debug, Clone, Copy)]
+pub struct Query {
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Query {
+    // Build and run the query from its SQL, timing it.
+    pub async fn run<F, Fut>(self, run: F) -> Fut::Output
+    where
+        F: FnOnce(&'static str) -> Fut,
+        Fut: Future,
+    {
+        self.time(run(self.sql)).await
+    }
+
+    // Run the query for one campus, handing the campus's id to `run` to bind. Only for queries
+    // that filter on it; the rest are in CHECKED or aren't campus data at all. tests/tenant_isolation.rs
+    // checks every call in src/ against that, so this only checks again in debug builds.
+    pub fn scoped<C>(self, campus: C) -> Scoped<C> {
+        debug_assert!(self.scope() == Scope::Campus, "{} doesn't filter on CampusId.", self.name);
+        Scoped { query: self, campus }
+    }
+
+    // Time a query that's already built, e.g. with a `QueryBuilder` that starts from `sql`.
+    pub async fn time<Fut: Future>(self, query: Fut) -> Fut::Output {
+        let started = Instant::now();
+        let result = {
+            let _span = telemetry::query(self.name);
+            query.await
+        };
+        let elapsed = started.elapsed();
+        metrics::record_query(self.name, elapsed);
+        slow_queries::record(self.name, elapsed);
+        result
+    }
+}
+
+pub struct Scoped<C> {
+    query: Query,
+    campus: C,
+}
+
+impl<C> Scoped<C> {
+    pub async fn run<F, Fut>(self, run: F) -> Fut::Output
+    where
+        F: FnOnce(&'static str, C) -> Fut,
+        Fut: Future,
+    {
+        self.query.time(run(self.query.sql, self.campus)).await
+    }
+}
+
+// How a query keeps one campus's rows from another's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    // Filters on CampusId, or inserts rows with one.
+    Campus,
+    // Reaches campus rows only through something already looked up for the campus; see CHECKED.
+    Checked,
+    // Touches no campus's rows.
+    Global,
+    // Touches campus rows with nothing keeping it to one campus. No query should be this.
+    Unscoped,
+}
+
+// Tables whose rows belong to one campus, directly or through their student, receipt or term.
+pub const CAMPUS_TABLES: &[&str] = &[
+    "CreditCosts", "orientation_fee", "HealthInsuranceFee", "RateIncrease", "InternationalFees", "IndirectCosts",
+    "CourseFees", "Programs", "Terms", "ProrationRules", "RefundRules", "CustomLineItems", "ValidationRules",
+    "OrientationExemptions", "FormFields", "Students", "TuitionRecords", "EmailVerifications", "Receipts",
+    "ReceiptAdjustments", "RefundEstimates", "Scenarios", "ReciprocityAgreements", "StudentSignIns", "BulkDeletes",
+];
+
+// Queries on campus tables that don't filter on CampusId themselves. Each is only run with an id
+// that was first looked up with the campus, or, for the nightly jobs and the demo seed, across
+// every campus on purpose. A new one goes here only after checking its callers do the same.
+pub const CHECKED: &[&str] = &[
+    // By a student, receipt or term found with the campus.
+    "UPSERT_TUITION_RECORD", "INSERT_RECEIPT_ADJUSTMENT", "INSERT_REFUND_ESTIMATE", "CANCEL_EMAIL_VERIFICATIONS",
+    "INSERT_EMAIL_VERIFICATION", "COUNT_VERIFICATION_ATTEMPT", "CONFIRM_EMAIL_VERIFICATION", "SET_STUDENT_EMAIL",
+    "UPSERT_REFUND_RULE", "RECEIPT_ADJUSTMENTS", "STUDENT_RECORDS", "STUDENT_RECEIPTS", "STUDENT_REFUND_ESTIMATES",
+    "STUDENT_RECEIPT_ADJUSTMENTS", "CANCEL_STUDENT_SIGN_INS", "COUNT_STUDENT_SIGN_INS", "PRUNE_STUDENT_SIGN_INS",
+    "INSERT_STUDENT_SIGN_IN", "COUNT_SIGN_IN_ATTEMPT", "USE_STUDENT_SIGN_IN",
+    // By a record or rule fetched with the campus, on the admin pages.
+    "RENAME_STUDENT", "UPDATE_TUITION_COST", "DELETE_TUITION_RECORD", "DELETE_REFUND_RULE", "DELETE_LINE_ITEM",
+    "DELETE_VALIDATION_RULE", "DELETE_ORIENTATION_EXEMPTION", "DELETE_RECIPROCITY_AGREEMENT",
+    // The record search adds the CampusId condition as it builds the rest.
+    "SEARCH_RECORDS_COUNT", "SEARCH_RECORDS",
+    // Deleting and merging students, both fetched with the campus.
+    "COUNT_STUDENT_RECORDS", "COUNT_STUDENT_SCENARIOS", "ANONYMIZE_STUDENT_RECEIPTS", "DELETE_STUDENT_RECEIPTS",
+    "DELETE_STUDENT", "DROP_SUPERSEDED_RECORDS", "MOVE_STUDENT_RECORDS", "MOVE_STUDENT_RECEIPTS",
+    "MOVE_EMAIL_VERIFICATIONS", "MERGE_SCENARIO_NAMES", "CLASHING_SCENARIOS", "RENAME_SCENARIO", "MOVE_STUDENT_SCENARIOS",
+    "KEEP_STUDENT_EMAIL",
+    // Receipt codes are unique across campuses, so a new one is checked against all of them.
+    "RECEIPT_CODE_EXISTS",
+    // Retention runs over every campus's receipts.
+    "COUNT_EXPIRED_RECEIPTS", "ANONYMIZE_RECEIPTS", "DELETE_RECEIPTS_BEFORE",
+    // The demo seed, for a term or student it just created.
+    "SEED_PRORATION_RULE", "SEED_TUITION_RECORD",
+];
+
+impl Query {
+    // The tables the query reads or writes: whatever follows from, join, into or update.
+    pub fn tables(&self) -> Vec<&'static str> {
+        let words: Vec<&'static str> = self.sql
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|word| !word.is_empty() && !word.eq_ignore_ascii_case("ignore"))
+            .collect();
+        let mut tables = Vec::new();
+        for pair in words.windows(2) {
+            if ["from", "join", "into", "update"].iter().any(|keyword| pair[0].eq_ignore_ascii_case(keyword)) && !tables.contains(&pair[1]) {
+                tables.push(pair[1]);
+            }
+        }
+        tables
+    }
+
+    // Whether the query compares CampusId, or inserts rows with one. Only selecting the column
+    // doesn't count.
+    fn filters_on_campus(&self) -> bool {
+        if self.sql.trim_start().get(..6).is_some_and(|word| word.eq_ignore_ascii_case("insert")) {
+            return self.sql.contains("CampusId");
+        }
+        self.sql.match_indices("CampusId").any(|(at, column)| {
+            let rest = self.sql[at + column.len()..].trim_start();
+            rest.starts_with('=') || rest.get(..3).is_some_and(|word| word.eq_ignore_ascii_case("in ") || word.eq_ignore_ascii_case("in("))
+        })
+    }
+
+    pub fn scope(&self) -> Scope {
+        if !self.tables().iter().any(|table| CAMPUS_TABLES.contains(table)) {
+            Scope::Global
+        } else if self.filters_on_campus() {
+            Scope::Campus
+        } else if CHECKED.contains(&self.name) {
+            Scope::Checked
+        } else {
+            Scope::Unscoped
+        }
+    }
+}
+
+// The Receipt columns, qualified so they can be joined with Students.
+macro_rules! receipt_columns {
+    () => {
+        "Receipts.Id, Code, Receipts.CampusId, StudentId, Receipts.FirstName, Receipts.LastName, Term, NumCredits, Orientation, StudentType, StudentStudies,
+        CreditsCost, NonresidencyFee, OrientationFee, TuitionCost, Receipts.CreatedAt, EnrollmentDate, TuitionPercent, CourseCodes, CourseFees, InsuranceWaived,
+        HealthInsuranceFee, InternationalFees, CustomFees, CustomItems, EnteredBy"
+    };
+}
+
+macro_rules! queries {
+    ($($name:ident = $sql:expr;)*) => {
+        $(pub const $name: Query = Query { name: stringify!($name), sql: $sql };)*
+
+        // Every query above, for tests/tenant_isolation.rs.
+        #[allow(dead_code)]
+        pub const ALL: &[Query] = &[$($name),*];
+    };
+}
+
+queries! {
+    // Campuses.
+    CAMPUSES = "select Id, Slug, Name, Hostname, State
+        from Campuses
+        order by Id";
+
+    // Rates, when they come from the database rather than FEE_SCHEDULE_FILE.
+    CREDIT_COSTS = "SELECT CreditCosts.CreditsCost, CreditCosts.NonresidencyFee
+        FROM CreditCosts
+        WHERE CreditCosts.CampusId = ?
+        AND CreditCosts.Studies = ?
+        AND CreditCosts.Residency = ?";
+    ORIENTATION_FEE = "SELECT Fee
+        FROM orientation_fee
+        WHERE CampusId = ?";
+    HEALTH_INSURANCE_FEE = "SELECT Fee
+        FROM HealthInsuranceFee
+        WHERE CampusId = ?";
+    RATE_INCREASE = "SELECT AnnualPercent
+        FROM RateIncrease
+        WHERE CampusId = ?";
+    INTERNATIONAL_FEES = "SELECT Label, Amount
+        FROM InternationalFees
+        WHERE CampusId = ?
+        ORDER BY Id";
+    INDIRECT_COSTS = "SELECT Label, Amount
+        FROM IndirectCosts
+        WHERE CampusId = ?
+        AND Studies = ?
+        ORDER BY Id";
+    PROGRAMS = "SELECT Name, TotalCredits
+        FROM Programs
+        WHERE CampusId = ?
+        AND Studies = ?
+        ORDER BY TotalCredits, Name";
+    PRORATION_RULES = "SELECT Terms.StartsOn, ProrationRules.AfterWeek, ProrationRules.TuitionPercent
+        FROM Terms
+        JOIN ProrationRules ON ProrationRules.TermId = Terms.Id
+        WHERE Terms.CampusId = ?
+        AND Terms.Name = ?
+        ORDER BY ProrationRules.AfterWeek";
+    REFUND_RULES = "SELECT Terms.StartsOn, RefundRules.ThroughWeek, RefundRules.RefundPercent
+        FROM Terms
+        JOIN RefundRules ON RefundRules.TermId = Terms.Id
+        WHERE Terms.CampusId = ?
+        AND Terms.Name = ?
+        ORDER BY RefundRules.ThroughWeek";
+    TERM_NAMES = "SELECT Name
+        FROM (
+            SELECT Name, StartsOn
+            FROM Terms
+            WHERE CampusId = ?
+            UNION
+            SELECT DISTINCT Term, NULL
+            FROM CustomLineItems
+            WHERE CampusId = ?
+            AND Term NOT IN (SELECT Name FROM Terms WHERE CampusId = ?)
+        ) AS AllTerms
+        ORDER BY StartsOn IS NULL, StartsOn, Name";
+    TERM_WINDOWS = "SELECT Name, StartsOn, OpensOn, ClosesOn
+        FROM Terms
+        WHERE CampusId = ?
+        ORDER BY StartsOn";
+    LINE_ITEMS = "SELECT Label, Amount, AppliesWhen, Category
+        FROM CustomLineItems
+        WHERE CampusId = ?
+        AND Term = ?
+        ORDER BY Id";
+    VALIDATION_RULES = "SELECT Field, RejectWhen, Message
+        FROM ValidationRules
+        WHERE CampusId = ?
+        ORDER BY Id";
+    ORIENTATION_EXEMPTIONS = "SELECT Label, ExemptWhen
+        FROM OrientationExemptions
+        WHERE CampusId = ?
+        ORDER BY Id";
+    RECIPROCITY_AGREEMENTS = "SELECT State, Label, Residency, NonresidencyFeePercent
+        FROM ReciprocityAgreements
+        WHERE CampusId = ?
+        ORDER BY State";
+    COURSE_FEES = "SELECT Department, CourseCode, Label, Fee
+        FROM CourseFees
+        WHERE CampusId = ?
+        ORDER BY Department, CourseCode";
+
+    // Calculating and looking up totals.
+    RECORDS_BY_NAME = "select TuitionRecords.Id, StudentId, PublicId as StudentPublicId, CampusId, FirstName, LastName, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where CampusId = ?
+        and FirstName = ?
+        and LastName = ?
+        order by TuitionRecords.Id desc";
+    LATEST_RECORD_BY_NAME = "select TuitionRecords.Id, StudentId, PublicId as StudentPublicId, CampusId, FirstName, LastName, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where CampusId = ?
+        and FirstName = ?
+        and LastName = ?
+        order by TuitionRecords.Id desc
+        limit 1";
+    STUDENT_ID_BY_NAME = "select Id
+        from Students
+        where CampusId = ?
+        and FirstName = ?
+        and LastName = ?";
+    INSERT_STUDENT = "insert into Students
+        (CampusId, PublicId, FirstName, LastName)
+        VALUES
+        (?, ?, ?, ?)";
+    // The last placeholders are whether the student gave consent, then how many seconds ago the
+    // estimate was calculated (twice), which is more than zero when it's replayed after an outage.
+    // A replayed estimate doesn't overwrite a newer one, and one saved without consent being asked
+    // for keeps the time it was last given.
+    UPSERT_TUITION_RECORD = "insert into TuitionRecords
+        (StudentId, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived, ConsentedAt, UpdatedAt)
+        VALUES
+        (?, ?, ?, ?, ?, ?, ?, ?, if(?, current_timestamp - interval ? second, NULL), current_timestamp - interval ? second)
+        on duplicate key update
+        TuitionCost = if(UpdatedAt <= values(UpdatedAt), values(TuitionCost), TuitionCost),
+        NumCredits = if(UpdatedAt <= values(UpdatedAt), values(NumCredits), NumCredits),
+        Orientation = if(UpdatedAt <= values(UpdatedAt), values(Orientation), Orientation),
+        StudentType = if(UpdatedAt <= values(UpdatedAt), values(StudentType), StudentType),
+        StudentStudies = if(UpdatedAt <= values(UpdatedAt), values(StudentStudies), StudentStudies),
+        InsuranceWaived = if(UpdatedAt <= values(UpdatedAt), values(InsuranceWaived), InsuranceWaived),
+        ConsentedAt = if(UpdatedAt <= values(UpdatedAt), coalesce(values(ConsentedAt), ConsentedAt), ConsentedAt),
+        UpdatedAt = greatest(UpdatedAt, values(UpdatedAt))";
+    INSERT_RECEIPT = "insert into Receipts
+        (Code, CampusId, StudentId, FirstName, LastName, Term, NumCredits, Orientation, StudentType, StudentStudies,
+        CreditsCost, NonresidencyFee, OrientationFee, TuitionCost, EnrollmentDate, TuitionPercent, CourseCodes, CourseFees,
+        InsuranceWaived, HealthInsuranceFee, InternationalFees, CustomFees, CustomItems, ClientIpHash, UserAgent, Referrer, EnteredBy, OrientationAnswer, Channel, CreatedAt)
+        VALUES
+        (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, current_timestamp - interval ? second)";
+    RECEIPT_CODE_EXISTS = "select count(*)
+        from Receipts
+        where Code = ?";
+
+    // Receipts and refund estimates.
+    RECEIPT_BY_CODE = concat!("select ", receipt_columns!(), "
+        from Receipts
+        where Receipts.CampusId = ?
+        and Code = ?");
+    RECEIPT_BY_ID = concat!("select ", receipt_columns!(), "
+        from Receipts
+        where Receipts.CampusId = ?
+        and Receipts.Id = ?");
+    // Followed by the campus and the codes the visitor's cookie remembers, as bind parameters.
+    RECENT_RECEIPTS = concat!("select ", receipt_columns!(), ", Students.PublicId as StudentPublicId, Students.FirstName as StudentFirstName, Students.LastName as StudentLastName
+        from Receipts
+        join Students on Students.Id = Receipts.StudentId
+        where Receipts.CampusId = ");
+    STUDENT_RECEIPTS = concat!("select ", receipt_columns!(), "
+        from Receipts
+        where StudentId = ?
+        order by Receipts.Id");
+    // Followed by the receipt ids, as bind parameters.
+    RECEIPT_ADJUSTMENTS = "select ReceiptId, ItemLabel, OriginalAmount, AdjustedAmount, Note, AdjustedBy, CreatedAt
+        from ReceiptAdjustments
+        where ReceiptId in (";
+    INSERT_RECEIPT_ADJUSTMENT = "insert into ReceiptAdjustments
+        (ReceiptId, ItemLabel, OriginalAmount, AdjustedAmount, Note, AdjustedBy)
+        VALUES
+        (?, ?, ?, ?, ?, ?)";
+    INSERT_REFUND_ESTIMATE = "insert into RefundEstimates
+        (ReceiptId, WithdrawalDate, Week, RefundPercent, RefundAmount)
+        VALUES
+        (?, ?, ?, ?, ?)";
+
+    // Saved scenarios.
+    INSERT_SCENARIO = "insert into Scenarios
+        (CampusId, StudentId, ScenarioName, FirstName, LastName, NumCredits, NewStudent, Orientation, StudentType, StudentStudies, IncludeAdditionalCosts, CourseCodes, InsuranceWaived, HomeState)
+        VALUES
+        (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        on duplicate key update
+        FirstName = values(FirstName),
+        LastName = values(LastName),
+        NumCredits = values(NumCredits),
+        NewStudent = values(NewStudent),
+        Orientation = values(Orientation),
+        StudentType = values(StudentType),
+        StudentStudies = values(StudentStudies),
+        IncludeAdditionalCosts = values(IncludeAdditionalCosts),
+        CourseCodes = values(CourseCodes),
+        InsuranceWaived = values(InsuranceWaived),
+        HomeState = values(HomeState)";
+    SCENARIOS_FOR_STUDENT = "select Id, CampusId, ScenarioName, FirstName, LastName, NumCredits, NewStudent, Orientation, StudentType, StudentStudies, IncludeAdditionalCosts, CourseCodes, InsuranceWaived, HomeState
+        from Scenarios
+        where CampusId = ?
+        and StudentId = ?
+        order by ScenarioName";
+    SCENARIO_BY_ID = "select Id, CampusId, ScenarioName, FirstName, LastName, NumCredits, NewStudent, Orientation, StudentType, StudentStudies, IncludeAdditionalCosts, CourseCodes, InsuranceWaived, HomeState
+        from Scenarios
+        where Id = ?
+        and CampusId = ?
+        and StudentId = ?";
+    DELETE_SCENARIO = "delete from Scenarios
+        where Id = ?
+        and CampusId = ?
+        and StudentId = ?";
+
+    // Confirming the email address a student gives; see `verification`.
+    CANCEL_EMAIL_VERIFICATIONS = "delete from EmailVerifications
+        where StudentId = ?
+        and ConfirmedAt is null";
+    INSERT_EMAIL_VERIFICATION = "insert into EmailVerifications
+        (PublicId, StudentId, Email, CodeHash, ExpiresAt)
+        VALUES
+        (?, ?, ?, ?, ?)";
+    EMAIL_VERIFICATION = "select EmailVerifications.Id, StudentId, Email, CodeHash, Attempts, ExpiresAt, ConfirmedAt
+        from EmailVerifications
+        join Students on Students.Id = EmailVerifications.StudentId
+        where EmailVerifications.PublicId = ?
+        and Students.CampusId = ?
+        for update";
+    COUNT_VERIFICATION_ATTEMPT = "update EmailVerifications
+        set Attempts = Attempts + 1
+        where Id = ?";
+    CONFIRM_EMAIL_VERIFICATION = "update EmailVerifications
+        set ConfirmedAt = current_timestamp
+        where Id = ?";
+    SET_STUDENT_EMAIL = "update Students
+        set Email = ?
+        where Id = ?";
+
+    // Students signing in with a code sent to the address they confirmed; see `student_auth`.
+    // Two students who confirmed the same address sign in as the later one until they're merged.
+    STUDENT_BY_EMAIL = "select Id
+        from Students
+        where CampusId = ?
+        and Email = ?
+        order by Id desc
+        limit 1";
+    // Expired rather than deleted, so they still count towards the codes sent in the hour.
+    CANCEL_STUDENT_SIGN_INS = "update StudentSignIns
+        set ExpiresAt = ?
+        where StudentId = ?
+        and UsedAt is null
+        and ExpiresAt > ?";
+    COUNT_STUDENT_SIGN_INS = "select count(*)
+        from StudentSignIns
+        where StudentId = ?
+        and CreatedAt >= ?";
+    PRUNE_STUDENT_SIGN_INS = "delete from StudentSignIns
+        where StudentId = ?
+        and CreatedAt < ?";
+    INSERT_STUDENT_SIGN_IN = "insert into StudentSignIns
+        (PublicId, StudentId, CodeHash, ExpiresAt)
+        VALUES
+        (?, ?, ?, ?)";
+    STUDENT_SIGN_IN = "select StudentSignIns.Id, StudentId, FirstName, LastName, CodeHash, Attempts, ExpiresAt, UsedAt
+        from StudentSignIns
+        join Students on Students.Id = StudentSignIns.StudentId
+        where StudentSignIns.PublicId = ?
+        and Students.CampusId = ?
+        for update";
+    COUNT_SIGN_IN_ATTEMPT = "update StudentSignIns
+        set Attempts = Attempts + 1
+        where Id = ?";
+    USE_STUDENT_SIGN_IN = "update StudentSignIns
+        set UsedAt = current_timestamp
+        where Id = ?";
+
+    // API keys.
+    API_KEY_BY_HASH = "select Id, Name, RequestsPerMinute, CreatedAt, RevokedAt
+        from ApiKeys
+        where KeyHash = ?
+        and RevokedAt is null";
+    API_KEYS = "select Id, Name, RequestsPerMinute, CreatedAt, RevokedAt
+        from ApiKeys
+        order by Id";
+    INSERT_API_KEY = "insert into ApiKeys
+        (Name, KeyHash, RequestsPerMinute)
+        VALUES
+        (?, ?, ?)";
+    REVOKE_API_KEY = "update ApiKeys
+        set RevokedAt = current_timestamp
+        where Id = ?
+        and RevokedAt is null";
+
+    // Correcting saved records from the admin pages.
+    RECORD_BY_ID = "select TuitionRecords.Id, StudentId, PublicId as StudentPublicId, CampusId, FirstName, LastName, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where TuitionRecords.Id = ?
+        and CampusId = ?";
+    OTHER_STUDENT_WITH_NAME = "select Id
+        from Students
+        where CampusId = ?
+        and FirstName = ?
+        and LastName = ?
+        and Id <> ?";
+    RENAME_STUDENT = "update Students
+        set FirstName = ?,
+        LastName = ?
+        where Id = ?";
+    UPDATE_TUITION_COST = "update TuitionRecords
+        set TuitionCost = ?
+        where Id = ?";
+    DELETE_TUITION_RECORD = "delete from TuitionRecords
+        where Id = ?";
+
+    // Re-pricing saved totals with proposed rates, for a random sample or all of them.
+    SIMULATE_SAMPLE = "select TuitionRecords.Id, StudentId, PublicId as StudentPublicId, CampusId, FirstName, LastName, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where CampusId = ?
+        order by rand()
+        limit ?";
+    SIMULATE_ALL = "select TuitionRecords.Id, StudentId, PublicId as StudentPublicId, CampusId, FirstName, LastName, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where CampusId = ?";
+
+    // The record search; `filters::Conditions` adds the where clause, then ordering and paging.
+    SEARCH_RECORDS_COUNT = "select count(*)
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId";
+    SEARCH_RECORDS = "select TuitionRecords.Id, PublicId as StudentPublicId, FirstName, LastName, Term, TuitionCost, NumCredits, StudentType, StudentStudies, UpdatedAt
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId";
+
+    // Terms and refund schedules.
+    REFUND_SCHEDULE = "select RefundRules.Id, Terms.Name as Term, Terms.StartsOn, RefundRules.ThroughWeek, RefundRules.RefundPercent
+        from RefundRules
+        join Terms on Terms.Id = RefundRules.TermId
+        where Terms.CampusId = ?
+        order by Terms.StartsOn desc, RefundRules.ThroughWeek";
+    UPSERT_TERM_START = "insert into Terms
+        (CampusId, Name, StartsOn)
+        VALUES
+        (?, ?, ?)
+        on duplicate key update
+        StartsOn = values(StartsOn)";
+    TERM_ID = "select Id
+        from Terms
+        where CampusId = ?
+        and Name = ?";
+    UPSERT_REFUND_RULE = "insert into RefundRules
+        (TermId, ThroughWeek, RefundPercent)
+        VALUES
+        (?, ?, ?)
+        on duplicate key update
+        RefundPercent = values(RefundPercent)";
+    REFUND_RULE_BY_ID = "select RefundRules.Id, Terms.Name as Term, Terms.StartsOn, RefundRules.ThroughWeek, RefundRules.RefundPercent
+        from RefundRules
+        join Terms on Terms.Id = RefundRules.TermId
+        where RefundRules.Id = ?
+        and Terms.CampusId = ?";
+    DELETE_REFUND_RULE = "delete from RefundRules
+        where Id = ?";
+    UPSERT_TERM = "insert into Terms
+        (CampusId, Name, StartsOn, OpensOn, ClosesOn)
+        VALUES
+        (?, ?, ?, ?, ?)
+        on duplicate key update
+        StartsOn = values(StartsOn),
+        OpensOn = values(OpensOn),
+        ClosesOn = values(ClosesOn)";
+
+    // Custom line items, validation rules and orientation exemptions.
+    ADMIN_LINE_ITEMS = "select Id, Term, Label, Amount, AppliesWhen, Category
+        from CustomLineItems
+        where CampusId = ?
+        order by Term, Id";
+    INSERT_LINE_ITEM = "insert into CustomLineItems
+        (CampusId, Term, Label, Amount, AppliesWhen, Category)
+        VALUES
+        (?, ?, ?, ?, ?, ?)";
+    LINE_ITEM_BY_ID = "select Id, Term, Label, Amount, AppliesWhen, Category
+        from CustomLineItems
+        where Id = ?
+        and CampusId = ?";
+    DELETE_LINE_ITEM = "delete from CustomLineItems
+        where Id = ?";
+    ADMIN_VALIDATION_RULES = "select Id, Field, RejectWhen, Message
+        from ValidationRules
+        where CampusId = ?
+        order by Id";
+    INSERT_VALIDATION_RULE = "insert into ValidationRules
+        (CampusId, Field, RejectWhen, Message)
+        VALUES
+        (?, ?, ?, ?)";
+    VALIDATION_RULE_BY_ID = "select Id, Field, RejectWhen, Message
+        from ValidationRules
+        where Id = ?
+        and CampusId = ?";
+    DELETE_VALIDATION_RULE = "delete from ValidationRules
+        where Id = ?";
+    ADMIN_ORIENTATION_EXEMPTIONS = "select Id, Label, ExemptWhen
+        from OrientationExemptions
+        where CampusId = ?
+        order by Id";
+    INSERT_ORIENTATION_EXEMPTION = "insert into OrientationExemptions
+        (CampusId, Label, ExemptWhen)
+        VALUES
+        (?, ?, ?)";
+    ORIENTATION_EXEMPTION_BY_ID = "select Id, Label, ExemptWhen
+        from OrientationExemptions
+        where Id = ?
+        and CampusId = ?";
+    DELETE_ORIENTATION_EXEMPTION = "delete from OrientationExemptions
+        where Id = ?";
+    ADMIN_RECIPROCITY_AGREEMENTS = "select Id, State, Label, Residency, NonresidencyFeePercent
+        from ReciprocityAgreements
+        where CampusId = ?
+        order by State";
+    // Replacing a state's agreement keeps its Id, which last_insert_id then returns for the audit log.
+    UPSERT_RECIPROCITY_AGREEMENT = "insert into ReciprocityAgreements
+        (CampusId, State, Label, Residency, NonresidencyFeePercent)
+        VALUES
+        (?, ?, ?, ?, ?)
+        on duplicate key update
+        Id = last_insert_id(Id),
+        Label = values(Label),
+        Residency = values(Residency),
+        NonresidencyFeePercent = values(NonresidencyFeePercent)";
+    RECIPROCITY_AGREEMENT_BY_ID = "select Id, State, Label, Residency, NonresidencyFeePercent
+        from ReciprocityAgreements
+        where Id = ?
+        and CampusId = ?";
+    DELETE_RECIPROCITY_AGREEMENT = "delete from ReciprocityAgreements
+        where Id = ?";
+
+    // Which optional inputs the calculator form shows.
+    FORM_FIELDS = "select Field, Visible
+        from FormFields
+        where CampusId = ?";
+    SET_FORM_FIELD = "insert into FormFields
+        (CampusId, Field, Visible)
+        VALUES
+        (?, ?, ?)
+        on duplicate key update
+        Visible = values(Visible)";
+
+    // The audit log, maintenance mode and health checks.
+    INSERT_AUDIT_ENTRY = "insert into AuditLog
+        (Actor, Action, Entity, EntityId, Details)
+        VALUES
+        (?, ?, ?, ?, ?)";
+    MAINTENANCE = "select Enabled, Message
+        from Maintenance
+        where Id = 1";
+    SET_MAINTENANCE = "insert into Maintenance
+        (Id, Enabled, Message)
+        VALUES
+        (1, ?, ?)
+        on duplicate key update
+        Enabled = values(Enabled),
+        Message = values(Message)";
+    PING = "select 1";
+    SCHEMA_COLUMNS = "select TABLE_NAME as TableName, COLUMN_NAME as ColumnName
+        from information_schema.COLUMNS
+        where TABLE_SCHEMA = database()";
+    SCHEMA_INDEXES = "select TABLE_NAME as TableName, INDEX_NAME as IndexName, COLUMN_NAME as ColumnName
+        from information_schema.STATISTICS
+        where TABLE_SCHEMA = database()
+        order by TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX";
+    // What sqlx has recorded applying, for --plan-migrations; the table is missing on a new database.
+    MIGRATIONS_TABLE = "select count(*)
+        from information_schema.TABLES
+        where TABLE_SCHEMA = database() and TABLE_NAME = '_sqlx_migrations'";
+    APPLIED_MIGRATIONS = "select version, success, checksum
+        from _sqlx_migrations
+        order by version";
+
+    // Queries that ran past SLOW_QUERY_MS; see `slow_queries`.
+    INSERT_SLOW_QUERY = "insert into SlowQueries
+        (QueryName, DurationMs, RecordedAt)
+        VALUES
+        (?, ?, ?)";
+    DELETE_SLOW_QUERIES_BEFORE = "delete from SlowQueries where RecordedAt < ?";
+    CLEAR_SLOW_QUERIES = "delete from SlowQueries";
+    SLOW_QUERY_SUMMARY = "select QueryName, count(*) as Times, cast(avg(DurationMs) as unsigned) as AverageMs, max(DurationMs) as MaxMs, max(RecordedAt) as LastSeen
+        from SlowQueries
+        group by QueryName
+        order by sum(DurationMs) desc";
+    RECENT_SLOW_QUERIES = "select QueryName, DurationMs, RecordedAt
+        from SlowQueries
+        order by Id desc
+        limit ?";
+
+    // Exports.
+    EXPORT_RECORDS_PAGE = "select TuitionRecords.Id, PublicId as StudentPublicId, FirstName, LastName, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived, TuitionRecords.UpdatedAt
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where CampusId = ?
+        and TuitionRecords.Id > ?
+        order by TuitionRecords.Id
+        limit ?";
+    STUDENT_BY_PUBLIC_ID = "select Id, PublicId, FirstName, LastName, Email, CreatedAt
+        from Students
+        where CampusId = ?
+        and PublicId = ?";
+    STUDENT_RECORDS = "select TuitionRecords.Id, StudentId, PublicId as StudentPublicId, CampusId, FirstName, LastName, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where StudentId = ?
+        order by TuitionRecords.Id";
+    STUDENT_REFUND_ESTIMATES = "select ReceiptId, WithdrawalDate, Week, RefundPercent, RefundAmount, RefundEstimates.CreatedAt
+        from RefundEstimates
+        join Receipts on Receipts.Id = RefundEstimates.ReceiptId
+        where Receipts.StudentId = ?
+        order by RefundEstimates.Id";
+    STUDENT_RECEIPT_ADJUSTMENTS = "select ReceiptId, ItemLabel, OriginalAmount, AdjustedAmount, Note, AdjustedBy, ReceiptAdjustments.CreatedAt
+        from ReceiptAdjustments
+        join Receipts on Receipts.Id = ReceiptAdjustments.ReceiptId
+        where Receipts.StudentId = ?
+        order by ReceiptAdjustments.Id";
+
+    // Deleting a student at their request; see `students::delete`.
+    STUDENT_TO_DELETE = "select Id, FirstName, LastName
+        from Students
+        where CampusId = ?
+        and PublicId = ?
+        for update";
+    COUNT_STUDENT_RECORDS = "select count(*)
+        from TuitionRecords
+        where StudentId = ?";
+    ANONYMIZE_STUDENT_RECEIPTS = "update Receipts
+        set FirstName = ?, LastName = ?, ClientIpHash = NULL, UserAgent = NULL, Referrer = NULL
+        where StudentId = ?";
+    DELETE_STUDENT_RECEIPTS = "delete from Receipts
+        where StudentId = ?";
+    COUNT_STUDENT_SCENARIOS = "select count(*)
+        from Scenarios
+        where StudentId = ?";
+    DELETE_STUDENT = "delete from Students
+        where Id = ?";
+
+    // Merging duplicate students; see `students::merge`.
+    DUPLICATE_CANDIDATES = "select PublicId, FirstName, LastName, Email, CreatedAt,
+        (select count(*) from TuitionRecords where TuitionRecords.StudentId = Students.Id) as Records,
+        (select count(*) from Receipts where Receipts.StudentId = Students.Id) as Receipts
+        from Students
+        where CampusId = ?
+        order by Id";
+    STUDENT_TO_MERGE = "select Id, PublicId, FirstName, LastName, Email
+        from Students
+        where CampusId = ?
+        and PublicId = ?
+        for update";
+    // The two students' ids twice, then the kept one's to break a tie.
+    DROP_SUPERSEDED_RECORDS = "delete Older
+        from TuitionRecords as Older
+        join TuitionRecords as Newer on Newer.Term = Older.Term
+        where Older.StudentId in (?, ?)
+        and Newer.StudentId in (?, ?)
+        and Newer.StudentId <> Older.StudentId
+        and (Newer.UpdatedAt > Older.UpdatedAt or (Newer.UpdatedAt = Older.UpdatedAt and Newer.StudentId = ?))";
+    MOVE_STUDENT_RECORDS = "update TuitionRecords
+        set StudentId = ?
+        where StudentId = ?";
+    MOVE_STUDENT_RECEIPTS = "update Receipts
+        set StudentId = ?
+        where StudentId = ?";
+    MOVE_EMAIL_VERIFICATIONS = "update EmailVerifications
+        set StudentId = ?
+        where StudentId = ?";
+    // Both students' scenario names, and which of the other's clash with the kept one's: the kept
+    // student's id, then the other's.
+    MERGE_SCENARIO_NAMES = "select ScenarioName
+        from Scenarios
+        where StudentId in (?, ?)";
+    CLASHING_SCENARIOS = "select Other.Id, Other.ScenarioName
+        from Scenarios as Other
+        join Scenarios as Kept on Kept.ScenarioName = Other.ScenarioName
+        where Kept.StudentId = ?
+        and Other.StudentId = ?
+        order by Other.Id";
+    RENAME_SCENARIO = "update Scenarios
+        set ScenarioName = ?
+        where Id = ?";
+    // Clashing names are renamed first; see `students::merge`.
+    MOVE_STUDENT_SCENARIOS = "update Scenarios
+        set StudentId = ?, FirstName = ?, LastName = ?
+        where StudentId = ?";
+    KEEP_STUDENT_EMAIL = "update Students
+        set Email = coalesce(Email, ?)
+        where Id = ?";
+
+    // Clearing a term or a range of dates at once; see `bulk_delete`. Each condition after the
+    // campus is skipped when its value is null: the term, then the start and end of the range,
+    // each bound twice.
+    COUNT_BULK_RECEIPTS = "select count(*)
+        from Receipts
+        where CampusId = ?
+        and (? is null or Term = ?)
+        and (? is null or CreatedAt >= ?)
+        and (? is null or CreatedAt < ?)";
+    COUNT_BULK_RECORDS = "select count(*)
+        from TuitionRecords
+        join Students on Students.Id = TuitionRecords.StudentId
+        where CampusId = ?
+        and (? is null or Term = ?)
+        and (? is null or TuitionRecords.UpdatedAt >= ?)
+        and (? is null or TuitionRecords.UpdatedAt < ?)";
+    // Students who'd have nothing left once the selection is gone, so none outside it: the
+    // selection for receipts and for records, then both again.
+    COUNT_BULK_STUDENTS = "select count(*)
+        from Students
+        where CampusId = ?
+        and (exists (select 1 from Receipts where Receipts.StudentId = Students.Id
+                and (? is null or Term = ?) and (? is null or CreatedAt >= ?) and (? is null or CreatedAt < ?))
+            or exists (select 1 from TuitionRecords where TuitionRecords.StudentId = Students.Id
+                and (? is null or Term = ?) and (? is null or UpdatedAt >= ?) and (? is null or UpdatedAt < ?)))
+        and not exists (select 1 from Receipts where Receipts.StudentId = Students.Id
+            and not ((? is null or Term = ?) and (? is null or CreatedAt >= ?) and (? is null or CreatedAt < ?)))
+        and not exists (select 1 from TuitionRecords where TuitionRecords.StudentId = Students.Id
+            and not ((? is null or Term = ?) and (? is null or UpdatedAt >= ?) and (? is null or UpdatedAt < ?)))";
+    // The students of the rows the next batch takes, locked so it takes the same ones. When
+    // anonymizing, the name it leaves is bound twice after the campus; null when purging.
+    BULK_RECEIPT_STUDENTS = "select StudentId
+        from Receipts
+        where CampusId = ?
+        and (? is null or StudentId is not null or FirstName <> ?)
+        and (? is null or Term = ?)
+        and (? is null or CreatedAt >= ?)
+        and (? is null or CreatedAt < ?)
+        order by Id
+        limit ?
+        for update";
+    BULK_RECORD_STUDENTS = "select StudentId
+        from TuitionRecords
+        where StudentId in (select Id from Students where CampusId = ?)
+        and (? is null or Term = ?)
+        and (? is null or UpdatedAt >= ?)
+        and (? is null or UpdatedAt < ?)
+        order by Id
+        limit ?
+        for update";
+    ANONYMIZE_BULK_RECEIPTS = "update Receipts
+        set FirstName = ?, LastName = ?, StudentId = NULL, ClientIpHash = NULL, UserAgent = NULL, Referrer = NULL
+        where CampusId = ?
+        and (StudentId is not null or FirstName <> ?)
+        and (? is null or Term = ?)
+        and (? is null or CreatedAt >= ?)
+        and (? is null or CreatedAt < ?)
+        order by Id
+        limit ?";
+    DELETE_BULK_RECEIPTS = "delete from Receipts
+        where CampusId = ?
+        and (? is null or Term = ?)
+        and (? is null or CreatedAt >= ?)
+        and (? is null or CreatedAt < ?)
+        order by Id
+        limit ?";
+    DELETE_BULK_RECORDS = "delete from TuitionRecords
+        where StudentId in (select Id from Students where CampusId = ?)
+        and (? is null or Term = ?)
+        and (? is null or UpdatedAt >= ?)
+        and (? is null or UpdatedAt < ?)
+        order by Id
+        limit ?";
+    // Of the students a batch took rows from, the ones with nothing left to them; their scenarios,
+    // sign-ins and email confirmations go with them. Followed by the campus and then their ids, as
+    // bind parameters.
+    DELETE_LEFTOVER_STUDENTS = "delete from Students
+        where not exists (select 1 from TuitionRecords where TuitionRecords.StudentId = Students.Id)
+        and not exists (select 1 from Receipts where Receipts.StudentId = Students.Id)
+        and CampusId = ";
+    // The jobs themselves. Starting one fails on RunningCampusId while another runs at the campus.
+    START_BULK_DELETE = "insert into BulkDeletes
+        (CampusId, Selection, Action, Actor, ReceiptsTotal, RecordsTotal, StartedAt, UpdatedAt)
+        values
+        (?, ?, ?, ?, ?, ?, ?, ?)";
+    BULK_DELETE_PROGRESS = "update BulkDeletes
+        set ReceiptsDone = ?, RecordsDone = ?, StudentsDone = ?, Batches = ?, UpdatedAt = ?
+        where CampusId = ? and Id = ?";
+    FINISH_BULK_DELETE = "update BulkDeletes
+        set FinishedAt = ?, UpdatedAt = ?, Error = ?
+        where CampusId = ? and Id = ? and FinishedAt is null";
+    // One that hasn't moved since before the given time, e.g. because the server running it went
+    // down, is finished with the error given, so another can start.
+    STOP_STALE_BULK_DELETES = "update BulkDeletes
+        set FinishedAt = ?, Error = ?
+        where CampusId = ? and FinishedAt is null and UpdatedAt < ?";
+    LATEST_BULK_DELETE = "select Selection, Action, Actor, StartedAt, ReceiptsTotal, RecordsTotal, ReceiptsDone, RecordsDone, StudentsDone, Batches, FinishedAt, Error
+        from BulkDeletes
+        where CampusId = ?
+        order by Id desc
+        limit 1";
+
+    // Retention and statistics.
+    COUNT_EXPIRED_RECEIPTS = "select count(*)
+        from Receipts
+        where CreatedAt < ?
+        and (? is null or FirstName <> ?)";
+    ANONYMIZE_RECEIPTS = "update Receipts
+        set FirstName = ?, LastName = ?, ClientIpHash = NULL, UserAgent = NULL, Referrer = NULL
+        where CreatedAt < ?
+        and FirstName <> ?";
+    DELETE_RECEIPTS_BEFORE = "delete from Receipts
+        where CreatedAt < ?";
+    CALCULATION_SOURCES = "select Referrer, EnteredBy
+        from Receipts
+        where CampusId = ?
+        and CreatedAt >= ?
+        and CreatedAt < ?
+        and (Referrer is not null or EnteredBy is not null)";
+    RECEIPT_STATS = "select count(*), avg(TuitionCost)
+        from Receipts
+        where CampusId = ?
+        and CreatedAt >= ?
+        and CreatedAt < ?";
+    ORIENTATION_ANSWERS = "select cast(coalesce(sum(OrientationAnswer = true), 0) as signed), cast(coalesce(sum(OrientationAnswer = false), 0) as signed)
+        from Receipts
+        where CampusId = ?
+        and CreatedAt >= ?
+        and CreatedAt < ?";
+    CALCULATION_CHANNELS = "select Channel, count(*)
+        from Receipts
+        where CampusId = ?
+        and CreatedAt >= ?
+        and CreatedAt < ?
+        and Channel is not null
+        group by Channel
+        order by Channel";
+    FIRST_TIME_STUDENTS = "select count(*)
+        from (
+            select StudentId
+            from Receipts
+            where CampusId = ?
+            and StudentId is not null
+            group by StudentId
+            having min(CreatedAt) >= ?
+            and min(CreatedAt) < ?
+        ) as FirstCalculations";
+
+    // Seeding a development database.
+    SEED_CREDIT_COST = "insert into CreditCosts
+        (CampusId, Studies, Residency, CreditsCost, NonresidencyFee)
+        VALUES
+        (?, ?, ?, ?, ?)
+        on duplicate key update
+        CreditsCost = values(CreditsCost),
+        NonresidencyFee = values(NonresidencyFee)";
+    SEED_ORIENTATION_FEE = "insert into orientation_fee (CampusId, Fee) VALUES (?, ?) on duplicate key update Fee = values(Fee)";
+    SEED_HEALTH_INSURANCE_FEE = "insert into HealthInsuranceFee (CampusId, Fee) VALUES (?, ?) on duplicate key update Fee = values(Fee)";
+    SET_RATE_INCREASE = "insert into RateIncrease (CampusId, AnnualPercent) VALUES (?, ?) on duplicate key update AnnualPercent = values(AnnualPercent)";
+    CLEAR_PROGRAMS = "delete from Programs where CampusId = ?";
+    INSERT_PROGRAM = "insert into Programs (CampusId, Studies, Name, TotalCredits) VALUES (?, ?, ?, ?)";
+    CLEAR_INTERNATIONAL_FEES = "delete from InternationalFees where CampusId = ?";
+    INSERT_INTERNATIONAL_FEE = "insert into InternationalFees (CampusId, Label, Amount) VALUES (?, ?, ?)";
+    CLEAR_INDIRECT_COSTS = "delete from IndirectCosts where CampusId = ?";
+    INSERT_INDIRECT_COST = "insert into IndirectCosts (CampusId, Studies, Label, Amount) VALUES (?, ?, ?, ?)";
+    SEED_COURSE_FEE = "insert into CourseFees
+        (CampusId, Department, CourseCode, Label, Fee)
+        VALUES
+        (?, ?, ?, ?, ?)
+        on duplicate key update
+        Department = values(Department),
+        Label = values(Label),
+        Fee = values(Fee)";
+    SEED_PRORATION_RULE = "insert into ProrationRules (TermId, AfterWeek, TuitionPercent) VALUES (?, ?, ?) on duplicate key update TuitionPercent = values(TuitionPercent)";
+    CLEAR_LINE_ITEMS = "delete from CustomLineItems where CampusId = ? and Term = ?";
+    SEED_STUDENT = "insert ignore into Students (CampusId, PublicId, FirstName, LastName) VALUES (?, ?, ?, ?)";
+    SEED_TUITION_RECORD = "insert into TuitionRecords
+        (StudentId, Term, TuitionCost, NumCredits, Orientation, StudentType, StudentStudies, InsuranceWaived, UpdatedAt)
+        VALUES
+        (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+    SEED_RECEIPT = "insert into Receipts
+        (Code, CampusId, StudentId, FirstName, LastName, Term, NumCredits, Orientation, StudentType, StudentStudies,
+        CreditsCost, NonresidencyFee, OrientationFee, TuitionCost, CreatedAt, InsuranceWaived, HealthInsuranceFee, InternationalFees)
+        VALUES
+        (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+}
@@ -0,0 +1,75 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, HttpResponse, Result,
+};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{client_ip, error::ErrorCode, AppState};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Past this many callers, ones whose window is over are dropped, so a crowd of addresses can't
+// grow the map for good.
+const PRUNE_AT: usize = 10_000;
+
+// Counts requests per caller in fixed one-minute windows: per API key on the keyed API, and per
+// client address on /api/v1/rates.
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    windows: Mutex<HashMap<K, (Instant, u32)>>,
+}
+
+impl<K> Default for RateLimiter<K> {
+    fn default() -> RateLimiter<K> {
+        RateLimiter { windows: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    // Returns false when the caller has used up its requests for the current window.
+    pub fn check(&self, key: K, requests_per_minute: u32) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        let now = Instant::now();
+        if windows.len() >= PRUNE_AT {
+            windows.retain(|_, window| now.duration_since(window.0) < WINDOW);
+        }
+        let window = windows.entry(key).or_insert((now, 0));
+        if now.duration_since(window.0) >= WINDOW {
+            *window = (now, 0);
+        }
+        if window.1 >= requests_per_minute {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+// Middleware for /api/v1/rates, /api/v1/preview and /api/graphql, which need no key:
+// RATES_REQUESTS_PER_MINUTE per client address across them, so a widget reloading in a loop, or
+// someone scraping, can't tie up the rate lookups. Clients
+// whose address isn't known share one allowance rather than going unlimited.
+pub async fn limit_rates(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<EitherBody<impl MessageBody>>> {
+    let allowed = match req.app_data::<web::Data<AppState>>() {
+        Some(state) => state.rates_limiter.check(client_ip::for_request(req.request()), state.rates_requests_per_minute),
+        None => true,
+    };
+    if !allowed {
+        // As the keyed API answers a key over its limit.
+        let response = HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, WINDOW.as_secs().to_string()))
+            .json(json!({ "code": ErrorCode::RateLimited, "error": "Rate limit exceeded." }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
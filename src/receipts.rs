@@ -0,0 +1,167 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Datelike, NaiveDate};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{adjustments, config::LetterheadConfig, error::AppError, form, mailer::Mailer, models::{Campus, Receipt, ReceiptId}, pricing::CategorySubtotal, queries, recent, render, renderer, AppState};
+
+// No 0/O or 1/I, so a code read over the phone comes out right.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 10;
+
+pub fn new_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LENGTH)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+// The term a calculation made on this date is for: Spring runs January to May, Summer June
+// and July, Fall August to December.
+pub fn term_for(date: NaiveDate) -> String {
+    let season = match date.month() {
+        1..=5 => "Spring",
+        6..=7 => "Summer",
+        _ => "Fall",
+    };
+    format!("{} {}", season, date.year())
+}
+
+// Look up a receipt by the code printed on it. Codes are case-insensitive.
+pub async fn fetch_receipt(state: &AppState, campus: &Campus, code: &str) -> Result<Receipt, AppError> {
+    let code = code.trim().to_ascii_uppercase();
+
+    match queries::RECEIPT_BY_CODE.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, Receipt>(sql)
+    .bind(campus_id)
+    .bind(&code)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => Ok(val),
+        Ok(None) => Err(AppError::NotFound(format!("No receipt was found with code {}.", code))),
+        Err(why) => Err(AppError::from(why)),
+    }
+}
+
+// For a shared link, which identifies the receipt by id rather than by its code.
+pub async fn fetch_receipt_by_id(state: &AppState, campus: &Campus, id: ReceiptId) -> Result<Receipt, AppError> {
+    match queries::RECEIPT_BY_ID.run(|sql| sqlx::query_as::<_, Receipt>(sql)
+    .bind(campus.id)
+    .bind(id)
+    .fetch_optional(&state.conn)).await {
+        Ok(Some(val)) => Ok(val),
+        Ok(None) => Err(AppError::NotFound("This shared estimate no longer exists.".to_string())),
+        Err(why) => Err(AppError::from(why)),
+    }
+}
+
+#[derive(Serialize)]
+struct ReceiptPage {
+    #[serde(flatten)]
+    receipt: Receipt,
+    categories: Vec<CategorySubtotal>,
+    // Set once a counselor has adjusted a charge; shown below the original.
+    revision: Option<adjustments::Revision>,
+    // Only offered when SMTP is set up.
+    can_email: bool,
+    emailed_to: Option<String>,
+}
+
+pub async fn receipt(state: web::Data<AppState>, campus: Campus, code: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let receipt = fetch_receipt(&state, &campus, &code).await?;
+    let categories = recent::breakdown(&receipt);
+    let revision = state.revision(&receipt, &categories).await?;
+    render(&state, "receipt", &ReceiptPage { receipt, categories, revision, can_email: state.mailer.is_some(), emailed_to: None }).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EmailReceiptParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    email: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EstimateEmail<'a> {
+    campus: &'a Campus,
+    receipt: &'a Receipt,
+    categories: &'a [CategorySubtotal],
+    url: &'a str,
+    // BRAND_LOGO made absolute; mail clients have no page to resolve a path against.
+    logo_url: Option<String>,
+}
+
+// The same breakdown for mail clients that don't show HTML.
+fn estimate_text(campus: &Campus, receipt: &Receipt, categories: &[CategorySubtotal], url: &str, contact_lines: &[String]) -> String {
+    let mut body = format!(
+        "{} tuition estimate {}\n\nName: {} {}\nTerm: {}\nCalculated: {}\n",
+        campus.name, receipt.code, receipt.first_name, receipt.last_name, receipt.term, receipt.created_at,
+    );
+    for category in categories {
+        body += &format!("\n{}\n", category.label);
+        for item in &category.items {
+            body += &format!("  {}: {}\n", item.label, item.amount.format());
+        }
+        body += &format!("  {} subtotal: {}\n", category.label, category.subtotal.format());
+    }
+    body += &format!(
+        "\nTotal: {}\n\nThese are the rates in effect when this estimate was made. Current rates may differ.\nView this estimate online at {} with the code {}.\n",
+        receipt.tuition_cost.format(), url, receipt.code,
+    );
+    if !contact_lines.is_empty() {
+        body += &format!("\n{}\n", contact_lines.join("\n"));
+    }
+    body
+}
+
+// Send the receipt's breakdown to an address the student gives, rendered from the same
+// partial as the page.
+pub async fn email_receipt(state: web::Data<AppState>, campus: Campus, req: HttpRequest, code: web::Path<String>, params: web::Form<EmailReceiptParams>) -> Result<HttpResponse, AppError> {
+    let mailer = match &state.mailer {
+        Some(val) => val,
+        None => {
+            return Err(AppError::NotFound("Estimates can't be emailed from this site.".to_string()));
+        }
+    };
+    let email = match &params.email {
+        Some(val) if Mailer::valid_address(val) => val.clone(),
+        Some(val) => {
+            return Err(AppError::validation("email", &format!("\"{}\" is not a valid email address.", val)));
+        }
+        None => {
+            return Err(AppError::validation("email", "No email address was provided!"));
+        }
+    };
+    let receipt = fetch_receipt(&state, &campus, &code).await?;
+    let categories = recent::breakdown(&receipt);
+    let origin = {
+        let info = req.connection_info();
+        format!("{}://{}", info.scheme(), info.host())
+    };
+    let url = format!("{}/receipt/{}", origin, receipt.code);
+    let logo_url = state.branding.logo.as_ref().map(|logo| {
+        if logo.starts_with('/') { format!("{}{}", origin, logo) } else { logo.clone() }
+    });
+
+    let html = renderer::email(&state, "email_estimate", &EstimateEmail { campus: &campus, receipt: &receipt, categories: &categories, url: &url, logo_url })?;
+    let text = estimate_text(&campus, &receipt, &categories, &url, &state.branding.contact_lines);
+    let subject = format!("Your {} tuition estimate for {}", campus.name, receipt.term);
+    if let Err(why) = mailer.send_html(std::slice::from_ref(&email), &subject, &text, &html).await {
+        return Err(AppError::Internal(why));
+    }
+
+    let revision = state.revision(&receipt, &categories).await?;
+    render(&state, "receipt", &ReceiptPage { receipt, categories, revision, can_email: true, emailed_to: Some(email) }).await
+}
+
+#[derive(Serialize)]
+struct PrintReceiptPage<'a> {
+    campus: Campus,
+    letterhead: &'a LetterheadConfig,
+    receipt: Receipt,
+    categories: Vec<CategorySubtotal>,
+}
+
+// The receipt on the school's letterhead, without the navigation, for printing.
+pub async fn print_receipt(state: web::Data<AppState>, campus: Campus, code: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let receipt = fetch_receipt(&state, &campus, &code).await?;
+    let categories = recent::breakdown(&receipt);
+    render(&state, "receipt_print", &PrintReceiptPage { campus, letterhead: &state.letterhead, receipt, categories }).await
+}
@@ -0,0 +1,231 @@
+use actix_session::{
+    config::CookieContentSecurity,
+    storage::{CookieSessionStore, LoadError, SaveError, SessionKey, SessionStore, UpdateError},
+    Session, SessionMiddleware,
+};
+use actix_web::cookie::{time::Duration, Key};
+use serde::Serialize;
+use sqlx::{MySql, QueryBuilder};
+use std::collections::HashMap;
+#[cfg(feature = "redis")]
+use std::sync::Arc;
+
+#[cfg(feature = "redis")]
+use crate::redis_store::{self, Redis};
+
+use crate::{adjustments::{self, Revision}, error::AppError, models::{Campus, Receipt}, money::Money, pricing::{self, BreakdownItem, CategorySubtotal, FeeCategory}, queries, AppState};
+
+const RECENT_KEY: &str = "recent_receipts";
+const MAX_RECENT: usize = 5;
+
+// Where sessions live. By default the whole session is in a signed cookie, so nothing is stored
+// server-side for visitors. With REDIS_URL the cookie only names a session kept in Redis, so
+// every replica behind the load balancer sees the same one.
+#[derive(Clone, Default)]
+pub struct SessionBackend {
+    #[cfg(feature = "redis")]
+    pub redis: Option<Arc<Redis>>,
+}
+
+impl SessionStore for SessionBackend {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<HashMap<String, String>>, LoadError> {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            return redis_store::load_session(redis, session_key).await;
+        }
+        CookieSessionStore::default().load(session_key).await
+    }
+
+    async fn save(&self, session_state: HashMap<String, String>, ttl: &Duration) -> Result<SessionKey, SaveError> {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            return redis_store::save_session(redis, session_state, ttl).await;
+        }
+        CookieSessionStore::default().save(session_state, ttl).await
+    }
+
+    async fn update(&self, session_key: SessionKey, session_state: HashMap<String, String>, ttl: &Duration) -> Result<SessionKey, UpdateError> {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            return redis_store::update_session(redis, session_key, session_state, ttl).await;
+        }
+        CookieSessionStore::default().update(session_key, session_state, ttl).await
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            return redis_store::update_session_ttl(redis, session_key, ttl).await;
+        }
+        CookieSessionStore::default().update_ttl(session_key, ttl).await
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            return redis_store::delete_session(redis, session_key).await;
+        }
+        CookieSessionStore::default().delete(session_key).await
+    }
+}
+
+pub fn session_middleware(key: Key, backend: SessionBackend) -> SessionMiddleware<SessionBackend> {
+    SessionMiddleware::builder(backend, key)
+        .cookie_content_security(CookieContentSecurity::Signed)
+        .cookie_name("tuition_session".to_string())
+        .build()
+}
+
+fn codes(session: &Session) -> Vec<String> {
+    match session.get::<Vec<String>>(RECENT_KEY) {
+        Ok(Some(val)) => val,
+        // A missing or tampered cookie just means no history.
+        _ => Vec::new(),
+    }
+}
+
+// Whether this browser has made any estimates, without looking them up.
+pub fn has_recent(session: &Session) -> bool {
+    !codes(session).is_empty()
+}
+
+// Remember a receipt code in the visitor's cookie, newest first.
+pub fn remember(session: &Session, code: &str) {
+    let mut recent = codes(session);
+    recent.retain(|val| val != code);
+    recent.insert(0, code.to_string());
+    recent.truncate(MAX_RECENT);
+    if let Err(why) = session.insert(RECENT_KEY, recent) {
+        println!("Error while saving recent estimates to the session: {}", why);
+    }
+}
+
+// A receipt with the student it's for, from one joined query.
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct HistoryRow {
+    #[sqlx(flatten)]
+    receipt: Receipt,
+    student_public_id: String,
+    student_first_name: String,
+    student_last_name: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Calculation {
+    #[serde(flatten)]
+    pub receipt: Receipt,
+    pub categories: Vec<CategorySubtotal>,
+    // Set once a counselor has adjusted a charge.
+    pub revision: Option<Revision>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StudentHistory {
+    pub public_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    // Newest first.
+    pub calculations: Vec<Calculation>,
+}
+
+// The history page: everyone this browser has made estimates for, the student with the newest
+// estimate first.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct History {
+    pub students: Vec<StudentHistory>,
+}
+
+// What the receipt's total is made of, by category. Everything needed is stored on the receipt
+// itself; receipts from before custom items were kept one by one show their total as one charge.
+pub fn breakdown(receipt: &Receipt) -> Vec<CategorySubtotal> {
+    let mut items = vec![BreakdownItem {
+        label: match receipt.tuition_percent {
+            Some(percent) => format!("Tuition, {} credit(s) at {}%", receipt.num_credits, percent),
+            None => format!("Tuition, {} credit(s)", receipt.num_credits),
+        },
+        amount: pricing::charged_tuition(receipt.num_credits, receipt.credits_cost, receipt.tuition_percent),
+        category: FeeCategory::Tuition,
+    }];
+    let mut push = |label: &str, amount: Money, category: FeeCategory| {
+        if !amount.is_zero() {
+            items.push(BreakdownItem { label: label.to_string(), amount, category });
+        }
+    };
+    push("Non-residency fee", receipt.nonresidency_fee, FeeCategory::MandatoryFees);
+    push("Lab and course fees", receipt.course_fees, FeeCategory::MandatoryFees);
+    if !receipt.insurance_waived {
+        push("Health insurance", receipt.health_insurance_fee, FeeCategory::MandatoryFees);
+    }
+    push("International student fees", receipt.international_fees, FeeCategory::MandatoryFees);
+    if receipt.orientation {
+        push("Orientation fee", receipt.orientation_fee, FeeCategory::OptionalFees);
+    }
+    match receipt.custom_items.as_deref().map(serde_json::from_str::<Vec<BreakdownItem>>) {
+        Some(Ok(val)) => items.extend(val),
+        Some(Err(why)) => {
+            println!("Error while reading the custom line items on receipt {}: {}", receipt.code, why);
+            push("Other charges", receipt.custom_fees.unwrap_or_default(), FeeCategory::MandatoryFees);
+        }
+        None => push("Other charges", receipt.custom_fees.unwrap_or_default(), FeeCategory::MandatoryFees),
+    }
+    pricing::categorize(items)
+}
+
+impl AppState {
+    // The estimates this browser made at this campus, in one query, newest first. Codes whose
+    // receipts have since been removed are skipped.
+    async fn remembered(&self, campus: &Campus, session: &Session) -> Result<Vec<HistoryRow>, AppError> {
+        let codes = codes(session);
+        if codes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(queries::RECENT_RECEIPTS.sql);
+        query.push_bind(campus.id);
+        query.push(" and Code in (");
+        let mut separated = query.separated(", ");
+        for code in &codes {
+            separated.push_bind(code);
+        }
+        separated.push_unseparated(")");
+
+        let mut rows = match queries::RECENT_RECEIPTS.time(query.build_query_as::<HistoryRow>().fetch_all(&self.conn)).await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(AppError::from(why));
+            }
+        };
+        // The cookie keeps them newest first.
+        rows.sort_by_key(|row| codes.iter().position(|code| *code == row.receipt.code));
+        Ok(rows)
+    }
+
+    // The estimates this browser made at this campus, for "your recent estimates" on the index page.
+    pub async fn recent_receipts(&self, campus: &Campus, session: &Session) -> Result<Vec<Receipt>, AppError> {
+        Ok(self.remembered(campus, session).await?.into_iter().map(|row| row.receipt).collect())
+    }
+
+    // Everything the history page shows, grouped by student, from the same single query.
+    pub async fn history(&self, campus: &Campus, session: &Session) -> Result<History, AppError> {
+        let mut history = History::default();
+        let rows = self.remembered(campus, session).await?;
+        let mut remaining = self.adjustments(&rows.iter().map(|row| row.receipt.id).collect::<Vec<_>>()).await?;
+        for row in rows {
+            let categories = breakdown(&row.receipt);
+            let (own, others): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|adjustment| adjustment.receipt_id == row.receipt.id);
+            remaining = others;
+            let calculation = Calculation { revision: adjustments::revise(&row.receipt, &categories, own), categories, receipt: row.receipt };
+            match history.students.iter_mut().find(|student| student.public_id == row.student_public_id) {
+                Some(student) => student.calculations.push(calculation),
+                None => history.students.push(StudentHistory {
+                    public_id: row.student_public_id,
+                    first_name: row.student_first_name,
+                    last_name: row.student_last_name,
+                    calculations: vec![calculation],
+                }),
+            }
+        }
+        Ok(history)
+    }
+}
@@ -0,0 +1,289 @@
+use actix_session::storage::{LoadError, SaveError, SessionKey, UpdateError};
+use actix_web::cookie::time;
+use rand::{distributions::Alphanumeric, Rng};
+use std::{collections::HashMap, fmt, io, sync::Mutex, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream},
+    net::TcpStream,
+};
+
+// Everything the app keeps in Redis starts with this, so it can share a server with other apps.
+const PREFIX: &str = "tuition:";
+const SESSION_KEY_LENGTH: usize = 64;
+
+type Connection = BufStream<TcpStream>;
+
+// What Redis answered a command with. Error replies come back as Err.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Status(String),
+    Integer(i64),
+    // None for a key that isn't there.
+    Bulk(Option<Vec<u8>>),
+}
+
+// A small client for the few commands the app needs, over REDIS_URL
+// (`redis://[user][:password@]host[:port][/database]`). Connections are kept and reused; one
+// that fails midway is dropped rather than handed to the next command.
+pub struct Redis {
+    address: String,
+    username: Option<String>,
+    password: Option<String>,
+    database: Option<u32>,
+    timeout: Duration,
+    idle: Mutex<Vec<Connection>>,
+}
+
+// Leaves out the password.
+impl fmt::Debug for Redis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redis").field("address", &self.address).field("database", &self.database).finish_non_exhaustive()
+    }
+}
+
+impl Redis {
+    pub fn new(url: &str, timeout: Duration) -> Result<Redis, String> {
+        let parsed = match reqwest::Url::parse(url) {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("REDIS_URL isn't a valid URL: {}", why));
+            }
+        };
+        if parsed.scheme() != "redis" {
+            return Err("REDIS_URL must start with redis://".to_string());
+        }
+        let host = match parsed.host_str() {
+            Some(val) => val,
+            None => {
+                return Err("REDIS_URL has no host".to_string());
+            }
+        };
+        let database = match parsed.path().trim_start_matches('/') {
+            "" => None,
+            val => match val.parse::<u32>() {
+                Ok(database) => Some(database),
+                Err(_) => {
+                    return Err(format!("\"{}\" in REDIS_URL isn't a database number", val));
+                }
+            },
+        };
+        Ok(Redis {
+            address: format!("{}:{}", host, parsed.port().unwrap_or(6379)),
+            username: Some(parsed.username()).filter(|val| !val.is_empty()).map(str::to_string),
+            password: parsed.password().map(str::to_string),
+            database,
+            timeout,
+            idle: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    async fn connect(&self) -> Result<Connection, String> {
+        let stream = match TcpStream::connect(&self.address).await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Couldn't connect to Redis at {}: {}", self.address, why));
+            }
+        };
+        let mut connection = BufStream::new(stream);
+        if let Some(password) = &self.password {
+            let auth: Vec<&[u8]> = match &self.username {
+                Some(username) => vec![b"AUTH", username.as_bytes(), password.as_bytes()],
+                None => vec![b"AUTH", password.as_bytes()],
+            };
+            checked(exchange(&mut connection, &auth).await)?;
+        }
+        if let Some(database) = self.database {
+            checked(exchange(&mut connection, &[b"SELECT", database.to_string().as_bytes()]).await)?;
+        }
+        Ok(connection)
+    }
+
+    pub async fn command(&self, args: &[&[u8]]) -> Result<Reply, String> {
+        let idle = self.idle.lock().unwrap().pop();
+        let mut connection = match idle {
+            Some(val) => val,
+            None => match actix_web::rt::time::timeout(self.timeout, self.connect()).await {
+                Ok(val) => val?,
+                Err(_) => {
+                    return Err(format!("Timed out connecting to Redis at {}", self.address));
+                }
+            },
+        };
+        match actix_web::rt::time::timeout(self.timeout, exchange(&mut connection, args)).await {
+            // An error reply leaves the connection usable.
+            Ok(Ok(reply)) => {
+                self.idle.lock().unwrap().push(connection);
+                reply
+            }
+            Ok(Err(why)) => Err(format!("Lost the connection to Redis at {}: {}", self.address, why)),
+            Err(_) => Err(format!("Timed out waiting for Redis at {}", self.address)),
+        }
+    }
+
+    // At startup, so a wrong REDIS_URL stops the server instead of failing every request.
+    pub async fn ping(&self) -> Result<(), String> {
+        self.command(&[b"PING"]).await.map(|_| ())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        match self.command(&[b"GET", prefixed(key).as_bytes()]).await? {
+            Reply::Bulk(val) => Ok(val),
+            other => Err(format!("Unexpected reply to GET: {:?}", other)),
+        }
+    }
+
+    // Whether it was stored; with `only_existing`, a key that's gone (e.g. expired) isn't recreated.
+    pub async fn set(&self, key: &str, val: &[u8], ttl: Duration, only_existing: bool) -> Result<bool, String> {
+        let key = prefixed(key);
+        let millis = ttl.as_millis().max(1).to_string();
+        let mut args: Vec<&[u8]> = vec![b"SET", key.as_bytes(), val, b"PX", millis.as_bytes()];
+        if only_existing {
+            args.push(b"XX");
+        }
+        match self.command(&args).await? {
+            Reply::Status(_) => Ok(true),
+            Reply::Bulk(None) => Ok(false),
+            other => Err(format!("Unexpected reply to SET: {:?}", other)),
+        }
+    }
+
+    pub async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>, String> {
+        match self.command(&[b"HGET", prefixed(key).as_bytes(), field.as_bytes()]).await? {
+            Reply::Bulk(val) => Ok(val),
+            other => Err(format!("Unexpected reply to HGET: {:?}", other)),
+        }
+    }
+
+    pub async fn hset(&self, key: &str, field: &str, val: &[u8]) -> Result<(), String> {
+        self.command(&[b"HSET", prefixed(key).as_bytes(), field.as_bytes(), val]).await.map(|_| ())
+    }
+
+    pub async fn expire(&self, key: &str, ttl: Duration) -> Result<(), String> {
+        self.command(&[b"PEXPIRE", prefixed(key).as_bytes(), ttl.as_millis().max(1).to_string().as_bytes()]).await.map(|_| ())
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<(), String> {
+        self.command(&[b"DEL", prefixed(key).as_bytes()]).await.map(|_| ())
+    }
+}
+
+fn prefixed(key: &str) -> String {
+    format!("{}{}", PREFIX, key)
+}
+
+fn checked(reply: io::Result<Result<Reply, String>>) -> Result<Reply, String> {
+    match reply {
+        Ok(val) => val,
+        Err(why) => Err(why.to_string()),
+    }
+}
+
+// Send one command and read its reply, in the RESP protocol. The outer error means the
+// connection is no good; the inner one is Redis refusing the command.
+async fn exchange(connection: &mut Connection, args: &[&[u8]]) -> io::Result<Result<Reply, String>> {
+    let mut request = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        request.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        request.extend_from_slice(arg);
+        request.extend_from_slice(b"\r\n");
+    }
+    connection.write_all(&request).await?;
+    connection.flush().await?;
+
+    let mut line = String::new();
+    if connection.read_line(&mut line).await? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Redis closed the connection"));
+    }
+    let line = line.trim_end_matches("\r\n");
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Unexpected reply from Redis: {:?}", line));
+    match line.split_at_checked(1) {
+        Some(("+", rest)) => Ok(Ok(Reply::Status(rest.to_string()))),
+        Some(("-", rest)) => Ok(Err(rest.to_string())),
+        Some((":", rest)) => match rest.parse::<i64>() {
+            Ok(val) => Ok(Ok(Reply::Integer(val))),
+            Err(_) => Err(invalid()),
+        },
+        Some(("$", rest)) => match rest.parse::<i64>() {
+            Ok(len) if len < 0 => Ok(Ok(Reply::Bulk(None))),
+            Ok(len) => {
+                // The value, then its CRLF.
+                let mut val = vec![0; len as usize + 2];
+                connection.read_exact(&mut val).await?;
+                val.truncate(len as usize);
+                Ok(Ok(Reply::Bulk(Some(val))))
+            }
+            Err(_) => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+// Sessions as JSON under a random key; the cookie only carries the key.
+fn session_key_name(session_key: &SessionKey) -> String {
+    format!("session:{}", session_key.as_ref())
+}
+
+fn session_ttl(ttl: &time::Duration) -> Duration {
+    Duration::from_secs(ttl.whole_seconds().max(1) as u64)
+}
+
+pub async fn load_session(redis: &Redis, session_key: &SessionKey) -> Result<Option<HashMap<String, String>>, LoadError> {
+    match redis.get(&session_key_name(session_key)).await {
+        Ok(Some(val)) => match serde_json::from_slice(&val) {
+            Ok(state) => Ok(Some(state)),
+            Err(why) => Err(LoadError::Deserialization(anyhow::anyhow!(why))),
+        },
+        Ok(None) => Ok(None),
+        Err(why) => Err(LoadError::Other(anyhow::anyhow!(why))),
+    }
+}
+
+pub async fn save_session(redis: &Redis, session_state: HashMap<String, String>, ttl: &time::Duration) -> Result<SessionKey, SaveError> {
+    let body = match serde_json::to_vec(&session_state) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(SaveError::Serialization(anyhow::anyhow!(why)));
+        }
+    };
+    let key: String = rand::thread_rng().sample_iter(&Alphanumeric).take(SESSION_KEY_LENGTH).map(char::from).collect();
+    let session_key = match SessionKey::try_from(key) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(SaveError::Other(anyhow::anyhow!(why)));
+        }
+    };
+    match redis.set(&session_key_name(&session_key), &body, session_ttl(ttl), false).await {
+        Ok(_) => Ok(session_key),
+        Err(why) => Err(SaveError::Other(anyhow::anyhow!(why))),
+    }
+}
+
+// A session that expired in the meantime is saved under a new key instead.
+pub async fn update_session(redis: &Redis, session_key: SessionKey, session_state: HashMap<String, String>, ttl: &time::Duration) -> Result<SessionKey, UpdateError> {
+    let body = match serde_json::to_vec(&session_state) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(UpdateError::Serialization(anyhow::anyhow!(why)));
+        }
+    };
+    match redis.set(&session_key_name(&session_key), &body, session_ttl(ttl), true).await {
+        Ok(true) => Ok(session_key),
+        Ok(false) => match save_session(redis, session_state, ttl).await {
+            Ok(val) => Ok(val),
+            Err(why) => Err(UpdateError::Other(anyhow::anyhow!(why.to_string()))),
+        },
+        Err(why) => Err(UpdateError::Other(anyhow::anyhow!(why))),
+    }
+}
+
+pub async fn update_session_ttl(redis: &Redis, session_key: &SessionKey, ttl: &time::Duration) -> Result<(), anyhow::Error> {
+    redis.expire(&session_key_name(session_key), session_ttl(ttl)).await.map_err(|why| anyhow::anyhow!(why))
+}
+
+pub async fn delete_session(redis: &Redis, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+    redis.delete(&session_key_name(session_key)).await.map_err(|why| anyhow::anyhow!(why))
+}
@@ -0,0 +1,99 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::{AppError, FormErrors}, form, models::{Campus, Receipt}, money::Money, pricing, queries, receipts, render, AppState};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefundFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    code: Option<String>,
+    // YYYY-MM-DD, from the date input.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    withdrawal_date: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RefundFormPage {
+    campus: Campus,
+    form: RefundFormParams,
+    errors: Option<FormErrors>,
+}
+
+#[derive(Serialize)]
+struct RefundPage {
+    campus: Campus,
+    receipt: Receipt,
+    withdrawal_date: NaiveDate,
+    week: i64,
+    charged_tuition: Money,
+    refund_percent: Decimal,
+    refund_amount: Money,
+}
+
+// `/refund?code=..` fills in the receipt code, e.g. from the receipt page.
+pub async fn refund_form(state: web::Data<AppState>, campus: Campus, params: web::Query<RefundFormParams>) -> Result<HttpResponse, AppError> {
+    render(&state, "refund", &RefundFormPage { campus, form: params.into_inner(), errors: None }).await
+}
+
+// What withdrawing on a given date would refund for a saved estimate, by the term's refund schedule.
+pub async fn estimate_refund(state: web::Data<AppState>, campus: Campus, params: web::Form<RefundFormParams>) -> Result<HttpResponse, AppError> {
+    let form = params.into_inner();
+    match refund_estimate(&state, campus.clone(), &form).await {
+        // The form again, as filled in, with the problem marked on its field.
+        Err(why @ AppError::Validation { .. }) => {
+            println!("{}", why);
+            let mut response = render(&state, "refund", &RefundFormPage { campus, form, errors: FormErrors::from_error(&why) }).await?;
+            *response.status_mut() = actix_web::http::StatusCode::BAD_REQUEST;
+            Ok(response)
+        }
+        result => result,
+    }
+}
+
+async fn refund_estimate(state: &AppState, campus: Campus, params: &RefundFormParams) -> Result<HttpResponse, AppError> {
+    let code = match &params.code {
+        Some(val) => val.clone(),
+        None => {
+            return Err(AppError::validation("code", "No receipt code was provided!"));
+        }
+    };
+    let withdrawal_date = match &params.withdrawal_date {
+        Some(val) => match NaiveDate::parse_from_str(val, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                return Err(AppError::validation("withdrawal_date", &format!("\"{}\" is not a valid withdrawal date.", val)));
+            }
+        },
+        None => {
+            return Err(AppError::validation("withdrawal_date", "No withdrawal date was provided!"));
+        }
+    };
+
+    let receipt = receipts::fetch_receipt(state, &campus, &code).await?;
+    let rules = state.refund_rules(&campus, &receipt.term).await?;
+    if rules.is_empty() {
+        return Err(AppError::NotFound(format!("No refund schedule has been set up for {}.", receipt.term)));
+    }
+
+    let charged_tuition = pricing::charged_tuition(receipt.num_credits, receipt.credits_cost, receipt.tuition_percent);
+    let (week, refund_percent) = pricing::refund_percent(&rules, withdrawal_date);
+    let refund_amount = pricing::refund_amount(charged_tuition, refund_percent);
+
+    match queries::INSERT_REFUND_ESTIMATE.run(|sql| sqlx::query(sql)
+    .bind(receipt.id)
+    .bind(withdrawal_date)
+    .bind(week)
+    .bind(refund_percent)
+    .bind(refund_amount)
+    .execute(&state.conn))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    render(state, "refund_result", &RefundPage { campus, receipt, withdrawal_date, week, charged_tuition, refund_percent, refund_amount }).await
+}
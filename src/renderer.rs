@@ -0,0 +1,106 @@
+use actix_web::HttpResponse;
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use crate::{assets, config::BrandingConfig, error::AppError, money, AppState};
+
+// Every page and email is rendered from the templates registered here, so a partial like the
+// breakdown looks the same wherever it's shown.
+pub fn templates() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("money", Box::new(money::money));
+    handlebars.register_helper("asset", Box::new(assets::asset_helper));
+    // Every page renders inside the layout, which brings in the stylesheet and navigation.
+    handlebars.register_partial("layout", include_str!("htdoc/layout.html")).expect("Invalid layout template.");
+    // The school's color and contact lines from the branding config, for pages with and without the layout.
+    handlebars.register_partial("brand_style", include_str!("htdoc/brand_style.html")).expect("Invalid brand style template.");
+    handlebars.register_partial("contact_footer", include_str!("htdoc/contact_footer.html")).expect("Invalid contact footer template.");
+    handlebars.register_partial("recent_estimates", include_str!("htdoc/recent_estimates.html")).expect("Invalid recent estimates template.");
+    // The charges grouped by category, for the result, receipt and history pages.
+    handlebars.register_partial("breakdown", include_str!("htdoc/breakdown.html")).expect("Invalid breakdown template.");
+    // The validation error summary, and one field's message for its aria-describedby.
+    handlebars.register_partial("form_errors", include_str!("htdoc/form_errors.html")).expect("Invalid form errors template.");
+    handlebars.register_partial("field_error", include_str!("htdoc/field_error.html")).expect("Invalid field error template.");
+    handlebars.register_partial("residency_choice", include_str!("htdoc/residency_choice.html")).expect("Invalid residency choice template.");
+    handlebars.register_template_string("index", include_str!("htdoc/index.html")).expect("Invalid index template.");
+    handlebars.register_template_string("wizard", include_str!("htdoc/wizard.html")).expect("Invalid step-by-step calculator template.");
+    handlebars.register_template_string("result", include_str!("htdoc/result.html")).expect("Invalid result template.");
+    handlebars.register_template_string("lookup", include_str!("htdoc/lookup.html")).expect("Invalid lookup template.");
+    handlebars.register_template_string("history", include_str!("htdoc/history.html")).expect("Invalid history template.");
+    handlebars.register_template_string("error", include_str!("htdoc/error.html")).expect("Invalid error template.");
+    handlebars.register_template_string("scenarios", include_str!("htdoc/scenarios.html")).expect("Invalid scenarios template.");
+    handlebars.register_template_string("no_record", include_str!("htdoc/no_record.html")).expect("Invalid no record template.");
+    handlebars.register_template_string("maintenance", include_str!("htdoc/maintenance.html")).expect("Invalid maintenance template.");
+    handlebars.register_template_string("receipt", include_str!("htdoc/receipt.html")).expect("Invalid receipt template.");
+    handlebars.register_template_string("receipt_print", include_str!("htdoc/receipt_print.html")).expect("Invalid printable receipt template.");
+    handlebars.register_template_string("share", include_str!("htdoc/share.html")).expect("Invalid share link template.");
+    handlebars.register_template_string("shared", include_str!("htdoc/shared.html")).expect("Invalid shared estimate template.");
+    handlebars.register_template_string("verify_email", include_str!("htdoc/verify_email.html")).expect("Invalid email verification template.");
+    handlebars.register_template_string("sign_in", include_str!("htdoc/sign_in.html")).expect("Invalid sign-in template.");
+    handlebars.register_template_string("course_fees", include_str!("htdoc/course_fees.html")).expect("Invalid course fees template.");
+    handlebars.register_template_string("refund", include_str!("htdoc/refund.html")).expect("Invalid refund template.");
+    handlebars.register_template_string("refund_result", include_str!("htdoc/refund_result.html")).expect("Invalid refund result template.");
+    handlebars.register_template_string("projection", include_str!("htdoc/projection.html")).expect("Invalid projection template.");
+    handlebars.register_template_string("projection_result", include_str!("htdoc/projection_result.html")).expect("Invalid projection result template.");
+    handlebars.register_template_string("admin_rate_increase", include_str!("htdoc/admin_rate_increase.html")).expect("Invalid rate increase template.");
+    handlebars.register_template_string("admin_index", include_str!("htdoc/admin_index.html")).expect("Invalid admin template.");
+    handlebars.register_template_string("admin_api_keys", include_str!("htdoc/admin_api_keys.html")).expect("Invalid API keys template.");
+    handlebars.register_template_string("admin_simulate", include_str!("htdoc/admin_simulate.html")).expect("Invalid simulate template.");
+    handlebars.register_template_string("admin_record", include_str!("htdoc/admin_record.html")).expect("Invalid record template.");
+    handlebars.register_template_string("admin_records", include_str!("htdoc/admin_records.html")).expect("Invalid record search template.");
+    handlebars.register_template_string("admin_duplicates", include_str!("htdoc/admin_duplicates.html")).expect("Invalid duplicate students template.");
+    handlebars.register_template_string("admin_receipt", include_str!("htdoc/admin_receipt.html")).expect("Invalid receipt adjustment template.");
+    handlebars.register_template_string("admin_maintenance", include_str!("htdoc/admin_maintenance.html")).expect("Invalid maintenance admin template.");
+    handlebars.register_template_string("admin_refunds", include_str!("htdoc/admin_refunds.html")).expect("Invalid refund schedule template.");
+    handlebars.register_template_string("admin_terms", include_str!("htdoc/admin_terms.html")).expect("Invalid terms template.");
+    handlebars.register_template_string("admin_line_items", include_str!("htdoc/admin_line_items.html")).expect("Invalid line items template.");
+    handlebars.register_template_string("admin_validation_rules", include_str!("htdoc/admin_validation_rules.html")).expect("Invalid validation rules template.");
+    handlebars.register_template_string("admin_orientation_exemptions", include_str!("htdoc/admin_orientation_exemptions.html")).expect("Invalid orientation exemptions template.");
+    handlebars.register_template_string("admin_reciprocity", include_str!("htdoc/admin_reciprocity.html")).expect("Invalid reciprocity template.");
+    handlebars.register_template_string("admin_form_fields", include_str!("htdoc/admin_form_fields.html")).expect("Invalid form fields template.");
+    handlebars.register_template_string("admin_slow_queries", include_str!("htdoc/admin_slow_queries.html")).expect("Invalid slow queries template.");
+    handlebars.register_template_string("admin_bulk_delete", include_str!("htdoc/admin_bulk_delete.html")).expect("Invalid bulk delete template.");
+    handlebars.register_template_string("admin_explain", include_str!("htdoc/admin_explain.html")).expect("Invalid rate explainer template.");
+    // Sent by email, so rendered through `email` below.
+    handlebars.register_template_string("email_estimate", include_str!("htdoc/email_estimate.html")).expect("Invalid estimate email template.");
+    handlebars
+}
+
+// What every template gets besides its own data.
+#[derive(Serialize)]
+struct TemplateData<'a, T: Serialize> {
+    #[serde(flatten)]
+    data: &'a T,
+    // For the layout, printed receipts and emails.
+    branding: &'a BrandingConfig,
+}
+
+pub fn render_string<T: Serialize>(state: &AppState, template: &str, data: &T) -> Result<String, AppError> {
+    match state.templates.render(template, &TemplateData { data, branding: &state.branding }) {
+        Ok(body) => Ok(body),
+        Err(why) => Err(AppError::Internal(format!("Error while rendering the {} page: {}", template, why))),
+    }
+}
+
+pub fn html(body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(body)
+}
+
+pub async fn render<T: Serialize>(state: &AppState, template: &str, data: &T) -> Result<HttpResponse, AppError> {
+    Ok(html(render_string(state, template, data)?))
+}
+
+#[derive(Serialize)]
+struct EmailData<'a, T: Serialize> {
+    #[serde(flatten)]
+    data: &'a T,
+    // Partials check `@root.email` to put their styles inline; mail clients drop the stylesheet.
+    email: bool,
+}
+
+// A template rendered for an email body instead of a page.
+pub fn email<T: Serialize>(state: &AppState, template: &str, data: &T) -> Result<String, AppError> {
+    render_string(state, template, &EmailData { data, email: true })
+}
@@ -0,0 +1,246 @@
+use actix_web::rt;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlDatabaseError, MySqlPool};
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::{config::ReplayConfig, ids, models::{CampusId, StudentId}, money::{Money, PerCredit}, queries, AppState};
+
+// Everything one estimate saves: the student if they're new, their record for the term, and the
+// receipt. Kept whole so an estimate that couldn't be saved can be saved later exactly as priced.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingEstimate {
+    pub campus_id: CampusId,
+    pub first_name: String,
+    pub last_name: String,
+    pub term: String,
+    pub total: Money,
+    pub num_credits: u8,
+    pub orientation: bool,
+    // Queue files from before it was kept read as not asked.
+    #[serde(default)]
+    pub orientation_answer: Option<bool>,
+    pub student_type: String,
+    pub student_studies: String,
+    pub insurance_waived: bool,
+    pub receipt_code: String,
+    pub credits_cost: PerCredit,
+    pub nonresidency_fee: Money,
+    pub orientation_fee: Money,
+    pub enrollment_date: Option<NaiveDate>,
+    pub tuition_percent: Option<Decimal>,
+    pub course_codes: Option<String>,
+    pub course_fee_total: Money,
+    pub health_insurance_fee: Money,
+    pub international_fee_total: Money,
+    pub custom_fees: Option<Money>,
+    pub custom_items: Option<String>,
+    pub client_ip_hash: Option<String>,
+    pub user_agent: Option<String>,
+    pub referrer: Option<String>,
+    pub entered_by: Option<String>,
+    // web, api or counselor. Queue files from before it was kept leave it unknown.
+    #[serde(default)]
+    pub channel: Option<String>,
+    // Ticked the consent box, where it's asked for. Queue files from before it read as false.
+    #[serde(default)]
+    pub consented: bool,
+    // When the student calculated it, which the saved rows keep however late they're written.
+    pub calculated_at: DateTime<Utc>,
+}
+
+// Errors that say the database was briefly out of reach rather than that the rows are wrong, so
+// trying again later can work: a dropped connection, no free connection, a deadlock or lock wait,
+// or a primary that turned read-only during a failover.
+pub fn transient(why: &sqlx::Error) -> bool {
+    match why {
+        sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::Protocol(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(why) => match why.try_downcast_ref::<MySqlDatabaseError>() {
+            Some(why) => matches!(why.number(), 1205 | 1213 | 1290 | 1792),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+// Write the estimate's rows in one transaction. Saving the same estimate twice is harmless: a
+// receipt code that's already there means an earlier try went through. An older estimate never
+// overwrites a newer record for the term.
+pub async fn save(pool: &MySqlPool, estimate: &PendingEstimate) -> Result<StudentId, sqlx::Error> {
+    let age = (Utc::now() - estimate.calculated_at).num_seconds().max(0);
+    let mut tx = pool.begin().await?;
+
+    let existing_id = queries::STUDENT_ID_BY_NAME.scoped(estimate.campus_id).run(|sql, campus_id| sqlx::query_scalar::<_, StudentId>(sql)
+        .bind(campus_id)
+        .bind(&estimate.first_name)
+        .bind(&estimate.last_name)
+        .fetch_optional(&mut tx)).await?;
+    let student_id = match existing_id {
+        Some(id) => id,
+        None => {
+            let inserted = queries::INSERT_STUDENT.scoped(estimate.campus_id).run(|sql, campus_id| sqlx::query(sql)
+                .bind(campus_id)
+                .bind(ids::new_public_id())
+                .bind(&estimate.first_name)
+                .bind(&estimate.last_name)
+                .execute(&mut tx)).await?;
+            StudentId(inserted.last_insert_id() as i32)
+        }
+    };
+
+    let already_saved = queries::RECEIPT_CODE_EXISTS.run(|sql| sqlx::query_scalar::<_, i64>(sql)
+        .bind(&estimate.receipt_code)
+        .fetch_one(&mut tx)).await?;
+    if already_saved > 0 {
+        tx.commit().await?;
+        return Ok(student_id);
+    }
+
+    queries::UPSERT_TUITION_RECORD.run(|sql| sqlx::query(sql)
+        .bind(student_id)
+        .bind(&estimate.term)
+        .bind(estimate.total)
+        .bind(estimate.num_credits)
+        .bind(estimate.orientation)
+        .bind(&estimate.student_type)
+        .bind(&estimate.student_studies)
+        .bind(estimate.insurance_waived)
+        .bind(estimate.consented)
+        .bind(age)
+        .bind(age)
+        .execute(&mut tx)).await?;
+
+    queries::INSERT_RECEIPT.scoped(estimate.campus_id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(&estimate.receipt_code)
+        .bind(campus_id)
+        .bind(student_id)
+        .bind(&estimate.first_name)
+        .bind(&estimate.last_name)
+        .bind(&estimate.term)
+        .bind(estimate.num_credits)
+        .bind(estimate.orientation)
+        .bind(&estimate.student_type)
+        .bind(&estimate.student_studies)
+        .bind(estimate.credits_cost)
+        .bind(estimate.nonresidency_fee)
+        .bind(estimate.orientation_fee)
+        .bind(estimate.total)
+        .bind(estimate.enrollment_date)
+        .bind(estimate.tuition_percent)
+        .bind(&estimate.course_codes)
+        .bind(estimate.course_fee_total)
+        .bind(estimate.insurance_waived)
+        .bind(estimate.health_insurance_fee)
+        .bind(estimate.international_fee_total)
+        .bind(estimate.custom_fees)
+        .bind(&estimate.custom_items)
+        .bind(&estimate.client_ip_hash)
+        .bind(&estimate.user_agent)
+        .bind(&estimate.referrer)
+        .bind(&estimate.entered_by)
+        .bind(estimate.orientation_answer)
+        .bind(&estimate.channel)
+        .bind(age)
+        .execute(&mut tx)).await?;
+
+    tx.commit().await?;
+    Ok(student_id)
+}
+
+// Estimates waiting for the database to come back, oldest first. With REPLAY_QUEUE_FILE they're
+// also written there, so a restart during an outage doesn't lose them.
+#[derive(Debug)]
+pub struct ReplayQueue {
+    config: ReplayConfig,
+    pending: Mutex<VecDeque<PendingEstimate>>,
+}
+
+impl ReplayQueue {
+    pub fn new(config: ReplayConfig) -> ReplayQueue {
+        let mut pending = VecDeque::new();
+        if let Some(path) = &config.file {
+            match std::fs::read_to_string(path) {
+                Ok(text) => match serde_json::from_str::<VecDeque<PendingEstimate>>(&text) {
+                    Ok(val) => pending = val,
+                    Err(why) => println!("Ignoring {}, which isn't a replay queue: {}", path, why),
+                },
+                Err(why) if why.kind() == std::io::ErrorKind::NotFound => {},
+                Err(why) => println!("Error while reading the replay queue from {}: {}", path, why),
+            }
+        }
+        ReplayQueue { config, pending: Mutex::new(pending) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    // False when the queue is full; the student gets the error instead.
+    pub fn push(&self, estimate: PendingEstimate) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() >= self.config.max_pending {
+            return false;
+        }
+        pending.push_back(estimate);
+        self.persist(&pending);
+        true
+    }
+
+    fn persist(&self, pending: &VecDeque<PendingEstimate>) {
+        let path = match &self.config.file {
+            Some(val) => val,
+            None => {
+                return;
+            }
+        };
+        let written = match serde_json::to_string(pending) {
+            Ok(text) => std::fs::write(path, text).map_err(|why| why.to_string()),
+            Err(why) => Err(why.to_string()),
+        };
+        if let Err(why) = written {
+            println!("Error while writing the replay queue to {}: {}", path, why);
+        }
+    }
+
+    // Save what's waiting, oldest first, until the queue is empty or the database is still out.
+    pub async fn flush(&self, pool: &MySqlPool) -> usize {
+        let mut saved = 0;
+        loop {
+            let next = self.pending.lock().unwrap().front().cloned();
+            let estimate = match next {
+                Some(val) => val,
+                None => break,
+            };
+            match save(pool, &estimate).await {
+                Ok(_val) => saved += 1,
+                Err(why) if transient(&why) => break,
+                // Trying again won't help; keep it in the log so it can be entered by hand.
+                Err(why) => println!("Dropping the queued estimate {} ({}): {}", estimate.receipt_code, serde_json::to_string(&estimate).unwrap_or_default(), why),
+            }
+            let mut pending = self.pending.lock().unwrap();
+            pending.pop_front();
+            self.persist(&pending);
+        }
+        saved
+    }
+}
+
+// Every REPLAY_INTERVAL_SECS, save whatever is waiting.
+pub fn spawn(state: AppState) {
+    if state.replay.len() > 0 {
+        println!("{} estimate(s) saved while the database was unavailable are waiting to be written.", state.replay.len());
+    }
+    rt::spawn(async move {
+        loop {
+            rt::time::sleep(state.replay.config.interval).await;
+            if state.replay.len() == 0 {
+                continue;
+            }
+            let saved = state.replay.flush(&state.conn).await;
+            if saved > 0 {
+                println!("Saved {} queued estimate(s); {} still waiting.", saved, state.replay.len());
+            }
+        }
+    });
+}
@@ -0,0 +1,93 @@
+use actix_web::{http::header, HttpRequest};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::config::RequestMetadataConfig;
+
+// The columns are VARCHAR(255); anything past that is noise.
+const MAX_HEADER_LEN: usize = 255;
+
+// Where a calculation came from, kept with its receipt.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMetadata {
+    pub client_ip_hash: Option<String>,
+    pub user_agent: Option<String>,
+    pub referrer: Option<String>,
+}
+
+// What gets recorded about each calculation. IPs are only ever stored salted and hashed, so
+// repeat visitors can be told apart without keeping their addresses.
+#[derive(Clone)]
+pub struct MetadataPolicy {
+    enabled: bool,
+    salt: Vec<u8>,
+    counselor_referrers: Vec<String>,
+}
+
+// Keep the salt out of logs.
+impl std::fmt::Debug for MetadataPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataPolicy")
+            .field("enabled", &self.enabled)
+            .field("counselor_referrers", &self.counselor_referrers)
+            .finish()
+    }
+}
+
+fn header_value(req: &HttpRequest, name: header::HeaderName) -> Option<String> {
+    let val = req.headers().get(name)?.to_str().ok()?.trim();
+    if val.is_empty() {
+        return None;
+    }
+    Some(val.chars().take(MAX_HEADER_LEN).collect())
+}
+
+impl MetadataPolicy {
+    pub fn new(config: &RequestMetadataConfig) -> MetadataPolicy {
+        let salt = match &config.ip_salt {
+            Some(val) => val.as_bytes().to_vec(),
+            None => {
+                if config.enabled {
+                    println!("REQUEST_METADATA_SALT is not set; hashed IPs won't match across restarts.");
+                }
+                let mut salt = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut salt);
+                salt
+            }
+        };
+        MetadataPolicy {
+            enabled: config.enabled,
+            salt,
+            counselor_referrers: config.counselor_referrers.clone(),
+        }
+    }
+
+    pub fn hash_ip(&self, ip: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.salt);
+        hasher.update(ip.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    // Nothing at all when REQUEST_METADATA is off.
+    pub fn capture(&self, req: &HttpRequest, peer_ip: Option<&str>) -> RequestMetadata {
+        if !self.enabled {
+            return RequestMetadata::default();
+        }
+        RequestMetadata {
+            client_ip_hash: peer_ip.map(|ip| self.hash_ip(ip)),
+            user_agent: header_value(req, header::USER_AGENT),
+            referrer: header_value(req, header::REFERER),
+        }
+    }
+
+    pub fn counselor_referrers(&self) -> &[String] {
+        &self.counselor_referrers
+    }
+}
+
+// Counselors open the calculator from their advising portal, so its referrer marks an estimate
+// as counselor-entered; everything else is self-service.
+pub fn is_counselor_referrer(referrer: &str, prefixes: &[String]) -> bool {
+    prefixes.iter().any(|prefix| referrer.starts_with(prefix.as_str()))
+}
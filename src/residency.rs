@@ -0,0 +1,79 @@
+use serde::Serialize;
+
+// A state a student can say they live in, by its USPS code. DC and the territories are included
+// since their students pay nonresident rates at public schools unless an agreement says otherwise.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct HomeState {
+    pub code: &'static str,
+    pub name: &'static str,
+}
+
+pub const STATES: [HomeState; 56] = [
+    HomeState { code: "AL", name: "Alabama" },
+    HomeState { code: "AK", name: "Alaska" },
+    HomeState { code: "AZ", name: "Arizona" },
+    HomeState { code: "AR", name: "Arkansas" },
+    HomeState { code: "CA", name: "California" },
+    HomeState { code: "CO", name: "Colorado" },
+    HomeState { code: "CT", name: "Connecticut" },
+    HomeState { code: "DE", name: "Delaware" },
+    HomeState { code: "DC", name: "District of Columbia" },
+    HomeState { code: "FL", name: "Florida" },
+    HomeState { code: "GA", name: "Georgia" },
+    HomeState { code: "HI", name: "Hawaii" },
+    HomeState { code: "ID", name: "Idaho" },
+    HomeState { code: "IL", name: "Illinois" },
+    HomeState { code: "IN", name: "Indiana" },
+    HomeState { code: "IA", name: "Iowa" },
+    HomeState { code: "KS", name: "Kansas" },
+    HomeState { code: "KY", name: "Kentucky" },
+    HomeState { code: "LA", name: "Louisiana" },
+    HomeState { code: "ME", name: "Maine" },
+    HomeState { code: "MD", name: "Maryland" },
+    HomeState { code: "MA", name: "Massachusetts" },
+    HomeState { code: "MI", name: "Michigan" },
+    HomeState { code: "MN", name: "Minnesota" },
+    HomeState { code: "MS", name: "Mississippi" },
+    HomeState { code: "MO", name: "Missouri" },
+    HomeState { code: "MT", name: "Montana" },
+    HomeState { code: "NE", name: "Nebraska" },
+    HomeState { code: "NV", name: "Nevada" },
+    HomeState { code: "NH", name: "New Hampshire" },
+    HomeState { code: "NJ", name: "New Jersey" },
+    HomeState { code: "NM", name: "New Mexico" },
+    HomeState { code: "NY", name: "New York" },
+    HomeState { code: "NC", name: "North Carolina" },
+    HomeState { code: "ND", name: "North Dakota" },
+    HomeState { code: "OH", name: "Ohio" },
+    HomeState { code: "OK", name: "Oklahoma" },
+    HomeState { code: "OR", name: "Oregon" },
+    HomeState { code: "PA", name: "Pennsylvania" },
+    HomeState { code: "RI", name: "Rhode Island" },
+    HomeState { code: "SC", name: "South Carolina" },
+    HomeState { code: "SD", name: "South Dakota" },
+    HomeState { code: "TN", name: "Tennessee" },
+    HomeState { code: "TX", name: "Texas" },
+    HomeState { code: "UT", name: "Utah" },
+    HomeState { code: "VT", name: "Vermont" },
+    HomeState { code: "VA", name: "Virginia" },
+    HomeState { code: "WA", name: "Washington" },
+    HomeState { code: "WV", name: "West Virginia" },
+    HomeState { code: "WI", name: "Wisconsin" },
+    HomeState { code: "WY", name: "Wyoming" },
+    HomeState { code: "AS", name: "American Samoa" },
+    HomeState { code: "GU", name: "Guam" },
+    HomeState { code: "MP", name: "Northern Mariana Islands" },
+    HomeState { code: "PR", name: "Puerto Rico" },
+    HomeState { code: "VI", name: "U.S. Virgin Islands" },
+];
+
+// The state with this code, in any case.
+pub fn find(code: &str) -> Option<HomeState> {
+    STATES.into_iter().find(|state| state.code.eq_ignore_ascii_case(code))
+}
+
+// What the form sends instead of a state for students on a student visa.
+pub const INTERNATIONAL: &str = "international";
+
+// The rate categories an agreement can put a state's students in.
+pub const AGREEMENT_RESIDENCIES: [&str; 2] = ["resident", "nonresident"];
@@ -0,0 +1,96 @@
+use actix_web::rt;
+use chrono::{Duration, Local, NaiveDateTime};
+use std::{fmt, str::FromStr};
+
+use crate::{audit, config::RetentionConfig, queries, summary, AppState};
+
+// What happens to calculations older than the retention period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionAction {
+    // Keep the receipt and its amounts for the stats, but drop the name and request metadata.
+    // The student's own row is left alone; their saved record and newer receipts still use it.
+    Anonymize,
+    // Delete the receipt, and its refund estimates with it.
+    Purge,
+}
+
+impl FromStr for RetentionAction {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<RetentionAction, String> {
+        match val.to_ascii_lowercase().as_str() {
+            "anonymize" => Ok(RetentionAction::Anonymize),
+            "purge" => Ok(RetentionAction::Purge),
+            _ => Err(format!("Unknown retention action \"{}\".", val)),
+        }
+    }
+}
+
+impl fmt::Display for RetentionAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetentionAction::Anonymize => write!(f, "anonymize"),
+            RetentionAction::Purge => write!(f, "purge"),
+        }
+    }
+}
+
+// Anonymized receipts keep this as the name, so they're easy to tell apart in the admin pages.
+pub const ANONYMIZED: &str = "(removed)";
+
+// How many receipts from before `cutoff` are due, or were handled. A dry run only counts them.
+async fn apply(state: &AppState, config: &RetentionConfig, cutoff: NaiveDateTime) -> Result<u64, sqlx::Error> {
+    if config.dry_run {
+        // Already-anonymized receipts aren't counted again.
+        let skipped = match config.action {
+            RetentionAction::Anonymize => Some(ANONYMIZED),
+            RetentionAction::Purge => None,
+        };
+        let count = queries::COUNT_EXPIRED_RECEIPTS.run(|sql| sqlx::query_as::<_, (i64,)>(sql)
+            .bind(cutoff)
+            .bind(skipped)
+            .bind(skipped)
+            .fetch_one(&state.conn)).await?;
+        return Ok(count.0 as u64);
+    }
+
+    let result = match config.action {
+        RetentionAction::Anonymize => queries::ANONYMIZE_RECEIPTS.run(|sql| sqlx::query(sql)
+            .bind(ANONYMIZED)
+            .bind(ANONYMIZED)
+            .bind(cutoff)
+            .bind(ANONYMIZED)
+            .execute(&state.conn)).await?,
+        RetentionAction::Purge => queries::DELETE_RECEIPTS_BEFORE.run(|sql| sqlx::query(sql)
+            .bind(cutoff)
+            .execute(&state.conn)).await?,
+    };
+    Ok(result.rows_affected())
+}
+
+// One pass of the policy, recorded in the audit log whether or not anything was due.
+pub async fn run(state: &AppState, config: &RetentionConfig) -> Result<u64, sqlx::Error> {
+    let cutoff = Local::now().naive_local() - Duration::days(i64::from(config.days));
+    let count = apply(state, config, cutoff).await?;
+    let action = if config.dry_run { format!("{} (dry run)", config.action) } else { config.action.to_string() };
+    let details = if config.dry_run {
+        format!("{} receipt(s) created before {} would be {}d; nothing was changed", count, cutoff.format("%Y-%m-%d %H:%M"), config.action)
+    } else {
+        format!("{} receipt(s) created before {} were {}d", count, cutoff.format("%Y-%m-%d %H:%M"), config.action)
+    };
+    println!("Retention: {}.", details);
+    audit::record(&state.conn, "retention", &action, "Receipt", 0, &details).await?;
+    Ok(count)
+}
+
+// Every night at RETENTION_HOUR. Failures are logged and tried again the next night.
+pub fn spawn(state: AppState, config: RetentionConfig) {
+    rt::spawn(async move {
+        loop {
+            rt::time::sleep(summary::until_next(config.hour)).await;
+            if let Err(why) = run(&state, &config).await {
+                println!("Error while applying the retention policy: {}", why);
+            }
+        }
+    });
+}
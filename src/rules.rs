@@ -0,0 +1,340 @@
+use std::fmt;
+
+use crate::{error::FieldError, fees, models::{LineItem, OrientationExemption, ValidationRule}, pricing::BreakdownItem};
+
+// Who a custom line item applies to, written by admins as a small expression, e.g.
+//
+//     studies = undergraduate and credits >= 12
+//     new_student and not residency = international
+//     (studies = graduate or studies = dual_enrollment) and credits < 6
+//
+// `studies` and `residency` compare with = and != against the form values, `credits` with any
+// of = != < <= > >=, and `new_student` and `orientation` stand on their own. A blank rule
+// applies to everyone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Always,
+    Studies(bool, String),
+    Residency(bool, String),
+    Credits(Comparison, u8),
+    NewStudent,
+    Orientation,
+    Not(Box<Rule>),
+    And(Box<Rule>, Box<Rule>),
+    Or(Box<Rule>, Box<Rule>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+// What a rule is checked against: the student's calculation.
+pub struct Facts<'a> {
+    pub studies: &'a str,
+    pub residency: &'a str,
+    pub credits: u8,
+    pub new_student: bool,
+    pub orientation: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(u32),
+    Op(Comparison),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "\"{}\"", word),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Op(_) => write!(f, "a comparison"),
+            Token::Open => write!(f, "\"(\""),
+            Token::Close => write!(f, "\")\""),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {},
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '=' => tokens.push(Token::Op(Comparison::Equal)),
+            '!' | '<' | '>' => {
+                let equals = chars.next_if_eq(&'=').is_some();
+                tokens.push(Token::Op(match (c, equals) {
+                    ('!', true) => Comparison::NotEqual,
+                    ('<', false) => Comparison::Less,
+                    ('<', true) => Comparison::LessOrEqual,
+                    ('>', false) => Comparison::Greater,
+                    ('>', true) => Comparison::GreaterOrEqual,
+                    _ => {
+                        return Err("\"!\" must be followed by \"=\"".to_string());
+                    }
+                }));
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                    number.push(digit);
+                }
+                match number.parse::<u32>() {
+                    Ok(val) => tokens.push(Token::Number(val)),
+                    Err(_) => {
+                        return Err(format!("{} is too large", number));
+                    }
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(letter) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    word.push(letter);
+                }
+                tokens.push(Token::Word(word.to_ascii_lowercase()));
+            }
+            c => {
+                return Err(format!("Unexpected \"{}\"", c));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// Recursive descent over: or := and ("or" and)*, and := not ("and" not)*,
+// not := "not" not | "(" or ")" | condition.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if self.peek() == Some(&Token::Word(keyword.to_string())) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn or(&mut self) -> Result<Rule, String> {
+        let mut rule = self.and()?;
+        while self.keyword("or") {
+            rule = Rule::Or(Box::new(rule), Box::new(self.and()?));
+        }
+        Ok(rule)
+    }
+
+    fn and(&mut self) -> Result<Rule, String> {
+        let mut rule = self.not()?;
+        while self.keyword("and") {
+            rule = Rule::And(Box::new(rule), Box::new(self.not()?));
+        }
+        Ok(rule)
+    }
+
+    fn not(&mut self) -> Result<Rule, String> {
+        if self.keyword("not") {
+            return Ok(Rule::Not(Box::new(self.not()?)));
+        }
+        match self.next() {
+            Some(Token::Open) => {
+                let rule = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(rule),
+                    Some(token) => Err(format!("Expected \")\" but found {}", token)),
+                    None => Err("Missing \")\"".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => self.condition(&word),
+            Some(token) => Err(format!("Expected a condition but found {}", token)),
+            None => Err("The rule ends too early".to_string()),
+        }
+    }
+
+    fn condition(&mut self, fact: &str) -> Result<Rule, String> {
+        match fact {
+            "new_student" => Ok(Rule::NewStudent),
+            "orientation" => Ok(Rule::Orientation),
+            "studies" | "residency" => {
+                let equal = match self.next() {
+                    Some(Token::Op(Comparison::Equal)) => true,
+                    Some(Token::Op(Comparison::NotEqual)) => false,
+                    _ => {
+                        return Err(format!("{} can only be compared with = or !=", fact));
+                    }
+                };
+                let allowed: &[&str] = if fact == "studies" { &fees::STUDIES } else { &fees::RESIDENCIES };
+                match self.next() {
+                    Some(Token::Word(val)) if allowed.contains(&val.as_str()) => {
+                        if fact == "studies" { Ok(Rule::Studies(equal, val)) } else { Ok(Rule::Residency(equal, val)) }
+                    }
+                    _ => Err(format!("{} must be one of {}", fact, allowed.join(", "))),
+                }
+            }
+            "credits" => {
+                let comparison = match self.next() {
+                    Some(Token::Op(comparison)) => comparison,
+                    _ => {
+                        return Err("credits must be followed by a comparison like >=".to_string());
+                    }
+                };
+                match self.next() {
+                    Some(Token::Number(val)) if val <= u32::from(u8::MAX) => Ok(Rule::Credits(comparison, val as u8)),
+                    _ => Err("credits must be compared with a number of credits".to_string()),
+                }
+            }
+            _ => Err(format!("Unknown condition \"{}\"; use studies, residency, credits, new_student or orientation", fact)),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Rule, String> {
+    if text.trim().is_empty() {
+        return Ok(Rule::Always);
+    }
+    let mut parser = Parser { tokens: tokenize(text)?, position: 0 };
+    let rule = parser.or()?;
+    match parser.next() {
+        Some(token) => Err(format!("Unexpected {} after the end of the rule", token)),
+        None => Ok(rule),
+    }
+}
+
+impl Rule {
+    pub fn applies(&self, facts: &Facts) -> bool {
+        match self {
+            Rule::Always => true,
+            Rule::Studies(equal, val) => (facts.studies == val) == *equal,
+            Rule::Residency(equal, val) => (facts.residency == val) == *equal,
+            Rule::Credits(comparison, val) => match comparison {
+                Comparison::Equal => facts.credits == *val,
+                Comparison::NotEqual => facts.credits != *val,
+                Comparison::Less => facts.credits < *val,
+                Comparison::LessOrEqual => facts.credits <= *val,
+                Comparison::Greater => facts.credits > *val,
+                Comparison::GreaterOrEqual => facts.credits >= *val,
+            },
+            Rule::NewStudent => facts.new_student,
+            Rule::Orientation => facts.orientation,
+            Rule::Not(rule) => !rule.applies(facts),
+            Rule::And(left, right) => left.applies(facts) && right.applies(facts),
+            Rule::Or(left, right) => left.applies(facts) || right.applies(facts),
+        }
+    }
+
+    fn mentions_orientation(&self) -> bool {
+        match self {
+            Rule::Orientation => true,
+            Rule::Not(rule) => rule.mentions_orientation(),
+            Rule::And(left, right) | Rule::Or(left, right) => left.mentions_orientation() || right.mentions_orientation(),
+            _ => false,
+        }
+    }
+}
+
+// The line items whose rules pick out this student, with aid taken off. Rules are checked when
+// they're saved, so an error here means one was stored some other way.
+pub fn applicable(items: &[LineItem], facts: &Facts) -> Result<Vec<BreakdownItem>, String> {
+    let mut fees = Vec::new();
+    for item in items {
+        match parse(&item.applies_when) {
+            Ok(rule) if rule.applies(facts) => fees.push(BreakdownItem { label: item.label.clone(), amount: item.category.signed(item.amount), category: item.category }),
+            Ok(_) => {},
+            Err(why) => {
+                return Err(format!("The rule for \"{}\" is invalid: {}", item.label, why));
+            }
+        }
+    }
+    Ok(fees)
+}
+
+// The calculator fields a validation rule can put its message on.
+pub const FIELDS: [&str; 6] = ["num_credits", "new_student", "orientation", "student_type", "student_studies", "insurance_waiver"];
+
+pub fn field(name: &str) -> Option<&'static str> {
+    FIELDS.iter().find(|field| **field == name).copied()
+}
+
+// Whether a validation rule can be saved. A blank rule would turn everyone away.
+pub fn check_validation_rule(field_name: &str, reject_when: &str) -> Result<(), String> {
+    if field(field_name).is_none() {
+        return Err(format!("\"{}\" isn't a calculator field; use one of {}", field_name, FIELDS.join(", ")));
+    }
+    if reject_when.trim().is_empty() {
+        return Err("A rule saying which submissions to turn away is required".to_string());
+    }
+    parse(reject_when).map(|_| ())
+}
+
+// Every validation rule the submission breaks, so the student can fix them all in one go. Like
+// line item rules, these are checked when they're saved.
+pub fn violations(validation_rules: &[ValidationRule], facts: &Facts) -> Result<Vec<FieldError>, String> {
+    let mut errors = Vec::new();
+    for validation_rule in validation_rules {
+        let field = match field(&validation_rule.field) {
+            Some(val) => val,
+            None => {
+                return Err(format!("The validation rule \"{}\" is for an unknown field \"{}\"", validation_rule.message, validation_rule.field));
+            }
+        };
+        match parse(&validation_rule.reject_when) {
+            Ok(rule) if rule.applies(facts) => errors.push(FieldError { field, message: validation_rule.message.clone() }),
+            Ok(_) => {},
+            Err(why) => {
+                return Err(format!("The validation rule \"{}\" is invalid: {}", validation_rule.message, why));
+            }
+        }
+    }
+    Ok(errors)
+}
+
+// Whether an orientation exemption can be saved. A blank rule would exempt everyone, which is
+// the same as an orientation fee of zero, and whether the box was checked is what's exempted.
+pub fn check_orientation_exemption(exempt_when: &str) -> Result<(), String> {
+    if exempt_when.trim().is_empty() {
+        return Err("A rule saying who is exempt is required".to_string());
+    }
+    if parse(exempt_when)?.mentions_orientation() {
+        return Err("An exemption can't depend on orientation itself".to_string());
+    }
+    Ok(())
+}
+
+// The first exemption that picks out this student, if any. Checked when saved, as above.
+pub fn orientation_exemption<'a>(exemptions: &'a [OrientationExemption], facts: &Facts) -> Result<Option<&'a OrientationExemption>, String> {
+    for exemption in exemptions {
+        match parse(&exemption.exempt_when) {
+            Ok(rule) if rule.applies(facts) => {
+                return Ok(Some(exemption));
+            }
+            Ok(_) => {},
+            Err(why) => {
+                return Err(format!("The orientation exemption \"{}\" is invalid: {}", exemption.label, why));
+            }
+        }
+    }
+    Ok(None)
+}
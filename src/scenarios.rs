@@ -0,0 +1,187 @@
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{error::AppError, form, form_with_errors, models::{Campus, Scenario, ScenarioId}, queries, render, residency, student_auth::{self, Student}, AppState, CalculateTuitionFormParams, IndexPage, course_codes_column, resolve_residency, MAX_NAME_LENGTH, TypeSafeParameters};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveScenarioFormParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    scenario_name: Option<String>,
+    #[serde(flatten)]
+    params: CalculateTuitionFormParams,
+}
+
+#[derive(Serialize)]
+struct ScenariosPage {
+    student: Student,
+    scenarios: Vec<Scenario>,
+}
+
+// Send the student back to their list of scenarios.
+fn redirect_to_list() -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/scenarios"))
+        .finish()
+}
+
+// Scenarios belong to the student signed in when they were saved, and only they see them.
+pub async fn save(state: web::Data<AppState>, campus: Campus, session: Session, form: web::Form<SaveScenarioFormParams>) -> Result<HttpResponse, AppError> {
+    let form = form.into_inner();
+    let student = match student_auth::signed_in(&campus, &session) {
+        Some(val) => val,
+        None => {
+            let why = AppError::validation("scenario_name", "Sign in with the email address you confirmed to save scenarios.");
+            return form_with_errors(&state, campus, &session, form.params, form.scenario_name, None, &why).await;
+        }
+    };
+    match save_scenario(&state, &campus, &student, &form).await {
+        Err(why @ AppError::Validation { .. }) => form_with_errors(&state, campus, &session, form.params, form.scenario_name, None, &why).await,
+        result => result,
+    }
+}
+
+async fn save_scenario(state: &AppState, campus: &Campus, student: &Student, form: &SaveScenarioFormParams) -> Result<HttpResponse, AppError> {
+    let pool = &state.conn;
+
+    let scenario_name = match &form.scenario_name {
+        Some(val) => val.nfc().collect::<String>(),
+        None => {
+            return Err(AppError::validation("scenario_name", "No scenario name was provided!"));
+        }
+    };
+    if scenario_name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::validation("scenario_name", &format!("Scenario names can be at most {} characters.", MAX_NAME_LENGTH)));
+    }
+    let mut type_safe_parameters = match TypeSafeParameters::from_form(&form.params) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why);
+        }
+    };
+    state.form_fields(campus).await?.apply(&mut type_safe_parameters);
+    resolve_residency(state, campus, &mut type_safe_parameters).await?;
+    // A scenario keeps the student's name and answers too.
+    if state.consent.is_some() && !form.params.consent {
+        return Err(AppError::validation("consent", "Tick the box agreeing to us keeping your answers to save a scenario."));
+    }
+
+    // Saving under an existing name replaces that scenario.
+    match queries::INSERT_SCENARIO.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(campus_id)
+    .bind(student.id)
+    .bind(&scenario_name)
+    .bind(&type_safe_parameters.first_name)
+    .bind(&type_safe_parameters.last_name)
+    .bind(type_safe_parameters.num_credits)
+    .bind(type_safe_parameters.new_student)
+    .bind(type_safe_parameters.orientation)
+    .bind(type_safe_parameters.student_type.as_str())
+    .bind(type_safe_parameters.student_studies.as_str())
+    .bind(type_safe_parameters.include_additional_costs)
+    .bind(course_codes_column(&type_safe_parameters.course_codes))
+    .bind(type_safe_parameters.insurance_waived)
+    .bind(&type_safe_parameters.home_state)
+    .execute(pool))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    Ok(redirect_to_list())
+}
+
+pub async fn list(state: web::Data<AppState>, campus: Campus, session: Session) -> Result<HttpResponse, AppError> {
+    let pool = &state.conn;
+
+    let student = match student_auth::signed_in(&campus, &session) {
+        Some(val) => val,
+        None => {
+            return Ok(student_auth::redirect_to_sign_in());
+        }
+    };
+
+    let scenarios = match queries::SCENARIOS_FOR_STUDENT.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, Scenario>(sql)
+    .bind(campus_id)
+    .bind(student.id)
+    .fetch_all(pool)).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    render(&state, "scenarios", &ScenariosPage { student, scenarios }).await
+}
+
+pub async fn load(state: web::Data<AppState>, campus: Campus, session: Session, id: web::Path<ScenarioId>) -> Result<HttpResponse, AppError> {
+    let pool = &state.conn;
+
+    let student = match student_auth::signed_in(&campus, &session) {
+        Some(val) => val,
+        None => {
+            return Ok(student_auth::redirect_to_sign_in());
+        }
+    };
+
+    // Someone else's scenario looks the same as one that doesn't exist.
+    let scenario = match queries::SCENARIO_BY_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, Scenario>(sql)
+    .bind(id.into_inner())
+    .bind(campus_id)
+    .bind(student.id)
+    .fetch_optional(pool)).await {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return Err(AppError::NotFound("That scenario doesn't exist.".to_string()));
+        }
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    let recent_receipts = state.recent_receipts(&campus, &session).await?;
+    let term_notice = state.current_term_notice(&campus).await?;
+    let fields = state.form_fields(&campus).await?;
+    render(&state, "index", &IndexPage {
+        campus,
+        form: Some(scenario.to_form()),
+        scenario_name: Some(scenario.scenario_name),
+        captcha: state.captcha_widget(),
+        can_email: state.mailer.is_some(),
+        recent_receipts,
+        errors: None,
+        counselor: None,
+        term_notice,
+        consent: state.consent.clone(),
+        fields,
+        states: &residency::STATES,
+    }).await
+}
+
+pub async fn delete(state: web::Data<AppState>, campus: Campus, session: Session, id: web::Path<ScenarioId>) -> Result<HttpResponse, AppError> {
+    let pool = &state.conn;
+
+    let student = match student_auth::signed_in(&campus, &session) {
+        Some(val) => val,
+        None => {
+            return Err(AppError::Unauthorized("Sign in to delete your scenarios.".to_string()));
+        }
+    };
+
+    match queries::DELETE_SCENARIO.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+    .bind(id.into_inner())
+    .bind(campus_id)
+    .bind(student.id)
+    .execute(pool))
+    .await {
+        Ok(_val) => {},
+        Err(why) => {
+            return Err(AppError::from(why));
+        }
+    };
+
+    Ok(redirect_to_list())
+}
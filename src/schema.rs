@@ -0,0 +1,146 @@
+use sqlx::MySqlPool;
+use std::collections::{HashMap, HashSet};
+
+use crate::queries;
+
+// Every table and column the queries use. Keep this in step with the migrations: a column
+// added in a new migration goes here too.
+const EXPECTED: &[(&str, &[&str])] = &[
+    ("Campuses", &["Id", "Slug", "Name", "Hostname", "State"]),
+    ("CreditCosts", &["CampusId", "Studies", "Residency", "CreditsCost", "NonresidencyFee"]),
+    ("orientation_fee", &["CampusId", "Fee"]),
+    ("HealthInsuranceFee", &["CampusId", "Fee"]),
+    ("RateIncrease", &["CampusId", "AnnualPercent"]),
+    ("InternationalFees", &["Id", "CampusId", "Label", "Amount"]),
+    ("IndirectCosts", &["Id", "CampusId", "Studies", "Label", "Amount"]),
+    ("CourseFees", &["Id", "CampusId", "Department", "CourseCode", "Label", "Fee"]),
+    ("Terms", &["Id", "CampusId", "Name", "StartsOn", "OpensOn", "ClosesOn"]),
+    ("ProrationRules", &["Id", "TermId", "AfterWeek", "TuitionPercent"]),
+    ("RefundRules", &["Id", "TermId", "ThroughWeek", "RefundPercent"]),
+    ("CustomLineItems", &["Id", "CampusId", "Term", "Label", "Amount", "AppliesWhen", "Category"]),
+    ("ValidationRules", &["Id", "CampusId", "Field", "RejectWhen", "Message"]),
+    ("FormFields", &["CampusId", "Field", "Visible"]),
+    ("Students", &["Id", "CampusId", "PublicId", "FirstName", "LastName", "Email", "CreatedAt"]),
+    ("TuitionRecords", &["Id", "StudentId", "Term", "TuitionCost", "NumCredits", "Orientation", "StudentType", "StudentStudies", "InsuranceWaived", "ConsentedAt", "UpdatedAt"]),
+    ("Receipts", &[
+        "Id", "Code", "CampusId", "StudentId", "FirstName", "LastName", "Term", "NumCredits", "Orientation", "StudentType",
+        "StudentStudies", "CreditsCost", "NonresidencyFee", "OrientationFee", "TuitionCost", "CreatedAt", "EnrollmentDate",
+        "TuitionPercent", "CourseCodes", "CourseFees", "InsuranceWaived", "HealthInsuranceFee", "InternationalFees",
+        "CustomFees", "CustomItems", "ClientIpHash", "UserAgent", "Referrer", "EnteredBy", "OrientationAnswer", "Channel",
+    ]),
+    ("EmailVerifications", &["Id", "PublicId", "StudentId", "Email", "CodeHash", "Attempts", "ExpiresAt", "ConfirmedAt", "CreatedAt"]),
+    ("StudentSignIns", &["Id", "PublicId", "StudentId", "CodeHash", "Attempts", "ExpiresAt", "UsedAt", "CreatedAt"]),
+    ("OrientationExemptions", &["Id", "CampusId", "Label", "ExemptWhen"]),
+    ("ReciprocityAgreements", &["Id", "CampusId", "State", "Label", "Residency", "NonresidencyFeePercent"]),
+    ("ReceiptAdjustments", &["Id", "ReceiptId", "ItemLabel", "OriginalAmount", "AdjustedAmount", "Note", "AdjustedBy", "CreatedAt"]),
+    ("Programs", &["Id", "CampusId", "Studies", "Name", "TotalCredits"]),
+    ("RefundEstimates", &["Id", "ReceiptId", "WithdrawalDate", "Week", "RefundPercent", "RefundAmount", "CreatedAt"]),
+    ("Scenarios", &["Id", "CampusId", "StudentId", "ScenarioName", "FirstName", "LastName", "NumCredits", "NewStudent", "Orientation", "StudentType", "StudentStudies", "IncludeAdditionalCosts", "CourseCodes", "InsuranceWaived", "HomeState"]),
+    ("ApiKeys", &["Id", "Name", "KeyHash", "RequestsPerMinute", "CreatedAt", "RevokedAt"]),
+    ("AuditLog", &["Id", "Actor", "Action", "Entity", "EntityId", "Details", "CreatedAt"]),
+    ("Maintenance", &["Id", "Enabled", "Message", "UpdatedAt"]),
+    ("SlowQueries", &["Id", "QueryName", "DurationMs", "RecordedAt"]),
+    ("BulkDeletes", &["Id", "CampusId", "Selection", "Action", "Actor", "ReceiptsTotal", "RecordsTotal", "ReceiptsDone", "RecordsDone", "StudentsDone", "Batches", "StartedAt", "UpdatedAt", "FinishedAt", "Error", "RunningCampusId"]),
+];
+
+// The indexes the lookups count on, by their leading columns. Without them the lookups still
+// work but scan the table, which is slow past about 100,000 rows.
+const EXPECTED_INDEXES: &[(&str, &[&str])] = &[
+    // Finding a student by name, and by the id in their links.
+    ("Students", &["CampusId", "LastName", "FirstName"]),
+    ("Students", &["PublicId"]),
+    ("TuitionRecords", &["StudentId", "Term"]),
+    ("Receipts", &["Code"]),
+    ("Receipts", &["StudentId", "Term"]),
+    ("Receipts", &["CampusId", "CreatedAt"]),
+    ("Receipts", &["CreatedAt"]),
+    ("ReceiptAdjustments", &["ReceiptId"]),
+    ("Scenarios", &["StudentId", "ScenarioName"]),
+    // Fee lookups, for every calculation when rates come from the database.
+    ("CreditCosts", &["CampusId", "Studies", "Residency"]),
+    ("orientation_fee", &["CampusId"]),
+    ("HealthInsuranceFee", &["CampusId"]),
+    ("InternationalFees", &["CampusId"]),
+    ("IndirectCosts", &["CampusId", "Studies"]),
+    ("CourseFees", &["CampusId", "CourseCode"]),
+    ("Terms", &["CampusId", "Name"]),
+    ("ProrationRules", &["TermId"]),
+    ("RefundRules", &["TermId"]),
+    ("CustomLineItems", &["CampusId", "Term"]),
+    ("Programs", &["CampusId", "Studies"]),
+];
+
+// What's missing from the database's schema, e.g. a replica that hasn't caught up with the last
+// migration or a column someone dropped by hand. Empty when everything is there.
+pub async fn missing(pool: &MySqlPool) -> Result<Vec<String>, sqlx::Error> {
+    let columns = queries::SCHEMA_COLUMNS.run(|sql| sqlx::query_as::<_, (String, String)>(sql)
+    .fetch_all(pool)).await?;
+
+    // Table names are case-insensitive on some servers, depending on lower_case_table_names.
+    let found: HashSet<(String, String)> = columns.into_iter()
+        .map(|(table, column)| (table.to_lowercase(), column.to_lowercase()))
+        .collect();
+    let tables: HashSet<String> = found.iter().map(|(table, _)| table.clone()).collect();
+
+    let mut missing = Vec::new();
+    for (table, expected) in EXPECTED {
+        if !tables.contains(&table.to_lowercase()) {
+            missing.push(format!("table {}", table));
+            continue;
+        }
+        for column in expected.iter() {
+            if !found.contains(&(table.to_lowercase(), column.to_lowercase())) {
+                missing.push(format!("column {}.{}", table, column));
+            }
+        }
+    }
+    Ok(missing)
+}
+
+// The expected indexes no index on the table starts with, e.g. one dropped by hand or never
+// made on a replica that was set up separately.
+pub async fn missing_indexes(pool: &MySqlPool) -> Result<Vec<String>, sqlx::Error> {
+    let columns = queries::SCHEMA_INDEXES.run(|sql| sqlx::query_as::<_, (String, String, String)>(sql)
+    .fetch_all(pool)).await?;
+
+    // Each index's columns in order, by table.
+    let mut indexes: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for (table, index, column) in columns {
+        indexes.entry((table.to_lowercase(), index)).or_default().push(column.to_lowercase());
+    }
+
+    let mut missing = Vec::new();
+    for (table, expected) in EXPECTED_INDEXES {
+        let covered = indexes.iter().any(|((indexed_table, _), columns)| {
+            *indexed_table == table.to_lowercase()
+                && columns.len() >= expected.len()
+                && expected.iter().zip(columns).all(|(want, have)| want.to_lowercase() == *have)
+        });
+        if !covered {
+            missing.push(format!("{} ({})", table, expected.join(", ")));
+        }
+    }
+    Ok(missing)
+}
+
+// Stop at startup rather than fail every request that touches what's missing. Missing indexes
+// only slow things down, so they're warned about and the server starts anyway.
+pub async fn check(pool: &MySqlPool, name: &str) -> Result<(), sqlx::Error> {
+    let missing = missing(pool).await?;
+    if missing.is_empty() {
+        let missing = missing_indexes(pool).await?;
+        if !missing.is_empty() {
+            println!("The {} is missing {} index(es) lookups need, so they'll scan the whole table:", name, missing.len());
+            for index in &missing {
+                println!("  - {}", index);
+            }
+        }
+        return Ok(());
+    }
+    println!("The {} is missing {} part(s) of the schema this version needs:", name, missing.len());
+    for part in &missing {
+        println!("  - {}", part);
+    }
+    println!("Run the migrations against it (or wait for the replica to catch up) and start the server again.");
+    std::process::exit(1);
+}
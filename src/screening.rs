@@ -0,0 +1,185 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web, HttpMessage,
+};
+use std::{fmt, str::FromStr};
+
+use crate::{client_ip, error::{AppError, RequestId}, logs, AppState};
+
+// What happens to a submission with a field that looks like an attack or makes no sense. The
+// public form is probed constantly; the queries are parameterized and the templates escape, so
+// this is for seeing the probes and turning the obvious ones away early, not what keeps us safe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreeningMode {
+    Off,
+    // Log the anomaly and carry on with the request.
+    Log,
+    // Log it and answer 400 without running the handler.
+    Block,
+}
+
+impl FromStr for ScreeningMode {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<ScreeningMode, String> {
+        match val.to_ascii_lowercase().as_str() {
+            "off" => Ok(ScreeningMode::Off),
+            "log" => Ok(ScreeningMode::Log),
+            "block" => Ok(ScreeningMode::Block),
+            _ => Err(format!("Unknown screening mode \"{}\"; use off, log or block.", val)),
+        }
+    }
+}
+
+impl fmt::Display for ScreeningMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreeningMode::Off => write!(f, "off"),
+            ScreeningMode::Log => write!(f, "log"),
+            ScreeningMode::Block => write!(f, "block"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    SqlInjection,
+    Script,
+    PathTraversal,
+    ControlCharacters,
+    TooLong,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::SqlInjection => write!(f, "SQL injection"),
+            Anomaly::Script => write!(f, "script injection"),
+            Anomaly::PathTraversal => write!(f, "path traversal"),
+            Anomaly::ControlCharacters => write!(f, "control characters"),
+            Anomaly::TooLong => write!(f, "an overlong value"),
+        }
+    }
+}
+
+// No field on any of the forms needs more; names are capped at 100.
+const MAX_FIELD_CHARS: usize = 1000;
+
+// Matched against the value lowercased, with comments and runs of whitespace made one space.
+const SQL_PATTERNS: &[&str] = &[
+    "union select", "union all select", "' or '", "' or 1", "\" or \"", "or 1=1", "'--", "' --", "'#",
+    "; drop ", ";drop ", "; delete ", "; insert ", "; update ", "information_schema", "sleep(",
+    "benchmark(", "waitfor delay", "xp_cmdshell", "load_file(", "into outfile",
+];
+const SCRIPT_PATTERNS: &[&str] = &[
+    "<script", "</script", "javascript:", "vbscript:", "onerror=", "onload=", "onmouseover=", "onfocus=",
+    "<iframe", "<svg", "<img", "document.cookie", "eval(", "alert(",
+];
+const PATH_PATTERNS: &[&str] = &["../", "..\\", "%2e%2e", "/etc/passwd"];
+
+fn normalize(val: &str) -> String {
+    val.to_lowercase()
+        .replace("/**/", " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" =", "=")
+}
+
+// What's wrong with one value, if anything.
+pub fn check(val: &str) -> Option<Anomaly> {
+    if val.chars().count() > MAX_FIELD_CHARS {
+        return Some(Anomaly::TooLong);
+    }
+    if val.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')) {
+        return Some(Anomaly::ControlCharacters);
+    }
+    let normalized = normalize(val);
+    if SQL_PATTERNS.iter().any(|pattern| normalized.contains(pattern)) {
+        return Some(Anomaly::SqlInjection);
+    }
+    if SCRIPT_PATTERNS.iter().any(|pattern| normalized.contains(pattern)) {
+        return Some(Anomaly::Script);
+    }
+    if PATH_PATTERNS.iter().any(|pattern| normalized.contains(pattern)) {
+        return Some(Anomaly::PathTraversal);
+    }
+    None
+}
+
+// Every string in a JSON body, named by its path, e.g. "course_codes.0".
+fn json_fields(prefix: &str, val: &serde_json::Value, fields: &mut Vec<(String, String)>) {
+    match val {
+        serde_json::Value::String(text) => fields.push((prefix.to_string(), text.clone())),
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                json_fields(&format!("{}.{}", prefix, i), item, fields);
+            }
+        }
+        serde_json::Value::Object(entries) => {
+            for (key, item) in entries {
+                let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                json_fields(&name, item, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+// The submitted fields, going by the Content-Type as `form::Submitted` does. Anything that
+// doesn't parse is left to the handler to turn down.
+fn body_fields(content_type: &str, body: &[u8]) -> Vec<(String, String)> {
+    if content_type == "application/json" {
+        let mut fields = Vec::new();
+        if let Ok(val) = serde_json::from_slice::<serde_json::Value>(body) {
+            json_fields("", &val, &mut fields);
+        }
+        return fields;
+    }
+    serde_urlencoded::from_bytes::<Vec<(String, String)>>(body).unwrap_or_default()
+}
+
+// The first field that trips a check, and why. Field names are checked too.
+fn screen_fields(fields: &[(String, String)]) -> Option<(String, Anomaly)> {
+    fields.iter().find_map(|(name, val)| {
+        check(name).or_else(|| check(val)).map(|anomaly| (name.chars().take(64).collect(), anomaly))
+    })
+}
+
+// Screens the query string and, on posts, the body of requests to the public pages. The body
+// is read here and put back for the handler.
+pub async fn screen(mut req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let mode = req.app_data::<web::Data<AppState>>().map(|state| state.screening).unwrap_or(ScreeningMode::Off);
+    if mode == ScreeningMode::Off {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    }
+
+    let mut fields = serde_urlencoded::from_str::<Vec<(String, String)>>(req.query_string()).unwrap_or_default();
+    if req.method() == Method::POST {
+        let body = req.extract::<web::Bytes>().await?;
+        fields.extend(body_fields(req.content_type(), &body));
+        req.set_payload(body.into());
+    }
+
+    let (field, anomaly) = match screen_fields(&fields) {
+        Some(val) => val,
+        None => {
+            return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+        }
+    };
+    let request_id = RequestId::of(req.request());
+    let action = if mode == ScreeningMode::Block { "blocked" } else { "allowed" };
+    // Scanners send the same probe over and over; one line a minute per kind is enough.
+    logs::throttled_as(
+        &format!("Screening: {} in {} on {} {} ({})", anomaly, field, req.method(), req.path(), action),
+        &format!("[{}] Screening: {} in {} on {} {} from {} ({})", request_id, anomaly, field, req.method(), req.path(),
+            client_ip::for_request(req.request()).map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string()), action),
+    );
+    if mode == ScreeningMode::Log {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    }
+    Ok(req.error_response(AppError::Blocked(format!("The {} field can't be accepted. Please enter plain text and try again.", field))))
+}
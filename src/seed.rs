@@ -0,0 +1,262 @@
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use rand::{seq::SliceRandom, Rng};
+use rust_decimal::Decimal;
+use sqlx::MySqlPool;
+
+use crate::{fees::FeeSchedule, ids, models::Campus, money::Money, pricing, queries, receipts};
+
+// How many made-up students each campus gets.
+const DEMO_STUDENTS: usize = 300;
+
+const FIRST_NAMES: &[&str] = &[
+    "Olivia", "Liam", "Emma", "Noah", "Ava", "Mateo", "Sophia", "Elijah", "Isabella", "Lucas", "Mia", "Amir",
+    "Charlotte", "Ethan", "Amelia", "Kai", "Harper", "Diego", "Evelyn", "Hiro", "Priya", "Jamal", "Chloe", "Omar",
+    "Grace", "Wei", "Lena", "Santiago", "Zoe", "Ibrahim",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Smith", "Johnson", "Garcia", "Nguyen", "Brown", "Martinez", "Lee", "Patel", "Kim", "Hernandez", "Okafor",
+    "Lopez", "Wilson", "Anderson", "Tanaka", "Thomas", "Moore", "Jackson", "Kowalski", "White", "Haddad", "Clark",
+    "Lewis", "Robinson", "Walker", "Young", "Allen", "Chen", "Singh", "Rossi",
+];
+
+// Most students are resident undergraduates, the way most real traffic is.
+const RESIDENCIES: &[(&str, u32)] = &[("resident", 70), ("nonresident", 20), ("international", 10)];
+const STUDIES: &[(&str, u32)] = &[("undergraduate", 75), ("graduate", 20), ("dual_enrollment", 5)];
+
+fn weighted<'a>(rng: &mut impl Rng, choices: &[(&'a str, u32)]) -> &'a str {
+    let total: u32 = choices.iter().map(|(_, weight)| weight).sum();
+    let mut pick = rng.gen_range(0..total);
+    for (choice, weight) in choices {
+        if pick < *weight {
+            return choice;
+        }
+        pick -= weight;
+    }
+    choices[0].0
+}
+
+// The rates in fees.example.toml, so the demo prices the same as the example file.
+fn demo_schedule() -> FeeSchedule {
+    FeeSchedule::parse(include_str!("../fees.example.toml"), "fees.example.toml").expect("Invalid example fee schedule.")
+}
+
+// The last year's worth of terms up to today, oldest first, with a day in each to date
+// calculations on.
+fn recent_terms(today: NaiveDate) -> Vec<(String, NaiveDate)> {
+    let mut terms: Vec<(String, NaiveDate)> = Vec::new();
+    for months_back in (0..12).rev() {
+        let day = today - Duration::days(30 * months_back);
+        let term = receipts::term_for(day);
+        if !terms.iter().any(|(name, _)| *name == term) {
+            terms.push((term, day));
+        }
+    }
+    terms
+}
+
+// Rates, terms and fees for the campus from the example schedule, replacing what's there.
+async fn seed_rates(pool: &MySqlPool, campus: &Campus, schedule: &FeeSchedule) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for entry in &schedule.credit_costs {
+        queries::SEED_CREDIT_COST.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(campus_id)
+        .bind(&entry.studies)
+        .bind(&entry.residency)
+        .bind(entry.costs.credits_cost)
+        .bind(entry.costs.nonresidency_fee)
+        .execute(&mut tx)).await?;
+    }
+    queries::SEED_ORIENTATION_FEE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(campus_id)
+        .bind(schedule.orientation_fee)
+        .execute(&mut tx)).await?;
+    queries::SEED_HEALTH_INSURANCE_FEE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(campus_id)
+        .bind(schedule.health_insurance_fee)
+        .execute(&mut tx)).await?;
+    queries::SET_RATE_INCREASE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(campus_id)
+        .bind(schedule.annual_rate_increase)
+        .execute(&mut tx)).await?;
+
+    // These have no natural key, so running the seed again replaces them instead of adding copies.
+    queries::CLEAR_INTERNATIONAL_FEES.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql).bind(campus_id).execute(&mut tx)).await?;
+    for fee in &schedule.international_fees {
+        queries::INSERT_INTERNATIONAL_FEE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(campus_id)
+            .bind(&fee.label)
+            .bind(fee.amount)
+            .execute(&mut tx)).await?;
+    }
+    queries::CLEAR_INDIRECT_COSTS.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql).bind(campus_id).execute(&mut tx)).await?;
+    for entry in &schedule.indirect_costs {
+        queries::INSERT_INDIRECT_COST.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(campus_id)
+            .bind(&entry.studies)
+            .bind(&entry.cost.label)
+            .bind(entry.cost.amount)
+            .execute(&mut tx)).await?;
+    }
+    queries::CLEAR_PROGRAMS.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql).bind(campus_id).execute(&mut tx)).await?;
+    for entry in &schedule.programs {
+        queries::INSERT_PROGRAM.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(campus_id)
+            .bind(&entry.studies)
+            .bind(&entry.program.name)
+            .bind(entry.program.total_credits)
+            .execute(&mut tx)).await?;
+    }
+    for fee in &schedule.course_fees {
+        queries::SEED_COURSE_FEE.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(campus_id)
+        .bind(&fee.department)
+        .bind(&fee.course_code)
+        .bind(&fee.label)
+        .bind(fee.fee)
+        .execute(&mut tx)).await?;
+    }
+
+    for term in &schedule.terms {
+        queries::UPSERT_TERM.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+        .bind(campus_id)
+        .bind(&term.name)
+        .bind(term.starts_on)
+        .bind(term.opens_on)
+        .bind(term.closes_on)
+        .execute(&mut tx)).await?;
+        let term_id = queries::TERM_ID.scoped(campus.id).run(|sql, campus_id| sqlx::query_scalar::<_, i32>(sql)
+            .bind(campus_id)
+            .bind(&term.name)
+            .fetch_one(&mut tx)).await?;
+        for rule in &term.proration {
+            queries::SEED_PRORATION_RULE.run(|sql| sqlx::query(sql)
+                .bind(term_id)
+                .bind(rule.after_week)
+                .bind(rule.tuition_percent)
+                .execute(&mut tx)).await?;
+        }
+        for rule in &term.refunds {
+            queries::UPSERT_REFUND_RULE.run(|sql| sqlx::query(sql)
+                .bind(term_id)
+                .bind(rule.through_week)
+                .bind(rule.refund_percent)
+                .execute(&mut tx)).await?;
+        }
+        queries::CLEAR_LINE_ITEMS.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(campus_id)
+            .bind(&term.name)
+            .execute(&mut tx)).await?;
+        for item in &term.line_items {
+            queries::INSERT_LINE_ITEM.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+                .bind(campus_id)
+                .bind(&term.name)
+                .bind(&item.label)
+                .bind(item.amount)
+                .bind(&item.applies_when)
+                .bind(item.category)
+                .execute(&mut tx)).await?;
+        }
+    }
+    tx.commit().await
+}
+
+// Made-up students, each with a saved record and a receipt for one to three recent terms,
+// priced with the campus's seeded rates. Names that already exist are skipped.
+async fn seed_students(pool: &MySqlPool, campus: &Campus, schedule: &FeeSchedule) -> Result<usize, sqlx::Error> {
+    let terms = recent_terms(Local::now().date_naive());
+    let international_total: Money = schedule.international_fees.iter().map(|fee| fee.amount).sum();
+    let mut rng = rand::thread_rng();
+    let mut tx = pool.begin().await?;
+    let mut added = 0;
+
+    for _ in 0..DEMO_STUDENTS {
+        let first_name = *FIRST_NAMES.choose(&mut rng).unwrap_or(&"Alex");
+        let last_name = *LAST_NAMES.choose(&mut rng).unwrap_or(&"Doe");
+        let inserted = queries::SEED_STUDENT.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(campus_id)
+            .bind(ids::new_public_id())
+            .bind(first_name)
+            .bind(last_name)
+            .execute(&mut tx)).await?;
+        if inserted.rows_affected() == 0 {
+            continue;
+        }
+        let student_id = inserted.last_insert_id() as i32;
+        added += 1;
+
+        let mut studies = weighted(&mut rng, STUDIES);
+        let mut residency = weighted(&mut rng, RESIDENCIES);
+        let costs = loop {
+            match schedule.credit_costs.iter().find(|entry| entry.studies == studies && entry.residency == residency) {
+                Some(entry) => break entry.costs.clone(),
+                // Not every pair is offered, e.g. international dual enrollment.
+                None => {
+                    studies = weighted(&mut rng, STUDIES);
+                    residency = weighted(&mut rng, RESIDENCIES);
+                }
+            }
+        };
+
+        let how_many = rng.gen_range(1..=terms.len().min(3));
+        for (term, day) in &terms[terms.len() - how_many..] {
+            let num_credits: u8 = if studies == "dual_enrollment" { rng.gen_range(3..=8) } else { rng.gen_range(6..=18) };
+            let orientation = rng.gen_bool(0.3);
+            let insurance_waived = rng.gen_bool(0.4);
+            let health_insurance_fee = pricing::health_insurance_charge(insurance_waived, schedule.health_insurance_fee);
+            let international_fees = if residency == "international" { international_total } else { Money::new(Decimal::ZERO) };
+            let total = pricing::tuition_total(num_credits, orientation, &costs, schedule.orientation_fee) + health_insurance_fee + international_fees;
+            let created_at: NaiveDateTime = (*day - Duration::days(rng.gen_range(0..30)))
+                .and_hms_opt(rng.gen_range(8..22), rng.gen_range(0..60), 0)
+                .unwrap_or_default();
+
+            queries::SEED_TUITION_RECORD.run(|sql| sqlx::query(sql)
+            .bind(student_id)
+            .bind(term)
+            .bind(total)
+            .bind(num_credits)
+            .bind(orientation)
+            .bind(residency)
+            .bind(studies)
+            .bind(insurance_waived)
+            .bind(created_at)
+            .execute(&mut tx)).await?;
+
+            queries::SEED_RECEIPT.scoped(campus.id).run(|sql, campus_id| sqlx::query(sql)
+            .bind(receipts::new_code())
+            .bind(campus_id)
+            .bind(student_id)
+            .bind(first_name)
+            .bind(last_name)
+            .bind(term)
+            .bind(num_credits)
+            .bind(orientation)
+            .bind(residency)
+            .bind(studies)
+            .bind(costs.credits_cost)
+            .bind(costs.nonresidency_fee)
+            .bind(if orientation { schedule.orientation_fee } else { Money::new(Decimal::ZERO) })
+            .bind(total)
+            .bind(created_at)
+            .bind(insurance_waived)
+            .bind(health_insurance_fee)
+            .bind(international_fees)
+            .execute(&mut tx)).await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(added)
+}
+
+// `--seed-demo`: fill every campus with sample rates, terms and students for QA and
+// screenshots. Never run it against production; the rates it writes replace the real ones.
+pub async fn demo(pool: &MySqlPool, campuses: &[Campus]) -> Result<(), sqlx::Error> {
+    let schedule = demo_schedule();
+    for campus in campuses {
+        let campus_schedule = schedule.for_campus(campus);
+        seed_rates(pool, campus, campus_schedule).await?;
+        let added = seed_students(pool, campus, campus_schedule).await?;
+        println!("Seeded {} with the example rates and {} demo student(s).", campus.name, added);
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::Sha256;
+use std::fmt;
+
+use crate::{error::AppError, models::{Campus, Receipt, ReceiptId}, pricing::CategorySubtotal, receipts, recent, render, AppState};
+
+// Signs and checks the links a student can send to a parent. A token is
+// `<receipt id>.<expiry as unix seconds>.<hex HMAC-SHA256 of the first two>`, so the link can't
+// be changed to point at another receipt or to last longer, and it doesn't carry the receipt
+// code, which would open the receipt for good.
+#[derive(Clone)]
+pub struct ShareSigner {
+    key: Vec<u8>,
+    lifetime: Duration,
+}
+
+// Keeps the key out of the logs.
+impl fmt::Debug for ShareSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShareSigner").field("lifetime", &self.lifetime).finish_non_exhaustive()
+    }
+}
+
+impl ShareSigner {
+    // Without a configured key, links stop working when the server restarts.
+    pub fn new(key: Option<&[u8]>, days: u32) -> ShareSigner {
+        let key = match key {
+            Some(val) => val.to_vec(),
+            None => {
+                println!("SHARE_LINK_KEY is not set; shared estimate links will stop working when the server restarts.");
+                let mut key = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                key
+            }
+        };
+        ShareSigner { key, lifetime: Duration::days(i64::from(days)) }
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes a key of any length.");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    pub fn token(&self, id: ReceiptId, now: DateTime<Utc>) -> (String, DateTime<Utc>) {
+        let expires = now + self.lifetime;
+        let payload = format!("{}.{}", id, expires.timestamp());
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+        (format!("{}.{}", payload, signature), expires)
+    }
+
+    // The receipt a token is for, if it was signed by us and hasn't expired.
+    pub fn verify(&self, token: &str, now: DateTime<Utc>) -> Result<(ReceiptId, DateTime<Utc>), AppError> {
+        let invalid = || AppError::NotFound("This shared estimate link is invalid.".to_string());
+        let (payload, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+        if self.mac(payload).verify_slice(&signature).is_err() {
+            return Err(invalid());
+        }
+        let (id, expires) = payload.split_once('.').ok_or_else(invalid)?;
+        let id = id.parse::<i32>().map_err(|_| invalid())?;
+        let expires = expires.parse::<i64>().ok().and_then(|val| DateTime::from_timestamp(val, 0)).ok_or_else(invalid)?;
+        if expires <= now {
+            return Err(AppError::NotFound("This shared estimate link has expired. Ask for a new one.".to_string()));
+        }
+        Ok((ReceiptId(id), expires))
+    }
+}
+
+#[derive(Serialize)]
+struct ShareLinkPage {
+    code: String,
+    url: String,
+    expires: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct SharedPage {
+    campus: Campus,
+    receipt: Receipt,
+    categories: Vec<CategorySubtotal>,
+    expires: DateTime<Utc>,
+}
+
+// Make a link to a receipt that works without the code until it expires.
+pub async fn share(state: web::Data<AppState>, campus: Campus, req: HttpRequest, code: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let receipt = receipts::fetch_receipt(&state, &campus, &code).await?;
+    let (token, expires) = state.share_signer.token(receipt.id, Utc::now());
+    let url = {
+        let info = req.connection_info();
+        format!("{}://{}/shared/{}", info.scheme(), info.host(), token)
+    };
+    render(&state, "share", &ShareLinkPage { code: receipt.code, url, expires }).await
+}
+
+// The read-only breakdown behind a shared link.
+pub async fn shared(state: web::Data<AppState>, campus: Campus, token: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let (id, expires) = state.share_signer.verify(&token, Utc::now())?;
+    let receipt = receipts::fetch_receipt_by_id(&state, &campus, id).await?;
+    let categories = recent::breakdown(&receipt);
+    render(&state, "shared", &SharedPage { campus, receipt, categories, expires }).await
+}
@@ -0,0 +1,125 @@
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use chrono::{Local, NaiveDateTime};
+use serde::Serialize;
+use std::{
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use crate::{audit, error::AppError, queries, render, AppState};
+
+// Queries are timed in `queries`, where there's no pool to write with, so the slow ones wait
+// here until the next flush writes them to SlowQueries.
+static THRESHOLD: OnceLock<Duration> = OnceLock::new();
+static PENDING: Mutex<Vec<(&'static str, u32, NaiveDateTime)>> = Mutex::new(Vec::new());
+
+// Past this many waiting for a flush, more are dropped; the database is struggling anyway.
+const MAX_PENDING: usize = 1000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+// Older rows are pruned as new ones are written.
+const KEEP_DAYS: i64 = 14;
+// Rows listed under "Latest" on the admin page.
+const RECENT_LIMIT: i64 = 100;
+
+// Note a query if it took at least the threshold. Does nothing until `spawn` has set one.
+pub fn record(name: &'static str, elapsed: Duration) {
+    let threshold = match THRESHOLD.get() {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    // Writing a slow query down shouldn't be what gets written down next.
+    if elapsed < *threshold || name == queries::INSERT_SLOW_QUERY.name {
+        return;
+    }
+    if let Ok(mut pending) = PENDING.lock() {
+        if pending.len() < MAX_PENDING {
+            pending.push((name, elapsed.as_millis().min(u128::from(u32::MAX)) as u32, Local::now().naive_local()));
+        }
+    }
+}
+
+async fn flush(state: &AppState) -> Result<usize, sqlx::Error> {
+    let pending = match PENDING.lock() {
+        Ok(mut val) => std::mem::take(&mut *val),
+        Err(_) => Vec::new(),
+    };
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let cutoff = Local::now().naive_local() - chrono::Duration::days(KEEP_DAYS);
+    queries::DELETE_SLOW_QUERIES_BEFORE.run(|sql| sqlx::query(sql)
+    .bind(cutoff)
+    .execute(&state.conn)).await?;
+    for (name, duration_ms, recorded_at) in &pending {
+        queries::INSERT_SLOW_QUERY.run(|sql| sqlx::query(sql)
+        .bind(name)
+        .bind(duration_ms)
+        .bind(recorded_at)
+        .execute(&state.conn)).await?;
+    }
+    Ok(pending.len())
+}
+
+pub fn spawn(state: AppState, threshold: Duration) {
+    if THRESHOLD.set(threshold).is_err() {
+        return;
+    }
+    rt::spawn(async move {
+        loop {
+            rt::time::sleep(FLUSH_INTERVAL).await;
+            if let Err(why) = flush(&state).await {
+                println!("Error while recording slow queries: {}", why);
+            }
+        }
+    });
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct SlowQuerySummary {
+    query_name: String,
+    times: i64,
+    average_ms: u64,
+    max_ms: u32,
+    last_seen: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct SlowQuery {
+    query_name: String,
+    duration_ms: u32,
+    recorded_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+struct SlowQueriesPage {
+    threshold_ms: Option<u128>,
+    summary: Vec<SlowQuerySummary>,
+    recent: Vec<SlowQuery>,
+}
+
+// The queries that ran slow, worst in total first, then the latest one by one.
+pub async fn slow_queries(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let summary = queries::SLOW_QUERY_SUMMARY.run(|sql| sqlx::query_as::<_, SlowQuerySummary>(sql)
+    .fetch_all(&state.conn)).await?;
+    let recent = queries::RECENT_SLOW_QUERIES.run(|sql| sqlx::query_as::<_, SlowQuery>(sql)
+    .bind(RECENT_LIMIT)
+    .fetch_all(&state.conn)).await?;
+    render(&state, "admin_slow_queries", &SlowQueriesPage { threshold_ms: THRESHOLD.get().map(Duration::as_millis), summary, recent }).await
+}
+
+// Start over, e.g. after adding an index.
+pub async fn clear(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let cleared = queries::CLEAR_SLOW_QUERIES.run(|sql| sqlx::query(sql)
+    .execute(&state.conn)).await?.rows_affected();
+    let details = format!("Cleared {} slow query row(s)", cleared);
+    audit::record(&state.conn, &audit::actor(&req), "delete", "SlowQueries", 0, &details).await?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/slow-queries"))
+        .finish())
+}
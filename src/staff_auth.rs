@@ -0,0 +1,401 @@
+use actix_session::{Session, SessionExt};
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method},
+    middleware::Next,
+    web, HttpMessage, HttpResponse,
+};
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::{Arc, Mutex}};
+
+use crate::{config::OidcConfig, passwords::PasswordHash, error::AppError, http_client::{BoxFuture, HttpClient}, AppState};
+
+const SIGNED_IN_KEY: &str = "staff_signed_in";
+const PENDING_KEY: &str = "staff_sign_in";
+const CALLBACK_PATH: &str = "/admin/sign-in/callback";
+
+// Where staff accounts live. Signing in goes out to the provider and comes back to the callback
+// with a code, which the provider then turns into the staff member's name.
+pub trait AuthProvider: fmt::Debug + Send + Sync {
+    // Where to send someone who needs to sign in. `state` and `nonce` are checked when they come back.
+    fn sign_in_url<'a>(&'a self, state: &'a str, nonce: &'a str) -> BoxFuture<'a, Result<String, String>>;
+    // Who the code from the callback belongs to, once the provider vouches for them.
+    fn finish_sign_in<'a>(&'a self, code: &'a str, nonce: &'a str) -> BoxFuture<'a, Result<String, String>>;
+    // How long a sign-in lasts before the provider is asked again.
+    fn session_hours(&self) -> u32;
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == client_id,
+            Audience::Many(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    aud: Audience,
+    exp: i64,
+    nonce: Option<String>,
+    sub: String,
+    preferred_username: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+// OpenID Connect's authorization code flow, e.g. against the campus's Entra ID or Okta tenant.
+#[derive(Debug)]
+pub struct OidcProvider {
+    config: OidcConfig,
+    http: Arc<dyn HttpClient>,
+    // Fetched from the issuer on first use, then kept.
+    discovery: Mutex<Option<Discovery>>,
+}
+
+impl OidcProvider {
+    pub fn new(config: &OidcConfig, http: Arc<dyn HttpClient>) -> OidcProvider {
+        OidcProvider { config: config.clone(), http, discovery: Mutex::new(None) }
+    }
+
+    async fn discovery(&self) -> Result<Discovery, String> {
+        if let Some(discovery) = self.discovery.lock().unwrap().clone() {
+            return Ok(discovery);
+        }
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+        let response = match self.http.get(&url).await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Error contacting the sign-in provider: {}", why));
+            }
+        };
+        if response.status != 200 {
+            return Err(format!("The sign-in provider answered {} with status {}.", url, response.status));
+        }
+        let discovery = match serde_json::from_str::<Discovery>(&response.body) {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Invalid OpenID configuration from {}: {}", url, why));
+            }
+        };
+        if discovery.issuer.trim_end_matches('/') != self.config.issuer {
+            return Err(format!("{} says its issuer is \"{}\", not OIDC_ISSUER.", url, discovery.issuer));
+        }
+        *self.discovery.lock().unwrap() = Some(discovery.clone());
+        Ok(discovery)
+    }
+
+    async fn authorization_url(&self, state: &str, nonce: &str) -> Result<String, String> {
+        let discovery = self.discovery().await?;
+        let query = serde_urlencoded::to_string([
+            ("response_type", "code"),
+            ("client_id", self.config.client_id.as_str()),
+            ("redirect_uri", self.config.redirect_url.as_str()),
+            ("scope", "openid profile email"),
+            ("state", state),
+            ("nonce", nonce),
+        ]).unwrap_or_default();
+        let separator = if discovery.authorization_endpoint.contains('?') { '&' } else { '?' };
+        Ok(format!("{}{}{}", discovery.authorization_endpoint, separator, query))
+    }
+
+    async fn exchange(&self, code: &str, nonce: &str) -> Result<String, String> {
+        let discovery = self.discovery().await?;
+        let form = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_url.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("client_secret", self.config.client_secret.as_str()),
+        ];
+        let response = match self.http.post_form(&discovery.token_endpoint, &form).await {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Error contacting the sign-in provider: {}", why));
+            }
+        };
+        if response.status != 200 {
+            return Err(format!("The sign-in provider's token endpoint answered with status {}: {}", response.status, response.body));
+        }
+        let token = match serde_json::from_str::<TokenResponse>(&response.body) {
+            Ok(val) => val.id_token,
+            Err(why) => {
+                return Err(format!("Invalid token response from the sign-in provider: {}", why));
+            }
+        };
+        // The token came straight from the provider's token endpoint over TLS, so its signature
+        // doesn't need checking (OpenID Connect Core 3.1.3.7); its claims still do.
+        let claims = match decode_claims(&token) {
+            Ok(val) => val,
+            Err(why) => {
+                return Err(format!("Invalid ID token from the sign-in provider: {}", why));
+            }
+        };
+        if claims.iss.trim_end_matches('/') != self.config.issuer {
+            return Err(format!("The ID token was issued by \"{}\", not OIDC_ISSUER.", claims.iss));
+        }
+        if !claims.aud.contains(&self.config.client_id) {
+            return Err("The ID token is for a different client.".to_string());
+        }
+        if claims.exp < chrono::Utc::now().timestamp() {
+            return Err("The ID token has expired.".to_string());
+        }
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err("The ID token isn't for this sign-in.".to_string());
+        }
+        let name = claims.preferred_username.or(claims.email).unwrap_or(claims.sub);
+        if !self.config.allowed_groups.is_empty() && !claims.groups.iter().any(|group| self.config.allowed_groups.contains(group)) {
+            return Err(format!("{} isn't in any of OIDC_ALLOWED_GROUPS.", name));
+        }
+        Ok(name)
+    }
+}
+
+fn decode_claims(token: &str) -> Result<Claims, String> {
+    let payload = match token.split('.').nth(1) {
+        Some(val) => val,
+        None => {
+            return Err("it isn't a JWT".to_string());
+        }
+    };
+    let bytes = match URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')) {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(why.to_string());
+        }
+    };
+    match serde_json::from_slice::<Claims>(&bytes) {
+        Ok(val) => Ok(val),
+        Err(why) => Err(why.to_string()),
+    }
+}
+
+impl AuthProvider for OidcProvider {
+    fn sign_in_url<'a>(&'a self, state: &'a str, nonce: &'a str) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(self.authorization_url(state, nonce))
+    }
+
+    fn finish_sign_in<'a>(&'a self, code: &'a str, nonce: &'a str) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(self.exchange(code, nonce))
+    }
+
+    fn session_hours(&self) -> u32 {
+        self.config.session_hours
+    }
+}
+
+// HTTP Basic auth on /admin, for deployments with neither a sign-in provider nor a proxy to
+// guard it. Only hashes are kept, as in ADMIN_BASIC_AUTH.
+pub struct BasicAuth {
+    accounts: Vec<(String, PasswordHash)>,
+}
+
+// Keep the hashes out of logs.
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("accounts", &self.accounts.iter().map(|(name, _)| name).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl BasicAuth {
+    pub fn new(accounts: &[(String, PasswordHash)]) -> BasicAuth {
+        BasicAuth { accounts: accounts.to_vec() }
+    }
+
+    // The staff member an Authorization header's credentials belong to, if they're right. Slow on
+    // purpose, so it's run off the async workers; see `require_staff`.
+    pub fn check(&self, authorization: &str) -> Option<String> {
+        let encoded = match authorization.split_once(' ') {
+            Some((scheme, val)) if scheme.eq_ignore_ascii_case("basic") => val.trim(),
+            _ => return None,
+        };
+        let credentials = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+        let (name, password) = credentials.split_once(':')?;
+        self.accounts.iter()
+            .find(|(account, hash)| account == name && hash.verify(password))
+            .map(|(account, _)| account.clone())
+    }
+}
+
+// Who `require_staff` let in with Basic auth, so the hash is only checked once a request.
+#[derive(Clone)]
+struct BasicAuthUser(String);
+
+// The staff member whose Basic auth credentials came with the request, when that's turned on.
+pub fn basic_auth_user(req: &actix_web::HttpRequest) -> Option<String> {
+    req.extensions().get::<BasicAuthUser>().map(|user| user.0.clone())
+}
+
+// Kept in the session cookie, which is signed, so it can't be made up or edited.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SignedIn {
+    name: String,
+    at: i64,
+}
+
+// A sign-in that went out to the provider and hasn't come back yet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PendingSignIn {
+    state: String,
+    nonce: String,
+    return_to: String,
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// The staff member signed in to this session, if their sign-in hasn't run out.
+pub fn signed_in(provider: &dyn AuthProvider, session: &Session) -> Option<String> {
+    let signed_in = match session.get::<SignedIn>(SIGNED_IN_KEY) {
+        Ok(Some(val)) => val,
+        _ => {
+            return None;
+        }
+    };
+    let expires = signed_in.at + i64::from(provider.session_hours()) * 3600;
+    if chrono::Utc::now().timestamp() >= expires {
+        return None;
+    }
+    Some(signed_in.name)
+}
+
+// Guards /admin when the app signs staff in itself, through the provider or with Basic auth.
+// Otherwise the proxy in front of /admin does the guarding, as before.
+pub async fn require_staff(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let (provider, basic_auth) = match &state {
+        Some(state) => (state.staff_auth.clone(), state.basic_auth.clone()),
+        None => (None, None),
+    };
+    let provider = match (provider, basic_auth) {
+        (Some(val), _) => val,
+        (None, Some(basic_auth)) => {
+            let authorization = req.headers().get(header::AUTHORIZATION).and_then(|val| val.to_str().ok()).map(str::to_string);
+            let user = match authorization {
+                Some(authorization) => web::block(move || basic_auth.check(&authorization)).await.unwrap_or(None),
+                None => None,
+            };
+            if let Some(name) = user {
+                req.extensions_mut().insert(BasicAuthUser(name));
+                return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+            }
+            let response = HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Basic realm=\"Tuition Calculator admin\", charset=\"UTF-8\""))
+                .body("Sign in with your admin name and password.");
+            return Ok(req.into_response(response));
+        }
+        (None, None) => {
+            return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+        }
+    };
+    let session = req.get_session();
+    if req.path() == CALLBACK_PATH || signed_in(provider.as_ref(), &session).is_some() {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    }
+
+    // A form post can't be replayed after the round trip, so those land back on the admin index.
+    let return_to = match (req.method(), req.uri().path_and_query()) {
+        (&Method::GET, Some(path)) => path.to_string(),
+        _ => "/admin".to_string(),
+    };
+    let pending = PendingSignIn { state: random_token(), nonce: random_token(), return_to };
+    let url = match provider.sign_in_url(&pending.state, &pending.nonce).await {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(AppError::Internal(why).into());
+        }
+    };
+    if let Err(why) = session.insert(PENDING_KEY, pending) {
+        return Err(AppError::Internal(format!("Error while saving the sign-in to the session: {}", why)).into());
+    }
+    let response = HttpResponse::SeeOther().append_header(("Location", url)).finish();
+    Ok(req.into_response(response))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+// Where the provider sends staff back after they sign in.
+pub async fn callback(state: web::Data<AppState>, session: Session, params: web::Query<CallbackParams>) -> Result<HttpResponse, AppError> {
+    let provider = match &state.staff_auth {
+        Some(val) => val.clone(),
+        None => {
+            return Err(AppError::NotFound("Staff sign-in isn't turned on.".to_string()));
+        }
+    };
+    let pending = match session.remove_as::<PendingSignIn>(PENDING_KEY) {
+        Some(Ok(val)) => val,
+        _ => {
+            return Err(AppError::Unauthorized("This sign-in has expired. Go back to the admin pages to sign in again.".to_string()));
+        }
+    };
+    if let Some(error) = &params.error {
+        println!("Staff sign-in was refused by the provider: {} {}", error, params.error_description.as_deref().unwrap_or_default());
+        return Err(AppError::Unauthorized("The campus sign-in didn't go through.".to_string()));
+    }
+    // Stops someone else's sign-in from being finished in this browser.
+    if params.state.as_deref() != Some(pending.state.as_str()) {
+        return Err(AppError::Unauthorized("This sign-in doesn't match the one that was started here. Please sign in again.".to_string()));
+    }
+    let code = match &params.code {
+        Some(val) => val,
+        None => {
+            return Err(AppError::Unauthorized("The campus sign-in didn't go through.".to_string()));
+        }
+    };
+    let name = match provider.finish_sign_in(code, &pending.nonce).await {
+        Ok(val) => val,
+        Err(why) => {
+            println!("Staff sign-in failed: {}", why);
+            return Err(AppError::Unauthorized("You couldn't be signed in to the admin pages with that account.".to_string()));
+        }
+    };
+
+    session.renew();
+    let signed_in = SignedIn { name: name.chars().take(255).collect(), at: chrono::Utc::now().timestamp() };
+    if let Err(why) = session.insert(SIGNED_IN_KEY, signed_in) {
+        return Err(AppError::Internal(format!("Error while saving the sign-in to the session: {}", why)));
+    }
+    println!("{} signed in to the admin pages.", name);
+    Ok(HttpResponse::SeeOther().append_header(("Location", pending.return_to)).finish())
+}
+
+pub async fn sign_out(session: Session) -> Result<HttpResponse, AppError> {
+    session.remove(SIGNED_IN_KEY);
+    Ok(HttpResponse::SeeOther().append_header(("Location", "/")).finish())
+}
@@ -0,0 +1,57 @@
+use sqlx::{MySql, Pool};
+
+use crate::{error::AppError, models::Campus, money::Money, pricing, AppState};
+
+// Open `count` connections up front, so the first students after a restart don't each wait for
+// one. They go back to the pool idle, and DB_MIN_CONNECTIONS keeps them there.
+pub async fn prewarm(pool: &Pool<MySql>, count: u32) -> Result<(), sqlx::Error> {
+    let mut connections = Vec::new();
+    for _ in 0..count {
+        connections.push(pool.acquire().await?);
+    }
+    if count > 0 {
+        println!("Opened {} database connection(s) ahead of traffic.", connections.len());
+    }
+    Ok(())
+}
+
+// The self-test estimate: a new, full-time resident undergraduate taking orientation, which
+// every campus should be able to price.
+const CREDITS: u8 = 12;
+const STUDIES: &str = "undergraduate";
+const RESIDENCY: &str = "resident";
+
+// Price the self-test estimate at one campus the way `estimate` would, without saving anything.
+async fn price(state: &AppState, campus: &Campus) -> Result<Money, AppError> {
+    let costs = match state.tuition_costs(campus, STUDIES, RESIDENCY).await {
+        Ok(val) => val,
+        Err(why @ AppError::Validation { .. }) => match state.default_tuition_costs(RESIDENCY) {
+            Some(val) => val,
+            None => {
+                return Err(why);
+            }
+        },
+        Err(why) => {
+            return Err(why);
+        }
+    };
+    let orientation_fee = state.orientation_fee(campus).await?;
+    let health_insurance_fee = state.health_insurance_fee(campus).await?;
+    let total = pricing::tuition_total(CREDITS, true, &costs, orientation_fee) + pricing::health_insurance_charge(false, health_insurance_fee);
+    if total.is_sign_negative() {
+        return Err(AppError::Internal(format!("The estimate came out negative ({}).", total.format())));
+    }
+    Ok(total)
+}
+
+// Run the self-test estimate at every campus. Each campus that can't price it is one problem.
+pub async fn self_test(state: &AppState) -> Vec<String> {
+    let mut problems = Vec::new();
+    for campus in state.campuses.iter() {
+        match price(state, campus).await {
+            Ok(total) => println!("Self-test: {} prices {} {} {} credits at {}.", campus.name, CREDITS, RESIDENCY, STUDIES, total.format()),
+            Err(why) => problems.push(format!("{}: {}", campus.name, why)),
+        }
+    }
+    problems
+}
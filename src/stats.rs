@@ -0,0 +1,74 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::MySqlPool;
+
+use crate::{models::CampusId, queries, request_meta};
+
+// Activity for one campus on one day, counted from the receipts of each calculation.
+#[derive(Serialize, Debug, Clone)]
+pub struct DailyStats {
+    pub calculations: i64,
+    // Students whose first calculation was on this day.
+    pub new_students: i64,
+    pub average_estimate: Option<Decimal>,
+    // Calculations a counselor entered from the admin pages or that came from one of the counselor
+    // portals; the rest are self-service.
+    pub counselor_calculations: i64,
+    pub self_service_calculations: i64,
+    // Of the students offered orientation, how many checked it and how many left it unchecked.
+    pub orientation_accepted: i64,
+    pub orientation_declined: i64,
+    // Calculations by the entry point they came through, e.g. ("web", 40); receipts from before
+    // it was kept aren't counted.
+    pub channels: Vec<(String, i64)>,
+}
+
+pub async fn daily_stats(pool: &MySqlPool, campus_id: CampusId, date: NaiveDate, counselor_referrers: &[String]) -> Result<DailyStats, sqlx::Error> {
+    let start = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let end = start + chrono::Duration::days(1);
+
+    let (calculations, average_estimate) = queries::RECEIPT_STATS.scoped(campus_id).run(|sql, campus_id| sqlx::query_as::<_, (i64, Option<Decimal>)>(sql)
+    .bind(campus_id)
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)).await?;
+
+    let new_students = queries::FIRST_TIME_STUDENTS.scoped(campus_id).run(|sql, campus_id| sqlx::query_scalar::<_, i64>(sql)
+    .bind(campus_id)
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)).await?;
+
+    let sources = queries::CALCULATION_SOURCES.scoped(campus_id).run(|sql, campus_id| sqlx::query_as::<_, (Option<String>, Option<String>)>(sql)
+    .bind(campus_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)).await?;
+    let counselor_calculations = sources.iter()
+        .filter(|(referrer, entered_by)| entered_by.is_some() || referrer.as_deref().is_some_and(|referrer| request_meta::is_counselor_referrer(referrer, counselor_referrers)))
+        .count() as i64;
+
+    let (orientation_accepted, orientation_declined) = queries::ORIENTATION_ANSWERS.scoped(campus_id).run(|sql, campus_id| sqlx::query_as::<_, (i64, i64)>(sql)
+    .bind(campus_id)
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)).await?;
+
+    let channels = queries::CALCULATION_CHANNELS.scoped(campus_id).run(|sql, campus_id| sqlx::query_as::<_, (String, i64)>(sql)
+    .bind(campus_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)).await?;
+
+    Ok(DailyStats {
+        calculations,
+        new_students,
+        average_estimate: average_estimate.map(|val| val.round_dp(2)),
+        counselor_calculations,
+        self_service_calculations: calculations - counselor_calculations,
+        orientation_accepted,
+        orientation_declined,
+        channels,
+    })
+}
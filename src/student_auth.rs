@@ -0,0 +1,268 @@
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Local, NaiveDateTime};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{client_ip, error::AppError, form, ids, models::{Campus, CampusId, StudentId}, peer_ip, queries, render, verification::code_hash, AppState, MAX_EMAIL_LENGTH};
+
+const SIGNED_IN_KEY: &str = "student_signed_in";
+const PENDING_KEY: &str = "student_sign_in";
+
+// Codes work for as long, and for as many guesses, as the ones confirming an address.
+const CODE_MINUTES: i64 = 30;
+const MAX_ATTEMPTS: u32 = 5;
+
+// How long a student stays signed in before they're sent another code.
+const SESSION_HOURS: i64 = 12;
+
+// Codes sent to one student in an hour, so asking for new ones can't buy unlimited guesses, and
+// requests to send one to an address per minute, so nobody's inbox can be flooded.
+const CODES_PER_HOUR: i64 = 5;
+const CODES_PER_MINUTE_PER_EMAIL: u32 = 2;
+
+// Sign-in requests, sending codes and entering them, from one client address per minute.
+const REQUESTS_PER_MINUTE_PER_ADDRESS: u32 = 10;
+
+// Turns away a client over REQUESTS_PER_MINUTE_PER_ADDRESS. Clients whose address isn't known
+// share one allowance.
+fn check_address(state: &AppState, req: &HttpRequest, field: &'static str) -> Result<(), AppError> {
+    if state.sign_in_limiter.check(client_ip::for_request(req), REQUESTS_PER_MINUTE_PER_ADDRESS) {
+        Ok(())
+    } else {
+        Err(AppError::validation(field, "Too many sign-in attempts. Wait a minute and try again."))
+    }
+}
+
+// Kept in the session cookie, which is signed, so it can't be made up or edited. The campus is
+// kept too, since one browser can visit several.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SignedIn {
+    campus_id: CampusId,
+    student_id: StudentId,
+    first_name: String,
+    last_name: String,
+    at: i64,
+}
+
+// The student signed in to this session at this campus.
+#[derive(Serialize, Debug, Clone)]
+pub struct Student {
+    #[serde(skip)]
+    pub id: StudentId,
+    pub first_name: String,
+    pub last_name: String,
+}
+
+// The student signed in to this session at `campus`, if their sign-in hasn't run out.
+pub fn signed_in(campus: &Campus, session: &Session) -> Option<Student> {
+    let signed_in = match session.get::<SignedIn>(SIGNED_IN_KEY) {
+        Ok(Some(val)) => val,
+        _ => {
+            return None;
+        }
+    };
+    if signed_in.campus_id != campus.id || chrono::Utc::now().timestamp() >= signed_in.at + SESSION_HOURS * 3600 {
+        return None;
+    }
+    Some(Student { id: signed_in.student_id, first_name: signed_in.first_name, last_name: signed_in.last_name })
+}
+
+// Send a page that needs a signed-in student to sign in first.
+pub fn redirect_to_sign_in() -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/sign-in"))
+        .finish()
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct StudentWithEmail {
+    id: StudentId,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct PendingSignIn {
+    id: i32,
+    student_id: StudentId,
+    first_name: String,
+    last_name: String,
+    code_hash: String,
+    attempts: u32,
+    expires_at: NaiveDateTime,
+    used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+struct SignInPage {
+    // Whether sign-in can work at all: the codes go out by email.
+    can_email: bool,
+    // Set once a code was asked for, to show where to enter it.
+    email: Option<String>,
+    captcha: Option<crate::captcha::CaptchaWidget>,
+}
+
+// GET /sign-in
+pub async fn sign_in_form(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    render(&state, "sign_in", &SignInPage { can_email: state.mailer.is_some(), email: None, captcha: state.captcha_widget() }).await
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SignInParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    email: Option<String>,
+    #[serde(alias = "g-recaptcha-response", alias = "h-captcha-response")]
+    captcha_response: Option<String>,
+}
+
+// POST /sign-in: send a code to the address, if a student here confirmed it. The page is the same
+// either way, even when the code couldn't be sent or the student has had too many, so it can't be
+// used to find out whose addresses are on file.
+pub async fn send_code(state: web::Data<AppState>, campus: Campus, req: HttpRequest, session: Session, params: web::Form<SignInParams>) -> Result<HttpResponse, AppError> {
+    let mailer = match &state.mailer {
+        Some(val) => val,
+        None => {
+            return Err(AppError::NotFound("Signing in needs email, which isn't set up here.".to_string()));
+        }
+    };
+    check_address(&state, &req, "email")?;
+    state.check_captcha(&params.captcha_response, peer_ip(&req).as_deref()).await?;
+    let email = match &params.email {
+        Some(val) if val.len() <= MAX_EMAIL_LENGTH => val.clone(),
+        Some(_) => {
+            return Err(AppError::validation("email", &format!("Email addresses can be at most {} characters.", MAX_EMAIL_LENGTH)));
+        }
+        None => {
+            return Err(AppError::validation("email", "Enter the email address you confirmed with an estimate."));
+        }
+    };
+    // Whether or not a student has it, so this says nothing about who's on file either.
+    if !state.sign_in_email_limiter.check(email.to_lowercase(), CODES_PER_MINUTE_PER_EMAIL) {
+        return Err(AppError::validation("email", "A code was just sent to this address. Wait a minute before asking for another."));
+    }
+
+    // Made up front, so an address nobody confirmed gets a sign-in that no code will finish.
+    let public_id = ids::new_public_id();
+    let student = queries::STUDENT_BY_EMAIL.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, StudentWithEmail>(sql)
+    .bind(campus_id)
+    .bind(&email)
+    .fetch_optional(&state.conn)).await?;
+    if let Some(student) = student {
+        let now = Local::now().naive_local();
+        let mut tx = state.conn.begin().await?;
+        queries::PRUNE_STUDENT_SIGN_INS.run(|sql| sqlx::query(sql)
+        .bind(student.id)
+        .bind(now - Duration::days(1))
+        .execute(&mut tx)).await?;
+        let sent = queries::COUNT_STUDENT_SIGN_INS.run(|sql| sqlx::query_scalar::<_, i64>(sql)
+        .bind(student.id)
+        .bind(now - Duration::hours(1))
+        .fetch_one(&mut tx)).await?;
+        if sent < CODES_PER_HOUR {
+            let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+
+            // Only the newest code works.
+            queries::CANCEL_STUDENT_SIGN_INS.run(|sql| sqlx::query(sql)
+            .bind(now)
+            .bind(student.id)
+            .bind(now)
+            .execute(&mut tx)).await?;
+            queries::INSERT_STUDENT_SIGN_IN.run(|sql| sqlx::query(sql)
+            .bind(&public_id)
+            .bind(student.id)
+            .bind(code_hash(&public_id, &code))
+            .bind(now + Duration::minutes(CODE_MINUTES))
+            .execute(&mut tx)).await?;
+            tx.commit().await?;
+
+            let subject = format!("Your {} sign-in code", campus.name);
+            let body = format!(
+                "Your sign-in code is {}.\n\nEnter it within {} minutes to see your saved scenarios. If you didn't ask for this, you can ignore this message.\n",
+                code, CODE_MINUTES,
+            );
+            if let Err(why) = mailer.send(std::slice::from_ref(&email), &subject, &body).await {
+                println!("Error while sending a sign-in code to student {}: {}", student.id, why);
+            }
+        } else {
+            tx.commit().await?;
+            println!("Student {} has had {} sign-in codes in the last hour; not sending another.", student.id, sent);
+        }
+    }
+
+    if let Err(why) = session.insert(PENDING_KEY, &public_id) {
+        return Err(AppError::Internal(format!("Error while saving the sign-in to the session: {}", why)));
+    }
+    render(&state, "sign_in", &SignInPage { can_email: true, email: Some(email), captcha: None }).await
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CodeParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    code: Option<String>,
+}
+
+// POST /sign-in/code. A wrong code counts against the limit whether or not it was close.
+pub async fn confirm_code(state: web::Data<AppState>, campus: Campus, req: HttpRequest, session: Session, params: web::Form<CodeParams>) -> Result<HttpResponse, AppError> {
+    check_address(&state, &req, "code")?;
+    let public_id = match session.get::<String>(PENDING_KEY) {
+        Ok(Some(val)) => val,
+        _ => {
+            return Err(AppError::Unauthorized("This sign-in has expired. Enter your email address to get a new code.".to_string()));
+        }
+    };
+    let code = match &params.code {
+        Some(val) => val.clone(),
+        None => {
+            return Err(AppError::validation("code", "Enter the code from the email."));
+        }
+    };
+
+    let mut tx = state.conn.begin().await?;
+    let pending = queries::STUDENT_SIGN_IN.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, PendingSignIn>(sql)
+    .bind(&public_id)
+    .bind(campus_id)
+    .fetch_optional(&mut tx)).await?;
+    let pending = match pending {
+        Some(val) if val.used_at.is_none() && val.expires_at > Local::now().naive_local() && val.attempts < MAX_ATTEMPTS => val,
+        Some(_) => {
+            return Err(AppError::validation("code", "This code has expired. Enter your email address to get a new one."));
+        }
+        // Nobody at this campus confirmed the address.
+        None => {
+            return Err(AppError::validation("code", "That code isn't right. Check the email and try again."));
+        }
+    };
+
+    if code_hash(&public_id, &code) != pending.code_hash {
+        queries::COUNT_SIGN_IN_ATTEMPT.run(|sql| sqlx::query(sql)
+        .bind(pending.id)
+        .execute(&mut tx)).await?;
+        tx.commit().await?;
+        return Err(AppError::validation("code", "That code isn't right. Check the email and try again."));
+    }
+    queries::USE_STUDENT_SIGN_IN.run(|sql| sqlx::query(sql)
+    .bind(pending.id)
+    .execute(&mut tx)).await?;
+    tx.commit().await?;
+
+    session.renew();
+    session.remove(PENDING_KEY);
+    let signed_in = SignedIn {
+        campus_id: campus.id,
+        student_id: pending.student_id,
+        first_name: pending.first_name,
+        last_name: pending.last_name,
+        at: chrono::Utc::now().timestamp(),
+    };
+    if let Err(why) = session.insert(SIGNED_IN_KEY, signed_in) {
+        return Err(AppError::Internal(format!("Error while saving the sign-in to the session: {}", why)));
+    }
+    Ok(HttpResponse::SeeOther().append_header(("Location", "/scenarios")).finish())
+}
+
+// POST /sign-out
+pub async fn sign_out(session: Session) -> Result<HttpResponse, AppError> {
+    session.remove(SIGNED_IN_KEY);
+    Ok(HttpResponse::SeeOther().append_header(("Location", "/")).finish())
+}
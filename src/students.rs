@@ -0,0 +1,329 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlPool;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{audit, error::AppError, form, models::{Campus, ScenarioId, StudentId}, queries, render, retention::{RetentionAction, ANONYMIZED}, AppState, MAX_NAME_LENGTH};
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct StudentToDelete {
+    id: StudentId,
+    first_name: String,
+    last_name: String,
+}
+
+// What a delete took with it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deleted {
+    pub records: i64,
+    pub receipts: u64,
+    pub scenarios: i64,
+}
+
+// Remove a student at their request, in one transaction with its audit entry so nothing is left
+// half-deleted. Their saved records and scenarios go with the student row. Receipts are deleted
+// with their refund estimates when purging; anonymizing keeps them for the stats without the name
+// or request metadata, and without the student, since the row they pointed at is gone.
+pub async fn delete(pool: &MySqlPool, campus: &Campus, public_id: &str, action: RetentionAction, actor: &str) -> Result<Deleted, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let student = match queries::STUDENT_TO_DELETE.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, StudentToDelete>(sql)
+    .bind(campus_id)
+    .bind(public_id)
+    .fetch_optional(&mut tx)).await? {
+        Some(val) => val,
+        None => {
+            return Err(AppError::NotFound(format!("No student {} was found at {}.", public_id, campus.name)));
+        }
+    };
+
+    let records = queries::COUNT_STUDENT_RECORDS.run(|sql| sqlx::query_scalar::<_, i64>(sql)
+    .bind(student.id)
+    .fetch_one(&mut tx)).await?;
+
+    let receipts = match action {
+        RetentionAction::Anonymize => queries::ANONYMIZE_STUDENT_RECEIPTS.run(|sql| sqlx::query(sql)
+            .bind(ANONYMIZED)
+            .bind(ANONYMIZED)
+            .bind(student.id)
+            .execute(&mut tx)).await?,
+        RetentionAction::Purge => queries::DELETE_STUDENT_RECEIPTS.run(|sql| sqlx::query(sql)
+            .bind(student.id)
+            .execute(&mut tx)).await?,
+    };
+
+    let scenarios = queries::COUNT_STUDENT_SCENARIOS.run(|sql| sqlx::query_scalar::<_, i64>(sql)
+    .bind(student.id)
+    .fetch_one(&mut tx)).await?;
+
+    // TuitionRecords and Scenarios cascade; anonymized receipts have their StudentId cleared.
+    queries::DELETE_STUDENT.run(|sql| sqlx::query(sql)
+    .bind(student.id)
+    .execute(&mut tx)).await?;
+
+    let deleted = Deleted {
+        records,
+        receipts: receipts.rows_affected(),
+        scenarios,
+    };
+    // The name stays in the log, as with other deletes, so a mistaken one can be traced.
+    let details = format!("Deleted {} {} ({}): {} record(s), {} receipt(s) {}d, {} scenario(s)",
+        student.first_name, student.last_name, public_id, deleted.records, deleted.receipts, action, deleted.scenarios);
+    audit::record(&mut tx, actor, "delete", "Student", student.id.0, &details).await?;
+
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DeleteStudentParams {
+    // anonymize (the default) or purge.
+    action: Option<String>,
+}
+
+pub async fn delete_student(state: web::Data<AppState>, campus: Campus, req: HttpRequest, public_id: web::Path<String>, params: web::Form<DeleteStudentParams>) -> Result<HttpResponse, AppError> {
+    let action = match params.action.as_deref().map(str::parse::<RetentionAction>) {
+        None => RetentionAction::Anonymize,
+        Some(Ok(val)) => val,
+        Some(Err(why)) => {
+            return Err(AppError::validation("action", &why));
+        }
+    };
+    delete(&state.conn, &campus, &public_id, action, &audit::actor(&req)).await?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin"))
+        .finish())
+}
+
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct DuplicateCandidate {
+    public_id: String,
+    first_name: String,
+    last_name: String,
+    email: Option<String>,
+    created_at: NaiveDateTime,
+    records: i64,
+    receipts: i64,
+}
+
+// Students who are probably the same person, and why.
+#[derive(Serialize, Debug, Clone)]
+struct DuplicateGroup {
+    reason: &'static str,
+    students: Vec<DuplicateCandidate>,
+}
+
+// Groups past this many aren't listed; merge some and look again.
+const MAX_GROUPS: usize = 200;
+
+// Names are unique on a campus as typed, so duplicates differ in case, spacing or punctuation:
+// "Mary-Ann O'Neil" and "maryann oneil" are the same here.
+fn name_key(first_name: &str, last_name: &str) -> String {
+    let squash = |val: &str| val.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>();
+    format!("{} {}", squash(first_name), squash(last_name))
+}
+
+// Addresses compared without case or a +tag, so "Ann+fall@Example.edu" and "ann@example.edu"
+// are the same here.
+fn email_key(email: &str) -> Option<String> {
+    let (local, domain) = email.trim().rsplit_once('@')?;
+    let local = local.split('+').next().unwrap_or(local);
+    if local.is_empty() || domain.is_empty() {
+        return None;
+    }
+    Some(format!("{}@{}", local.to_lowercase(), domain.to_lowercase()))
+}
+
+// Students with the same name or a similar email address, oldest first in each group. A group
+// found both ways is only listed once.
+fn duplicate_groups(candidates: Vec<DuplicateCandidate>) -> Vec<DuplicateGroup> {
+    let mut by_name: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut by_email: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        by_name.entry(name_key(&candidate.first_name, &candidate.last_name)).or_default().push(i);
+        if let Some(key) = candidate.email.as_deref().and_then(email_key) {
+            by_email.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut groups = Vec::new();
+    for (reason, matches) in [("Same name", by_name), ("Similar email", by_email)] {
+        for members in matches.into_values() {
+            if members.len() > 1 && seen.insert(members.clone()) {
+                groups.push(DuplicateGroup { reason, students: members.iter().map(|&i| candidates[i].clone()).collect() });
+            }
+        }
+    }
+    groups
+}
+
+#[derive(Serialize)]
+struct DuplicatesPage {
+    groups: Vec<DuplicateGroup>,
+    // More groups were found than are listed.
+    more: bool,
+}
+
+// GET /admin/students/duplicates, to pick which of each group to keep.
+pub async fn duplicates(state: web::Data<AppState>, campus: Campus) -> Result<HttpResponse, AppError> {
+    let candidates = queries::DUPLICATE_CANDIDATES.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, DuplicateCandidate>(sql)
+    .bind(campus_id)
+    .fetch_all(state.read_conn())).await?;
+    let mut groups = duplicate_groups(candidates);
+    let more = groups.len() > MAX_GROUPS;
+    groups.truncate(MAX_GROUPS);
+    render(&state, "admin_duplicates", &DuplicatesPage { groups, more }).await
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct StudentToMerge {
+    id: StudentId,
+    public_id: String,
+    first_name: String,
+    last_name: String,
+    email: Option<String>,
+}
+
+// What a merge moved onto the kept student.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Merged {
+    pub records: u64,
+    // Records for a term both students had, where the other one was newer.
+    pub superseded: u64,
+    pub receipts: u64,
+    pub scenarios: u64,
+    // Scenarios named the same as one the kept student has, moved under a new name.
+    pub renamed_scenarios: u64,
+}
+
+// A name for a scenario that clashed in a merge, e.g. "Full time (merged)", that neither student
+// has. Names are compared without case, as the unique key on them is.
+fn merged_scenario_name(name: &str, taken: &HashSet<String>) -> String {
+    let mut suffix = " (merged)".to_string();
+    let mut n = 1;
+    loop {
+        let kept: String = name.chars().take(MAX_NAME_LENGTH - suffix.chars().count()).collect();
+        let candidate = format!("{}{}", kept, suffix);
+        if !taken.contains(&candidate.to_lowercase()) {
+            return candidate;
+        }
+        n += 1;
+        suffix = format!(" (merged {})", n);
+    }
+}
+
+async fn student_to_merge(tx: &mut sqlx::Transaction<'_, sqlx::MySql>, campus: &Campus, public_id: &str) -> Result<StudentToMerge, AppError> {
+    match queries::STUDENT_TO_MERGE.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, StudentToMerge>(sql)
+    .bind(campus_id)
+    .bind(public_id)
+    .fetch_optional(&mut *tx)).await? {
+        Some(val) => Ok(val),
+        None => Err(AppError::NotFound(format!("No student {} was found at {}.", public_id, campus.name))),
+    }
+}
+
+// Fold one student into another, in one transaction with its audit entry. Where both have a
+// record for the same term the newer one is kept; receipts, pending email confirmations and
+// scenarios move over, and the kept student takes the other's email if they had none. A scenario
+// named the same as one the kept student already has moves over renamed, so none is lost.
+pub async fn merge(pool: &MySqlPool, campus: &Campus, keep_id: &str, merge_id: &str, actor: &str) -> Result<Merged, AppError> {
+    if keep_id == merge_id {
+        return Err(AppError::validation("merge", "Pick two different students to merge."));
+    }
+    let mut tx = pool.begin().await?;
+    let keep = student_to_merge(&mut tx, campus, keep_id).await?;
+    let other = student_to_merge(&mut tx, campus, merge_id).await?;
+
+    let superseded = queries::DROP_SUPERSEDED_RECORDS.run(|sql| sqlx::query(sql)
+    .bind(keep.id)
+    .bind(other.id)
+    .bind(keep.id)
+    .bind(other.id)
+    .bind(keep.id)
+    .execute(&mut tx)).await?.rows_affected();
+    let records = queries::MOVE_STUDENT_RECORDS.run(|sql| sqlx::query(sql)
+    .bind(keep.id)
+    .bind(other.id)
+    .execute(&mut tx)).await?.rows_affected();
+    let receipts = queries::MOVE_STUDENT_RECEIPTS.run(|sql| sqlx::query(sql)
+    .bind(keep.id)
+    .bind(other.id)
+    .execute(&mut tx)).await?.rows_affected();
+    queries::MOVE_EMAIL_VERIFICATIONS.run(|sql| sqlx::query(sql)
+    .bind(keep.id)
+    .bind(other.id)
+    .execute(&mut tx)).await?;
+    // A scenario named the same as one the kept student has is renamed, so it can move too.
+    let mut taken: HashSet<String> = queries::MERGE_SCENARIO_NAMES.run(|sql| sqlx::query_scalar::<_, String>(sql)
+    .bind(keep.id)
+    .bind(other.id)
+    .fetch_all(&mut tx)).await?.into_iter().map(|name| name.to_lowercase()).collect();
+    let clashing = queries::CLASHING_SCENARIOS.run(|sql| sqlx::query_as::<_, (ScenarioId, String)>(sql)
+    .bind(keep.id)
+    .bind(other.id)
+    .fetch_all(&mut tx)).await?;
+    for (id, name) in &clashing {
+        let renamed = merged_scenario_name(name, &taken);
+        queries::RENAME_SCENARIO.run(|sql| sqlx::query(sql)
+        .bind(&renamed)
+        .bind(id)
+        .execute(&mut tx)).await?;
+        taken.insert(renamed.to_lowercase());
+    }
+    let scenarios = queries::MOVE_STUDENT_SCENARIOS.run(|sql| sqlx::query(sql)
+    .bind(keep.id)
+    .bind(&keep.first_name)
+    .bind(&keep.last_name)
+    .bind(other.id)
+    .execute(&mut tx)).await?.rows_affected();
+    if let Some(email) = &other.email {
+        queries::KEEP_STUDENT_EMAIL.run(|sql| sqlx::query(sql)
+        .bind(email)
+        .bind(keep.id)
+        .execute(&mut tx)).await?;
+    }
+    queries::DELETE_STUDENT.run(|sql| sqlx::query(sql)
+    .bind(other.id)
+    .execute(&mut tx)).await?;
+
+    let merged = Merged { records, superseded, receipts, scenarios, renamed_scenarios: clashing.len() as u64 };
+    let details = format!("Merged {} {} ({}) into {} {} ({}): {} record(s) moved, {} older record(s) dropped, {} receipt(s), {} scenario(s), {} of them renamed",
+        other.first_name, other.last_name, other.public_id, keep.first_name, keep.last_name, keep.public_id,
+        merged.records, merged.superseded, merged.receipts, merged.scenarios, merged.renamed_scenarios);
+    audit::record(&mut tx, actor, "merge", "Student", keep.id.0, &details).await?;
+
+    tx.commit().await?;
+    Ok(merged)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MergeStudentsParams {
+    // Public ids: the student to keep, and the one folded into them.
+    #[serde(default, deserialize_with = "form::trimmed")]
+    keep: Option<String>,
+    #[serde(default, deserialize_with = "form::trimmed")]
+    merge: Option<String>,
+}
+
+pub async fn merge_students(state: web::Data<AppState>, campus: Campus, req: HttpRequest, params: web::Form<MergeStudentsParams>) -> Result<HttpResponse, AppError> {
+    let (keep, other) = match (&params.keep, &params.merge) {
+        (Some(keep), Some(other)) => (keep, other),
+        (None, _) => {
+            return Err(AppError::validation("keep", "Pick the student to keep."));
+        }
+        (_, None) => {
+            return Err(AppError::validation("merge", "Pick the student to merge into them."));
+        }
+    };
+    merge(&state.conn, &campus, keep, other, &audit::actor(&req)).await?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/students/duplicates"))
+        .finish())
+}
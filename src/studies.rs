@@ -0,0 +1,35 @@
+// What a student is studying, which picks their per-credit rate along with their residency.
+pub enum StudentStudies {
+    Undergraduate,
+    Graduate,
+    // High-school students taking college courses.
+    DualEnrollment,
+}
+
+impl StudentStudies {
+    // From the form's value. Anything else is priced as undergraduate, as it always has been.
+    pub fn parse(val: &str) -> StudentStudies {
+        match val {
+            "graduate" => StudentStudies::Graduate,
+            "dual_enrollment" => StudentStudies::DualEnrollment,
+            _ => StudentStudies::Undergraduate,
+        }
+    }
+
+    // The value used by the form and the CreditCosts table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StudentStudies::Undergraduate => "undergraduate",
+            StudentStudies::Graduate => "graduate",
+            StudentStudies::DualEnrollment => "dual_enrollment",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StudentStudies::Undergraduate => "Undergraduate",
+            StudentStudies::Graduate => "Graduate",
+            StudentStudies::DualEnrollment => "Dual Enrollment",
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use actix_web::rt;
+use chrono::{Duration, Local, NaiveDate};
+
+use crate::{config::SummaryConfig, mailer::Mailer, money::format_money, stats, AppState};
+
+// Build the summary for every campus on the given day.
+async fn summary_body(state: &AppState, date: NaiveDate) -> Result<String, sqlx::Error> {
+    let mut body = format!("Tuition calculator summary for {}\n", date.format("%A, %B %-d, %Y"));
+    for campus in state.campuses.iter() {
+        let counselor_referrers = state.request_metadata.counselor_referrers();
+        let day = stats::daily_stats(state.read_conn(), campus.id, date, counselor_referrers).await?;
+        body += &format!(
+            "\n{}\n  Calculations performed: {}\n  New students: {}\n  Average estimate: {}\n",
+            campus.name,
+            day.calculations,
+            day.new_students,
+            match day.average_estimate {
+                Some(val) => format_money(val),
+                None => "n/a".to_string(),
+            },
+        );
+        // Left out for campuses where counselors don't use the calculator.
+        if !counselor_referrers.is_empty() || day.counselor_calculations > 0 {
+            body += &format!(
+                "  Entered by counselors: {}\n  Self-service: {}\n",
+                day.counselor_calculations,
+                day.self_service_calculations,
+            );
+        }
+        if day.orientation_accepted + day.orientation_declined > 0 {
+            body += &format!(
+                "  Orientation: {} accepted, {} declined\n",
+                day.orientation_accepted,
+                day.orientation_declined,
+            );
+        }
+        if !day.channels.is_empty() {
+            let channels: Vec<String> = day.channels.iter().map(|(channel, count)| format!("{} {}", channel, count)).collect();
+            body += &format!("  By channel: {}\n", channels.join(", "));
+        }
+    }
+    Ok(body)
+}
+
+// How long until the next time the clock reads `hour`:00.
+pub fn until_next(hour: u32) -> std::time::Duration {
+    let now = Local::now().naive_local();
+    let mut next = now.date().and_hms_opt(hour, 0, 0).unwrap_or(now);
+    if next <= now {
+        next += Duration::days(1);
+    }
+    (next - now).to_std().unwrap_or_default()
+}
+
+// Every night, email the previous day's summary. Failures are logged and tried again the next night.
+pub fn spawn(state: AppState, mailer: Mailer, config: SummaryConfig) {
+    rt::spawn(async move {
+        loop {
+            rt::time::sleep(until_next(config.hour)).await;
+            let yesterday = Local::now().date_naive() - Duration::days(1);
+            let body = match summary_body(&state, yesterday).await {
+                Ok(val) => val,
+                Err(why) => {
+                    println!("Error while building the nightly summary: {}", why);
+                    continue;
+                }
+            };
+            let subject = format!("Tuition calculator summary for {}", yesterday);
+            match mailer.send(&config.recipients, &subject, &body).await {
+                Ok(_val) => println!("Sent the nightly summary for {} to {} recipient(s).", yesterday, config.recipients.len()),
+                Err(why) => println!("Error while sending the nightly summary: {}", why),
+            }
+        }
+    });
+}
@@ -0,0 +1,133 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::HeaderMap,
+    middleware::Next,
+    Error,
+};
+use opentelemetry::{
+    global::{self, BoxedSpan},
+    propagation::{Extractor, TextMapPropagator},
+    trace::{FutureExt, Span, SpanKind, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use std::sync::OnceLock;
+
+use crate::config::TelemetryConfig;
+
+// Set once tracing is on; kept to flush the last spans at shutdown.
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+const TRACER: &str = "tuition-calculator";
+
+// Export spans to the collector in batches, off the request path.
+pub fn init(config: &TelemetryConfig) -> Result<(), String> {
+    let exporter = match SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", config.endpoint.trim_end_matches('/')))
+        .build() {
+        Ok(val) => val,
+        Err(why) => {
+            return Err(format!("Couldn't set up the OTLP exporter: {}", why));
+        }
+    };
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(config.service_name.clone()).build())
+        .build();
+    global::set_tracer_provider(provider.clone());
+    let _ = PROVIDER.set(provider);
+    Ok(())
+}
+
+// Send whatever spans are still waiting.
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(why) = provider.shutdown() {
+            println!("Error while sending the last traces: {}", why);
+        }
+    }
+}
+
+// A span for part of the request being handled, e.g. its validation, from here until it's dropped.
+// Outside a traced request, like the nightly jobs, it's nothing.
+pub struct Stage(Option<BoxedSpan>);
+
+pub fn stage(name: &'static str) -> Stage {
+    if PROVIDER.get().is_none() || !Context::current().has_active_span() {
+        return Stage(None);
+    }
+    Stage(Some(global::tracer(TRACER).start(name)))
+}
+
+// One run of a named query in `queries`.
+pub fn query(name: &'static str) -> Stage {
+    let mut stage = stage(name);
+    if let Some(span) = &mut stage.0 {
+        span.set_attribute(KeyValue::new("db.system.name", "mysql"));
+        span.set_attribute(KeyValue::new("db.query.summary", name));
+    }
+    stage
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        if let Some(span) = &mut self.0 {
+            span.end();
+        }
+    }
+}
+
+struct Headers<'a>(&'a HeaderMap);
+
+impl Extractor for Headers<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|val| val.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// Middleware: one trace per request, continuing the caller's when it sends a traceparent header.
+// Everything the handler does while it runs is recorded under it.
+pub async fn trace(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if PROVIDER.get().is_none() {
+        return next.call(req).await;
+    }
+
+    let parent = TraceContextPropagator::new().extract(&Headers(req.headers()));
+    let method = req.method().to_string();
+    let tracer = global::tracer(TRACER);
+    let span = tracer.span_builder(format!("{} {}", method, req.path()))
+        .with_kind(SpanKind::Server)
+        .with_attributes(vec![
+            KeyValue::new("http.request.method", method.clone()),
+            KeyValue::new("url.path", req.path().to_string()),
+        ])
+        .start_with_context(&tracer, &parent);
+    let cx = parent.with_span(span);
+
+    let result = next.call(req).with_context(cx.clone()).await;
+    let span = cx.span();
+    match &result {
+        Ok(response) => {
+            // Named by route rather than path, so every receipt's page is the same operation.
+            if let Some(route) = response.request().match_pattern() {
+                span.update_name(format!("{} {}", method, route));
+                span.set_attribute(KeyValue::new("http.route", route));
+            }
+            let status = response.status();
+            span.set_attribute(KeyValue::new("http.response.status_code", i64::from(status.as_u16())));
+            if status.is_server_error() {
+                span.set_status(Status::error(status.to_string()));
+            }
+        }
+        Err(why) => span.set_status(Status::error(why.to_string())),
+    }
+    span.end();
+    result
+}
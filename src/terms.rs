@@ -0,0 +1,46 @@
+use chrono::NaiveDate;
+
+use crate::{error::AppError, models::{Campus, TermWindow}, receipts, AppState};
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%B %-d, %Y").to_string()
+}
+
+// Why students can't make an estimate for `term` on `today`, or None if they can. A term with no
+// window on file is always open.
+pub fn notice(windows: &[TermWindow], term: &str, today: NaiveDate) -> Option<String> {
+    let window = windows.iter().find(|window| window.name == term)?;
+    if let Some(opens_on) = window.opens_on {
+        if today < opens_on {
+            return Some(format!("Estimates for {} open on {}.", window.name, format_date(opens_on)));
+        }
+    }
+    if let Some(closes_on) = window.closes_on {
+        if today > closes_on {
+            // Point students at the next term they'll be able to estimate, if it's set up yet.
+            let next = windows.iter()
+                .filter(|next| next.starts_on > window.starts_on)
+                .find_map(|next| next.opens_on.filter(|opens_on| *opens_on > today).map(|opens_on| (next, opens_on)));
+            return Some(match next {
+                Some((next, opens_on)) => format!(
+                    "Estimates for {} closed on {}. Estimates for {} open on {}.",
+                    window.name, format_date(closes_on), next.name, format_date(opens_on),
+                ),
+                None => format!("Estimates for {} closed on {}.", window.name, format_date(closes_on)),
+            });
+        }
+    }
+    None
+}
+
+impl AppState {
+    pub async fn term_notice(&self, campus: &Campus, term: &str) -> Result<Option<String>, AppError> {
+        let windows = self.term_windows(campus).await?;
+        Ok(notice(&windows, term, chrono::Local::now().date_naive()))
+    }
+
+    // For the top of the calculator, before the student has picked an enrollment date.
+    pub async fn current_term_notice(&self, campus: &Campus) -> Result<Option<String>, AppError> {
+        self.term_notice(campus, &receipts::term_for(chrono::Local::now().date_naive())).await
+    }
+}
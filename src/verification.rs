@@ -0,0 +1,126 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, Local, NaiveDateTime};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{error::AppError, form, ids, mailer::Mailer, models::{Campus, StudentId}, queries, render, AppState};
+
+// How long a code works, and how many wrong guesses it takes before it stops working. Six
+// digits and five tries gives a guesser one chance in 200,000.
+const CODE_MINUTES: i64 = 30;
+const MAX_ATTEMPTS: u32 = 5;
+
+// The code is salted with the verification's public id, so two rows with the same code don't
+// have the same hash.
+pub fn code_hash(public_id: &str, code: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}:{}", public_id, code).as_bytes()))
+}
+
+// Send a code to the address a student gave with a calculation. The address isn't kept on the
+// student until they enter the code; an earlier code still waiting is replaced. Returns the
+// verification's public id, for the form the code is entered in.
+pub async fn start(state: &AppState, mailer: &Mailer, campus: &Campus, student_id: StudentId, email: &str) -> Result<String, AppError> {
+    let public_id = ids::new_public_id();
+    let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+    let expires_at = Local::now().naive_local() + Duration::minutes(CODE_MINUTES);
+
+    let mut tx = state.conn.begin().await?;
+    queries::CANCEL_EMAIL_VERIFICATIONS.run(|sql| sqlx::query(sql)
+    .bind(student_id)
+    .execute(&mut tx)).await?;
+    queries::INSERT_EMAIL_VERIFICATION.run(|sql| sqlx::query(sql)
+    .bind(&public_id)
+    .bind(student_id)
+    .bind(email)
+    .bind(code_hash(&public_id, &code))
+    .bind(expires_at)
+    .execute(&mut tx)).await?;
+    tx.commit().await?;
+
+    let subject = format!("Your {} verification code", campus.name);
+    let body = format!(
+        "Your verification code is {}.\n\nEnter it on the results page within {} minutes to add this address to your tuition records. If you didn't ask for this, you can ignore this message.\n",
+        code, CODE_MINUTES,
+    );
+    if let Err(why) = mailer.send(&[email.to_string()], &subject, &body).await {
+        return Err(AppError::Internal(why));
+    }
+    Ok(public_id)
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+#[sqlx(rename_all = "PascalCase")]
+struct PendingVerification {
+    id: i32,
+    student_id: StudentId,
+    email: String,
+    code_hash: String,
+    attempts: u32,
+    expires_at: NaiveDateTime,
+    confirmed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ConfirmParams {
+    #[serde(default, deserialize_with = "form::trimmed")]
+    code: Option<String>,
+}
+
+#[derive(Serialize)]
+struct VerifyEmailPage {
+    id: String,
+    // Set once the code was right.
+    verified_email: Option<String>,
+}
+
+pub async fn verify_form(state: web::Data<AppState>, id: web::Path<String>) -> Result<HttpResponse, AppError> {
+    render(&state, "verify_email", &VerifyEmailPage { id: id.into_inner(), verified_email: None }).await
+}
+
+// POST /verify-email/{id}. A wrong code counts against the limit whether or not it was close.
+pub async fn confirm(state: web::Data<AppState>, campus: Campus, id: web::Path<String>, params: form::Submitted<ConfirmParams>) -> Result<HttpResponse, AppError> {
+    let public_id = id.into_inner();
+    let code = match &params.code {
+        Some(val) => val.clone(),
+        None => {
+            return Err(AppError::validation("code", "Enter the code from the email."));
+        }
+    };
+
+    let mut tx = state.conn.begin().await?;
+    let pending = match queries::EMAIL_VERIFICATION.scoped(campus.id).run(|sql, campus_id| sqlx::query_as::<_, PendingVerification>(sql)
+    .bind(&public_id)
+    .bind(campus_id)
+    .fetch_optional(&mut tx)).await? {
+        Some(val) => val,
+        None => {
+            return Err(AppError::NotFound("This verification code was replaced or no longer exists. Calculate again to get a new one.".to_string()));
+        }
+    };
+    if pending.confirmed_at.is_some() {
+        return render(&state, "verify_email", &VerifyEmailPage { id: public_id, verified_email: Some(pending.email) }).await;
+    }
+    if pending.expires_at < Local::now().naive_local() || pending.attempts >= MAX_ATTEMPTS {
+        return Err(AppError::validation("code", "This code has expired. Calculate again to get a new one."));
+    }
+
+    if code_hash(&public_id, &code) != pending.code_hash {
+        queries::COUNT_VERIFICATION_ATTEMPT.run(|sql| sqlx::query(sql)
+        .bind(pending.id)
+        .execute(&mut tx)).await?;
+        tx.commit().await?;
+        return Err(AppError::validation("code", "That code isn't right. Check the email and try again."));
+    }
+
+    queries::CONFIRM_EMAIL_VERIFICATION.run(|sql| sqlx::query(sql)
+    .bind(pending.id)
+    .execute(&mut tx)).await?;
+    queries::SET_STUDENT_EMAIL.run(|sql| sqlx::query(sql)
+    .bind(&pending.email)
+    .bind(pending.student_id)
+    .execute(&mut tx)).await?;
+    tx.commit().await?;
+
+    render(&state, "verify_email", &VerifyEmailPage { id: public_id, verified_email: Some(pending.email) }).await
+}
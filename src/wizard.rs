@@ -0,0 +1,268 @@
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    captcha, config, error::{AppError, FormErrors}, form, form_fields::FormFields, models::Campus, estimate, render, residency, AppState,
+    CalculateTuitionFormParams, Channel, TypeSafeParameters,
+};
+
+// The answers given so far, kept in the session between steps.
+const WIZARD_KEY: &str = "wizard";
+
+// The calculator one screen at a time, for phones and for browsers without JavaScript. Each step
+// posts back to the server, which keeps what was entered and sends the browser on to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Student,
+    Enrollment,
+    AddOns,
+    Review,
+}
+
+const STEPS: [Step; 4] = [Step::Student, Step::Enrollment, Step::AddOns, Step::Review];
+
+impl Step {
+    fn parse(name: &str) -> Option<Step> {
+        STEPS.into_iter().find(|step| step.name() == name)
+    }
+
+    // As it appears in the URL, e.g. /wizard/add-ons.
+    fn name(&self) -> &'static str {
+        match self {
+            Step::Student => "student",
+            Step::Enrollment => "enrollment",
+            Step::AddOns => "add-ons",
+            Step::Review => "review",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Step::Student => "About You",
+            Step::Enrollment => "Enrollment",
+            Step::AddOns => "Add-ons",
+            Step::Review => "Review",
+        }
+    }
+
+    fn index(&self) -> usize {
+        STEPS.iter().position(|step| step == self).unwrap_or(0)
+    }
+
+    fn next(&self) -> Step {
+        STEPS.get(self.index() + 1).copied().unwrap_or(Step::Review)
+    }
+
+    fn previous(&self) -> Step {
+        STEPS[self.index().saturating_sub(1)]
+    }
+
+    // The step that asks for a field, to send a problem with it back to. Anything that isn't one
+    // of the inputs, like the captcha or a validation rule on several, is shown on the review.
+    fn for_field(field: &str) -> Step {
+        match field {
+            "first_name" | "last_name" | "email" => Step::Student,
+            "num_credits" | "new_student" | "student_type" | "home_state" | "student_studies" | "enrollment_date" => Step::Enrollment,
+            "orientation" | "course_codes" | "insurance_waiver" | "include_additional_costs" => Step::AddOns,
+            _ => Step::Review,
+        }
+    }
+
+    // Keep this step's answers from what was submitted. A checkbox left out of the submission was
+    // unchecked.
+    fn take(&self, saved: &mut CalculateTuitionFormParams, submitted: CalculateTuitionFormParams, fields: &FormFields) {
+        match self {
+            Step::Student => {
+                saved.first_name = submitted.first_name;
+                saved.last_name = submitted.last_name;
+                saved.email = submitted.email;
+            }
+            Step::Enrollment => {
+                saved.num_credits = submitted.num_credits;
+                saved.new_student = submitted.new_student;
+                saved.student_type = submitted.student_type;
+                saved.home_state = submitted.home_state;
+                saved.student_studies = submitted.student_studies;
+                saved.enrollment_date = submitted.enrollment_date;
+                if !offers_orientation(saved, fields) {
+                    saved.orientation = None;
+                }
+            }
+            Step::AddOns => {
+                // Offered and left unchecked is a "no", as on the single-page form.
+                saved.orientation = if offers_orientation(saved, fields) { Some(submitted.orientation.unwrap_or(false)) } else { None };
+                saved.course_codes = submitted.course_codes;
+                saved.insurance_waiver = submitted.insurance_waiver;
+                saved.include_additional_costs = submitted.include_additional_costs;
+            }
+            Step::Review => {
+                saved.consent = submitted.consent;
+                saved.captcha_response = submitted.captcha_response;
+            }
+        }
+    }
+}
+
+// New students are asked about orientation, except dual-enrollment students, who don't attend.
+fn offers_orientation(form: &CalculateTuitionFormParams, fields: &FormFields) -> bool {
+    fields.orientation && form.new_student && form.student_studies.as_deref() != Some("dual_enrollment")
+}
+
+fn saved(session: &Session) -> CalculateTuitionFormParams {
+    match session.get::<CalculateTuitionFormParams>(WIZARD_KEY) {
+        Ok(Some(val)) => val,
+        // A missing or tampered cookie starts over.
+        _ => CalculateTuitionFormParams::default(),
+    }
+}
+
+fn save(session: &Session, form: &CalculateTuitionFormParams) {
+    if let Err(why) = session.insert(WIZARD_KEY, form) {
+        println!("Error while saving the calculator's steps to the session: {}", why);
+    }
+}
+
+// The first step with a problem in what's been entered, and the problem, if it comes before
+// `step`. Everything is checked again when the estimate is made.
+fn earlier_problem(form: &CalculateTuitionFormParams, step: Step) -> Option<(Step, AppError)> {
+    let why = TypeSafeParameters::from_form(form).err()?;
+    let first = why.field_errors().iter().map(|error| Step::for_field(error.field)).min_by_key(Step::index)?;
+    if first.index() < step.index() { Some((first, why)) } else { None }
+}
+
+fn redirect(step: Step) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", format!("/wizard/{}", step.name())))
+        .finish()
+}
+
+#[derive(Serialize)]
+struct StepLink {
+    name: &'static str,
+    title: &'static str,
+    number: usize,
+    current: bool,
+    done: bool,
+}
+
+#[derive(Serialize)]
+struct WizardPage {
+    campus: Campus,
+    step: &'static str,
+    title: &'static str,
+    number: usize,
+    steps: Vec<StepLink>,
+    form: CalculateTuitionFormParams,
+    errors: Option<FormErrors>,
+    fields: FormFields,
+    offer_orientation: bool,
+    can_email: bool,
+    // Asked for on the review, just before the estimate is made.
+    captcha: Option<captcha::CaptchaWidget>,
+    consent: Option<config::ConsentConfig>,
+    term_notice: Option<String>,
+    states: &'static [residency::HomeState],
+}
+
+async fn render_step(state: &AppState, campus: Campus, step: Step, form: CalculateTuitionFormParams, why: Option<&AppError>) -> Result<HttpResponse, AppError> {
+    let fields = state.form_fields(&campus).await?;
+    let term_notice = match why {
+        Some(AppError::Closed(message)) => Some(message.clone()),
+        _ => state.current_term_notice(&campus).await?,
+    };
+    let steps = STEPS.iter().map(|other| StepLink {
+        name: other.name(),
+        title: other.title(),
+        number: other.index() + 1,
+        current: *other == step,
+        done: other.index() < step.index(),
+    }).collect();
+    let mut response = render(state, "wizard", &WizardPage {
+        offer_orientation: offers_orientation(&form, &fields),
+        campus,
+        step: step.name(),
+        title: step.title(),
+        number: step.index() + 1,
+        steps,
+        form,
+        errors: why.and_then(FormErrors::from_error),
+        fields,
+        can_email: state.mailer.is_some(),
+        captcha: if step == Step::Review { state.captcha_widget() } else { None },
+        consent: if step == Step::Review { state.consent.clone() } else { None },
+        term_notice,
+        states: &residency::STATES,
+    }).await?;
+    if let Some(why) = why {
+        *response.status_mut() = why.status_code();
+    }
+    Ok(response)
+}
+
+// GET /wizard: the first step, with anything entered before.
+pub async fn start() -> HttpResponse {
+    redirect(Step::Student)
+}
+
+// GET /wizard/{step}. Going straight to a later step than has been filled in goes back to the
+// first one missing something.
+pub async fn step(state: web::Data<AppState>, campus: Campus, session: Session, path: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let step = match Step::parse(&path) {
+        Some(val) => val,
+        None => {
+            return Err(AppError::NotFound(format!("There's no \"{}\" step.", path)));
+        }
+    };
+    let form = saved(&session);
+    if let Some((first, _)) = earlier_problem(&form, step) {
+        return Ok(redirect(first));
+    }
+    render_step(&state, campus, step, form, None).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StepQuery {
+    // Set by the Back button, which keeps what was entered without checking it.
+    #[serde(default, deserialize_with = "form::checkbox")]
+    back: bool,
+}
+
+// POST /wizard/{step}: keep the step's answers, then go on to the next step, or back to the one
+// with a problem. The review makes the estimate, the same as the single-page form.
+pub async fn submit(state: web::Data<AppState>, campus: Campus, req: HttpRequest, session: Session, path: web::Path<String>, query: web::Query<StepQuery>, params: web::Form<CalculateTuitionFormParams>) -> Result<HttpResponse, AppError> {
+    let step = match Step::parse(&path) {
+        Some(val) => val,
+        None => {
+            return Err(AppError::NotFound(format!("There's no \"{}\" step.", path)));
+        }
+    };
+    let fields = state.form_fields(&campus).await?;
+    let mut form = saved(&session);
+    step.take(&mut form, params.into_inner(), &fields);
+    save(&session, &form);
+    if query.back {
+        return Ok(redirect(step.previous()));
+    }
+
+    if step != Step::Review {
+        return match earlier_problem(&form, step.next()) {
+            Some((first, why)) if first == step => render_step(&state, campus, step, form, Some(&why)).await,
+            Some((first, _)) => Ok(redirect(first)),
+            None => Ok(redirect(step.next())),
+        };
+    }
+
+    match estimate(&state, campus.clone(), &req, &session, &form, None, Channel::Web).await {
+        Ok(response) => {
+            session.remove(WIZARD_KEY);
+            Ok(response)
+        }
+        Err(why @ (AppError::Validation { .. } | AppError::Rejected(_) | AppError::Closed(_))) => {
+            println!("{}", why);
+            let first = why.field_errors().iter().map(|error| Step::for_field(error.field)).min_by_key(Step::index).unwrap_or(Step::Review);
+            render_step(&state, campus, first, form, Some(&why)).await
+        }
+        Err(why) => Err(why),
+    }
+}
@@ -0,0 +1,193 @@
+// Two campuses with the same student in each, checked against a real database: the lookup, the
+// exports and the admin pages each get only their own campus's rows, by name, by id or listed.
+// tests/tenant_isolation.rs checks the SQL of every query; this checks what the ones behind those
+// pages actually return.
+//
+// These need a MySQL database the tests can migrate, e.g.
+// `TEST_DATABASE_URL=mysql://root@localhost/tuition_test cargo test`; see tests/common/mod.rs.
+// Each test makes its own campuses, so they can share one database and run again against it.
+//
+// The app is a binary crate, so `queries` and `filters` are compiled in directly alongside
+// stand-ins for the timing and tracing they report to, as in tests/tenant_isolation.rs.
+use sqlx::MySqlPool;
+
+mod common;
+
+use common::{database, random};
+
+mod metrics {
+    pub fn record_query(_name: &'static str, _elapsed: std::time::Duration) {}
+}
+
+mod slow_queries {
+    pub fn record(_name: &'static str, _elapsed: std::time::Duration) {}
+}
+
+mod telemetry {
+    pub struct Stage;
+
+    pub fn query(_name: &'static str) -> Stage {
+        Stage
+    }
+}
+
+#[path = "../src/filters.rs"]
+#[allow(dead_code)]
+mod filters;
+#[path = "../src/queries.rs"]
+#[allow(dead_code)]
+mod queries;
+
+
+async fn insert(pool: &MySqlPool, query: sqlx::query::Query<'_, sqlx::MySql, sqlx::mysql::MySqlArguments>) -> i32 {
+    query.execute(pool).await.unwrap().last_insert_id() as i32
+}
+
+// What was seeded for one campus, by id.
+struct Seeded {
+    campus_id: i32,
+    student_id: i32,
+    public_id: String,
+    record_id: i32,
+    receipt_id: i32,
+    receipt_code: String,
+    scenario_id: i32,
+    line_item_id: i32,
+    agreement_id: i32,
+}
+
+// One campus with Ada Lovelace, a record, a receipt and a scenario of hers, a line item and a
+// reciprocity agreement. Every campus gets the same names, terms and states, so only the campus
+// tells them apart.
+async fn seed(pool: &MySqlPool) -> Seeded {
+    let slug = format!("test-{}", random(12).to_lowercase());
+    let campus_id = insert(pool, sqlx::query("insert into Campuses (Slug, Name, State) values (?, ?, 'MN')")
+    .bind(&slug)
+    .bind(&slug)).await;
+    let public_id = format!("{}-{}", random(8), random(27));
+    let student_id = insert(pool, sqlx::query("insert into Students (CampusId, FirstName, LastName, PublicId) values (?, 'Ada', 'Lovelace', ?)")
+    .bind(campus_id)
+    .bind(&public_id)).await;
+    let record_id = insert(pool, sqlx::query("insert into TuitionRecords (StudentId, Term, TuitionCost, NumCredits, StudentType, StudentStudies) values (?, 'Fall 2026', 1200, 12, 'resident', 'undergraduate')")
+    .bind(student_id)).await;
+    let receipt_code = random(10);
+    let receipt_id = insert(pool, sqlx::query("insert into Receipts
+        (Code, CampusId, StudentId, FirstName, LastName, Term, NumCredits, Orientation, StudentType, StudentStudies,
+        CreditsCost, NonresidencyFee, OrientationFee, TuitionCost)
+        values (?, ?, ?, 'Ada', 'Lovelace', 'Fall 2026', 12, false, 'resident', 'undergraduate', 100, 0, 0, 1200)")
+    .bind(&receipt_code)
+    .bind(campus_id)
+    .bind(student_id)).await;
+    let scenario_id = insert(pool, sqlx::query("insert into Scenarios
+        (CampusId, StudentId, ScenarioName, FirstName, LastName, NumCredits, NewStudent, Orientation, StudentType, StudentStudies, IncludeAdditionalCosts)
+        values (?, ?, 'Full time', 'Ada', 'Lovelace', 12, false, false, 'resident', 'undergraduate', false)")
+    .bind(campus_id)
+    .bind(student_id)).await;
+    let line_item_id = insert(pool, sqlx::query("insert into CustomLineItems (CampusId, Term, Label, Amount) values (?, 'Fall 2026', 'Graduation fee', 50)")
+    .bind(campus_id)).await;
+    let agreement_id = insert(pool, sqlx::query("insert into ReciprocityAgreements (CampusId, State, Label, Residency) values (?, 'WI', 'Wisconsin reciprocity', 'resident')")
+    .bind(campus_id)).await;
+    Seeded { campus_id, student_id, public_id, record_id, receipt_id, receipt_code, scenario_id, line_item_id, agreement_id }
+}
+
+// The ids (or other first column) of the rows a campus-scoped query returns, with the campus
+// bound first and then `binds`.
+async fn first_column<T>(pool: &MySqlPool, query: queries::Query, campus_id: i32, binds: &[&str]) -> Vec<T>
+where
+    T: for<'r> sqlx::Decode<'r, sqlx::MySql> + sqlx::Type<sqlx::MySql> + Send + Unpin,
+{
+    query.scoped(campus_id).run(|sql, campus_id| {
+        let mut query = sqlx::query_as::<_, (T,)>(sql).bind(campus_id);
+        for val in binds {
+            query = query.bind(*val);
+        }
+        query.fetch_all(pool)
+    }).await.unwrap().into_iter().map(|(val,)| val).collect()
+}
+
+// As above, for the by-id lookups, which take the id before the campus.
+async fn by_id(pool: &MySqlPool, query: queries::Query, id: i32, campus_id: i32) -> Vec<i32> {
+    query.scoped(campus_id).run(|sql, campus_id| sqlx::query_as::<_, (i32,)>(sql)
+    .bind(id)
+    .bind(campus_id)
+    .fetch_all(pool)).await.unwrap().into_iter().map(|(val,)| val).collect()
+}
+
+// The tuition lookup and the receipt pages, which students reach by name or code.
+#[actix_web::test]
+async fn lookups_find_only_their_campus() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let (a, b) = (seed(&pool).await, seed(&pool).await);
+
+    for (mine, theirs) in [(&a, &b), (&b, &a)] {
+        assert_eq!(first_column::<i32>(&pool, queries::RECORDS_BY_NAME, mine.campus_id, &["Ada", "Lovelace"]).await, vec![mine.record_id]);
+        assert_eq!(first_column::<i32>(&pool, queries::RECEIPT_BY_CODE, mine.campus_id, &[&mine.receipt_code]).await, vec![mine.receipt_id]);
+        assert!(first_column::<i32>(&pool, queries::RECEIPT_BY_CODE, mine.campus_id, &[&theirs.receipt_code]).await.is_empty());
+        assert!(first_column::<i32>(&pool, queries::RECEIPT_BY_ID, mine.campus_id, &[&theirs.receipt_id.to_string()]).await.is_empty());
+    }
+}
+
+// The records CSV and a student's own data export.
+#[actix_web::test]
+async fn exports_hold_only_their_campus() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let (a, b) = (seed(&pool).await, seed(&pool).await);
+
+    for (mine, theirs) in [(&a, &b), (&b, &a)] {
+        // From the start, with room for every record in the database.
+        let records = queries::EXPORT_RECORDS_PAGE.scoped(mine.campus_id).run(|sql, campus_id| sqlx::query_as::<_, (i32,)>(sql)
+        .bind(campus_id)
+        .bind(0)
+        .bind(i64::MAX)
+        .fetch_all(&pool)).await.unwrap();
+        assert_eq!(records, vec![(mine.record_id,)]);
+
+        assert_eq!(first_column::<i32>(&pool, queries::STUDENT_BY_PUBLIC_ID, mine.campus_id, &[&mine.public_id]).await, vec![mine.student_id]);
+        assert!(first_column::<i32>(&pool, queries::STUDENT_BY_PUBLIC_ID, mine.campus_id, &[&theirs.public_id]).await.is_empty());
+        assert_eq!(first_column::<i32>(&pool, queries::SCENARIOS_FOR_STUDENT, mine.campus_id, &[&mine.student_id.to_string()]).await, vec![mine.scenario_id]);
+        assert!(first_column::<i32>(&pool, queries::SCENARIOS_FOR_STUDENT, mine.campus_id, &[&theirs.student_id.to_string()]).await.is_empty());
+    }
+}
+
+// The record search, duplicate students, line items and reciprocity agreements, and the pages
+// that open one of them by id.
+#[actix_web::test]
+async fn admin_pages_list_only_their_campus() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let (a, b) = (seed(&pool).await, seed(&pool).await);
+
+    for (mine, theirs) in [(&a, &b), (&b, &a)] {
+        // Built the way `admin::records` builds it, with the name filter every campus matches.
+        let mut conditions = filters::Conditions::new(queries::SEARCH_RECORDS.sql);
+        conditions
+            .and("CampusId = ", mine.campus_id)
+            .and_some("concat(FirstName, ' ', LastName) like ", Some(filters::contains("Ada Lovelace")));
+        let mut search = conditions.finish();
+        let found = queries::SEARCH_RECORDS.time(search.build_query_as::<(i32,)>().fetch_all(&pool)).await.unwrap();
+        assert_eq!(found, vec![(mine.record_id,)]);
+
+        assert_eq!(first_column::<String>(&pool, queries::DUPLICATE_CANDIDATES, mine.campus_id, &[]).await, vec![mine.public_id.clone()]);
+        assert_eq!(first_column::<i32>(&pool, queries::ADMIN_LINE_ITEMS, mine.campus_id, &[]).await, vec![mine.line_item_id]);
+        assert_eq!(first_column::<i32>(&pool, queries::ADMIN_RECIPROCITY_AGREEMENTS, mine.campus_id, &[]).await, vec![mine.agreement_id]);
+
+        assert_eq!(by_id(&pool, queries::RECORD_BY_ID, mine.record_id, mine.campus_id).await, vec![mine.record_id]);
+        assert!(by_id(&pool, queries::RECORD_BY_ID, theirs.record_id, mine.campus_id).await.is_empty());
+        assert!(by_id(&pool, queries::LINE_ITEM_BY_ID, theirs.line_item_id, mine.campus_id).await.is_empty());
+        assert!(by_id(&pool, queries::RECIPROCITY_AGREEMENT_BY_ID, theirs.agreement_id, mine.campus_id).await.is_empty());
+    }
+}
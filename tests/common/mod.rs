@@ -0,0 +1,23 @@
+// Fixtures shared by the tests that run against a real database.
+use rand::{distributions::Alphanumeric, Rng};
+use sqlx::MySqlPool;
+
+// The database in TEST_DATABASE_URL, migrated. Without one there's nothing to check, so the
+// caller skips its test, except on CI (where CI is set), which has to provide one.
+pub async fn database() -> Option<MySqlPool> {
+    let url = match std::env::var("TEST_DATABASE_URL") {
+        Ok(val) => val,
+        Err(_) => {
+            assert!(std::env::var_os("CI").is_none(), "TEST_DATABASE_URL has to be set on CI.");
+            eprintln!("TEST_DATABASE_URL isn't set; skipping the database checks.");
+            return None;
+        }
+    };
+    let pool = MySqlPool::connect(&url).await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    Some(pool)
+}
+
+pub fn random(len: usize) -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(len).map(char::from).collect()
+}
@@ -0,0 +1,56 @@
+// The hashes ADMIN_BASIC_AUTH accepts. Both kinds are made here with the same crates that check
+// them, and with low costs, so the tests stay quick.
+#[path = "../src/passwords.rs"]
+mod passwords;
+
+use argon2::{password_hash::{rand_core::OsRng, SaltString}, Algorithm, Argon2, Params, PasswordHasher, Version};
+use passwords::PasswordHash;
+
+fn argon2id(password: &str) -> String {
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(1024, 1, 1, None).unwrap());
+    argon2.hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng)).unwrap().to_string()
+}
+
+#[test]
+fn argon2id_hashes_check_the_password() {
+    let hash = PasswordHash::parse(&argon2id("secret")).unwrap();
+    assert!(matches!(hash, PasswordHash::Argon2id(_)));
+    assert!(hash.verify("secret"));
+    assert!(!hash.verify("Secret"));
+    assert!(!hash.verify(""));
+}
+
+#[test]
+fn bcrypt_hashes_check_the_password() {
+    // As `htpasswd -B` writes them, and as other tools do.
+    for hash in [bcrypt::hash("secret", 4).unwrap(), bcrypt::hash_with_result("secret", 4).unwrap().format_for_version(bcrypt::Version::TwoA)] {
+        let hash = PasswordHash::parse(&hash).unwrap();
+        assert!(matches!(hash, PasswordHash::Bcrypt(_)));
+        assert!(hash.verify("secret"));
+        assert!(!hash.verify("secret "));
+    }
+}
+
+// The unsalted SHA-256 hashes ADMIN_BASIC_AUTH used to take are turned away when the config is
+// read, rather than never matching.
+#[test]
+fn sha256_and_other_hashes_are_refused() {
+    let refused = [
+        "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b",
+        "secret",
+        "",
+        "$argon2i$v=19$m=16,t=2,p=1$c2FsdHNhbHQ$2ngRpJa0OFU5Xx9+WCgtmQ",
+        "$argon2id$not-a-hash",
+        "$2y$12$tooshort",
+    ];
+    for val in refused {
+        assert!(PasswordHash::parse(val).is_err(), "{:?}", val);
+    }
+}
+
+#[test]
+fn hashes_stay_out_of_debug_output() {
+    let hash = argon2id("secret");
+    let parsed = PasswordHash::parse(&hash).unwrap();
+    assert_eq!(format!("{:?}", parsed), "argon2id");
+}
@@ -0,0 +1,113 @@
+// The per-address limit on /api/v1/rates: a client over it gets a 429 until the minute is up,
+// without using up anyone else's requests.
+//
+// The app is a binary crate, so `rate_limit` and `client_ip` are compiled in directly alongside
+// a stand-in for the app state they read, as in tests/tenant_isolation.rs.
+use actix_web::{http::StatusCode, middleware, test, web, App, HttpResponse};
+use std::{net::{IpAddr, SocketAddr}, sync::Arc};
+
+mod error {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ErrorCode {
+        RateLimited,
+    }
+}
+
+pub struct AppState {
+    trusted_proxies: client_ip::TrustedProxies,
+    rates_limiter: Arc<rate_limit::RateLimiter<Option<IpAddr>>>,
+    rates_requests_per_minute: u32,
+}
+
+#[path = "../src/client_ip.rs"]
+#[allow(dead_code)]
+mod client_ip;
+#[path = "../src/rate_limit.rs"]
+mod rate_limit;
+
+const PER_MINUTE: u32 = 3;
+
+fn state(trusted_proxies: &str) -> web::Data<AppState> {
+    web::Data::new(AppState {
+        trusted_proxies: trusted_proxies.parse().unwrap(),
+        rates_limiter: Arc::new(rate_limit::RateLimiter::default()),
+        rates_requests_per_minute: PER_MINUTE,
+    })
+}
+
+fn from(ip: &str) -> test::TestRequest {
+    test::TestRequest::get().uri("/api/v1/rates").peer_addr(SocketAddr::new(ip.parse().unwrap(), 40000))
+}
+
+macro_rules! rates_app {
+    ($state:expr) => {
+        test::init_service(App::new()
+            .app_data($state)
+            .service(web::resource("/api/v1/rates")
+                .wrap(middleware::from_fn(rate_limit::limit_rates))
+                .route(web::get().to(|| async { HttpResponse::Ok().body("rates") }))))
+        .await
+    };
+}
+
+#[actix_web::test]
+async fn over_the_limit_is_told_to_wait() {
+    let app = rates_app!(state(""));
+    for _ in 0..PER_MINUTE {
+        assert_eq!(test::call_service(&app, from("203.0.113.7").to_request()).await.status(), StatusCode::OK);
+    }
+    let response = test::call_service(&app, from("203.0.113.7").to_request()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers().get("Retry-After").unwrap(), "60");
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["code"], "rate_limited");
+}
+
+#[actix_web::test]
+async fn each_address_has_its_own_limit() {
+    let app = rates_app!(state(""));
+    for _ in 0..=PER_MINUTE {
+        test::call_service(&app, from("203.0.113.7").to_request()).await;
+    }
+    assert_eq!(test::call_service(&app, from("203.0.113.8").to_request()).await.status(), StatusCode::OK);
+}
+
+// Behind a trusted proxy the visitors are counted, not the proxy.
+#[actix_web::test]
+async fn visitors_behind_a_proxy_are_counted_apart() {
+    let app = rates_app!(state("10.0.0.1"));
+    for _ in 0..=PER_MINUTE {
+        test::call_service(&app, from("10.0.0.1").insert_header(("X-Forwarded-For", "203.0.113.7")).to_request()).await;
+    }
+    let other = from("10.0.0.1").insert_header(("X-Forwarded-For", "203.0.113.8")).to_request();
+    assert_eq!(test::call_service(&app, other).await.status(), StatusCode::OK);
+    let again = from("10.0.0.1").insert_header(("X-Forwarded-For", "203.0.113.7")).to_request();
+    assert_eq!(test::call_service(&app, again).await.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+// Without a peer address, e.g. over a Unix socket with no forwarded header, clients share one
+// allowance instead of going unlimited.
+#[actix_web::test]
+async fn unknown_addresses_share_a_limit() {
+    let app = rates_app!(state(""));
+    for _ in 0..PER_MINUTE {
+        assert_eq!(test::call_service(&app, test::TestRequest::get().uri("/api/v1/rates").to_request()).await.status(), StatusCode::OK);
+    }
+    let response = test::call_service(&app, test::TestRequest::get().uri("/api/v1/rates").to_request()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+// Over a Unix socket the local proxy is trusted, so its visitors are counted apart.
+#[actix_web::test]
+async fn visitors_over_a_unix_socket_are_counted_apart() {
+    let app = rates_app!(state(""));
+    let over_socket = |ip: &str| test::TestRequest::get().uri("/api/v1/rates").insert_header(("X-Forwarded-For", ip.to_string())).to_request();
+    for _ in 0..=PER_MINUTE {
+        test::call_service(&app, over_socket("203.0.113.7")).await;
+    }
+    assert_eq!(test::call_service(&app, over_socket("203.0.113.8")).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, over_socket("203.0.113.7")).await.status(), StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,213 @@
+// Snapshots of the pages students see, rendered from fixed data through the real templates and
+// helpers, so a change to either shows up as a diff to review instead of going out unnoticed.
+// After an intended change, accept the new output with `cargo insta review` (or run the tests
+// with INSTA_UPDATE=always) and commit the snapshots alongside it.
+use handlebars::Handlebars;
+use rust_decimal::Decimal;
+use serde_json::json;
+
+// The app is a binary crate, so the rendering modules are compiled in directly alongside
+// stand-ins for what they use, as in benches/pricing.rs.
+mod models {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use crate::money::{Money, PerCredit};
+
+    pub struct TuitionCosts {
+        pub credits_cost: PerCredit,
+        pub nonresidency_fee: Money,
+    }
+
+    pub struct ProrationRule {
+        pub starts_on: NaiveDate,
+        pub after_week: u32,
+        pub tuition_percent: Decimal,
+    }
+
+    pub struct RefundRule {
+        pub starts_on: NaiveDate,
+        pub through_week: u32,
+        pub refund_percent: Decimal,
+    }
+
+    pub struct ReciprocityAgreement {
+        pub state: String,
+        pub residency: String,
+        pub nonresidency_fee_percent: Decimal,
+    }
+}
+
+mod config {
+    use serde::Serialize;
+
+    #[derive(Default, Serialize)]
+    pub struct BrandingConfig {
+        pub name: Option<String>,
+        pub logo: Option<String>,
+        pub primary_color: Option<String>,
+        pub contact_lines: Vec<String>,
+    }
+}
+
+mod error {
+    pub enum AppError {
+        Internal(String),
+    }
+}
+
+pub struct AppState {
+    templates: Handlebars<'static>,
+    branding: config::BrandingConfig,
+}
+
+#[path = "../src/assets.rs"]
+#[allow(dead_code)]
+mod assets;
+#[path = "../src/chart.rs"]
+mod chart;
+#[path = "../src/money.rs"]
+#[allow(dead_code)]
+mod money;
+#[path = "../src/pricing.rs"]
+#[allow(dead_code)]
+mod pricing;
+#[path = "../src/renderer.rs"]
+#[allow(dead_code)]
+mod renderer;
+
+use money::Money;
+use pricing::{BreakdownItem, FeeCategory};
+
+fn render(template: &str, data: &serde_json::Value) -> String {
+    render_branded(template, data, config::BrandingConfig::default())
+}
+
+fn render_branded(template: &str, data: &serde_json::Value, branding: config::BrandingConfig) -> String {
+    let state = AppState { templates: renderer::templates(), branding };
+    match renderer::render_string(&state, template, data) {
+        Ok(body) => body,
+        Err(error::AppError::Internal(why)) => panic!("{}", why),
+    }
+}
+
+// The stylesheet's URL carries a hash of its contents; a CSS change isn't a page change.
+macro_rules! assert_page {
+    ($name:expr, $page:expr) => {
+        insta::with_settings!({ filters => vec![(r"/assets/style\.[0-9a-f]+\.css", "/assets/style.[hash].css")] }, {
+            insta::assert_snapshot!($name, $page);
+        });
+    };
+}
+
+fn item(label: &str, amount: Decimal, category: FeeCategory) -> BreakdownItem {
+    BreakdownItem { label: label.to_string(), amount: Money::new(amount), category }
+}
+
+#[test]
+fn result_page() {
+    let categories = pricing::categorize(vec![
+        item("Tuition, 12 credit(s)", Decimal::new(780000, 2), FeeCategory::Tuition),
+        item("Proration, 90% charged", Decimal::new(-78000, 2), FeeCategory::Tuition),
+        item("Non-residency fee", Decimal::new(125000, 2), FeeCategory::MandatoryFees),
+        item("BIO101: Lab fee", Decimal::new(7500, 2), FeeCategory::MandatoryFees),
+        item("Health insurance", Decimal::new(98000, 2), FeeCategory::MandatoryFees),
+        item("Orientation fee", Decimal::new(15000, 2), FeeCategory::OptionalFees),
+        item("Presidential scholarship", Decimal::new(-100000, 2), FeeCategory::Aid),
+    ]);
+    let segments: Vec<(&str, Decimal)> = categories.iter()
+        .filter(|category| category.subtotal.amount() > Decimal::ZERO)
+        .map(|category| (category.label, category.subtotal.amount()))
+        .chain([("Estimated additional costs", Decimal::new(640000, 2))])
+        .collect();
+    let page = json!({
+        "campus": { "id": 1, "slug": "main", "name": "Main Campus", "hostname": null },
+        "first_name": "Ada",
+        "last_name": "O'Neill",
+        "residency": "Nonresident",
+        "studies": "Undergraduate",
+        "new_student": true,
+        "orientation_fee": "150.00",
+        "nonresidency_fee": "1250.00",
+        "num_credits": 12,
+        "credits_cost": "650.00",
+        "total": "8475.00",
+        "enrollment_date": "2026-09-14",
+        "insurance_waived": false,
+        "health_insurance_fee": "980.00",
+        "categories": categories,
+        "proration": { "week": 2, "tuition_percent": "90" },
+        "breakdown_chart": chart::breakdown_svg(&segments),
+        "indirect_costs": [
+            { "label": "Books and supplies", "amount": "600.00" },
+            { "label": "Housing and food", "amount": "5800.00" },
+        ],
+        "additional_total": "6400.00",
+        "grand_total": "14875.00",
+        "projections": [
+            { "program": "Bachelor's degree", "total_credits": 120, "credits_per_term": 12, "terms": 10, "total": "84750.00" },
+        ],
+        "receipt_code": "K7Q2M9XA4D",
+        "saved_later": false,
+    });
+    assert_page!("result", render("result", &page));
+}
+
+#[test]
+fn lookup_page() {
+    // Oldest first, and only the records with a term, as the lookup handler charts them.
+    let trend = [("Spring 2026", Decimal::new(850000, 2)), ("Fall 2026", Decimal::new(847500, 2))];
+    let page = json!({
+        "records": [
+            { "id": 3, "student_id": "01927c3e-8a4b-7c1d-9e2f-3a4b5c6d7e8f", "campus_id": 1, "first_name": "Ada", "last_name": "O'Neill", "term": "Fall 2026", "tuition_cost": "8475.00" },
+            { "id": 2, "student_id": "01927c3e-8a4b-7c1d-9e2f-3a4b5c6d7e8f", "campus_id": 1, "first_name": "Ada", "last_name": "O'Neill", "term": "Spring 2026", "tuition_cost": "8500.00" },
+            { "id": 1, "student_id": "01927c3e-8a4b-7c1d-9e2f-3a4b5c6d7e8f", "campus_id": 1, "first_name": "Ada", "last_name": "O'Neill", "term": null, "tuition_cost": "8125.00" },
+        ],
+        "trend_chart": chart::sparkline_svg(&trend),
+    });
+    assert_page!("lookup", render("lookup", &page));
+}
+
+#[test]
+fn error_page() {
+    let page = json!({
+        "code": "invalid_request",
+        "message": "Number of credits must be between 1 and 21.",
+        "field": "num_credits",
+        "errors": [{ "field": "num_credits", "message": "Number of credits must be between 1 and 21." }],
+        "request_id": "5f0c2a9e1b7d4c3a",
+    });
+    assert_page!("error", render("error", &page));
+}
+
+// A server error names no field; the page only says what went wrong in general.
+#[test]
+fn error_page_without_field() {
+    let page = json!({
+        "code": "internal_error",
+        "message": "Something went wrong on our end. Please try again.",
+        "field": null,
+        "errors": [],
+        "request_id": "9d8e7f6a5b4c3d2e",
+    });
+    assert_page!("error_without_field", render("error", &page));
+}
+
+// Another school's name, logo, color and contact lines, all from config.
+#[test]
+fn branded_error_page() {
+    let branding = config::BrandingConfig {
+        name: Some("Example State University".to_string()),
+        logo: Some("https://www.example.edu/images/logo.png".to_string()),
+        primary_color: Some("#00447c".to_string()),
+        contact_lines: vec!["Office of the Bursar".to_string(), "(555) 555-0100".to_string()],
+    };
+    let page = json!({
+        "code": "not_found",
+        "message": "No receipt K7Q2M9XA4D was found.",
+        "field": null,
+        "errors": [],
+        "request_id": "0a1b2c3d4e5f6a7b",
+    });
+    assert_page!("branded_error", render_branded("error", &page, branding));
+}
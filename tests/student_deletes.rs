@@ -0,0 +1,379 @@
+// Deleting and merging students against a real database, since what they leave behind is down to
+// the foreign keys as much as the queries: records and scenarios go with the student, receipts
+// are kept without them or purged with their refund estimates, and a failure leaves everything
+// as it was.
+//
+// These need a MySQL database the tests can migrate, e.g.
+// `TEST_DATABASE_URL=mysql://root@localhost/tuition_test cargo test`; see tests/common/mod.rs.
+// Each test makes its own campus, so they can share one database and run again against it.
+//
+// The app is a binary crate, so `students` is compiled in directly alongside stand-ins for what
+// it uses, as in tests/tenant_isolation.rs.
+use sqlx::MySqlPool;
+
+mod common;
+
+use common::{database, random};
+
+mod metrics {
+    pub fn record_query(_name: &'static str, _elapsed: std::time::Duration) {}
+}
+
+mod slow_queries {
+    pub fn record(_name: &'static str, _elapsed: std::time::Duration) {}
+}
+
+mod telemetry {
+    pub struct Stage;
+
+    pub fn query(_name: &'static str) -> Stage {
+        Stage
+    }
+}
+
+// Writes the entry as the app does, except for FAIL_AS, which fails after everything else in
+// the transaction has run.
+mod audit {
+    use crate::queries;
+
+    pub const FAIL_AS: &str = "audit-fails";
+
+    pub fn actor(_req: &actix_web::HttpRequest) -> String {
+        "test".to_string()
+    }
+
+    pub async fn record<'c>(conn: impl sqlx::Executor<'c, Database = sqlx::MySql>, actor: &str, action: &str, entity: &str, entity_id: i32, details: &str) -> Result<(), sqlx::Error> {
+        if actor == FAIL_AS {
+            return Err(sqlx::Error::Protocol("The audit log is unavailable.".to_string()));
+        }
+        queries::INSERT_AUDIT_ENTRY.run(|sql| sqlx::query(sql)
+        .bind(actor)
+        .bind(action)
+        .bind(entity)
+        .bind(entity_id)
+        .bind(details)
+        .execute(conn)).await?;
+        Ok(())
+    }
+}
+
+mod error {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    pub enum AppError {
+        NotFound(String),
+        Validation(String, String),
+        Database(sqlx::Error),
+    }
+
+    impl AppError {
+        pub fn validation(field: &str, message: &str) -> AppError {
+            AppError::Validation(field.to_string(), message.to_string())
+        }
+    }
+
+    impl From<sqlx::Error> for AppError {
+        fn from(why: sqlx::Error) -> AppError {
+            AppError::Database(why)
+        }
+    }
+}
+
+// The typed ids, stored as INT columns as in src/models.rs.
+mod models {
+    use sqlx::{encode::IsNull, error::BoxDynError, mysql::{MySqlTypeInfo, MySqlValueRef}, Decode, Encode, MySql};
+
+    macro_rules! id_type {
+        ($name:ident) => {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct $name(pub i32);
+
+            impl sqlx::Type<MySql> for $name {
+                fn type_info() -> MySqlTypeInfo {
+                    <i32 as sqlx::Type<MySql>>::type_info()
+                }
+
+                fn compatible(ty: &MySqlTypeInfo) -> bool {
+                    <i32 as sqlx::Type<MySql>>::compatible(ty)
+                }
+            }
+
+            impl<'q> Encode<'q, MySql> for $name {
+                fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+                    <i32 as Encode<'q, MySql>>::encode_by_ref(&self.0, buf)
+                }
+            }
+
+            impl<'r> Decode<'r, MySql> for $name {
+                fn decode(value: MySqlValueRef<'r>) -> Result<Self, BoxDynError> {
+                    Ok($name(<i32 as Decode<'r, MySql>>::decode(value)?))
+                }
+            }
+        };
+    }
+
+    id_type!(CampusId);
+    id_type!(StudentId);
+    id_type!(ScenarioId);
+
+    #[derive(Debug, Clone)]
+    pub struct Campus {
+        pub id: CampusId,
+        pub name: String,
+    }
+}
+
+mod retention {
+    use std::{fmt, str::FromStr};
+
+    pub const ANONYMIZED: &str = "(removed)";
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum RetentionAction {
+        Anonymize,
+        Purge,
+    }
+
+    impl FromStr for RetentionAction {
+        type Err = String;
+
+        fn from_str(val: &str) -> Result<RetentionAction, String> {
+            match val {
+                "anonymize" => Ok(RetentionAction::Anonymize),
+                "purge" => Ok(RetentionAction::Purge),
+                _ => Err(format!("Unknown retention action \"{}\".", val)),
+            }
+        }
+    }
+
+    impl fmt::Display for RetentionAction {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                RetentionAction::Anonymize => write!(f, "anonymize"),
+                RetentionAction::Purge => write!(f, "purge"),
+            }
+        }
+    }
+}
+
+pub struct AppState {
+    conn: MySqlPool,
+}
+
+pub const MAX_NAME_LENGTH: usize = 100;
+
+impl AppState {
+    fn read_conn(&self) -> &MySqlPool {
+        &self.conn
+    }
+}
+
+async fn render<T: serde::Serialize>(_state: &AppState, _template: &str, _data: &T) -> Result<actix_web::HttpResponse, error::AppError> {
+    Ok(actix_web::HttpResponse::Ok().finish())
+}
+
+#[path = "../src/form.rs"]
+#[allow(dead_code)]
+mod form;
+#[path = "../src/queries.rs"]
+#[allow(dead_code)]
+mod queries;
+#[path = "../src/students.rs"]
+#[allow(dead_code)]
+mod students;
+
+use models::{Campus, CampusId, StudentId};
+use retention::{RetentionAction, ANONYMIZED};
+
+
+async fn campus(pool: &MySqlPool) -> Campus {
+    let slug = format!("test-{}", random(12).to_lowercase());
+    let id = sqlx::query("insert into Campuses (Slug, Name) values (?, ?)")
+    .bind(&slug)
+    .bind(&slug)
+    .execute(pool).await.unwrap().last_insert_id();
+    Campus { id: CampusId(id as i32), name: slug }
+}
+
+struct Student {
+    id: StudentId,
+    public_id: String,
+}
+
+// A student with a record for each of `terms` and a scenario for each of `scenarios`.
+async fn student(pool: &MySqlPool, campus: &Campus, first_name: &str, last_name: &str, terms: &[&str], scenarios: &[&str]) -> Student {
+    let public_id = format!("{}-{}", random(8), random(27));
+    let id = sqlx::query("insert into Students (CampusId, FirstName, LastName, PublicId) values (?, ?, ?, ?)")
+    .bind(campus.id)
+    .bind(first_name)
+    .bind(last_name)
+    .bind(&public_id)
+    .execute(pool).await.unwrap().last_insert_id();
+    let id = StudentId(id as i32);
+    for term in terms {
+        sqlx::query("insert into TuitionRecords (StudentId, Term, TuitionCost) values (?, ?, 1000)")
+        .bind(id)
+        .bind(term)
+        .execute(pool).await.unwrap();
+    }
+    for name in scenarios {
+        sqlx::query("insert into Scenarios
+            (CampusId, StudentId, ScenarioName, FirstName, LastName, NumCredits, NewStudent, Orientation, StudentType, StudentStudies, IncludeAdditionalCosts)
+            values (?, ?, ?, ?, ?, 12, false, false, 'resident', 'undergraduate', false)")
+        .bind(campus.id)
+        .bind(id)
+        .bind(name)
+        .bind(first_name)
+        .bind(last_name)
+        .execute(pool).await.unwrap();
+    }
+    Student { id, public_id }
+}
+
+// A receipt for the student with one refund estimate against it. Returns the receipt's id.
+async fn receipt(pool: &MySqlPool, campus: &Campus, student: &Student) -> i32 {
+    let id = sqlx::query("insert into Receipts
+        (Code, CampusId, StudentId, FirstName, LastName, Term, NumCredits, Orientation, StudentType, StudentStudies,
+        CreditsCost, NonresidencyFee, OrientationFee, TuitionCost, ClientIpHash, UserAgent)
+        values (?, ?, ?, 'Ada', 'Lovelace', 'Fall 2026', 12, false, 'resident', 'undergraduate', 100, 0, 0, 1200, ?, 'test')")
+    .bind(random(10))
+    .bind(campus.id)
+    .bind(student.id)
+    .bind("0".repeat(64))
+    .execute(pool).await.unwrap().last_insert_id() as i32;
+    sqlx::query("insert into RefundEstimates (ReceiptId, WithdrawalDate, Week, RefundPercent, RefundAmount) values (?, '2026-09-01', 1, 100, 1200)")
+    .bind(id)
+    .execute(pool).await.unwrap();
+    id
+}
+
+async fn count(pool: &MySqlPool, sql: &str, id: i32) -> i64 {
+    sqlx::query_scalar::<_, i64>(sql).bind(id).fetch_one(pool).await.unwrap()
+}
+
+async fn records(pool: &MySqlPool, student: &Student) -> i64 {
+    count(pool, "select count(*) from TuitionRecords where StudentId = ?", student.id.0).await
+}
+
+async fn scenarios(pool: &MySqlPool, student: &Student) -> i64 {
+    count(pool, "select count(*) from Scenarios where StudentId = ?", student.id.0).await
+}
+
+async fn refund_estimates(pool: &MySqlPool, receipt_id: i32) -> i64 {
+    count(pool, "select count(*) from RefundEstimates where ReceiptId = ?", receipt_id).await
+}
+
+#[actix_web::test]
+async fn deleting_leaves_no_records_or_scenarios_behind() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let campus = campus(&pool).await;
+    let other_campus = self::campus(&pool).await;
+    let ada = student(&pool, &campus, "Ada", "Lovelace", &["Fall 2026", "Spring 2027"], &["Full time", "Part time"]).await;
+    // Someone with the same name elsewhere keeps everything.
+    let namesake = student(&pool, &other_campus, "Ada", "Lovelace", &["Fall 2026"], &["Full time"]).await;
+
+    let deleted = students::delete(&pool, &campus, &ada.public_id, RetentionAction::Anonymize, "test").await.unwrap();
+    assert_eq!((deleted.records, deleted.scenarios), (2, 2));
+    assert_eq!(records(&pool, &ada).await, 0);
+    assert_eq!(scenarios(&pool, &ada).await, 0);
+    assert_eq!(count(&pool, "select count(*) from Students where Id = ?", ada.id.0).await, 0);
+    assert_eq!(records(&pool, &namesake).await, 1);
+    assert_eq!(scenarios(&pool, &namesake).await, 1);
+}
+
+#[actix_web::test]
+async fn anonymizing_keeps_receipts_without_the_student() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let campus = campus(&pool).await;
+    let ada = student(&pool, &campus, "Ada", "Lovelace", &["Fall 2026"], &[]).await;
+    let receipt_id = receipt(&pool, &campus, &ada).await;
+
+    let deleted = students::delete(&pool, &campus, &ada.public_id, RetentionAction::Anonymize, "test").await.unwrap();
+    assert_eq!(deleted.receipts, 1);
+    let (student_id, first_name, last_name, client_ip_hash, user_agent) = sqlx::query_as::<_, (Option<i32>, String, String, Option<String>, Option<String>)>(
+        "select StudentId, FirstName, LastName, ClientIpHash, UserAgent from Receipts where Id = ?")
+    .bind(receipt_id)
+    .fetch_one(&pool).await.unwrap();
+    assert_eq!(student_id, None);
+    assert_eq!((first_name.as_str(), last_name.as_str()), (ANONYMIZED, ANONYMIZED));
+    assert_eq!((client_ip_hash, user_agent), (None, None));
+    assert_eq!(refund_estimates(&pool, receipt_id).await, 1);
+}
+
+#[actix_web::test]
+async fn purging_takes_receipts_and_their_refund_estimates() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let campus = campus(&pool).await;
+    let ada = student(&pool, &campus, "Ada", "Lovelace", &["Fall 2026"], &[]).await;
+    let receipt_id = receipt(&pool, &campus, &ada).await;
+
+    let deleted = students::delete(&pool, &campus, &ada.public_id, RetentionAction::Purge, "test").await.unwrap();
+    assert_eq!(deleted.receipts, 1);
+    assert_eq!(count(&pool, "select count(*) from Receipts where Id = ?", receipt_id).await, 0);
+    assert_eq!(refund_estimates(&pool, receipt_id).await, 0);
+}
+
+// The audit entry is the last thing written, so a failure there comes after every other change.
+#[actix_web::test]
+async fn a_failed_delete_changes_nothing() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let campus = campus(&pool).await;
+    let ada = student(&pool, &campus, "Ada", "Lovelace", &["Fall 2026"], &["Full time"]).await;
+    let receipt_id = receipt(&pool, &campus, &ada).await;
+
+    for action in [RetentionAction::Anonymize, RetentionAction::Purge] {
+        assert!(students::delete(&pool, &campus, &ada.public_id, action, audit::FAIL_AS).await.is_err());
+        assert_eq!(count(&pool, "select count(*) from Students where Id = ?", ada.id.0).await, 1);
+        assert_eq!(records(&pool, &ada).await, 1);
+        assert_eq!(scenarios(&pool, &ada).await, 1);
+        assert_eq!(count(&pool, "select count(*) from Receipts where Id = ? and StudentId is not null and FirstName = 'Ada'", receipt_id).await, 1);
+        assert_eq!(refund_estimates(&pool, receipt_id).await, 1);
+    }
+}
+
+// Scenarios move by the student's id, whatever the two are called; one named the same as the
+// kept student's moves over renamed instead of being lost with the other student.
+#[actix_web::test]
+async fn merging_moves_scenarios_by_student() {
+    let pool = match database().await {
+        Some(val) => val,
+        None => {
+            return;
+        }
+    };
+    let campus = campus(&pool).await;
+    let keep = student(&pool, &campus, "Ada", "Lovelace", &[], &["Full time", "Full time (merged)"]).await;
+    let other = student(&pool, &campus, "Ada", "King", &[], &["Full time", "Part time"]).await;
+
+    let merged = students::merge(&pool, &campus, &keep.public_id, &other.public_id, "test").await.unwrap();
+    assert_eq!((merged.scenarios, merged.renamed_scenarios), (2, 1));
+    assert_eq!(scenarios(&pool, &keep).await, 4);
+    assert_eq!(scenarios(&pool, &other).await, 0);
+    let mut names = sqlx::query_as::<_, (String, String, String)>("select ScenarioName, FirstName, LastName from Scenarios where StudentId = ?")
+    .bind(keep.id)
+    .fetch_all(&pool).await.unwrap();
+    assert!(names.iter().all(|(_, first, last)| (first.as_str(), last.as_str()) == ("Ada", "Lovelace")), "{:?}", names);
+    names.sort();
+    let names: Vec<&str> = names.iter().map(|(name, _, _)| name.as_str()).collect();
+    assert_eq!(names, ["Full time", "Full time (merged 2)", "Full time (merged)", "Part time"]);
+}
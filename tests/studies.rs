@@ -0,0 +1,28 @@
+// How the calculator reads the study level the form sends. "graduate" once compared against
+// "nonresident" and fell through to the undergraduate rates, so each value is pinned here.
+#[path = "../src/studies.rs"]
+mod studies;
+
+use studies::StudentStudies;
+
+#[test]
+fn each_form_value_parses_to_its_level() {
+    for val in ["undergraduate", "graduate", "dual_enrollment"] {
+        assert_eq!(StudentStudies::parse(val).as_str(), val);
+    }
+}
+
+#[test]
+fn graduate_is_priced_as_graduate() {
+    assert!(matches!(StudentStudies::parse("graduate"), StudentStudies::Graduate));
+    assert_eq!(StudentStudies::parse("graduate").label(), "Graduate");
+}
+
+// Only the exact values the form sends are recognized; anything else keeps the undergraduate
+// rates it was always given.
+#[test]
+fn anything_else_is_priced_as_undergraduate() {
+    for val in ["", "Graduate", "grad", "nonresident"] {
+        assert!(matches!(StudentStudies::parse(val), StudentStudies::Undergraduate), "{:?}", val);
+    }
+}
@@ -0,0 +1,142 @@
+// Every query that touches a campus's rows has to keep to that campus, so one school's lookups,
+// exports and admin pages can never show another's. Checked here over the SQL itself, since
+// that's where a missing CampusId would let rows through.
+//
+// The app is a binary crate, so `queries` is compiled in directly alongside stand-ins for the
+// timing and tracing it reports to, as in tests/rendered_pages.rs.
+mod metrics {
+    pub fn record_query(_name: &'static str, _elapsed: std::time::Duration) {}
+}
+
+mod slow_queries {
+    pub fn record(_name: &'static str, _elapsed: std::time::Duration) {}
+}
+
+mod telemetry {
+    pub struct Stage;
+
+    pub fn query(_name: &'static str) -> Stage {
+        Stage
+    }
+}
+
+#[path = "../src/queries.rs"]
+#[allow(dead_code)]
+mod queries;
+
+use queries::{Scope, ALL, CAMPUS_TABLES, CHECKED};
+
+fn query(name: &str) -> queries::Query {
+    match ALL.iter().find(|query| query.name == name) {
+        Some(query) => *query,
+        None => panic!("There's no query named {}.", name),
+    }
+}
+
+#[test]
+fn every_campus_query_is_scoped() {
+    let unscoped: Vec<&str> = ALL.iter().filter(|query| query.scope() == Scope::Unscoped).map(|query| query.name).collect();
+    assert!(unscoped.is_empty(), "These queries touch campus tables without filtering on CampusId: {:?}", unscoped);
+}
+
+// A query that gains a CampusId, or stops touching campus rows, comes off the list so the list
+// stays a record of what needed checking by hand.
+#[test]
+fn checked_queries_still_need_it() {
+    for name in CHECKED {
+        assert_eq!(query(name).scope(), Scope::Checked, "{} doesn't need to be in CHECKED.", name);
+    }
+}
+
+// What the public lookups, the exports and the admin pages read from.
+#[test]
+fn lookups_exports_and_admin_pages_filter_on_campus() {
+    let names = [
+        // Lookups.
+        "RECORDS_BY_NAME", "LATEST_RECORD_BY_NAME", "RECEIPT_BY_CODE", "RECEIPT_BY_ID", "RECENT_RECEIPTS",
+        "SCENARIOS_FOR_STUDENT", "SCENARIO_BY_ID", "DELETE_SCENARIO", "EMAIL_VERIFICATION", "STUDENT_BY_EMAIL", "STUDENT_SIGN_IN",
+        // Rates and fees.
+        "CREDIT_COSTS", "ORIENTATION_FEE", "HEALTH_INSURANCE_FEE", "INTERNATIONAL_FEES", "INDIRECT_COSTS",
+        "COURSE_FEES", "PROGRAMS", "TERM_WINDOWS", "LINE_ITEMS", "VALIDATION_RULES", "ORIENTATION_EXEMPTIONS", "RECIPROCITY_AGREEMENTS",
+        // Exports.
+        "EXPORT_RECORDS_PAGE", "STUDENT_BY_PUBLIC_ID",
+        // Admin pages.
+        "RECORD_BY_ID", "REFUND_SCHEDULE", "REFUND_RULE_BY_ID", "ADMIN_LINE_ITEMS", "LINE_ITEM_BY_ID",
+        "ADMIN_VALIDATION_RULES", "VALIDATION_RULE_BY_ID", "ADMIN_ORIENTATION_EXEMPTIONS", "ORIENTATION_EXEMPTION_BY_ID",
+        "ADMIN_RECIPROCITY_AGREEMENTS", "RECIPROCITY_AGREEMENT_BY_ID", "UPSERT_RECIPROCITY_AGREEMENT",
+        "FORM_FIELDS", "STUDENT_TO_DELETE", "DUPLICATE_CANDIDATES", "STUDENT_TO_MERGE", "SIMULATE_SAMPLE",
+        "RECEIPT_STATS", "ORIENTATION_ANSWERS", "FIRST_TIME_STUDENTS", "CALCULATION_SOURCES", "CALCULATION_CHANNELS",
+        "COUNT_BULK_RECEIPTS", "COUNT_BULK_RECORDS", "ANONYMIZE_BULK_RECEIPTS", "DELETE_BULK_RECEIPTS",
+        "DELETE_BULK_RECORDS", "DELETE_LEFTOVER_STUDENTS", "COUNT_BULK_STUDENTS", "BULK_RECEIPT_STUDENTS", "BULK_RECORD_STUDENTS",
+        "SIMULATE_ALL", "START_BULK_DELETE", "BULK_DELETE_PROGRESS", "FINISH_BULK_DELETE", "STOP_STALE_BULK_DELETES",
+        "LATEST_BULK_DELETE",
+    ];
+    for name in names {
+        assert_eq!(query(name).scope(), Scope::Campus, "{} doesn't filter on CampusId.", name);
+    }
+}
+
+#[test]
+fn campus_tables_are_recognized() {
+    let mut seen: Vec<&str> = ALL.iter().flat_map(|query| query.tables()).collect();
+    seen.sort_unstable();
+    seen.dedup();
+    for table in CAMPUS_TABLES {
+        assert!(seen.contains(table), "No query touches {}; is the name right?", table);
+    }
+    assert_eq!(query("SEED_STUDENT").tables(), vec!["Students"]);
+    assert_eq!(query("STUDENT_RECEIPT_ADJUSTMENTS").tables(), vec!["ReceiptAdjustments", "Receipts"]);
+}
+
+#[test]
+fn global_queries_touch_no_campus_rows() {
+    for name in ["CAMPUSES", "API_KEYS", "INSERT_AUDIT_ENTRY", "MAINTENANCE", "PING", "SLOW_QUERY_SUMMARY"] {
+        assert_eq!(query(name).scope(), Scope::Global, "{} touches campus rows.", name);
+    }
+}
+
+// Selecting the column isn't the same as keeping to one campus.
+#[test]
+fn selecting_campus_id_is_not_filtering_on_it() {
+    assert_eq!(query("STUDENT_RECORDS").scope(), Scope::Checked);
+    assert_eq!(query("INSERT_STUDENT").scope(), Scope::Campus);
+}
+
+// `scoped` only checks this itself in debug builds, so every call to it in the app is checked
+// here instead, by the query it names.
+#[test]
+fn scoped_is_only_called_on_campus_queries() {
+    let mut calls = 0;
+    for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "rs") || path.ends_with("queries.rs") {
+            continue;
+        }
+        let source = std::fs::read_to_string(&path).unwrap();
+        for (at, _) in source.match_indices(".scoped(") {
+            let before = source[..at].trim_end();
+            let name = before.rsplit(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == ':')).next().unwrap_or_default();
+            let name = match name.strip_prefix("queries::") {
+                Some(val) => val,
+                None => panic!("{}: scoped is called on {}; call it on queries::NAME so the query can be checked.", path.display(), name),
+            };
+            assert_eq!(query(name).scope(), Scope::Campus, "{}: {} is scoped but doesn't filter on CampusId.", path.display(), name);
+            calls += 1;
+        }
+    }
+    assert!(calls > 0, "No calls to scoped were found; is the path right?");
+}
+
+#[test]
+#[cfg_attr(not(debug_assertions), ignore = "scoped only checks this in debug builds")]
+#[should_panic(expected = "DELETE_LINE_ITEM doesn't filter on CampusId.")]
+fn scoped_refuses_queries_without_campus_id() {
+    let _ = queries::DELETE_LINE_ITEM.scoped(1);
+}
+
+#[test]
+#[cfg_attr(not(debug_assertions), ignore = "scoped only checks this in debug builds")]
+#[should_panic(expected = "CAMPUSES doesn't filter on CampusId.")]
+fn scoped_refuses_queries_without_campus_rows() {
+    let _ = queries::CAMPUSES.scoped(1);
+}